@@ -0,0 +1,146 @@
+use crate::MAX_ARTIFACT_SIZE;
+
+#[derive(thiserror::Error, Debug)]
+pub enum ChunkerError {
+    #[error("Chunk sizes should satisfy 0 < min <= avg <= max, got min={0} avg={1} max={2}")]
+    /// Error whill occur when trying to create [`ChunkerConfig`] with invalid sizes
+    InvalidSizes(usize, usize, usize),
+}
+
+/// Table of random values for gear rolling hash. Generated at compile time with splitmix64 so
+/// the same content always produces the same cut points on every peer
+const GEAR: [u64; 256] = {
+    let mut table = [0u64; 256];
+    let mut state: u64 = 0x9e37_79b9_7f4a_7c15;
+    let mut i = 0;
+    while i < 256 {
+        state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        table[i] = z ^ (z >> 31);
+        i += 1;
+    }
+    table
+};
+
+/// Sizes of artifacts that [`Chunker`] produces
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChunkerConfig {
+    /// Artifacts are never cut before this size (except last one)
+    min_size: usize,
+    /// Size that most of artifacts will be close to
+    avg_size: usize,
+    /// Artifacts are always cut at this size
+    max_size: usize,
+}
+
+impl ChunkerConfig {
+    /// Create new [`ChunkerConfig`]. Sizes should satisfy `0 < min <= avg <= max`
+    pub fn new(min_size: usize, avg_size: usize, max_size: usize) -> Result<Self, ChunkerError> {
+        match 0 < min_size && min_size <= avg_size && avg_size <= max_size {
+            true => Ok(Self {
+                min_size,
+                avg_size,
+                max_size,
+            }),
+            false => Err(ChunkerError::InvalidSizes(min_size, avg_size, max_size)),
+        }
+    }
+    /// returns min size of artifact
+    pub fn min_size(&self) -> usize { self.min_size }
+    /// returns average size of artifact
+    pub fn avg_size(&self) -> usize { self.avg_size }
+    /// returns max size of artifact
+    pub fn max_size(&self) -> usize { self.max_size }
+}
+
+impl Default for ChunkerConfig {
+    /// Max size is [`MAX_ARTIFACT_SIZE`] so artifacts still fits into one network message
+    fn default() -> Self {
+        Self {
+            min_size: MAX_ARTIFACT_SIZE / 4,
+            avg_size: MAX_ARTIFACT_SIZE / 2,
+            max_size: MAX_ARTIFACT_SIZE,
+        }
+    }
+}
+
+/// Content-defined chunker based on FastCDC. Cut points depends only on content of data, so
+/// identical files always produce identical artifacts and small edits in file only change
+/// artifacts around edit
+#[derive(Debug, Clone, Copy)]
+pub struct Chunker {
+    /// Sizes of artifacts
+    config: ChunkerConfig,
+    /// Mask that used before average size. It have more bits so cut is less likely
+    mask_small: u64,
+    /// Mask that used after average size. It have less bits so cut is more likely
+    mask_large: u64,
+}
+
+impl Chunker {
+    /// Create new [`Chunker`] from [`ChunkerConfig`]
+    pub fn new(config: ChunkerConfig) -> Self {
+        let bits = config.avg_size.ilog2();
+        Self {
+            config,
+            mask_small: Self::mask(bits + 1),
+            mask_large: Self::mask(bits.saturating_sub(1)),
+        }
+    }
+    /// Mask with given count of high bits. Gear hash is shifted left on every byte so high bits
+    /// depends on more bytes than low bits
+    fn mask(bits: u32) -> u64 {
+        match bits {
+            0 => 0,
+            bits if bits >= 64 => u64::MAX,
+            bits => ((1u64 << bits) - 1) << (64 - bits),
+        }
+    }
+    /// returns [`ChunkerConfig`] of [`Chunker`]
+    pub fn config(&self) -> ChunkerConfig { self.config }
+    /// Find size of next artifact at the beginning of data.
+    ///
+    /// Returns None if data is not enough to find cut point, then caller should read more data.
+    /// If `eof` is true the rest of data is always returned as last artifact
+    pub fn find_cut(&self, data: &[u8], eof: bool) -> Option<usize> {
+        let ChunkerConfig {
+            min_size,
+            avg_size,
+            max_size,
+        } = self.config;
+
+        if data.is_empty() {
+            return None;
+        }
+        if data.len() <= min_size {
+            return eof.then_some(data.len());
+        }
+
+        let end = data.len().min(max_size);
+        let normal = avg_size.min(end);
+        let mut hash = 0u64;
+        for (idx, byte) in data
+            .iter()
+            .enumerate()
+            .take(end)
+            .skip(min_size)
+        {
+            hash = (hash << 1).wrapping_add(GEAR[*byte as usize]);
+            let mask = match idx < normal {
+                true => self.mask_small,
+                false => self.mask_large,
+            };
+            if hash & mask == 0 {
+                return Some(idx + 1);
+            }
+        }
+        // if we reached max size or end of file we are cut here. Otherwise we need more data
+        (end == max_size || eof).then_some(end)
+    }
+}
+
+impl Default for Chunker {
+    fn default() -> Self { Self::new(ChunkerConfig::default()) }
+}
@@ -1,7 +1,9 @@
 #![allow(dead_code)]
 mod artifact;
+mod chunker;
 mod id;
 mod magnet;
+mod merkle;
 mod reader;
 #[cfg(test)]
 mod test;
@@ -16,7 +18,9 @@ pub(crate) const MAX_ARTIFACT_SIZE: usize = 2048;
 
 pub use crate::{
     artifact::Artifact,
+    chunker::{Chunker, ChunkerConfig, ChunkerError},
     id::{ArtifactId, ArtifactIdError},
     magnet::{MagnetError, MagnetLink},
+    merkle::{MerkleProof, MerkleProofNode, MerkleTree},
     reader::ArtifactStreamReader,
 };
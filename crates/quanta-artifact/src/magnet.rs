@@ -4,14 +4,21 @@ use std::{
 };
 
 use async_std::path::Path;
+use bincode::Options;
 use futures::{AsyncReadExt, AsyncWriteExt};
+use quanta_crypto::HashValue;
 use serde::{Deserialize, Serialize};
 
 use crate::{
     id::ArtifactId,
+    merkle::MerkleTree,
     ziplib::{decode_gzip_all, encode_gzip_all},
 };
 
+/// Version of [`MagnetLink`] encoding. It is the first byte of bincode bytes, magnet links that
+/// were encoded before versions were added do not have it
+const MAGNET_VERSION: u8 = 1;
+
 #[derive(thiserror::Error, Debug)]
 pub enum MagnetError {
     #[error("To Bincode Error")]
@@ -43,6 +50,28 @@ pub struct MagnetLink {
     file_name: String,
     /// File size
     size: usize,
+    /// Root of [`MerkleTree`] that built over ordered artifact ids. Commits to the whole file
+    /// contents. None if magnet was updated after [`MagnetLink::update_merkle_root`] call
+    merkle_root: Option<HashValue>,
+}
+
+/// [`MagnetLink`] as it was encoded before merkle root and version were added
+#[derive(Deserialize)]
+struct LegacyMagnetLink {
+    artifact_id_mapping: HashMap<usize, ArtifactId>,
+    file_name: String,
+    size: usize,
+}
+
+impl From<LegacyMagnetLink> for MagnetLink {
+    fn from(legacy: LegacyMagnetLink) -> Self {
+        Self {
+            artifact_id_mapping: legacy.artifact_id_mapping,
+            file_name: legacy.file_name,
+            size: legacy.size,
+            merkle_root: None,
+        }
+    }
 }
 
 impl MagnetLink {
@@ -54,13 +83,58 @@ impl MagnetLink {
             artifact_id_mapping,
             file_name,
             size,
+            merkle_root: None,
         }
     }
-    /// updates the current state of [`artifact_id_mapping`]
+    /// updates the current state of [`artifact_id_mapping`]. Merkle root is reset and should be
+    /// computed again with [`MagnetLink::update_merkle_root`]
     pub fn new_update_with_artifact_id(&mut self, artifact_id: ArtifactId) {
         self.artifact_id_mapping
             .entry(self.next_idx())
             .or_insert(artifact_id);
+        self.merkle_root = None;
+    }
+    /// returns [`MerkleTree`] built over ordered artifact ids
+    pub fn merkle_tree(&self) -> MerkleTree { MerkleTree::new(self.artifact_ids().as_slice()) }
+    /// compute merkle root of current artifact ids and store it in magnet. Should be called
+    /// when all artifacts are added
+    pub fn update_merkle_root(&mut self) -> HashValue {
+        let merkle_root = self.merkle_tree().root();
+        self.merkle_root = Some(merkle_root);
+        merkle_root
+    }
+    /// returns stored merkle root
+    pub fn merkle_root(&self) -> Option<HashValue> { self.merkle_root }
+    /// returns merkle root that magnet claims: stored root or root of its artifact ids if root is
+    /// not stored. It can be trusted only as much as magnet itself
+    pub fn claimed_merkle_root(&self) -> HashValue {
+        self.merkle_root
+            .unwrap_or_else(|| self.merkle_tree().root())
+    }
+    /// Check that artifact ids of magnet commit to given merkle root. Root should come from
+    /// source that we are trust (e.g. signed announcement), not from magnet itself. Stored root
+    /// of magnet, if it is set, should be the same
+    pub fn verify(&self, merkle_root: &HashValue) -> bool {
+        self.merkle_root
+            .is_none_or(|stored| stored == *merkle_root) &&
+            self.merkle_tree().root() == *merkle_root
+    }
+    /// returns name of file
+    pub fn file_name(&self) -> &str { self.file_name.as_str() }
+    /// returns size of file
+    pub fn size(&self) -> usize { self.size }
+    /// returns artifact ids sorted by index. Its the order in which artifacts should be
+    /// collected into the final file
+    pub fn artifact_ids(&self) -> Vec<ArtifactId> {
+        let mut mapping = self
+            .artifact_id_mapping
+            .iter()
+            .collect::<Vec<(&usize, &ArtifactId)>>();
+        mapping.sort_by_key(|(idx, _)| **idx);
+        mapping
+            .into_iter()
+            .map(|(_, artifact_id)| *artifact_id)
+            .collect()
     }
     /// returns next index that be used in [`MagnetLink::new_update_with_artifact_id`]
     fn next_idx(&self) -> usize {
@@ -69,9 +143,11 @@ impl MagnetLink {
             None => 1,
         }
     }
-    /// returns bincode-based bytes
+    /// returns bincode-based bytes prefixed with version of encoding
     pub fn to_bincode(&self) -> Result<Vec<u8>, MagnetError> {
-        bincode::serialize(self).map_err(|_| MagnetError::ToBincode)
+        let mut bytes = vec![MAGNET_VERSION];
+        bincode::serialize_into(&mut bytes, self).map_err(|_| MagnetError::ToBincode)?;
+        Ok(bytes)
     }
     /// get bincode bytes and compress them with gzip
     pub fn to_bincode_compressed(&self) -> Result<Vec<u8>, MagnetError> {
@@ -79,11 +155,25 @@ impl MagnetLink {
     }
     /// get self from bincode-compressed bytes
     pub fn from_bincode_compressed(input: Vec<u8>) -> Result<Self, MagnetError> {
-        Self::from_bincode_compressed(decode_gzip_all(input.as_slice())?)
+        Self::from_bincode(decode_gzip_all(input.as_slice())?)
     }
-    /// returns [`Self`] from bincode-based bytes
+    /// returns [`Self`] from bincode-based bytes. Bytes without version are decoded as magnet
+    /// link without merkle root, so old magnet links are still readable
     pub fn from_bincode(bincod: Vec<u8>) -> Result<Self, MagnetError> {
-        bincode::deserialize(bincod.as_slice()).map_err(|_| MagnetError::FromBincode)
+        // first byte of old magnet link is a part of length of mapping, so it can be equal to
+        // version too. Versioned bytes should be decoded completely, otherwise it is old one
+        if let Some((&MAGNET_VERSION, bytes)) = bincod.split_first() {
+            let magnet_link = bincode::options()
+                .with_fixint_encoding()
+                .reject_trailing_bytes()
+                .deserialize(bytes);
+            if let Ok(magnet_link) = magnet_link {
+                return Ok(magnet_link);
+            }
+        }
+        bincode::deserialize::<LegacyMagnetLink>(bincod.as_slice())
+            .map(MagnetLink::from)
+            .map_err(|_| MagnetError::FromBincode)
     }
     /// save magnet link in file
     pub async fn save_into_file<P>(&self, path: P) -> Result<(), MagnetError>
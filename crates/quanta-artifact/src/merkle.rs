@@ -0,0 +1,126 @@
+use quanta_crypto::{AdvancedHasher, HashValue};
+use serde::{Deserialize, Serialize};
+use sha2::Digest;
+
+use crate::{artifact::Artifact, id::ArtifactId};
+
+/// Prefix of leaf hashes. Leafs and nodes are hashed with different prefixes so node can not be
+/// passed as leaf
+const LEAF_PREFIX: u8 = 0;
+/// Prefix of node hashes
+const NODE_PREFIX: u8 = 1;
+
+/// Hash of leaf that contains [`ArtifactId`]
+fn hash_leaf(artifact_id: &ArtifactId) -> HashValue {
+    let mut input = vec![LEAF_PREFIX];
+    input.extend(artifact_id.to_bytes());
+    AdvancedHasher::new(input.as_slice(), sha2::Sha256::new()).finalize()
+}
+
+/// Hash of node with two children
+fn hash_node(left: &HashValue, right: &HashValue) -> HashValue {
+    let mut input = vec![NODE_PREFIX];
+    input.extend(left.to_bytes());
+    input.extend(right.to_bytes());
+    AdvancedHasher::new(input.as_slice(), sha2::Sha256::new()).finalize()
+}
+
+/// Merkle tree over ordered [`ArtifactId`]s of file. Root of tree commits to the whole file
+/// contents, so the order and the ids of artifacts can not be changed without changing root.
+///
+/// If level has odd count of nodes the last node is promoted to the next level as is
+#[derive(Debug, Clone)]
+pub struct MerkleTree {
+    /// Levels of tree. First level contains leafs hashes and the last one contains root
+    levels: Vec<Vec<HashValue>>,
+}
+
+impl MerkleTree {
+    /// Build new [`MerkleTree`] from ordered artifact ids
+    pub fn new(artifact_ids: &[ArtifactId]) -> Self {
+        let mut levels = vec![artifact_ids
+            .iter()
+            .map(hash_leaf)
+            .collect::<Vec<HashValue>>()];
+        while let Some(level) = levels
+            .last()
+            .filter(|level| level.len() > 1)
+        {
+            let next = level
+                .chunks(2)
+                .map(|pair| match pair {
+                    [left, right] => hash_node(left, right),
+                    [single] => *single,
+                    _ => unreachable!("chunks(2) returns one or two nodes"),
+                })
+                .collect();
+            levels.push(next);
+        }
+        Self { levels }
+    }
+    /// Returns root of tree. Root of empty tree is the hash of empty input
+    pub fn root(&self) -> HashValue {
+        match self
+            .levels
+            .last()
+            .and_then(|level| level.first())
+        {
+            Some(root) => *root,
+            None => AdvancedHasher::new(&[], sha2::Sha256::new()).finalize(),
+        }
+    }
+    /// Returns [`MerkleProof`] that artifact with given position belongs to tree
+    pub fn proof(&self, position: usize) -> Option<MerkleProof> {
+        if position >= self.levels.first()?.len() {
+            return None;
+        }
+        let mut path = Vec::new();
+        let mut idx = position;
+        for level in &self.levels[..self.levels.len() - 1] {
+            let sibling = idx ^ 1;
+            // if there is no sibling node was promoted and nothing is added to path
+            if let Some(hash) = level.get(sibling) {
+                path.push(match sibling < idx {
+                    true => MerkleProofNode::Left(*hash),
+                    false => MerkleProofNode::Right(*hash),
+                });
+            }
+            idx /= 2;
+        }
+        Some(MerkleProof { path })
+    }
+}
+
+/// Sibling on the way from leaf to root
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MerkleProofNode {
+    /// Sibling is on the left side
+    Left(HashValue),
+    /// Sibling is on the right side
+    Right(HashValue),
+}
+
+/// Inclusion proof of [`ArtifactId`] in [`MerkleTree`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MerkleProof {
+    /// Siblings from leaf to root
+    path: Vec<MerkleProofNode>,
+}
+
+impl MerkleProof {
+    /// Check that artifact id belongs to tree with given root
+    pub fn verify(&self, root: &HashValue, artifact_id: &ArtifactId) -> bool {
+        let computed = self
+            .path
+            .iter()
+            .fold(hash_leaf(artifact_id), |hash, node| match node {
+                MerkleProofNode::Left(sibling) => hash_node(sibling, &hash),
+                MerkleProofNode::Right(sibling) => hash_node(&hash, sibling),
+            });
+        computed == *root
+    }
+    /// Check that data of artifact matches its id and artifact belongs to tree with given root
+    pub fn verify_artifact(&self, root: &HashValue, artifact: &Artifact) -> bool {
+        ArtifactId::new(artifact.data.as_slice()) == artifact.id && self.verify(root, &artifact.id)
+    }
+}
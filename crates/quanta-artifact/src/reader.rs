@@ -6,7 +6,7 @@ use std::{
 
 use futures::{AsyncRead, Stream};
 
-use crate::{artifact::Artifact, MAX_ARTIFACT_SIZE};
+use crate::{artifact::Artifact, chunker::Chunker, MAX_ARTIFACT_SIZE};
 
 /// Custom Artifact file reader
 pub struct ArtifactStreamReader<'a, R: AsyncRead + Unpin> {
@@ -14,13 +14,33 @@ pub struct ArtifactStreamReader<'a, R: AsyncRead + Unpin> {
     reader: &'a mut R,
     /// Buffer
     buf: [u8; MAX_ARTIFACT_SIZE],
+    /// If chunker is set artifacts are cut by content of file instead of read boundaries
+    chunker: Option<Chunker>,
+    /// Bytes that are already read but not yet turned into artifact
+    pending: Vec<u8>,
+    /// Set when reader returned zero bytes
+    eof: bool,
 }
 
 impl<'a, R: AsyncRead + Unpin> ArtifactStreamReader<'a, R> {
     /// Createw new [`ArtifactStreamReader`]
     pub fn new(reader: &'a mut R) -> Self {
         let buf = [0; MAX_ARTIFACT_SIZE];
-        Self { reader, buf }
+        Self {
+            reader,
+            buf,
+            chunker: None,
+            pending: Vec::new(),
+            eof: false,
+        }
+    }
+    /// Create new [`ArtifactStreamReader`] that cut artifacts with content-defined [`Chunker`].
+    /// The same content always gives the same artifacts
+    pub fn with_chunker(reader: &'a mut R, chunker: Chunker) -> Self {
+        Self {
+            chunker: Some(chunker),
+            ..Self::new(reader)
+        }
     }
 }
 
@@ -29,18 +49,42 @@ impl<'a, R: AsyncRead + Unpin> Stream for ArtifactStreamReader<'a, R> {
     type Item = Result<Artifact>;
 
     fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
-        let Self { reader, buf } = &mut *self;
+        let Self {
+            reader,
+            buf,
+            chunker,
+            pending,
+            eof,
+        } = &mut *self;
+
+        let Some(chunker) = chunker else {
+            let size = match futures::ready!(Pin::new(reader).poll_read(cx, &mut buf[..])) {
+                Ok(size) => size,
+                Err(error) => return Poll::Ready(Some(Err(error))),
+            };
 
-        let size = match futures::ready!(Pin::new(reader).poll_read(cx, &mut buf[..])) {
-            Ok(size) => size,
-            Err(error) => return Poll::Ready(Some(Err(error))),
+            return match size == 0 {
+                // if the size of the loaded buf == 0, then we have completely read the file
+                true => Poll::Ready(None),
+                // If the size is not equal to zero, then we create a new artifact
+                false => Poll::Ready(Some(Ok(Artifact::new(buf[..size].to_vec())))),
+            };
         };
 
-        match size == 0 {
-            // if the size of the loaded buf == 0, then we have completely read the file
-            true => Poll::Ready(None),
-            // If the size is not equal to zero, then we create a new artifact
-            false => Poll::Ready(Some(Ok(Artifact::new(buf[..size].to_vec())))),
+        loop {
+            // cut artifact as soon as chunker found cut point in pending bytes
+            if let Some(cut) = chunker.find_cut(pending.as_slice(), *eof) {
+                let data = pending.drain(..cut).collect();
+                return Poll::Ready(Some(Ok(Artifact::new(data))));
+            }
+            if *eof {
+                return Poll::Ready(None);
+            }
+            match futures::ready!(Pin::new(&mut **reader).poll_read(cx, &mut buf[..])) {
+                Ok(0) => *eof = true,
+                Ok(size) => pending.extend_from_slice(&buf[..size]),
+                Err(error) => return Poll::Ready(Some(Err(error))),
+            }
         }
     }
 }
@@ -1,4 +1,15 @@
-use crate::{id::ArtifactId, magnet::MagnetLink};
+use std::collections::{HashMap, HashSet};
+
+use futures::TryStreamExt;
+
+use crate::{
+    artifact::Artifact,
+    chunker::{Chunker, ChunkerConfig},
+    id::ArtifactId,
+    magnet::MagnetLink,
+    merkle::MerkleTree,
+    reader::ArtifactStreamReader,
+};
 
 #[test]
 fn test_artifact_id() {
@@ -10,11 +21,169 @@ fn test_artifact_id() {
 
 #[test]
 fn test_magnet_link() {
-    let mut magnet = MagnetLink::new("hello".to_string(),  5000);
+    let mut magnet = MagnetLink::new("hello".to_string(), 5000);
     magnet.new_update_with_artifact_id(ArtifactId::new(b"beep"));
     magnet.new_update_with_artifact_id(ArtifactId::new(b"boop"));
     let string_magnet = magnet.to_string();
     let from_string_magnet = MagnetLink::try_from(string_magnet).unwrap();
 
     assert_eq!(magnet, from_string_magnet);
+
+    magnet.update_merkle_root();
+    let from_string_magnet = MagnetLink::try_from(magnet.to_string()).unwrap();
+    assert_eq!(magnet, from_string_magnet);
+}
+
+/// Pseudo random bytes for chunking tests
+fn random_bytes(len: usize) -> Vec<u8> {
+    let mut state: u32 = 42;
+    (0..len)
+        .map(|_| {
+            state = state
+                .wrapping_mul(1_103_515_245)
+                .wrapping_add(12345);
+            (state >> 16) as u8
+        })
+        .collect()
+}
+
+/// Read all artifacts from bytes using content-defined chunker
+fn read_chunked(data: &[u8]) -> Vec<Artifact> {
+    let mut cursor = futures::io::Cursor::new(data.to_vec());
+    let reader = ArtifactStreamReader::with_chunker(&mut cursor, Chunker::default());
+    futures::executor::block_on(reader.try_collect::<Vec<Artifact>>()).unwrap()
+}
+
+#[test]
+fn test_chunker_config() {
+    assert!(ChunkerConfig::new(0, 1024, 2048).is_err());
+    assert!(ChunkerConfig::new(1024, 512, 2048).is_err());
+    assert!(ChunkerConfig::new(512, 4096, 2048).is_err());
+    assert!(ChunkerConfig::new(512, 1024, 2048).is_ok());
+}
+
+#[test]
+fn test_chunker_sizes() {
+    let data = random_bytes(64 * 1024);
+    let artifacts = read_chunked(data.as_slice());
+    let config = ChunkerConfig::default();
+    for artifact in &artifacts[..artifacts.len() - 1] {
+        assert!(artifact.data.len() >= config.min_size());
+        assert!(artifact.data.len() <= config.max_size());
+    }
+    let joined = artifacts
+        .into_iter()
+        .flat_map(|artifact| artifact.data)
+        .collect::<Vec<u8>>();
+    assert_eq!(data, joined);
+}
+
+#[test]
+fn test_chunker_dedup() {
+    let data = random_bytes(64 * 1024);
+    let mut edited = data.clone();
+    edited.insert(100, 0xff);
+
+    let ids = read_chunked(data.as_slice())
+        .into_iter()
+        .map(|artifact| artifact.id)
+        .collect::<HashSet<ArtifactId>>();
+    let edited_ids = read_chunked(edited.as_slice())
+        .into_iter()
+        .map(|artifact| artifact.id)
+        .collect::<HashSet<ArtifactId>>();
+    // the same content always gives the same artifacts
+    assert_eq!(
+        ids,
+        read_chunked(data.as_slice())
+            .into_iter()
+            .map(|artifact| artifact.id)
+            .collect::<HashSet<ArtifactId>>()
+    );
+    // small edit only changes few artifacts around edit
+    assert!(ids.difference(&edited_ids).count() <= 2);
+}
+
+#[test]
+fn test_merkle_proof() {
+    for count in [1, 2, 3, 5, 8, 13] {
+        let artifacts = (0..count)
+            .map(|idx| Artifact::new(vec![idx as u8]))
+            .collect::<Vec<Artifact>>();
+        let mut magnet = MagnetLink::new("hello".to_string(), count);
+        for artifact in &artifacts {
+            magnet.new_update_with_artifact_id(artifact.id);
+        }
+        assert!(magnet.merkle_root().is_none());
+        let merkle_root = magnet.update_merkle_root();
+        let tree = magnet.merkle_tree();
+        assert_eq!(merkle_root, tree.root());
+        assert!(magnet.verify(&merkle_root));
+
+        for (position, artifact) in artifacts.iter().enumerate() {
+            let proof = tree.proof(position).unwrap();
+            assert!(proof.verify_artifact(&merkle_root, artifact));
+            assert!(!proof.verify_artifact(&merkle_root, &Artifact::new(b"beep".to_vec())));
+            // artifact which data does not match its id is rejected
+            let forged = Artifact {
+                id: artifact.id,
+                data: b"beep".to_vec(),
+            };
+            assert!(!proof.verify_artifact(&merkle_root, &forged));
+        }
+        assert!(tree.proof(count).is_none());
+    }
+}
+
+#[test]
+fn test_magnet_verify_with_other_root() {
+    let mut magnet = MagnetLink::new("hello".to_string(), 2);
+    magnet.new_update_with_artifact_id(ArtifactId::new(b"beep"));
+    magnet.new_update_with_artifact_id(ArtifactId::new(b"boop"));
+    let merkle_root = magnet.update_merkle_root();
+    assert_eq!(magnet.claimed_merkle_root(), merkle_root);
+    let other_root = MerkleTree::new(&[ArtifactId::new(b"boop")]).root();
+    assert!(!magnet.verify(&other_root));
+    // magnet that was changed after root was computed does not match trusted root
+    magnet.new_update_with_artifact_id(ArtifactId::new(b"bop"));
+    assert!(!magnet.verify(&merkle_root));
+}
+
+#[test]
+fn test_legacy_magnet_link() {
+    /// [MagnetLink] as it was encoded before merkle root was added
+    #[derive(serde::Serialize)]
+    struct LegacyMagnetLink {
+        artifact_id_mapping: HashMap<usize, ArtifactId>,
+        file_name: String,
+        size: usize,
+    }
+    // lengths of mapping are chosen so first byte of old bytes is equal to version too
+    for count in [0, 1, 2] {
+        let legacy = LegacyMagnetLink {
+            artifact_id_mapping: (1..=count)
+                .map(|idx| (idx, ArtifactId::new(&[idx as u8])))
+                .collect(),
+            file_name: "hello".to_string(),
+            size: 5000,
+        };
+        let legacy_string = bs58::encode(bincode::serialize(&legacy).unwrap()).into_string();
+        let magnet = MagnetLink::try_from(legacy_string).unwrap();
+        assert_eq!(magnet.file_name(), "hello");
+        assert_eq!(magnet.size(), 5000);
+        assert_eq!(magnet.artifact_ids().len(), count);
+        assert!(magnet.merkle_root().is_none());
+        assert!(magnet.verify(&magnet.merkle_tree().root()));
+        assert_eq!(magnet.claimed_merkle_root(), magnet.merkle_tree().root());
+    }
+}
+
+#[test]
+fn test_merkle_root_order() {
+    let beep = ArtifactId::new(b"beep");
+    let boop = ArtifactId::new(b"boop");
+    assert_ne!(
+        MerkleTree::new(&[beep, boop]).root(),
+        MerkleTree::new(&[boop, beep]).root()
+    );
 }
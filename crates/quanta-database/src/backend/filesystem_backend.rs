@@ -0,0 +1,331 @@
+use std::{
+    fs,
+    io,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+        MutexGuard,
+        PoisonError,
+    },
+};
+
+use crate::backend::{
+    announcement_entry,
+    split_announcement_entry,
+    Backend,
+    BackendError,
+    StoredMagnetLink,
+};
+
+const ARTIFACT_DIR_NAME: &str = "artifacts";
+const MAGNET_DIR_NAME: &str = "magnets";
+const UNPINNED_DIR_NAME: &str = "unpinned";
+const ANNOUNCEMENT_DIR_NAME: &str = "feeds";
+/// Directory in directory of feed with empty files that are named by keys of announcements
+const ANNOUNCEMENT_KEY_DIR_NAME: &str = "keys";
+/// File with order in which artifacts were used
+const RECENCY_FILE_NAME: &str = "recency";
+/// Extension of files that are written before they are renamed into place
+const TEMPORARY_FILE_EXTENSION: &str = "tmp";
+/// Count of hex chars of artifact key that name shard directory of artifact
+const SHARD_PREFIX_LEN: usize = 2;
+
+/// [Backend] that stores everything in plain files. Artifacts are content-addressed: file name
+/// of artifact is a hex of its key, and artifacts are sharded into directories by first chars of
+/// it, so directories stay small. Magnet links and announcements are files named by their
+/// indexes, and keys of announcements are files named by hex of keys
+pub struct FilesystemBackend {
+    /// Directory where everything is stored
+    path: PathBuf,
+    /// Lock that is held while next index of magnet link or announcement is chosen
+    index_lock: Mutex<()>,
+}
+
+impl FilesystemBackend {
+    /// Open or create storage in given directory
+    pub fn open<P>(path: P) -> Result<Self, BackendError>
+    where
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref().to_path_buf();
+        for dir_name in [
+            ARTIFACT_DIR_NAME,
+            MAGNET_DIR_NAME,
+            UNPINNED_DIR_NAME,
+            ANNOUNCEMENT_DIR_NAME,
+        ] {
+            fs::create_dir_all(path.join(dir_name))?;
+        }
+        Ok(FilesystemBackend {
+            path,
+            index_lock: Mutex::default(),
+        })
+    }
+    /// Returns path of artifact file: `artifacts/<shard>/<hex of key>`
+    fn artifact_path(&self, key: &[u8]) -> PathBuf {
+        let name = hex::encode(key);
+        let shard = &name[..SHARD_PREFIX_LEN.min(name.len())];
+        self.path
+            .join(ARTIFACT_DIR_NAME)
+            .join(shard)
+            .join(&name)
+    }
+    /// Returns path of magnet link file
+    fn magnet_path(&self, index: u64) -> PathBuf {
+        self.path
+            .join(MAGNET_DIR_NAME)
+            .join(index.to_string())
+    }
+    /// Returns path of file which exists when magnet link is not pinned
+    fn unpinned_path(&self, index: u64) -> PathBuf {
+        self.path
+            .join(UNPINNED_DIR_NAME)
+            .join(index.to_string())
+    }
+    /// Returns path of directory with announcements of feed. Name of feed is hex-encoded, so it
+    /// is always a valid file name
+    fn announcement_dir(&self, topic: &str) -> PathBuf {
+        self.path
+            .join(ANNOUNCEMENT_DIR_NAME)
+            .join(hex::encode(topic))
+    }
+    /// Returns path of file that exists when announcement with given key is stored in feed
+    fn announcement_key_path(&self, topic: &str, key: &[u8]) -> PathBuf {
+        self.announcement_dir(topic)
+            .join(ANNOUNCEMENT_KEY_DIR_NAME)
+            .join(hex::encode(key))
+    }
+    /// Returns [StoredMagnetLink] if file of magnet link exists
+    fn read_magnet_link(&self, index: u64) -> Result<Option<StoredMagnetLink>, BackendError> {
+        let Some(bytes) = read_optional(&self.magnet_path(index))? else {
+            return Ok(None);
+        };
+        Ok(Some(StoredMagnetLink {
+            index,
+            bytes,
+            pinned: !self.unpinned_path(index).exists(),
+        }))
+    }
+    fn index_lock(&self) -> MutexGuard<'_, ()> {
+        self.index_lock
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl Backend for FilesystemBackend {
+    fn insert_artifact(&self, key: &[u8], artifact: &[u8]) -> Result<bool, BackendError> {
+        let path = self.artifact_path(key);
+        if path.exists() {
+            return Ok(false);
+        }
+        if let Some(shard) = path.parent() {
+            fs::create_dir_all(shard)?;
+        }
+        write_atomic(&path, artifact)?;
+        Ok(true)
+    }
+
+    fn get_artifact(&self, key: &[u8]) -> Result<Option<Vec<u8>>, BackendError> {
+        read_optional(&self.artifact_path(key))
+    }
+
+    fn contains_artifact(&self, key: &[u8]) -> Result<bool, BackendError> {
+        Ok(self.artifact_path(key).is_file())
+    }
+
+    fn remove_artifact(&self, key: &[u8]) -> Result<Option<u64>, BackendError> {
+        let path = self.artifact_path(key);
+        let size = match fs::metadata(&path) {
+            Ok(metadata) => metadata.len(),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(error) => return Err(error.into()),
+        };
+        match fs::remove_file(&path) {
+            Ok(()) => Ok(Some(size)),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(error) => Err(error.into()),
+        }
+    }
+
+    fn artifacts(&self) -> Result<Vec<(Vec<u8>, u64)>, BackendError> {
+        let mut artifacts = Vec::new();
+        for shard in fs::read_dir(self.path.join(ARTIFACT_DIR_NAME))? {
+            let shard = shard?;
+            if !shard.file_type()?.is_dir() {
+                continue;
+            }
+            for entry in fs::read_dir(shard.path())? {
+                let entry = entry?;
+                // temporary files of unfinished writes are not artifacts
+                let Some(key) = entry
+                    .file_name()
+                    .to_str()
+                    .and_then(|name| hex::decode(name).ok())
+                else {
+                    continue;
+                };
+                artifacts.push((key, entry.metadata()?.len()));
+            }
+        }
+        Ok(artifacts)
+    }
+
+    fn save_recency(&self, recency: &[u8]) -> Result<(), BackendError> {
+        Ok(write_atomic(&self.path.join(RECENCY_FILE_NAME), recency)?)
+    }
+
+    fn recency(&self) -> Result<Option<Vec<u8>>, BackendError> {
+        read_optional(&self.path.join(RECENCY_FILE_NAME))
+    }
+
+    fn insert_magnet_link(&self, magnet_link: &[u8]) -> Result<u64, BackendError> {
+        let _lock = self.index_lock();
+        let index = match read_indexes(&self.path.join(MAGNET_DIR_NAME))?.last() {
+            Some(index) => index + 1,
+            None => 1,
+        };
+        write_atomic(&self.magnet_path(index), magnet_link)?;
+        Ok(index)
+    }
+
+    fn get_magnet_link(&self, index: u64) -> Result<Option<StoredMagnetLink>, BackendError> {
+        self.read_magnet_link(index)
+    }
+
+    fn magnet_links(&self) -> Result<Vec<StoredMagnetLink>, BackendError> {
+        let mut magnet_links = Vec::new();
+        for index in read_indexes(&self.path.join(MAGNET_DIR_NAME))? {
+            // magnet link can be removed while we are reading others
+            if let Some(magnet_link) = self.read_magnet_link(index)? {
+                magnet_links.push(magnet_link);
+            }
+        }
+        Ok(magnet_links)
+    }
+
+    fn remove_magnet_link(&self, index: u64) -> Result<Option<StoredMagnetLink>, BackendError> {
+        let _lock = self.index_lock();
+        let Some(magnet_link) = self.read_magnet_link(index)? else {
+            return Ok(None);
+        };
+        fs::remove_file(self.magnet_path(index))?;
+        remove_optional(&self.unpinned_path(index))?;
+        Ok(Some(magnet_link))
+    }
+
+    fn set_magnet_link_pinned(&self, index: u64, pinned: bool) -> Result<bool, BackendError> {
+        let _lock = self.index_lock();
+        if !self.magnet_path(index).is_file() {
+            return Ok(false);
+        }
+        match pinned {
+            true => remove_optional(&self.unpinned_path(index))?,
+            false => fs::write(self.unpinned_path(index), [])?,
+        }
+        Ok(true)
+    }
+
+    fn insert_announcement(
+        &self,
+        topic: &str,
+        key: &[u8],
+        announcement: &[u8],
+        max_len: usize,
+    ) -> Result<bool, BackendError> {
+        let _lock = self.index_lock();
+        let key_path = self.announcement_key_path(topic, key);
+        if key_path.exists() {
+            return Ok(false);
+        }
+        let dir = self.announcement_dir(topic);
+        fs::create_dir_all(dir.join(ANNOUNCEMENT_KEY_DIR_NAME))?;
+        let mut indexes = read_indexes(&dir)?;
+        let index = match indexes.last() {
+            Some(index) => index + 1,
+            None => 0,
+        };
+        // key is written first, so announcement is never stored without its key
+        fs::write(&key_path, [])?;
+        write_atomic(
+            &dir.join(index.to_string()),
+            &announcement_entry(key, announcement),
+        )?;
+        indexes.push(index);
+        let removed = indexes.len().saturating_sub(max_len);
+        for oldest in &indexes[..removed] {
+            let oldest_path = dir.join(oldest.to_string());
+            if let Some(entry) = read_optional(&oldest_path)? {
+                if let Some((oldest_key, _)) = split_announcement_entry(&entry) {
+                    remove_optional(&self.announcement_key_path(topic, oldest_key))?;
+                }
+            }
+            remove_optional(&oldest_path)?;
+        }
+        Ok(true)
+    }
+
+    fn announcements(&self, topic: &str) -> Result<Vec<Vec<u8>>, BackendError> {
+        let dir = self.announcement_dir(topic);
+        if !dir.is_dir() {
+            return Ok(Vec::new());
+        }
+        let mut announcements = Vec::new();
+        for index in read_indexes(&dir)? {
+            if let Some((_, announcement)) =
+                split_announcement_entry(&fs::read(dir.join(index.to_string()))?)
+            {
+                announcements.push(announcement.to_vec());
+            }
+        }
+        Ok(announcements)
+    }
+}
+
+/// Write file into temporary file and rename it, so file is never read half-written. Every write
+/// has its own temporary file, so the same file can be written concurrently
+fn write_atomic(path: &Path, bytes: &[u8]) -> io::Result<()> {
+    static WRITES: AtomicU64 = AtomicU64::new(0);
+    let temporary_path = path.with_extension(format!(
+        "{}.{}",
+        WRITES.fetch_add(1, Ordering::Relaxed),
+        TEMPORARY_FILE_EXTENSION
+    ));
+    fs::write(&temporary_path, bytes)?;
+    fs::rename(temporary_path, path)
+}
+
+/// Read file. Returns None if file does not exist
+fn read_optional(path: &Path) -> Result<Option<Vec<u8>>, BackendError> {
+    match fs::read(path) {
+        Ok(bytes) => Ok(Some(bytes)),
+        Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(error) => Err(error.into()),
+    }
+}
+
+/// Remove file if it exists
+fn remove_optional(path: &Path) -> Result<(), BackendError> {
+    match fs::remove_file(path) {
+        Ok(()) => Ok(()),
+        Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(()),
+        Err(error) => Err(error.into()),
+    }
+}
+
+/// Returns sorted indexes of files in directory that are named by indexes
+fn read_indexes(dir: &Path) -> Result<Vec<u64>, BackendError> {
+    let mut indexes = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        if let Some(index) = entry?
+            .file_name()
+            .to_str()
+            .and_then(|name| name.parse::<u64>().ok())
+        {
+            indexes.push(index);
+        }
+    }
+    indexes.sort_unstable();
+    Ok(indexes)
+}
@@ -0,0 +1,185 @@
+use std::{
+    collections::{BTreeMap, HashMap, HashSet, VecDeque},
+    sync::{Mutex, MutexGuard, PoisonError},
+};
+
+use crate::backend::{Backend, BackendError, StoredMagnetLink};
+
+/// [Backend] that keeps everything in memory. Nothing is saved when it is dropped, so it is
+/// useful for tests and short-lived nodes
+#[derive(Debug, Default)]
+pub struct MemoryBackend {
+    inner: Mutex<MemoryBackendInner>,
+}
+
+#[derive(Debug, Default)]
+struct MemoryBackendInner {
+    /// Artifacts by their keys
+    artifacts: HashMap<Vec<u8>, Vec<u8>>,
+    /// Magnet links and their pins by indexes
+    magnet_links: BTreeMap<u64, (Vec<u8>, bool)>,
+    /// Announcements by names of feeds
+    announcements: HashMap<String, MemoryFeed>,
+    /// Order in which artifacts were used
+    recency: Option<Vec<u8>>,
+}
+/// Announcements of one feed
+#[derive(Debug, Default)]
+struct MemoryFeed {
+    /// Keys of stored announcements
+    keys: HashSet<Vec<u8>>,
+    /// Keys and bytes of announcements in order they were inserted
+    announcements: VecDeque<(Vec<u8>, Vec<u8>)>,
+}
+
+impl MemoryBackend {
+    fn inner(&self) -> MutexGuard<'_, MemoryBackendInner> {
+        self.inner
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl Backend for MemoryBackend {
+    fn insert_artifact(&self, key: &[u8], artifact: &[u8]) -> Result<bool, BackendError> {
+        Ok(self
+            .inner()
+            .artifacts
+            .insert(key.to_vec(), artifact.to_vec())
+            .is_none())
+    }
+
+    fn get_artifact(&self, key: &[u8]) -> Result<Option<Vec<u8>>, BackendError> {
+        Ok(self.inner().artifacts.get(key).cloned())
+    }
+
+    fn contains_artifact(&self, key: &[u8]) -> Result<bool, BackendError> {
+        Ok(self.inner().artifacts.contains_key(key))
+    }
+
+    fn remove_artifact(&self, key: &[u8]) -> Result<Option<u64>, BackendError> {
+        Ok(self
+            .inner()
+            .artifacts
+            .remove(key)
+            .map(|artifact| artifact.len() as u64))
+    }
+
+    fn artifacts(&self) -> Result<Vec<(Vec<u8>, u64)>, BackendError> {
+        Ok(self
+            .inner()
+            .artifacts
+            .iter()
+            .map(|(key, artifact)| (key.clone(), artifact.len() as u64))
+            .collect())
+    }
+
+    fn save_recency(&self, recency: &[u8]) -> Result<(), BackendError> {
+        self.inner().recency = Some(recency.to_vec());
+        Ok(())
+    }
+
+    fn recency(&self) -> Result<Option<Vec<u8>>, BackendError> { Ok(self.inner().recency.clone()) }
+
+    fn insert_magnet_link(&self, magnet_link: &[u8]) -> Result<u64, BackendError> {
+        let mut inner = self.inner();
+        let index = match inner.magnet_links.keys().next_back() {
+            Some(index) => index + 1,
+            None => 1,
+        };
+        inner
+            .magnet_links
+            .insert(index, (magnet_link.to_vec(), true));
+        Ok(index)
+    }
+
+    fn get_magnet_link(&self, index: u64) -> Result<Option<StoredMagnetLink>, BackendError> {
+        Ok(self
+            .inner()
+            .magnet_links
+            .get(&index)
+            .map(|(bytes, pinned)| StoredMagnetLink {
+                index,
+                bytes: bytes.clone(),
+                pinned: *pinned,
+            }))
+    }
+
+    fn magnet_links(&self) -> Result<Vec<StoredMagnetLink>, BackendError> {
+        Ok(self
+            .inner()
+            .magnet_links
+            .iter()
+            .map(|(index, (bytes, pinned))| StoredMagnetLink {
+                index: *index,
+                bytes: bytes.clone(),
+                pinned: *pinned,
+            })
+            .collect())
+    }
+
+    fn remove_magnet_link(&self, index: u64) -> Result<Option<StoredMagnetLink>, BackendError> {
+        Ok(self
+            .inner()
+            .magnet_links
+            .remove(&index)
+            .map(|(bytes, pinned)| StoredMagnetLink {
+                index,
+                bytes,
+                pinned,
+            }))
+    }
+
+    fn set_magnet_link_pinned(&self, index: u64, pinned: bool) -> Result<bool, BackendError> {
+        match self
+            .inner()
+            .magnet_links
+            .get_mut(&index)
+        {
+            Some((_, stored_pinned)) => {
+                *stored_pinned = pinned;
+                Ok(true)
+            },
+            None => Ok(false),
+        }
+    }
+
+    fn insert_announcement(
+        &self,
+        topic: &str,
+        key: &[u8],
+        announcement: &[u8],
+        max_len: usize,
+    ) -> Result<bool, BackendError> {
+        let mut inner = self.inner();
+        let feed = inner
+            .announcements
+            .entry(topic.to_string())
+            .or_default();
+        if !feed.keys.insert(key.to_vec()) {
+            return Ok(false);
+        }
+        feed.announcements
+            .push_back((key.to_vec(), announcement.to_vec()));
+        while feed.announcements.len() > max_len {
+            if let Some((oldest, _)) = feed.announcements.pop_front() {
+                feed.keys.remove(&oldest);
+            }
+        }
+        Ok(true)
+    }
+
+    fn announcements(&self, topic: &str) -> Result<Vec<Vec<u8>>, BackendError> {
+        Ok(self
+            .inner()
+            .announcements
+            .get(topic)
+            .map(|feed| {
+                feed.announcements
+                    .iter()
+                    .map(|(_, announcement)| announcement.clone())
+                    .collect()
+            })
+            .unwrap_or_default())
+    }
+}
@@ -0,0 +1,87 @@
+mod filesystem_backend;
+mod memory_backend;
+mod sled_backend;
+
+pub use filesystem_backend::FilesystemBackend;
+pub use memory_backend::MemoryBackend;
+pub use sled_backend::SledBackend;
+
+#[derive(thiserror::Error, Debug)]
+pub enum BackendError {
+    #[error("Got sled error: {0}")]
+    /// Error whill occur when [SledBackend] can not read or write its trees
+    Sled(#[from] sled::Error),
+    #[error("Got IO error: {0}")]
+    /// Error whill occur when [FilesystemBackend] can not read or write its files
+    Io(#[from] std::io::Error),
+}
+/// Magnet link that stored in [Backend]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StoredMagnetLink {
+    /// Index that was given to magnet link when it was inserted
+    pub index: u64,
+    /// Bincode-based bytes of magnet link
+    pub bytes: Vec<u8>,
+    /// Magnet links are pinned when they are inserted
+    pub pinned: bool,
+}
+/// Storage of artifacts, magnet links and announcements that [crate::Database] is built on.
+/// Backend only stores bytes, so pins, quota and eviction work the same way on every backend.
+/// Artifacts are content-addressed: the same key always has the same bytes
+pub trait Backend: Send + Sync {
+    /// Insert artifact with given key. Returns false if artifact was already stored
+    fn insert_artifact(&self, key: &[u8], artifact: &[u8]) -> Result<bool, BackendError>;
+    /// Get artifact by its key
+    fn get_artifact(&self, key: &[u8]) -> Result<Option<Vec<u8>>, BackendError>;
+    /// Check if artifact with given key is stored
+    fn contains_artifact(&self, key: &[u8]) -> Result<bool, BackendError>;
+    /// Remove artifact by its key. Returns size of removed artifact or None if artifact was not
+    /// stored
+    fn remove_artifact(&self, key: &[u8]) -> Result<Option<u64>, BackendError>;
+    /// Returns keys of all stored artifacts with their sizes. Artifacts are not read, so it is
+    /// cheap enough to be called when database is opened
+    fn artifacts(&self) -> Result<Vec<(Vec<u8>, u64)>, BackendError>;
+    /// Store order in which artifacts were used. It replaces order that was stored before
+    fn save_recency(&self, recency: &[u8]) -> Result<(), BackendError>;
+    /// Returns order in which artifacts were used that was stored with [Backend::save_recency]
+    fn recency(&self) -> Result<Option<Vec<u8>>, BackendError>;
+    /// Insert pinned magnet link. Returns index of magnet link that is greater than indexes of
+    /// stored magnet links
+    fn insert_magnet_link(&self, magnet_link: &[u8]) -> Result<u64, BackendError>;
+    /// Get magnet link by its index
+    fn get_magnet_link(&self, index: u64) -> Result<Option<StoredMagnetLink>, BackendError>;
+    /// Returns all stored magnet links ordered by index
+    fn magnet_links(&self) -> Result<Vec<StoredMagnetLink>, BackendError>;
+    /// Remove magnet link by its index. Returns removed magnet link or None if it was not stored
+    fn remove_magnet_link(&self, index: u64) -> Result<Option<StoredMagnetLink>, BackendError>;
+    /// Pin or unpin magnet link. Returns false if magnet link is not stored
+    fn set_magnet_link_pinned(&self, index: u64, pinned: bool) -> Result<bool, BackendError>;
+    /// Append announcement with given key to feed with given name, unless announcement with the
+    /// same key is stored in it. Oldest announcements are removed, so feed keeps at most
+    /// `max_len` of them. Check and insert are atomic. Returns false if announcement was already
+    /// stored
+    fn insert_announcement(
+        &self,
+        topic: &str,
+        key: &[u8],
+        announcement: &[u8],
+        max_len: usize,
+    ) -> Result<bool, BackendError>;
+    /// Returns announcements of feed in order they were inserted
+    fn announcements(&self, topic: &str) -> Result<Vec<Vec<u8>>, BackendError>;
+}
+/// Join key and bytes of announcement into one stored entry, so key of announcement is known when
+/// it is removed as oldest one. Keys are hashes, so their length fits into one byte
+fn announcement_entry(key: &[u8], announcement: &[u8]) -> Vec<u8> {
+    let mut entry = Vec::with_capacity(1 + key.len() + announcement.len());
+    entry.push(key.len() as u8);
+    entry.extend_from_slice(key);
+    entry.extend_from_slice(announcement);
+    entry
+}
+/// Split entry of [announcement_entry] into key and bytes of announcement. Returns None if entry
+/// is broken
+fn split_announcement_entry(entry: &[u8]) -> Option<(&[u8], &[u8])> {
+    let (key_len, rest) = entry.split_first()?;
+    (rest.len() >= *key_len as usize).then(|| rest.split_at(*key_len as usize))
+}
@@ -0,0 +1,340 @@
+use std::{
+    path::Path,
+    sync::{Mutex, MutexGuard, PoisonError},
+};
+
+use byteorder::{BigEndian, ByteOrder, LittleEndian};
+use sled::{transaction::TransactionError, Transactional};
+
+use crate::backend::{
+    announcement_entry,
+    split_announcement_entry,
+    Backend,
+    BackendError,
+    StoredMagnetLink,
+};
+
+const MAGNET_TREE_NAME: &str = "magnets";
+const ARTIFACT_SIZE_TREE_NAME: &str = "artifact_sizes";
+const META_TREE_NAME: &str = "meta";
+/// Key in meta tree with order in which artifacts were used
+const RECENCY_KEY: &[u8] = b"recency";
+/// Key in meta tree that is set when sizes of artifacts that were stored before artifact size
+/// tree existed are written
+const ARTIFACT_SIZES_MIGRATED_KEY: &[u8] = b"artifact_sizes_migrated";
+const ANNOUNCEMENT_TREE_NAME: &str = "feed_announcements";
+const ANNOUNCEMENT_KEY_TREE_NAME: &str = "feed_announcement_keys";
+const UNPINNED_TREE_NAME: &str = "unpinned";
+/// Separator between name of feed and index or key in keys of announcement trees
+const ANNOUNCEMENT_KEY_SEPARATOR: u8 = 0;
+
+/// [Backend] that stores everything in [sled]. Artifacts are stored in default tree
+pub struct SledBackend {
+    /// Artifact database - is a storage that store artifacts. Key is an artifact id
+    artifact_db: sled::Db,
+    /// Artifact size tree - is a storage of sizes of stored artifacts, so they are known without
+    /// reading artifacts. Key is an artifact id
+    artifact_size_tree: sled::Tree,
+    /// Magnet tree - is a storage that store magnetlinks.
+    magnet_tree: sled::Tree,
+    /// Announcement tree - is a storage that store announcements from feeds. Key is a name of
+    /// feed and index of announcement in it, value is key and bytes of announcement
+    announcement_tree: sled::Tree,
+    /// Announcement key tree - is a storage of keys of stored announcements. Key is a name of
+    /// feed and key of announcement
+    announcement_key_tree: sled::Tree,
+    /// Lock that is held while announcement is checked, inserted and oldest ones are removed
+    announcement_lock: Mutex<()>,
+    /// Unpinned tree - is a storage of indexes of magnet links that are not pinned
+    unpinned_tree: sled::Tree,
+    /// Meta tree - is a storage of state of database itself: finished migrations and order in
+    /// which artifacts were used
+    meta_tree: sled::Tree,
+}
+
+impl SledBackend {
+    /// Open or create [sled] database in given path with size of page cache in bytes
+    pub fn open<P>(path: P, cache_capacity: u64) -> Result<Self, BackendError>
+    where
+        P: AsRef<Path>,
+    {
+        let artifact_db = sled::Config::new()
+            .path(path)
+            .cache_capacity(cache_capacity)
+            .open()?;
+        Self::from_db(artifact_db)
+    }
+    /// Create [SledBackend] from opened [sled::Db]
+    pub fn from_db(artifact_db: sled::Db) -> Result<Self, BackendError> {
+        let artifact_size_tree = artifact_db.open_tree(ARTIFACT_SIZE_TREE_NAME)?;
+        let meta_tree = artifact_db.open_tree(META_TREE_NAME)?;
+        // artifacts are read once, later their sizes are written together with them
+        if !meta_tree.contains_key(ARTIFACT_SIZES_MIGRATED_KEY)? {
+            for result in artifact_db.iter() {
+                let (key, artifact) = result?;
+                artifact_size_tree.insert(key, &(artifact.len() as u64).to_be_bytes())?;
+            }
+            meta_tree.insert(ARTIFACT_SIZES_MIGRATED_KEY, &[])?;
+        }
+        Ok(SledBackend {
+            artifact_size_tree,
+            magnet_tree: artifact_db.open_tree(MAGNET_TREE_NAME)?,
+            announcement_tree: artifact_db.open_tree(ANNOUNCEMENT_TREE_NAME)?,
+            announcement_key_tree: artifact_db.open_tree(ANNOUNCEMENT_KEY_TREE_NAME)?,
+            announcement_lock: Mutex::default(),
+            unpinned_tree: artifact_db.open_tree(UNPINNED_TREE_NAME)?,
+            meta_tree,
+            artifact_db,
+        })
+    }
+    /// Returns [StoredMagnetLink] from key and value of magnet tree
+    fn stored_magnet_link(
+        &self,
+        key: &[u8],
+        bytes: sled::IVec,
+    ) -> Result<StoredMagnetLink, BackendError> {
+        Ok(StoredMagnetLink {
+            index: u64_from_bytes(key),
+            bytes: bytes.to_vec(),
+            pinned: !self.unpinned_tree.contains_key(key)?,
+        })
+    }
+    fn announcement_lock(&self) -> MutexGuard<'_, ()> {
+        self.announcement_lock
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl Backend for SledBackend {
+    fn insert_artifact(&self, key: &[u8], artifact: &[u8]) -> Result<bool, BackendError> {
+        let size = (artifact.len() as u64).to_be_bytes();
+        (&*self.artifact_db, &self.artifact_size_tree)
+            .transaction(|(artifact_tree, artifact_size_tree)| {
+                // artifacts are content-addressed, so stored artifact is not written again
+                if artifact_tree.get(key)?.is_some() {
+                    return Ok(false);
+                }
+                artifact_tree.insert(key, artifact)?;
+                artifact_size_tree.insert(key, size.as_slice())?;
+                Ok(true)
+            })
+            .map_err(transaction_error)
+    }
+
+    fn get_artifact(&self, key: &[u8]) -> Result<Option<Vec<u8>>, BackendError> {
+        Ok(self
+            .artifact_db
+            .get(key)?
+            .map(|artifact| artifact.to_vec()))
+    }
+
+    fn contains_artifact(&self, key: &[u8]) -> Result<bool, BackendError> {
+        Ok(self.artifact_db.contains_key(key)?)
+    }
+
+    fn remove_artifact(&self, key: &[u8]) -> Result<Option<u64>, BackendError> {
+        (&*self.artifact_db, &self.artifact_size_tree)
+            .transaction(|(artifact_tree, artifact_size_tree)| {
+                artifact_size_tree.remove(key)?;
+                Ok(artifact_tree
+                    .remove(key)?
+                    .map(|artifact| artifact.len() as u64))
+            })
+            .map_err(transaction_error)
+    }
+
+    fn artifacts(&self) -> Result<Vec<(Vec<u8>, u64)>, BackendError> {
+        self.artifact_size_tree
+            .iter()
+            .map(|result| {
+                let (key, size) = result?;
+                Ok((key.to_vec(), BigEndian::read_u64(&size)))
+            })
+            .collect()
+    }
+
+    fn save_recency(&self, recency: &[u8]) -> Result<(), BackendError> {
+        self.meta_tree
+            .insert(RECENCY_KEY, recency)?;
+        Ok(())
+    }
+
+    fn recency(&self) -> Result<Option<Vec<u8>>, BackendError> {
+        Ok(self
+            .meta_tree
+            .get(RECENCY_KEY)?
+            .map(|recency| recency.to_vec()))
+    }
+
+    fn insert_magnet_link(&self, magnet_link: &[u8]) -> Result<u64, BackendError> {
+        let index = match self.magnet_tree.last()? {
+            Some((index, _)) => u64_from_bytes(&index) + 1,
+            None => 1,
+        };
+        self.magnet_tree
+            .insert(u64_to_bytes(index), magnet_link)?;
+        Ok(index)
+    }
+
+    fn get_magnet_link(&self, index: u64) -> Result<Option<StoredMagnetLink>, BackendError> {
+        let key = u64_to_bytes(index);
+        match self.magnet_tree.get(&key)? {
+            Some(bytes) => Ok(Some(self.stored_magnet_link(&key, bytes)?)),
+            None => Ok(None),
+        }
+    }
+
+    fn magnet_links(&self) -> Result<Vec<StoredMagnetLink>, BackendError> {
+        self.magnet_tree
+            .iter()
+            .map(|result| {
+                let (key, bytes) = result?;
+                self.stored_magnet_link(&key, bytes)
+            })
+            .collect()
+    }
+
+    fn remove_magnet_link(&self, index: u64) -> Result<Option<StoredMagnetLink>, BackendError> {
+        let key = u64_to_bytes(index);
+        (&self.magnet_tree, &self.unpinned_tree)
+            .transaction(|(magnet_tree, unpinned_tree)| {
+                let Some(bytes) = magnet_tree.remove(key.as_slice())? else {
+                    return Ok(None);
+                };
+                let pinned = unpinned_tree
+                    .remove(key.as_slice())?
+                    .is_none();
+                Ok(Some(StoredMagnetLink {
+                    index,
+                    bytes: bytes.to_vec(),
+                    pinned,
+                }))
+            })
+            .map_err(transaction_error)
+    }
+
+    fn set_magnet_link_pinned(&self, index: u64, pinned: bool) -> Result<bool, BackendError> {
+        let key = u64_to_bytes(index);
+        (&self.magnet_tree, &self.unpinned_tree)
+            .transaction(|(magnet_tree, unpinned_tree)| {
+                if magnet_tree
+                    .get(key.as_slice())?
+                    .is_none()
+                {
+                    return Ok(false);
+                }
+                match pinned {
+                    true => unpinned_tree.remove(key.as_slice())?,
+                    false => unpinned_tree.insert(key.as_slice(), &[])?,
+                };
+                Ok(true)
+            })
+            .map_err(transaction_error)
+    }
+
+    fn insert_announcement(
+        &self,
+        topic: &str,
+        key: &[u8],
+        announcement: &[u8],
+        max_len: usize,
+    ) -> Result<bool, BackendError> {
+        let _lock = self.announcement_lock();
+        let prefix = announcement_key_prefix(topic);
+        let announcement_key = [prefix.as_slice(), key].concat();
+        if self
+            .announcement_key_tree
+            .contains_key(&announcement_key)?
+        {
+            return Ok(false);
+        }
+        let mut indexes = self
+            .announcement_tree
+            .scan_prefix(&prefix)
+            .keys();
+        let first = match indexes.next() {
+            Some(first) => Some(BigEndian::read_u64(&first?[prefix.len()..])),
+            None => None,
+        };
+        let index = match indexes.next_back() {
+            Some(last) => BigEndian::read_u64(&last?[prefix.len()..]) + 1,
+            None => first.map_or(0, |first| first + 1),
+        };
+        // indexes of feed are contiguous, because only oldest announcements are removed
+        let mut removed = Vec::new();
+        for oldest in first.unwrap_or(index)..(index + 1).saturating_sub(max_len as u64) {
+            let oldest_key = announcement_index_key(&prefix, oldest);
+            if let Some(entry) = self
+                .announcement_tree
+                .get(&oldest_key)?
+            {
+                let oldest_announcement_key = split_announcement_entry(&entry)
+                    .map(|(key, _)| [prefix.as_slice(), key].concat());
+                removed.push((oldest_key, oldest_announcement_key));
+            }
+        }
+        let index_key = announcement_index_key(&prefix, index);
+        let entry = announcement_entry(key, announcement);
+        (&self.announcement_tree, &self.announcement_key_tree)
+            .transaction(|(announcement_tree, announcement_key_tree)| {
+                announcement_tree.insert(index_key.as_slice(), entry.as_slice())?;
+                announcement_key_tree.insert(announcement_key.as_slice(), &[])?;
+                for (oldest_key, oldest_announcement_key) in &removed {
+                    announcement_tree.remove(oldest_key.as_slice())?;
+                    if let Some(oldest_announcement_key) = oldest_announcement_key {
+                        announcement_key_tree.remove(oldest_announcement_key.as_slice())?;
+                    }
+                }
+                Ok(())
+            })
+            .map_err(transaction_error)?;
+        Ok(true)
+    }
+
+    fn announcements(&self, topic: &str) -> Result<Vec<Vec<u8>>, BackendError> {
+        let mut announcements = Vec::new();
+        for entry in self
+            .announcement_tree
+            .scan_prefix(announcement_key_prefix(topic))
+            .values()
+        {
+            if let Some((_, announcement)) = split_announcement_entry(&entry?) {
+                announcements.push(announcement.to_vec());
+            }
+        }
+        Ok(announcements)
+    }
+}
+
+/// Convert error of transaction that is never aborted into [BackendError]
+fn transaction_error(error: TransactionError<()>) -> BackendError {
+    match error {
+        TransactionError::Storage(error) => error.into(),
+        TransactionError::Abort(()) => {
+            sled::Error::ReportableBug("transaction of database was aborted".to_string()).into()
+        },
+    }
+}
+
+/// Returns prefix of keys of announcements that published in feed
+fn announcement_key_prefix(topic: &str) -> Vec<u8> {
+    let mut prefix = topic.as_bytes().to_vec();
+    prefix.push(ANNOUNCEMENT_KEY_SEPARATOR);
+    prefix
+}
+
+/// Returns key of announcement with given index in announcement tree
+fn announcement_index_key(prefix: &[u8], index: u64) -> Vec<u8> {
+    [prefix, index.to_be_bytes().as_slice()].concat()
+}
+
+/// Convert [u64] into bytes this fn used when we are store magnets
+fn u64_to_bytes(val: u64) -> Vec<u8> {
+    let mut buf = [0; 8];
+    LittleEndian::write_u64(&mut buf, val);
+    buf.to_vec()
+}
+
+/// Convert bytes into u64 this fn used when we are store magnets
+fn u64_from_bytes(bytes: &[u8]) -> u64 { LittleEndian::read_u64(bytes) }
@@ -1,116 +1,947 @@
-use std::path::Path;
+mod backend;
+#[cfg(test)]
+mod test;
 
-use byteorder::{ByteOrder, LittleEndian};
-use log::error;
-use quanta_artifact::{Artifact, MagnetLink};
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+    path::Path,
+    str::FromStr,
+    sync::{Arc, Condvar, Mutex, MutexGuard, PoisonError},
+};
 
-const MAGNET_TREE_NAME: &str = "magnets";
+pub use backend::{
+    Backend,
+    BackendError,
+    FilesystemBackend,
+    MemoryBackend,
+    SledBackend,
+    StoredMagnetLink,
+};
+use log::{error, info, warn};
+use quanta_artifact::{Artifact, ArtifactId, MagnetLink};
+use quanta_crypto::HashValue;
+use quanta_feed::{Announcement, FeedStorage};
+use serde::Deserialize;
+
+/// Default size of [sled] page cache in bytes
+const DEFAULT_CACHE_CAPACITY: u64 = 1024 * 1024 * 1024;
+/// Default count of announcements that are kept in one feed
+const DEFAULT_MAX_FEED_ANNOUNCEMENTS: usize = 1000;
 
 #[derive(thiserror::Error, Debug)]
 pub enum DatabaseError {
-    #[error("Got err when trying to open ArtifactDatabase: {0}")]
-    /// Error whill occur in [Database::new] call when we are trying to open artifact database from
-    /// path that we are get
-    ArtifactStorageOpen(sled::Error),
-    #[error("Got err when trying to open MagnetTreeDatabase: {0}")]
-    /// Error whill occur in [Database::new] call when we are trying to open magnet tree database
-    /// from  path that we are get
-    MagnetTreeStorageOpen(sled::Error),
+    #[error("Got err when trying to open storage backend: {0}")]
+    /// Error whill occur in [Database::new] call when we are trying to open [Backend] from path
+    /// that we are get
+    StorageOpen(BackendError),
     #[error("Got error when trying to insert artifact into storage: {0}")]
     /// Err whill occur when we are call [Database::insert_artifact]
-    ArtifactInsert(sled::Error),
+    ArtifactInsert(BackendError),
+    #[error("Got error when trying to get artifact from storage: {0}")]
+    /// Err whill occur when we are call [Database::get_artifact]
+    ArtifactGet(BackendError),
     #[error("Got error when trying to insert magnet link into tree: {0}")]
     /// Err whill occur when we are call [Database::insert_magnet_link]
-    MagnetInsert(sled::Error),
+    MagnetInsert(BackendError),
+    #[error("Got error when trying to get magnet links from tree: {0}")]
+    /// Err whill occur when we are call [Database::get_magnet_links]
+    MagnetGet(BackendError),
+    #[error("Got error when trying to delete magnet link from tree: {0}")]
+    /// Err whill occur when we are call [Database::delete_magnet_link]
+    MagnetDelete(BackendError),
+    #[error("Got error when trying to pin or unpin magnet link: {0}")]
+    /// Err whill occur when we are call [Database::pin_magnet_link] or
+    /// [Database::unpin_magnet_link]
+    MagnetPin(BackendError),
+    #[error("Got error when trying to remove unreferenced artifacts: {0}")]
+    /// Err whill occur when we are call [Database::gc] or evict artifacts in
+    /// [Database::insert_artifact]
+    ArtifactRemove(BackendError),
     #[error("Got error when converting MagnetLink into json")]
     /// Error whill occur when trying to convert magnet link into json-bytes
     MagnetToJson(quanta_artifact::MagnetError),
     #[error("Got error when converting magnetlink from json")]
     /// Error whill occur when trying to convert json-bytes into magnet-link
     MagnetFromJson(quanta_artifact::MagnetError),
-    #[error("Got unexpected sled error: {0}")]
-    SledUnexpected(#[from] sled::Error),
+    #[error("Got error when trying to insert announcement into tree: {0}")]
+    /// Err whill occur when we are call [Database::insert_announcement]
+    AnnouncementInsert(BackendError),
+    #[error("Got error when trying to get announcements from tree: {0}")]
+    /// Err whill occur when we are call [Database::get_announcements]
+    AnnouncementGet(BackendError),
+    #[error("Got error when converting announcement into bincode: {0}")]
+    /// Error whill occur when trying to convert announcement into bincode-bytes
+    AnnouncementToBincode(quanta_feed::AnnouncementError),
+    #[error("Got error when trying to save order of use of artifacts: {0}")]
+    /// Err whill occur when we are call [Database::save_recency]
+    RecencySave(BackendError),
+    #[error("Storage is full: {size} bytes are used of {max_size}")]
+    /// Error whill occur in [Database::insert_artifact] when [DatabaseConfig::max_size] is reached
+    StorageFull { size: u64, max_size: u64 },
 }
-/// Local database that manages magnets and artifacts
-pub struct Database {
-    /// Artifact DB - is a storage that store artifacts
-    artifact_db: sled::Db,
-    /// Magnet tree - is a storage that store magnetlinks.
-    magnet_tree: sled::Tree,
+/// Kind of [Backend] that [Database::with_config] opens
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StorageBackend {
+    /// [SledBackend]
+    #[default]
+    Sled,
+    /// [MemoryBackend], nothing is saved between restarts
+    Memory,
+    /// [FilesystemBackend]
+    Filesystem,
+}
+
+impl FromStr for StorageBackend {
+    type Err = String;
+
+    fn from_str(backend: &str) -> Result<Self, Self::Err> {
+        match backend {
+            "sled" => Ok(StorageBackend::Sled),
+            "memory" => Ok(StorageBackend::Memory),
+            "filesystem" => Ok(StorageBackend::Filesystem),
+            _ => Err(format!("unknown storage backend: {backend}")),
+        }
+    }
+}
+/// Configuration of [Database]
+#[derive(Debug, Clone)]
+pub struct DatabaseConfig {
+    /// Kind of [Backend] where everything is stored
+    backend: StorageBackend,
+    /// Size of [sled] page cache in bytes
+    cache_capacity: u64,
+    /// Max size of stored artifacts in bytes. When it is reached least recently used artifacts
+    /// that are not pinned are evicted. If None size is not limited
+    max_size: Option<u64>,
+    /// Max count of announcements in one feed. When it is reached oldest announcements are
+    /// removed
+    max_feed_announcements: usize,
+}
+
+impl DatabaseConfig {
+    /// Set kind of [Backend]
+    pub fn with_backend(mut self, backend: StorageBackend) -> Self {
+        self.backend = backend;
+        self
+    }
+    /// Set size of [sled] page cache in bytes
+    pub fn with_cache_capacity(mut self, cache_capacity: u64) -> Self {
+        self.cache_capacity = cache_capacity;
+        self
+    }
+    /// Set max size of stored artifacts in bytes
+    pub fn with_max_size(mut self, max_size: Option<u64>) -> Self {
+        self.max_size = max_size;
+        self
+    }
+    /// Set max count of announcements in one feed
+    pub fn with_max_feed_announcements(mut self, max_feed_announcements: usize) -> Self {
+        self.max_feed_announcements = max_feed_announcements;
+        self
+    }
+    /// returns kind of backend
+    pub fn backend(&self) -> StorageBackend { self.backend }
+    /// returns size of page cache
+    pub fn cache_capacity(&self) -> u64 { self.cache_capacity }
+    /// returns max size of stored artifacts
+    pub fn max_size(&self) -> Option<u64> { self.max_size }
+    /// returns max count of announcements in one feed
+    pub fn max_feed_announcements(&self) -> usize { self.max_feed_announcements }
+}
+
+impl Default for DatabaseConfig {
+    fn default() -> Self {
+        Self {
+            backend: StorageBackend::default(),
+            cache_capacity: DEFAULT_CACHE_CAPACITY,
+            max_size: None,
+            max_feed_announcements: DEFAULT_MAX_FEED_ANNOUNCEMENTS,
+        }
+    }
+}
+/// Result of [Database::gc] or eviction of cached artifacts
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct GarbageCollection {
+    /// Count of artifacts that were removed
+    pub artifacts: usize,
+    /// Size of artifacts that were removed in bytes
+    pub bytes: u64,
+}
+/// Artifacts that were inserted with [ArtifactsHold::insert_artifact] are not removed by
+/// [Database::gc] or evicted until hold is dropped
+pub struct ArtifactsHold<'a> {
+    /// Database where artifacts are held
+    database: &'a Database,
+    /// Keys of artifacts that are held
+    artifact_ids: Vec<Vec<u8>>,
+}
+
+impl ArtifactsHold<'_> {
+    /// Hold artifact and insert it into [Database]
+    pub fn insert_artifact(&mut self, artifact: Artifact) -> Result<(), DatabaseError> {
+        self.hold(&artifact.id);
+        self.database.insert_artifact(artifact)
+    }
+    /// Hold artifact that is stored or is inserted later, e.g. artifact of file that is
+    /// downloading
+    pub fn hold(&mut self, artifact_id: &ArtifactId) {
+        let artifact_id = artifact_id.to_bytes();
+        {
+            let mut state = self.database.state();
+            *state
+                .held
+                .entry(artifact_id.clone())
+                .or_default() += 1;
+            state.refresh_cached(&artifact_id);
+        }
+        self.artifact_ids.push(artifact_id);
+    }
+}
+
+impl Drop for ArtifactsHold<'_> {
+    fn drop(&mut self) {
+        let mut state = self.database.state();
+        for artifact_id in &self.artifact_ids {
+            if let Some(count) = state.held.get_mut(artifact_id) {
+                *count -= 1;
+                if *count == 0 {
+                    state.held.remove(artifact_id);
+                    state.refresh_cached(artifact_id);
+                }
+            }
+        }
+    }
+}
+/// State of stored artifacts that [Database] keeps in memory. It is built from [Backend] when
+/// database is opened
+#[derive(Debug, Default)]
+struct DatabaseState {
+    /// How many pinned magnet links use artifact. Artifacts with references are pinned, others
+    /// are cached: they are evicted when [DatabaseConfig::max_size] is reached and removed by
+    /// [Database::gc]
+    references: HashMap<Vec<u8>, u64>,
+    /// Tick of [DatabaseState::clock] when artifact was used last time. Contains every stored
+    /// artifact
+    access: HashMap<Vec<u8>, u64>,
+    /// Cached artifacts (not pinned and not held) ordered by last use, so eviction takes oldest
+    /// ones without skipping artifacts that can not be removed
+    lru: BTreeMap<u64, Vec<u8>>,
+    /// Tick that is given to next use of artifact. It only grows, so order of
+    /// [DatabaseState::lru] is order of use
+    clock: u64,
+    /// Artifacts that are held by [ArtifactsHold]s with count of holds
+    held: HashMap<Vec<u8>, usize>,
+    /// Sizes of stored artifacts in bytes
+    sizes: HashMap<Vec<u8>, u64>,
+    /// Artifacts that were taken for eviction or [Database::gc] and are removed from [Backend]
+    /// right now. Insert of the same artifact waits until removal is finished
+    removing: HashSet<Vec<u8>>,
+    /// Artifacts that are written to [Backend] right now. Insert of the same artifact waits
+    /// until it is written, so removal never races with a write of the same artifact
+    inserting: HashSet<Vec<u8>>,
+    /// Size of stored artifacts and artifacts that are inserted right now in bytes. Used for
+    /// checking [DatabaseConfig::max_size] without walking artifacts on every insert
+    size: u64,
+    /// Count of stored magnet links
+    magnets: usize,
+    /// Indexes of stored magnet links by their merkle roots. Magnet links are served to other
+    /// peers by merkle root, so announcements do not need to carry them
+    magnet_roots: HashMap<Vec<u8>, BTreeSet<u64>>,
 }
 
+impl DatabaseState {
+    /// Remember that artifact was used now, so it is evicted after artifacts that were used
+    /// earlier
+    fn touch(&mut self, artifact_id: &[u8]) {
+        let tick = self.clock;
+        self.clock += 1;
+        if let Some(previous) = self
+            .access
+            .insert(artifact_id.to_vec(), tick)
+        {
+            self.lru.remove(&previous);
+        }
+        if self.is_removable(artifact_id) {
+            self.lru
+                .insert(tick, artifact_id.to_vec());
+        }
+    }
+    /// Add stored artifact to [DatabaseState::lru] or remove it from there after it was pinned,
+    /// unpinned, held or released
+    fn refresh_cached(&mut self, artifact_id: &[u8]) {
+        let Some(&tick) = self.access.get(artifact_id) else {
+            return;
+        };
+        if self.is_removable(artifact_id) {
+            self.lru
+                .insert(tick, artifact_id.to_vec());
+        } else {
+            self.lru.remove(&tick);
+        }
+    }
+    /// Remember artifact that was stored. Its size is already counted in [DatabaseState::size]
+    fn remember(&mut self, artifact_id: &[u8], artifact_size: u64) {
+        self.sizes
+            .insert(artifact_id.to_vec(), artifact_size);
+        self.touch(artifact_id);
+    }
+    /// Forget artifact that is removed. Returns its size
+    fn forget(&mut self, artifact_id: &[u8]) -> u64 {
+        if let Some(tick) = self.access.remove(artifact_id) {
+            self.lru.remove(&tick);
+        }
+        let artifact_size = self
+            .sizes
+            .remove(artifact_id)
+            .unwrap_or(0);
+        self.size = self.size.saturating_sub(artifact_size);
+        artifact_size
+    }
+    /// Check that cached artifacts take at least given count of bytes, so they can be evicted
+    /// for it
+    fn has_cached(&self, bytes: u64) -> bool {
+        let mut cached = 0;
+        for artifact_id in self.lru.values() {
+            if cached >= bytes {
+                break;
+            }
+            cached += self
+                .sizes
+                .get(artifact_id)
+                .copied()
+                .unwrap_or(0);
+        }
+        cached >= bytes
+    }
+    /// Take least recently used cached artifacts until given count of bytes is freed. Taken
+    /// artifacts are forgotten and marked as removing, caller removes them from [Backend] with
+    /// [Database::remove_artifacts]
+    fn take_cached(&mut self, bytes: u64) -> Vec<(Vec<u8>, u64)> {
+        let mut taken = Vec::new();
+        let mut freed = 0;
+        while freed < bytes {
+            let Some((_, artifact_id)) = self.lru.pop_first() else {
+                break;
+            };
+            let artifact_size = self.forget(&artifact_id);
+            freed += artifact_size;
+            self.removing
+                .insert(artifact_id.clone());
+            taken.push((artifact_id, artifact_size));
+        }
+        taken
+    }
+    /// Check that artifact is not pinned and not held, so it can be removed
+    fn is_removable(&self, artifact_id: &[u8]) -> bool {
+        !self.held.contains_key(artifact_id) &&
+            !self
+                .references
+                .contains_key(artifact_id)
+    }
+    /// Increment or decrement count of references of artifacts. Artifact without references is
+    /// forgotten
+    fn change_references(&mut self, artifact_ids: &HashSet<Vec<u8>>, increment: bool) {
+        for artifact_id in artifact_ids {
+            let count = self
+                .references
+                .get(artifact_id)
+                .copied()
+                .unwrap_or(0);
+            let count = match increment {
+                true => count + 1,
+                false => count.saturating_sub(1),
+            };
+            if count == 0 {
+                self.references.remove(artifact_id);
+            } else {
+                self.references
+                    .insert(artifact_id.clone(), count);
+            }
+            self.refresh_cached(artifact_id);
+        }
+    }
+    /// Add or remove index of magnet link with given merkle root
+    fn change_magnet_root(&mut self, merkle_root: Vec<u8>, index: u64, insert: bool) {
+        let indexes = self
+            .magnet_roots
+            .entry(merkle_root.clone())
+            .or_default();
+        match insert {
+            true => indexes.insert(index),
+            false => indexes.remove(&index),
+        };
+        if indexes.is_empty() {
+            self.magnet_roots.remove(&merkle_root);
+        }
+    }
+}
+/// Database that manages magnets and artifacts. Everything is stored in [Backend], while
+/// references of artifacts, order of their use and holds are kept in memory. Order of use is
+/// saved with [Database::save_recency], artifacts that are missing in saved order are evicted
+/// first
+pub struct Database {
+    /// Backend where artifacts, magnet links and announcements are stored
+    backend: Box<dyn Backend>,
+    /// State of stored artifacts. Lock is held only for bookkeeping, [Backend] is read and written
+    /// without it
+    state: Mutex<DatabaseState>,
+    /// Notified when artifacts that were taken for removal are removed from [Backend] or
+    /// artifacts that were inserted are written to it
+    written: Condvar,
+    /// Lock that is held while magnet links are inserted, pinned or deleted, so references of
+    /// artifacts are changed in the same order as magnet links in [Backend]
+    magnet_lock: Mutex<()>,
+    /// Max size of stored artifacts in bytes
+    max_size: Option<u64>,
+    /// Max count of announcements in one feed
+    max_feed_announcements: usize,
+}
+/// Local database that manages magnets and artifacts
 impl Database {
-    /// Creates new [Database]. Open [sled::Db] with given path
+    /// Creates new [Database]. Open [SledBackend] with given path
     pub fn new<P>(path: P) -> Result<Self, DatabaseError>
     where
         P: AsRef<Path>,
     {
-        let artifact_db = sled::open(&path).map_err(DatabaseError::ArtifactStorageOpen)?;
-        let magnet_tree = artifact_db
-            .open_tree(MAGNET_TREE_NAME)
-            .map_err(DatabaseError::MagnetTreeStorageOpen)?;
-
+        Self::with_config(path, DatabaseConfig::default())
+    }
+    /// Creates new [Database] with given [DatabaseConfig]. Open [Backend] of
+    /// [DatabaseConfig::backend] with given path
+    pub fn with_config<P>(path: P, config: DatabaseConfig) -> Result<Self, DatabaseError>
+    where
+        P: AsRef<Path>,
+    {
+        let backend: Box<dyn Backend> = match config.backend() {
+            StorageBackend::Sled => Box::new(
+                SledBackend::open(path, config.cache_capacity())
+                    .map_err(DatabaseError::StorageOpen)?,
+            ),
+            StorageBackend::Memory => Box::<MemoryBackend>::default(),
+            StorageBackend::Filesystem => {
+                Box::new(FilesystemBackend::open(path).map_err(DatabaseError::StorageOpen)?)
+            },
+        };
+        Self::with_boxed_backend(backend, config)
+    }
+    /// Creates new [Database] on top of given [Backend]. [DatabaseConfig::backend] and
+    /// [DatabaseConfig::cache_capacity] are not used
+    pub fn with_backend<B>(backend: B, config: DatabaseConfig) -> Result<Self, DatabaseError>
+    where
+        B: Backend + 'static,
+    {
+        Self::with_boxed_backend(Box::new(backend), config)
+    }
+    /// Build state of stored artifacts from [Backend] and create [Database]
+    fn with_boxed_backend(
+        backend: Box<dyn Backend>,
+        config: DatabaseConfig,
+    ) -> Result<Self, DatabaseError> {
+        let mut state = DatabaseState::default();
+        let mut artifacts = backend
+            .artifacts()
+            .map_err(DatabaseError::StorageOpen)?;
+        let recency = match backend.recency() {
+            Ok(recency) => decode_recency(&recency.unwrap_or_default()),
+            Err(error) => {
+                warn!(
+                    "Got error when reading order of use of artifacts: {}",
+                    error
+                );
+                HashMap::new()
+            },
+        };
+        // artifacts that are missing in saved order are older than ones in it
+        artifacts.sort_by_key(|(artifact_id, _)| {
+            recency
+                .get(artifact_id)
+                .map_or(0, |position| position + 1)
+        });
+        for (artifact_id, artifact_size) in artifacts {
+            state.remember(&artifact_id, artifact_size);
+            state.size += artifact_size;
+        }
+        let magnet_links = backend
+            .magnet_links()
+            .map_err(DatabaseError::StorageOpen)?;
+        state.magnets = magnet_links.len();
+        for stored_magnet_link in magnet_links {
+            // magnet link that can not be read does not have references and is not served
+            if let Ok(magnet_link) = MagnetLink::from_bincode(stored_magnet_link.bytes) {
+                state.change_magnet_root(magnet_root(&magnet_link), stored_magnet_link.index, true);
+                if stored_magnet_link.pinned {
+                    state.change_references(&unique_artifact_ids(&magnet_link), true);
+                }
+            }
+        }
+        info!(
+            "Opened database with {} artifacts ({} bytes) and {} magnet links",
+            state.access.len(),
+            state.size,
+            state.magnets
+        );
         Ok(Database {
-            artifact_db,
-            magnet_tree,
+            backend,
+            state: Mutex::new(state),
+            written: Condvar::new(),
+            magnet_lock: Mutex::default(),
+            max_size: config.max_size(),
+            max_feed_announcements: config.max_feed_announcements(),
         })
     }
-    /// Insert [Artifact] into Database... Key in t
+    /// Returns state of stored artifacts
+    fn state(&self) -> MutexGuard<'_, DatabaseState> {
+        self.state
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+    /// Wait until artifacts that are inserted or removed right now are written to [Backend]
+    fn wait_written<'a>(
+        &self,
+        state: MutexGuard<'a, DatabaseState>,
+    ) -> MutexGuard<'a, DatabaseState> {
+        self.written
+            .wait(state)
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+    /// Returns lock of magnet links
+    fn magnet_lock(&self) -> MutexGuard<'_, ()> {
+        self.magnet_lock
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+    /// Insert [Artifact] into Database... Key in t. Artifact is cached until magnet link that
+    /// uses it is pinned. When [DatabaseConfig::max_size] is reached least recently used cached
+    /// artifacts are evicted
     pub fn insert_artifact(&self, artifact: Artifact) -> Result<(), DatabaseError> {
-        self.artifact_db
-            .insert(artifact.id.to_bytes(), artifact.data)
-            .map_err(DatabaseError::ArtifactInsert)?;
-        Ok(())
+        let artifact_id = artifact.id.to_bytes();
+        let artifact_size = artifact.data.len() as u64;
+        let evicted = {
+            let mut state = self.state();
+            // artifact that is removed right now is written again after removal and artifact
+            // that is inserted right now is stored after its insert
+            while state.removing.contains(&artifact_id) || state.inserting.contains(&artifact_id) {
+                state = self.wait_written(state);
+            }
+            // artifacts are content-addressed, so stored artifact does not need more space
+            if state.access.contains_key(&artifact_id) {
+                state.touch(&artifact_id);
+                return Ok(());
+            }
+            let mut evicted = Vec::new();
+            if let Some(max_size) = self.max_size {
+                let needed = (state.size + artifact_size).saturating_sub(max_size);
+                if !state.has_cached(needed) {
+                    return Err(DatabaseError::StorageFull {
+                        size: state.size,
+                        max_size,
+                    });
+                }
+                evicted = state.take_cached(needed);
+            }
+            // size is reserved before artifact is written, so concurrent inserts do not exceed
+            // max size
+            state.size += artifact_size;
+            state
+                .inserting
+                .insert(artifact_id.clone());
+            evicted
+        };
+        let inserted = match self.remove_artifacts(evicted) {
+            Ok(eviction) => {
+                if eviction.artifacts > 0 {
+                    info!(
+                        "Evicted {} cached artifacts ({} bytes)",
+                        eviction.artifacts, eviction.bytes
+                    );
+                }
+                self.backend
+                    .insert_artifact(&artifact_id, &artifact.data)
+                    .map_err(DatabaseError::ArtifactInsert)
+            },
+            Err(error) => Err(DatabaseError::ArtifactRemove(error)),
+        };
+        let mut state = self.state();
+        state.inserting.remove(&artifact_id);
+        self.written.notify_all();
+        match inserted {
+            Ok(_) => {
+                state.remember(&artifact_id, artifact_size);
+                Ok(())
+            },
+            Err(error) => {
+                state.size -= artifact_size;
+                Err(error)
+            },
+        }
     }
-    /// Last index that be inserted into storage.
-    fn magnet_tree_last_index(&self) -> Result<u64, DatabaseError> {
-        match self.magnet_tree.last()? {
-            Some((index, _)) => Ok(u64_from_bytes(index.to_vec())),
-            None => Ok(0),
+    /// Get [Artifact] from Database by its [ArtifactId]
+    pub fn get_artifact(
+        &self,
+        artifact_id: &ArtifactId,
+    ) -> Result<Option<Artifact>, DatabaseError> {
+        Ok(self
+            .get_artifact_bytes(&artifact_id.to_bytes())
+            .map_err(DatabaseError::ArtifactGet)?
+            .map(Artifact::new))
+    }
+    /// Get bytes of artifact from [Backend]. Artifact is used, so it is evicted later
+    fn get_artifact_bytes(&self, artifact_id: &[u8]) -> Result<Option<Vec<u8>>, BackendError> {
+        let artifact = self.backend.get_artifact(artifact_id)?;
+        let mut state = self.state();
+        // artifact can be removed while we are reading it
+        if artifact.is_some() && state.access.contains_key(artifact_id) {
+            state.touch(artifact_id);
         }
+        Ok(artifact)
+    }
+    /// Remove artifacts that were taken with [DatabaseState::take_cached] from [Backend] and wake
+    /// up inserts that wait for them. Artifacts that were not removed because of error stay in
+    /// [Backend] until database is opened again
+    fn remove_artifacts(
+        &self,
+        artifacts: Vec<(Vec<u8>, u64)>,
+    ) -> Result<GarbageCollection, BackendError> {
+        let mut collection = GarbageCollection::default();
+        if artifacts.is_empty() {
+            return Ok(collection);
+        }
+        let mut result = Ok(());
+        for (artifact_id, artifact_size) in &artifacts {
+            match self
+                .backend
+                .remove_artifact(artifact_id)
+            {
+                Ok(_) => {
+                    collection.artifacts += 1;
+                    collection.bytes += artifact_size;
+                },
+                Err(error) => result = Err(error),
+            }
+        }
+        let mut state = self.state();
+        for (artifact_id, _) in &artifacts {
+            state.removing.remove(artifact_id);
+        }
+        self.written.notify_all();
+        result.map(|()| collection)
+    }
+    /// Returns size of stored artifact in bytes without reading it or None if artifact is not
+    /// stored
+    pub fn artifact_size(&self, artifact_id: &ArtifactId) -> Option<u64> {
+        self.state()
+            .sizes
+            .get(&artifact_id.to_bytes())
+            .copied()
+    }
+    /// Returns ids of all artifacts that stored in [Database]
+    pub fn get_artifact_ids(&self) -> Result<Vec<ArtifactId>, DatabaseError> {
+        Ok(self
+            .backend
+            .artifacts()
+            .map_err(DatabaseError::ArtifactGet)?
+            .into_iter()
+            .filter_map(|(key, _)| ArtifactId::from_bytes(key.as_ref()).ok())
+            .collect())
+    }
+    /// Returns count of artifacts that stored in [Database]
+    pub fn artifact_count(&self) -> usize { self.state().access.len() }
+    /// Returns count of magnet links that stored in [Database]
+    pub fn magnet_count(&self) -> usize { self.state().magnets }
+    /// Returns size of stored artifacts in bytes. Same size is used for checking
+    /// [DatabaseConfig::max_size]
+    pub fn size(&self) -> u64 { self.state().size }
+    /// Save order in which artifacts were used into [Backend], so least recently used artifacts
+    /// are evicted first after database is opened again. Order is kept in memory, so it should be
+    /// saved from time to time and before exit
+    pub fn save_recency(&self) -> Result<(), DatabaseError> {
+        let recency = {
+            let state = self.state();
+            let mut artifact_ids = state
+                .access
+                .iter()
+                .map(|(artifact_id, tick)| (*tick, artifact_id))
+                .collect::<Vec<_>>();
+            artifact_ids.sort_unstable();
+            encode_recency(
+                artifact_ids
+                    .into_iter()
+                    .map(|(_, artifact_id)| artifact_id.as_slice()),
+            )
+        };
+        self.backend
+            .save_recency(&recency)
+            .map_err(DatabaseError::RecencySave)
     }
     /// Insert [MagnetLink] into Tree... Key in is just a indexed-integer.
-    /// Value its a json-based bytes of magnet link
+    /// Value its a json-based bytes of magnet link. Magnet link is pinned, so its artifacts are
+    /// not evicted or removed by [Database::gc]
     pub fn insert_magnet_link(&self, magnet_link: MagnetLink) -> Result<u64, DatabaseError> {
-        let magnet_tree_last_index = self.magnet_tree_last_index()? + 1;
-        self.magnet_tree
-            .insert(
-                u64_to_bytes(magnet_tree_last_index),
-                magnet_link
-                    .to_bincode()
-                    .map_err(DatabaseError::MagnetToJson)?,
-            )
+        let magnet_bytes = magnet_link
+            .to_bincode()
+            .map_err(DatabaseError::MagnetToJson)?;
+        let _magnet_lock = self.magnet_lock();
+        let index = self
+            .backend
+            .insert_magnet_link(&magnet_bytes)
             .map_err(DatabaseError::MagnetInsert)?;
-        Ok(magnet_tree_last_index)
+        let mut state = self.state();
+        state.change_references(&unique_artifact_ids(&magnet_link), true);
+        state.change_magnet_root(magnet_root(&magnet_link), index, true);
+        state.magnets += 1;
+        Ok(index)
+    }
+    /// Delete [MagnetLink] with given index from Tree and remove references of its artifacts.
+    /// Artifacts are not removed until [Database::gc]. Returns false if magnet link does not exist
+    pub fn delete_magnet_link(&self, index: u64) -> Result<bool, DatabaseError> {
+        let _magnet_lock = self.magnet_lock();
+        let Some(stored_magnet_link) = self
+            .backend
+            .remove_magnet_link(index)
+            .map_err(DatabaseError::MagnetDelete)?
+        else {
+            return Ok(false);
+        };
+        let mut state = self.state();
+        state.magnets -= 1;
+        // unpinned magnet link and magnet link that can not be read do not have references
+        if let Ok(magnet_link) = MagnetLink::from_bincode(stored_magnet_link.bytes) {
+            state.change_magnet_root(magnet_root(&magnet_link), index, false);
+            if stored_magnet_link.pinned {
+                state.change_references(&unique_artifact_ids(&magnet_link), false);
+            }
+        }
+        Ok(true)
+    }
+    /// Pin [MagnetLink] with given index, so its artifacts are not evicted or removed by
+    /// [Database::gc]. Returns false if magnet link does not exist
+    pub fn pin_magnet_link(&self, index: u64) -> Result<bool, DatabaseError> {
+        self.set_magnet_link_pinned(index, true)
+    }
+    /// Unpin [MagnetLink] with given index. Its artifacts that are not used by other pinned magnet
+    /// links become cached. Returns false if magnet link does not exist
+    pub fn unpin_magnet_link(&self, index: u64) -> Result<bool, DatabaseError> {
+        self.set_magnet_link_pinned(index, false)
+    }
+    /// Pin or unpin [MagnetLink] and change references of its artifacts
+    fn set_magnet_link_pinned(&self, index: u64, pinned: bool) -> Result<bool, DatabaseError> {
+        let _magnet_lock = self.magnet_lock();
+        let Some(stored_magnet_link) = self
+            .backend
+            .get_magnet_link(index)
+            .map_err(DatabaseError::MagnetPin)?
+        else {
+            return Ok(false);
+        };
+        if stored_magnet_link.pinned == pinned {
+            return Ok(true);
+        }
+        self.backend
+            .set_magnet_link_pinned(index, pinned)
+            .map_err(DatabaseError::MagnetPin)?;
+        if let Ok(magnet_link) = MagnetLink::from_bincode(stored_magnet_link.bytes) {
+            self.state()
+                .change_references(&unique_artifact_ids(&magnet_link), pinned);
+        }
+        Ok(true)
+    }
+    /// Check that [MagnetLink] with given index is pinned. Returns false if magnet link does not
+    /// exist
+    pub fn is_magnet_link_pinned(&self, index: u64) -> Result<bool, DatabaseError> {
+        Ok(self
+            .backend
+            .get_magnet_link(index)
+            .map_err(DatabaseError::MagnetPin)?
+            .is_some_and(|stored_magnet_link| stored_magnet_link.pinned))
+    }
+    /// Returns how many stored pinned magnet links use artifact
+    pub fn artifact_references(&self, artifact_id: &ArtifactId) -> u64 {
+        self.state()
+            .references
+            .get(&artifact_id.to_bytes())
+            .copied()
+            .unwrap_or(0)
+    }
+    /// Returns [ArtifactsHold] that keeps artifacts until it is dropped, so artifacts of magnet
+    /// link that is not inserted yet (e.g. file is uploading) are not removed
+    pub fn hold_artifacts(&self) -> ArtifactsHold<'_> {
+        ArtifactsHold {
+            database: self,
+            artifact_ids: Vec::new(),
+        }
+    }
+    /// Remove all cached artifacts: artifacts that are not used by any pinned magnet link and are
+    /// not held
+    pub fn gc(&self) -> Result<GarbageCollection, DatabaseError> {
+        let cached = self.state().take_cached(u64::MAX);
+        let collection = self
+            .remove_artifacts(cached)
+            .map_err(DatabaseError::ArtifactRemove)?;
+        info!(
+            "Garbage collection removed {} artifacts ({} bytes)",
+            collection.artifacts, collection.bytes
+        );
+        Ok(collection)
     }
     /// Returns all magnet links that stored in [Database] tree
     pub fn get_magnet_links(&self) -> Result<Vec<(u64, MagnetLink)>, DatabaseError> {
         Ok(self
-            .magnet_tree
-            .iter()
-            .map(|result| -> Result<(u64, MagnetLink), DatabaseError> {
-                let (index_ivec, magnet_ivec) = result?;
-                let index = u64_from_bytes(index_ivec.to_vec());
-                match MagnetLink::from_bincode(magnet_ivec.to_vec()) {
-                    Ok(magnet) => Ok((index, magnet)),
+            .backend
+            .magnet_links()
+            .map_err(DatabaseError::MagnetGet)?
+            .into_iter()
+            .filter_map(|stored_magnet_link| {
+                match MagnetLink::from_bincode(stored_magnet_link.bytes) {
+                    Ok(magnet) => Some((stored_magnet_link.index, magnet)),
                     Err(kind) => {
                         error!("got invalid mangnet link bytes in storage: {}", kind);
-                        Err(DatabaseError::MagnetFromJson(kind))
+                        None
                     },
                 }
             })
-            .filter(|result| result.is_ok())
-            .flatten()
             .collect())
     }
+    /// Returns stored [MagnetLink] with given merkle root and its index
+    pub fn get_magnet_link_by_root(
+        &self,
+        merkle_root: &HashValue,
+    ) -> Result<Option<(u64, MagnetLink)>, DatabaseError> {
+        let Some(stored_magnet_link) = self
+            .get_magnet_link_bytes_by_root(&merkle_root.to_bytes())
+            .map_err(DatabaseError::MagnetGet)?
+        else {
+            return Ok(None);
+        };
+        Ok(MagnetLink::from_bincode(stored_magnet_link.bytes)
+            .ok()
+            .map(|magnet_link| (stored_magnet_link.index, magnet_link)))
+    }
+    /// Returns stored bytes of magnet link with given merkle root
+    fn get_magnet_link_bytes_by_root(
+        &self,
+        merkle_root: &[u8],
+    ) -> Result<Option<StoredMagnetLink>, BackendError> {
+        let index = self
+            .state()
+            .magnet_roots
+            .get(merkle_root)
+            .and_then(|indexes| indexes.first().copied());
+        match index {
+            Some(index) => self.backend.get_magnet_link(index),
+            None => Ok(None),
+        }
+    }
+    /// Insert [Announcement] that was published in feed into Tree. Key of announcement is its
+    /// [Announcement::id]. When feed has [DatabaseConfig::max_feed_announcements] oldest
+    /// announcements are removed. Returns false if the same announcement is already stored in feed
+    pub fn insert_announcement(
+        &self,
+        topic: &str,
+        announcement: &Announcement,
+    ) -> Result<bool, DatabaseError> {
+        let key = announcement
+            .id()
+            .map_err(DatabaseError::AnnouncementToBincode)?;
+        let bytes = announcement
+            .to_bincode()
+            .map_err(DatabaseError::AnnouncementToBincode)?;
+        self.backend
+            .insert_announcement(topic, &key.to_bytes(), &bytes, self.max_feed_announcements)
+            .map_err(DatabaseError::AnnouncementInsert)
+    }
+    /// Returns all announcements of feed in order they were received
+    pub fn get_announcements(&self, topic: &str) -> Result<Vec<Announcement>, DatabaseError> {
+        Ok(self
+            .backend
+            .announcements(topic)
+            .map_err(DatabaseError::AnnouncementGet)?
+            .into_iter()
+            .filter_map(|value| match Announcement::from_bincode(&value) {
+                Ok(announcement) => Some(announcement),
+                Err(error) => {
+                    error!("got invalid announcement bytes in storage: {}", error);
+                    None
+                },
+            })
+            .collect())
+    }
+}
+/// Implement [FeedStorage] for [quanta_network::QuantaNetwork] because announcements from feeds
+/// we are store in [Database] too
+impl FeedStorage for Database {
+    fn insert_announcement(&self, topic: &str, announcement: &Announcement) -> bool {
+        match Database::insert_announcement(self, topic, announcement) {
+            Ok(inserted) => inserted,
+            Err(error) => {
+                error!(
+                    "got an error when trying to insert announcement into database, : {:?}",
+                    error
+                );
+                false
+            },
+        }
+    }
 }
 /// Implement [quanta_swap::Storage] for [quanta_swap::Behaviour] because all artifacts we are
-/// store in [Database]
+/// store in [Database]. [Backend]s read from disk, so lookups are moved onto blocking thread
+/// pool and do not stall the swarm
+#[async_trait::async_trait]
 impl quanta_swap::Storage for Database {
-    /// Check if item exists in storage. Here we are dont need to convert
-    /// key-bytes into artifact id
-    fn exists(&self, key: Vec<u8>) -> bool {
-        match self.artifact_db.contains_key(key) {
+    /// Check if item exists in storage. Key is id of artifact or merkle root of magnet link
+    async fn exists(self: Arc<Self>, key: Vec<u8>) -> bool {
+        async_std::task::spawn_blocking(move || self.contains_artifact_bytes(&key)).await
+    }
+    /// Get item from storage. Key is id of artifact or merkle root of magnet link. Sent artifact
+    /// is used, so it is evicted later
+    async fn get(self: Arc<Self>, key: Vec<u8>) -> Option<Vec<u8>> {
+        async_std::task::spawn_blocking(move || self.get_artifact_or_none(&key)).await
+    }
+    /// Check which of items exist in storage with one blocking task for the whole want list
+    async fn exists_many(self: Arc<Self>, keys: Vec<Vec<u8>>) -> Vec<bool> {
+        async_std::task::spawn_blocking(move || {
+            keys.iter()
+                .map(|key| self.contains_artifact_bytes(key))
+                .collect()
+        })
+        .await
+    }
+    /// Get items from storage with one blocking task for the whole want list, we are stopping
+    /// at the first item that does not fit into `max_size`
+    async fn get_many(
+        self: Arc<Self>,
+        keys: Vec<Vec<u8>>,
+        max_size: usize,
+    ) -> Vec<(Vec<u8>, Vec<u8>)> {
+        async_std::task::spawn_blocking(move || {
+            let mut items = Vec::new();
+            let mut size = 0;
+            for key in keys {
+                let Some(item) = self.get_artifact_or_none(&key) else {
+                    continue;
+                };
+                if !items.is_empty() && size + item.len() > max_size {
+                    break;
+                }
+                size += item.len();
+                items.push((key, item));
+            }
+            items
+        })
+        .await
+    }
+}
+
+impl Database {
+    /// Check if artifact or magnet link exists for [quanta_swap::Storage::exists]
+    fn contains_artifact_bytes(&self, key: &[u8]) -> bool {
+        if self
+            .state()
+            .magnet_roots
+            .contains_key(key)
+        {
+            return true;
+        }
+        match self.backend.contains_artifact(key) {
             Ok(exists) => exists,
             Err(error) => {
                 error!(
@@ -122,10 +953,18 @@ impl quanta_swap::Storage for Database {
             },
         }
     }
-    /// Get item from storage. Here we are dont need to convert key-bytes into artifact id
-    fn get(&self, key: Vec<u8>) -> Option<Vec<u8>> {
-        match self.artifact_db.get(key) {
-            Ok(artifact) => artifact.map(|ivec| ivec.to_vec()),
+    /// Get artifact or magnet link for [quanta_swap::Storage::get]
+    fn get_artifact_or_none(&self, key: &[u8]) -> Option<Vec<u8>> {
+        let item = match self.get_artifact_bytes(key) {
+            Ok(None) => self
+                .get_magnet_link_bytes_by_root(key)
+                .map(|stored_magnet_link| {
+                    stored_magnet_link.map(|stored_magnet_link| stored_magnet_link.bytes)
+                }),
+            item => item,
+        };
+        match item {
+            Ok(item) => item,
             Err(error) => {
                 error!(
                     "got an error when trying to get artifact from database, : {:?}",
@@ -138,12 +977,44 @@ impl quanta_swap::Storage for Database {
     }
 }
 
-/// Convert [u64] into bytes this fn used when we are store magnets
-fn u64_to_bytes(val: u64) -> Vec<u8> {
-    let mut buf = [0; 8];
-    LittleEndian::write_u64(&mut buf, val);
-    buf.to_vec()
+/// Returns merkle root of magnet link that is computed from its artifacts
+fn magnet_root(magnet_link: &MagnetLink) -> Vec<u8> {
+    magnet_link
+        .merkle_tree()
+        .root()
+        .to_bytes()
+}
+/// Returns keys of artifacts that magnet link uses. Artifact that is used twice is referenced once
+fn unique_artifact_ids(magnet_link: &MagnetLink) -> HashSet<Vec<u8>> {
+    magnet_link
+        .artifact_ids()
+        .into_iter()
+        .map(|artifact_id| artifact_id.to_bytes())
+        .collect()
+}
+/// Encode ids of artifacts in order they were used. Every id is prefixed with its length
+fn encode_recency<'a>(artifact_ids: impl Iterator<Item = &'a [u8]>) -> Vec<u8> {
+    let mut recency = Vec::new();
+    for artifact_id in artifact_ids {
+        recency.extend_from_slice(&(artifact_id.len() as u32).to_be_bytes());
+        recency.extend_from_slice(artifact_id);
+    }
+    recency
+}
+/// Decode ids of artifacts that were encoded with [encode_recency] into their positions in order
+/// of use. Broken tail is skipped
+fn decode_recency(mut recency: &[u8]) -> HashMap<Vec<u8>, usize> {
+    let mut positions = HashMap::new();
+    while recency.len() >= 4 {
+        let (len, rest) = recency.split_at(4);
+        let len = u32::from_be_bytes([len[0], len[1], len[2], len[3]]) as usize;
+        if rest.len() < len {
+            break;
+        }
+        let (artifact_id, rest) = rest.split_at(len);
+        let position = positions.len();
+        positions.insert(artifact_id.to_vec(), position);
+        recency = rest;
+    }
+    positions
 }
-
-/// Convert bytes into u64 this fn used when we are store magnets
-fn u64_from_bytes(bytes: Vec<u8>) -> u64 { LittleEndian::read_u64(bytes.as_slice()) }
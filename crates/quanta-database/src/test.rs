@@ -0,0 +1,799 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+};
+
+use quanta_artifact::{Artifact, MagnetLink};
+use quanta_feed::Announcement;
+use quanta_swap::Storage;
+
+use crate::{
+    Backend,
+    BackendError,
+    Database,
+    DatabaseConfig,
+    FilesystemBackend,
+    GarbageCollection,
+    MemoryBackend,
+    SledBackend,
+    StorageBackend,
+};
+
+/// Directory in temp dir that is removed when it is dropped
+struct TempDir(PathBuf);
+
+impl TempDir {
+    fn new() -> Self {
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+        let path = std::env::temp_dir().join(format!(
+            "quanta-database-test-{}-{}",
+            std::process::id(),
+            COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        std::fs::create_dir_all(&path).unwrap();
+        TempDir(path)
+    }
+
+    fn path(&self) -> &Path { &self.0 }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) { let _ = std::fs::remove_dir_all(&self.0); }
+}
+
+/// Reopen storage in directory that was just used. Sled releases its lock file only when its
+/// background threads drop the database, so opening it right after drop may fail for a moment
+fn reopen<T, E: std::fmt::Debug>(open: impl Fn() -> Result<T, E>) -> T {
+    for _ in 0..100 {
+        if let Ok(opened) = open() {
+            return opened;
+        }
+        std::thread::sleep(std::time::Duration::from_millis(10));
+    }
+    open().unwrap()
+}
+
+/// Checks that every [Backend] must pass. Backend is opened with given function in directory
+/// that is the same for every call, so persistent backends can be checked for reopening
+fn backend_conformance<B, F>(open: F, persistent: bool)
+where
+    B: Backend,
+    F: Fn(&Path) -> Result<B, BackendError>,
+{
+    let dir = TempDir::new();
+    {
+        let backend = open(dir.path()).unwrap();
+        check_artifacts(&backend);
+        check_magnet_links(&backend);
+        check_announcements(&backend);
+    }
+    if persistent {
+        let backend = reopen(|| open(dir.path()));
+        check_reopened(&backend);
+    }
+}
+
+fn check_artifacts<B: Backend>(backend: &B) {
+    assert!(backend.artifacts().unwrap().is_empty());
+    assert!(backend
+        .insert_artifact(b"beep", b"hello")
+        .unwrap());
+    // artifacts are content-addressed, so second insert does not change anything
+    assert!(!backend
+        .insert_artifact(b"beep", b"hello")
+        .unwrap());
+    assert!(backend
+        .insert_artifact(b"boop", b"world!")
+        .unwrap());
+    assert!(backend
+        .contains_artifact(b"beep")
+        .unwrap());
+    assert!(!backend
+        .contains_artifact(b"bzzz")
+        .unwrap());
+    assert_eq!(
+        backend.get_artifact(b"beep").unwrap(),
+        Some(b"hello".to_vec())
+    );
+    assert_eq!(backend.get_artifact(b"bzzz").unwrap(), None);
+    let mut artifacts = backend.artifacts().unwrap();
+    artifacts.sort();
+    assert_eq!(artifacts, vec![
+        (b"beep".to_vec(), 5),
+        (b"boop".to_vec(), 6)
+    ]);
+    assert_eq!(
+        backend
+            .remove_artifact(b"boop")
+            .unwrap(),
+        Some(6)
+    );
+    assert_eq!(
+        backend
+            .remove_artifact(b"boop")
+            .unwrap(),
+        None
+    );
+    assert!(!backend
+        .contains_artifact(b"boop")
+        .unwrap());
+}
+
+fn check_magnet_links<B: Backend>(backend: &B) {
+    assert!(backend
+        .magnet_links()
+        .unwrap()
+        .is_empty());
+    let first = backend
+        .insert_magnet_link(b"first")
+        .unwrap();
+    let second = backend
+        .insert_magnet_link(b"second")
+        .unwrap();
+    let third = backend
+        .insert_magnet_link(b"third")
+        .unwrap();
+    assert!(first < second && second < third);
+
+    let stored = backend
+        .get_magnet_link(second)
+        .unwrap()
+        .unwrap();
+    assert_eq!(stored.index, second);
+    assert_eq!(stored.bytes, b"second".to_vec());
+    assert!(stored.pinned);
+    assert_eq!(
+        backend
+            .get_magnet_link(third + 1)
+            .unwrap(),
+        None
+    );
+
+    assert!(backend
+        .set_magnet_link_pinned(second, false)
+        .unwrap());
+    assert!(!backend
+        .set_magnet_link_pinned(third + 1, false)
+        .unwrap());
+    assert!(
+        !backend
+            .get_magnet_link(second)
+            .unwrap()
+            .unwrap()
+            .pinned
+    );
+    let magnet_links = backend.magnet_links().unwrap();
+    assert_eq!(
+        magnet_links
+            .iter()
+            .map(|magnet_link| (magnet_link.index, magnet_link.pinned))
+            .collect::<Vec<_>>(),
+        vec![(first, true), (second, false), (third, true)]
+    );
+
+    let removed = backend
+        .remove_magnet_link(second)
+        .unwrap()
+        .unwrap();
+    assert_eq!(removed.bytes, b"second".to_vec());
+    assert!(!removed.pinned);
+    assert_eq!(
+        backend
+            .remove_magnet_link(second)
+            .unwrap(),
+        None
+    );
+    assert_eq!(backend.magnet_links().unwrap().len(), 2);
+    // new magnet link does not take index of stored one
+    let fourth = backend
+        .insert_magnet_link(b"fourth")
+        .unwrap();
+    assert!(fourth > third);
+    // pin of removed magnet link is not inherited
+    assert!(
+        backend
+            .get_magnet_link(fourth)
+            .unwrap()
+            .unwrap()
+            .pinned
+    );
+}
+
+fn check_announcements<B: Backend>(backend: &B) {
+    assert!(backend
+        .announcements("music")
+        .unwrap()
+        .is_empty());
+    for (topic, key, announcement) in [
+        ("music", b"1", b"first".as_slice()),
+        ("musicians", b"1", b"other"),
+        ("music", b"2", b"second"),
+    ] {
+        assert!(backend
+            .insert_announcement(topic, key, announcement, 2)
+            .unwrap());
+    }
+    // the same key is stored once
+    assert!(!backend
+        .insert_announcement("music", b"1", b"first", 2)
+        .unwrap());
+    assert_eq!(backend.announcements("music").unwrap(), vec![
+        b"first".to_vec(),
+        b"second".to_vec()
+    ]);
+    // the oldest announcement is removed when feed is full
+    assert!(backend
+        .insert_announcement("music", b"3", b"third", 2)
+        .unwrap());
+    assert_eq!(backend.announcements("music").unwrap(), vec![
+        b"second".to_vec(),
+        b"third".to_vec()
+    ]);
+    // key of removed announcement is removed too
+    assert!(backend
+        .insert_announcement("music", b"1", b"first", 2)
+        .unwrap());
+    assert_eq!(backend.announcements("music").unwrap(), vec![
+        b"third".to_vec(),
+        b"first".to_vec()
+    ]);
+    assert_eq!(
+        backend
+            .announcements("musicians")
+            .unwrap(),
+        vec![b"other".to_vec()]
+    );
+}
+
+/// Checks what [check_artifacts], [check_magnet_links] and [check_announcements] left
+fn check_reopened<B: Backend>(backend: &B) {
+    assert_eq!(backend.artifacts().unwrap(), vec![(b"beep".to_vec(), 5)]);
+    assert_eq!(
+        backend
+            .magnet_links()
+            .unwrap()
+            .iter()
+            .map(|magnet_link| magnet_link.bytes.clone())
+            .collect::<Vec<_>>(),
+        vec![b"first".to_vec(), b"third".to_vec(), b"fourth".to_vec()]
+    );
+    assert_eq!(
+        backend
+            .announcements("music")
+            .unwrap()
+            .len(),
+        2
+    );
+}
+
+#[test]
+fn test_sled_backend_conformance() {
+    backend_conformance(|path| SledBackend::open(path, 1024 * 1024), true);
+}
+
+#[test]
+fn test_memory_backend_conformance() {
+    backend_conformance(|_| Ok(MemoryBackend::default()), false);
+}
+
+#[test]
+fn test_filesystem_backend_conformance() {
+    backend_conformance(|path| FilesystemBackend::open(path), true);
+}
+
+/// Kinds of [Backend] that [Database] is checked on
+const STORAGE_BACKENDS: [StorageBackend; 3] = [
+    StorageBackend::Sled,
+    StorageBackend::Memory,
+    StorageBackend::Filesystem,
+];
+
+/// Returns magnet link that uses given artifacts
+fn magnet_link_with(artifacts: &[&Artifact]) -> MagnetLink {
+    let mut magnet_link = MagnetLink::new("beep.txt".to_string(), 0);
+    for artifact in artifacts {
+        magnet_link.new_update_with_artifact_id(artifact.id);
+    }
+    magnet_link
+}
+
+#[test]
+fn test_database_pins_and_gc() {
+    for backend in STORAGE_BACKENDS {
+        let dir = TempDir::new();
+        let database =
+            Database::with_config(dir.path(), DatabaseConfig::default().with_backend(backend))
+                .unwrap();
+        let shared = Artifact::new(vec![1; 100]);
+        let own = Artifact::new(vec![2; 100]);
+        let cached = Artifact::new(vec![3; 100]);
+        for artifact in [&shared, &own, &cached] {
+            database
+                .insert_artifact(artifact.clone())
+                .unwrap();
+        }
+        let first = database
+            .insert_magnet_link(magnet_link_with(&[&shared, &own]))
+            .unwrap();
+        let second = database
+            .insert_magnet_link(magnet_link_with(&[&shared]))
+            .unwrap();
+        assert_eq!(database.size(), 300, "{backend:?}");
+        assert_eq!(database.artifact_references(&shared.id), 2);
+
+        assert!(database
+            .unpin_magnet_link(first)
+            .unwrap());
+        assert!(!database
+            .is_magnet_link_pinned(first)
+            .unwrap());
+        assert_eq!(database.artifact_references(&shared.id), 1);
+        assert_eq!(database.artifact_references(&own.id), 0);
+
+        let collection = database.gc().unwrap();
+        assert_eq!(collection.artifacts, 2, "{backend:?}");
+        assert_eq!(collection.bytes, 200);
+        assert!(database
+            .get_artifact(&shared.id)
+            .unwrap()
+            .is_some());
+        assert!(database
+            .get_artifact(&own.id)
+            .unwrap()
+            .is_none());
+
+        assert!(database
+            .delete_magnet_link(second)
+            .unwrap());
+        assert!(!database
+            .delete_magnet_link(second)
+            .unwrap());
+        assert!(!database
+            .pin_magnet_link(second)
+            .unwrap());
+        assert_eq!(database.artifact_references(&shared.id), 0);
+        assert_eq!(database.magnet_count(), 1);
+        assert_eq!(database.gc().unwrap().artifacts, 1);
+        assert_eq!(database.size(), 0);
+        assert_eq!(database.artifact_count(), 0);
+    }
+}
+
+#[test]
+fn test_database_counts_references_and_holds() {
+    for backend in STORAGE_BACKENDS {
+        let dir = TempDir::new();
+        let database =
+            Database::with_config(dir.path(), DatabaseConfig::default().with_backend(backend))
+                .unwrap();
+        let repeated = Artifact::new(vec![1; 100]);
+        // file with the same content twice uses artifact once
+        let index = database
+            .insert_magnet_link(magnet_link_with(&[&repeated, &repeated]))
+            .unwrap();
+        assert_eq!(database.artifact_references(&repeated.id), 1, "{backend:?}");
+        // the same magnet link inserted twice is two references
+        let copy = database
+            .insert_magnet_link(magnet_link_with(&[&repeated, &repeated]))
+            .unwrap();
+        assert_eq!(database.artifact_references(&repeated.id), 2);
+        assert!(database
+            .delete_magnet_link(copy)
+            .unwrap());
+        assert!(database
+            .unpin_magnet_link(index)
+            .unwrap());
+        assert_eq!(database.artifact_references(&repeated.id), 0);
+        // unpinning twice does not change references
+        assert!(database
+            .unpin_magnet_link(index)
+            .unwrap());
+        assert_eq!(database.artifact_references(&repeated.id), 0);
+        assert!(database.pin_magnet_link(index).unwrap());
+        assert_eq!(database.artifact_references(&repeated.id), 1);
+        assert!(database
+            .delete_magnet_link(index)
+            .unwrap());
+
+        // artifact that is held twice is removed only after both holds are dropped
+        let held = Artifact::new(vec![2; 100]);
+        let mut first = database.hold_artifacts();
+        let mut second = database.hold_artifacts();
+        first
+            .insert_artifact(held.clone())
+            .unwrap();
+        second
+            .insert_artifact(held.clone())
+            .unwrap();
+        assert_eq!(database.gc().unwrap().artifacts, 0);
+        drop(first);
+        assert_eq!(database.gc().unwrap().artifacts, 0);
+        drop(second);
+        // artifact that is already stored is held without insert
+        let stored = Artifact::new(vec![3; 50]);
+        database
+            .insert_artifact(stored.clone())
+            .unwrap();
+        assert_eq!(database.artifact_size(&stored.id), Some(50));
+        let mut hold = database.hold_artifacts();
+        hold.hold(&stored.id);
+        assert_eq!(database.gc().unwrap(), GarbageCollection {
+            artifacts: 1,
+            bytes: 100,
+        });
+        drop(hold);
+        assert_eq!(database.gc().unwrap(), GarbageCollection {
+            artifacts: 1,
+            bytes: 50,
+        });
+        assert_eq!(database.artifact_size(&stored.id), None);
+        assert_eq!(database.size(), 0);
+        assert_eq!(database.artifact_count(), 0);
+    }
+}
+
+#[test]
+fn test_database_evicts_least_recently_used() {
+    for backend in STORAGE_BACKENDS {
+        let dir = TempDir::new();
+        let database = Database::with_config(
+            dir.path(),
+            DatabaseConfig::default()
+                .with_backend(backend)
+                .with_max_size(Some(300)),
+        )
+        .unwrap();
+        let pinned = Artifact::new(vec![1; 100]);
+        let old = Artifact::new(vec![2; 100]);
+        let used = Artifact::new(vec![3; 100]);
+        for artifact in [&pinned, &old, &used] {
+            database
+                .insert_artifact(artifact.clone())
+                .unwrap();
+        }
+        database
+            .insert_magnet_link(magnet_link_with(&[&pinned]))
+            .unwrap();
+        // old artifact is used after pinned one, but it is least recently used of cached
+        database
+            .get_artifact(&pinned.id)
+            .unwrap();
+        database.get_artifact(&used.id).unwrap();
+
+        let new = Artifact::new(vec![4; 100]);
+        database
+            .insert_artifact(new.clone())
+            .unwrap();
+        assert!(
+            database
+                .get_artifact(&old.id)
+                .unwrap()
+                .is_none(),
+            "{backend:?}"
+        );
+        assert_eq!(database.size(), 300);
+
+        // held artifacts are not evicted, so storage is full when all cached artifacts are
+        // evicted
+        let mut hold = database.hold_artifacts();
+        let held = Artifact::new(vec![5; 100]);
+        hold.insert_artifact(held.clone())
+            .unwrap();
+        assert!(database
+            .get_artifact(&used.id)
+            .unwrap()
+            .is_none());
+        hold.insert_artifact(Artifact::new(vec![6; 100]))
+            .unwrap();
+        assert!(hold
+            .insert_artifact(Artifact::new(vec![7; 100]))
+            .is_err());
+        assert_eq!(database.gc().unwrap().artifacts, 0);
+        drop(hold);
+        assert_eq!(database.gc().unwrap().artifacts, 2);
+        assert!(database
+            .get_artifact(&pinned.id)
+            .unwrap()
+            .is_some());
+    }
+}
+
+#[test]
+fn test_database_evicts_unpinned_artifacts_by_last_use() {
+    for backend in STORAGE_BACKENDS {
+        let dir = TempDir::new();
+        let database = Database::with_config(
+            dir.path(),
+            DatabaseConfig::default()
+                .with_backend(backend)
+                .with_max_size(Some(300)),
+        )
+        .unwrap();
+        let unpinned = Artifact::new(vec![1; 100]);
+        let first = Artifact::new(vec![2; 100]);
+        let second = Artifact::new(vec![3; 100]);
+        for artifact in [&unpinned, &first, &second] {
+            database
+                .insert_artifact(artifact.clone())
+                .unwrap();
+        }
+        let index = database
+            .insert_magnet_link(magnet_link_with(&[&unpinned]))
+            .unwrap();
+        // artifact is used while it is pinned, so after unpin it is evicted last
+        database
+            .get_artifact(&unpinned.id)
+            .unwrap();
+        assert!(database
+            .unpin_magnet_link(index)
+            .unwrap());
+        for (evicted, artifact) in [(&first, vec![4; 100]), (&second, vec![5; 100])] {
+            database
+                .insert_artifact(Artifact::new(artifact))
+                .unwrap();
+            assert!(
+                database
+                    .get_artifact(&evicted.id)
+                    .unwrap()
+                    .is_none(),
+                "{backend:?}"
+            );
+        }
+        assert!(database
+            .get_artifact(&unpinned.id)
+            .unwrap()
+            .is_some());
+        assert_eq!(database.size(), 300);
+    }
+}
+
+#[test]
+fn test_sled_backend_migrates_artifact_sizes() {
+    let dir = TempDir::new();
+    {
+        // artifacts that were stored before sizes of artifacts were stored
+        let db = sled::open(dir.path()).unwrap();
+        db.insert(b"beep", vec![1; 5]).unwrap();
+        db.flush().unwrap();
+    }
+    for _ in 0..2 {
+        let backend = reopen(|| SledBackend::open(dir.path(), 1024 * 1024));
+        assert_eq!(backend.artifacts().unwrap(), vec![(b"beep".to_vec(), 5)]);
+    }
+}
+
+#[test]
+fn test_database_keeps_recency_after_reopen() {
+    for backend in [StorageBackend::Sled, StorageBackend::Filesystem] {
+        let dir = TempDir::new();
+        let config = DatabaseConfig::default()
+            .with_backend(backend)
+            .with_max_size(Some(200));
+        let used = Artifact::new(vec![1; 100]);
+        let old = Artifact::new(vec![2; 100]);
+        {
+            let database = Database::with_config(dir.path(), config.clone()).unwrap();
+            for artifact in [&used, &old] {
+                database
+                    .insert_artifact(artifact.clone())
+                    .unwrap();
+            }
+            database.get_artifact(&used.id).unwrap();
+            database.save_recency().unwrap();
+        }
+        let database = reopen(|| Database::with_config(dir.path(), config.clone()));
+        database
+            .insert_artifact(Artifact::new(vec![3; 100]))
+            .unwrap();
+        assert!(
+            database
+                .get_artifact(&old.id)
+                .unwrap()
+                .is_none(),
+            "{backend:?}"
+        );
+        assert!(database
+            .get_artifact(&used.id)
+            .unwrap()
+            .is_some());
+    }
+}
+
+#[test]
+fn test_database_concurrent_inserts_keep_max_size() {
+    let dir = TempDir::new();
+    let database = Arc::new(
+        Database::with_config(
+            dir.path(),
+            DatabaseConfig::default()
+                .with_backend(StorageBackend::Filesystem)
+                .with_max_size(Some(500)),
+        )
+        .unwrap(),
+    );
+    let threads = (0..8u8)
+        .map(|thread| {
+            let database = Arc::clone(&database);
+            std::thread::spawn(move || {
+                for artifact in 0..20u8 {
+                    // the same artifacts are inserted by two threads
+                    database
+                        .insert_artifact(Artifact::new(vec![
+                            thread / 2,
+                            artifact,
+                            0,
+                            0,
+                            0,
+                            0,
+                            0,
+                            0,
+                            0,
+                            0,
+                        ]))
+                        .unwrap();
+                    if artifact % 5 == 0 {
+                        database.gc().unwrap();
+                    }
+                }
+            })
+        })
+        .collect::<Vec<_>>();
+    for thread in threads {
+        thread.join().unwrap();
+    }
+    assert!(database.size() <= 500);
+    assert_eq!(database.size(), database.artifact_count() as u64 * 10);
+    assert_eq!(
+        database
+            .get_artifact_ids()
+            .unwrap()
+            .len(),
+        database.artifact_count()
+    );
+}
+
+#[test]
+fn test_database_reopen() {
+    for backend in [StorageBackend::Sled, StorageBackend::Filesystem] {
+        let dir = TempDir::new();
+        let config = DatabaseConfig::default().with_backend(backend);
+        let pinned = Artifact::new(vec![1; 100]);
+        let cached = Artifact::new(vec![2; 50]);
+        let announcement = Announcement {
+            merkle_root: "abcd".to_string(),
+            file_name: "beep.txt".to_string(),
+            size: 100,
+            publisher: libp2p::PeerId::random(),
+        };
+        {
+            let database = Database::with_config(dir.path(), config.clone()).unwrap();
+            database
+                .insert_artifact(pinned.clone())
+                .unwrap();
+            database
+                .insert_artifact(cached.clone())
+                .unwrap();
+            let index = database
+                .insert_magnet_link(magnet_link_with(&[&pinned, &cached]))
+                .unwrap();
+            database
+                .insert_magnet_link(magnet_link_with(&[&pinned]))
+                .unwrap();
+            database
+                .unpin_magnet_link(index)
+                .unwrap();
+            assert!(database
+                .insert_announcement("music", &announcement)
+                .unwrap());
+        }
+        let database = reopen(|| Database::with_config(dir.path(), config.clone()));
+        assert_eq!(database.size(), 150, "{backend:?}");
+        assert_eq!(database.magnet_count(), 2);
+        assert_eq!(database.artifact_references(&pinned.id), 1);
+        assert_eq!(database.artifact_references(&cached.id), 0);
+        // unpinned magnet link is still found by its merkle root
+        let merkle_root = magnet_link_with(&[&pinned, &cached])
+            .merkle_tree()
+            .root();
+        assert!(database
+            .get_magnet_link_by_root(&merkle_root)
+            .unwrap()
+            .is_some());
+        assert!(!database
+            .insert_announcement("music", &announcement)
+            .unwrap());
+        assert_eq!(database.gc().unwrap().bytes, 50);
+    }
+}
+
+#[test]
+fn test_database_serves_magnet_links_by_root() {
+    let dir = TempDir::new();
+    let database = Arc::new(
+        Database::with_config(
+            dir.path(),
+            DatabaseConfig::default().with_backend(StorageBackend::Memory),
+        )
+        .unwrap(),
+    );
+    let artifact = Artifact::new(vec![1; 100]);
+    let magnet_link = magnet_link_with(&[&artifact]);
+    let merkle_root = magnet_link.merkle_tree().root();
+    let key = merkle_root.to_bytes();
+    assert!(!async_std::task::block_on(
+        Arc::clone(&database).exists(key.clone())
+    ));
+    // the same file that stored twice is served until both magnet links are deleted
+    let first = database
+        .insert_magnet_link(magnet_link.clone())
+        .unwrap();
+    let second = database
+        .insert_magnet_link(magnet_link.clone())
+        .unwrap();
+    assert!(database
+        .delete_magnet_link(first)
+        .unwrap());
+    assert_eq!(
+        database
+            .get_magnet_link_by_root(&merkle_root)
+            .unwrap()
+            .map(|(index, _)| index),
+        Some(second)
+    );
+    assert!(async_std::task::block_on(
+        Arc::clone(&database).exists(key.clone())
+    ));
+    let item = async_std::task::block_on(Arc::clone(&database).get(key.clone())).unwrap();
+    assert!(MagnetLink::from_bincode(item)
+        .unwrap()
+        .verify(&merkle_root));
+    assert!(database
+        .delete_magnet_link(second)
+        .unwrap());
+    assert!(database
+        .get_magnet_link_by_root(&merkle_root)
+        .unwrap()
+        .is_none());
+    assert!(async_std::task::block_on(Arc::clone(&database).get(key)).is_none());
+}
+
+#[test]
+fn test_database_serves_want_lists_in_batches() {
+    let dir = TempDir::new();
+    let database = Arc::new(
+        Database::with_config(
+            dir.path(),
+            DatabaseConfig::default().with_backend(StorageBackend::Sled),
+        )
+        .unwrap(),
+    );
+    let artifacts: Vec<_> = (1..=3)
+        .map(|byte| Artifact::new(vec![byte; 100]))
+        .collect();
+    for artifact in &artifacts {
+        database
+            .insert_artifact(artifact.clone())
+            .unwrap();
+    }
+    let missing = Artifact::new(vec![4; 100]);
+    let keys: Vec<_> = [&artifacts[0], &missing, &artifacts[1], &artifacts[2]]
+        .into_iter()
+        .map(|artifact| artifact.id.to_bytes())
+        .collect();
+    assert_eq!(
+        async_std::task::block_on(Arc::clone(&database).exists_many(keys.clone())),
+        vec![true, false, true, true]
+    );
+    // missing key is skipped and the last artifact does not fit into the response
+    let items = async_std::task::block_on(Arc::clone(&database).get_many(keys.clone(), 250));
+    assert_eq!(items, vec![
+        (keys[0].clone(), vec![1; 100]),
+        (keys[2].clone(), vec![2; 100])
+    ]);
+    // the first item is always sent, so want list is never stuck on a large artifact
+    let items = async_std::task::block_on(Arc::clone(&database).get_many(keys[2..].to_vec(), 10));
+    assert_eq!(items, vec![(keys[2].clone(), vec![2; 100])]);
+}
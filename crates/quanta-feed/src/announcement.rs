@@ -0,0 +1,69 @@
+use libp2p::PeerId;
+use quanta_artifact::MagnetLink;
+use quanta_crypto::{AdvancedHasher, HashValue};
+use serde::{Deserialize, Serialize};
+use sha2::Digest;
+
+#[derive(thiserror::Error, Debug)]
+pub enum AnnouncementError {
+    #[error("Got error when trying to convert announcement into bincode: {0}")]
+    /// Error whill occur in [Announcement::to_bincode]
+    ToBincode(bincode::Error),
+    #[error("Got error when trying to convert bincode into announcement: {0}")]
+    /// Error whill occur in [Announcement::from_bincode]
+    FromBincode(bincode::Error),
+}
+/// Announcement of file that was published in feed. Announcements are sent over gossipsub with
+/// signature of publisher, so [Announcement::publisher] can be checked with source of message.
+/// Magnet link of large file does not fit into gossipsub message, so only its merkle root is
+/// announced. Magnet link is fetched from network by merkle root and verified with it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Announcement {
+    /// Hex-based merkle root of magnet link of published file
+    pub merkle_root: String,
+    /// Name of published file
+    pub file_name: String,
+    /// Size of published file
+    pub size: usize,
+    /// Peer that published file
+    pub publisher: PeerId,
+}
+
+impl Announcement {
+    /// Create new [Announcement] of [MagnetLink] that published by given peer
+    pub fn new(magnet_link: &MagnetLink, publisher: PeerId) -> Self {
+        Announcement {
+            merkle_root: magnet_link
+                .merkle_tree()
+                .root()
+                .to_string(),
+            file_name: magnet_link.file_name().to_string(),
+            size: magnet_link.size(),
+            publisher,
+        }
+    }
+    /// Check that announcement is sent by its publisher and has valid merkle root. Name and size
+    /// of file can be checked only when magnet link is fetched
+    pub fn validate(&self, source: Option<&PeerId>) -> bool {
+        source == Some(&self.publisher) && HashValue::try_from(self.merkle_root.as_str()).is_ok()
+    }
+    /// returns bincode-based bytes
+    pub fn to_bincode(&self) -> Result<Vec<u8>, AnnouncementError> {
+        bincode::serialize(self).map_err(AnnouncementError::ToBincode)
+    }
+    /// returns sha2-256 hash of bincode-based bytes. Identifies announcement in storage, so the
+    /// same announcement that received twice is stored once
+    pub fn id(&self) -> Result<HashValue, AnnouncementError> {
+        Ok(AdvancedHasher::new(&self.to_bincode()?, sha2::Sha256::new()).finalize())
+    }
+    /// returns [`Self`] from bincode-based bytes
+    pub fn from_bincode(bytes: &[u8]) -> Result<Self, AnnouncementError> {
+        bincode::deserialize(bytes).map_err(AnnouncementError::FromBincode)
+    }
+}
+/// Storage of announcements that were received from feeds that we are subscribed to
+pub trait FeedStorage {
+    /// Store announcement that was received in topic. Returns false if announcement was not
+    /// stored
+    fn insert_announcement(&self, topic: &str, announcement: &Announcement) -> bool;
+}
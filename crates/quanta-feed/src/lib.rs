@@ -0,0 +1,6 @@
+#![allow(dead_code)]
+mod announcement;
+#[cfg(test)]
+mod test;
+
+pub use announcement::{Announcement, AnnouncementError, FeedStorage};
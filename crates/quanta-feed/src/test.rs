@@ -0,0 +1,48 @@
+use libp2p::PeerId;
+use quanta_artifact::{Artifact, MagnetLink};
+
+use crate::Announcement;
+
+/// Returns magnet link of file with one artifact
+fn magnet_link(file_name: &str, data: &[u8]) -> MagnetLink {
+    let mut magnet_link = MagnetLink::new(file_name.to_string(), data.len());
+    magnet_link.new_update_with_artifact_id(Artifact::new(data.to_vec()).id);
+    magnet_link.update_merkle_root();
+    magnet_link
+}
+
+#[test]
+fn test_announcement_validation() {
+    let publisher = PeerId::random();
+    let magnet_link = magnet_link("beep.txt", b"beep");
+    let announcement = Announcement::new(&magnet_link, publisher);
+    assert_eq!(
+        announcement.merkle_root,
+        magnet_link
+            .merkle_root()
+            .unwrap()
+            .to_string()
+    );
+    let from_bincode = Announcement::from_bincode(&announcement.to_bincode().unwrap()).unwrap();
+    assert_eq!(announcement, from_bincode);
+    assert!(announcement.validate(Some(&publisher)));
+    // announcement can not be forwarded as announcement of other peer
+    assert!(!announcement.validate(Some(&PeerId::random())));
+    assert!(!announcement.validate(None));
+    let mut wrong_root = announcement.clone();
+    wrong_root.merkle_root = "beep".to_string();
+    assert!(!wrong_root.validate(Some(&publisher)));
+}
+
+#[test]
+fn test_announcement_of_large_file_is_small() {
+    // magnet link of file with many artifacts is larger than max size of gossipsub message
+    let mut magnet_link = MagnetLink::new("large.bin".to_string(), 4_000 * 16);
+    for index in 0..4_000u32 {
+        magnet_link.new_update_with_artifact_id(Artifact::new(index.to_be_bytes().to_vec()).id);
+    }
+    magnet_link.update_merkle_root();
+    assert!(magnet_link.to_bincode().unwrap().len() > 65536);
+    let announcement = Announcement::new(&magnet_link, PeerId::random());
+    assert!(announcement.to_bincode().unwrap().len() < 1024);
+}
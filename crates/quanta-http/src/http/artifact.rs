@@ -0,0 +1,48 @@
+use actix_web::{
+    web::{Data, Path},
+    HttpResponse,
+};
+use quanta_artifact::ArtifactId;
+use quanta_network::ProxyError;
+
+use crate::{
+    http::{
+        error::QuantaHttpResponse,
+        util::{generate_error_response, ErrorResponse},
+    },
+    state::HttpServerState,
+};
+
+/// Search artifact by its bs58-based [ArtifactId]. Artifact that stored in
+/// [quanta_database::Database] is returned immediately, others are fetched from network.
+/// Fetched artifact is not saved, because we are dont know which magnet link it belongs to
+pub async fn artifact_search_handler(
+    artifact_id: Path<String>,
+    state: Data<HttpServerState>,
+) -> QuantaHttpResponse {
+    let Ok(artifact_id) = ArtifactId::from_bs58_string(&artifact_id) else {
+        return generate_error_response("Invalid artifact id");
+    };
+    let artifact = match state
+        .database()
+        .get_artifact(&artifact_id)?
+    {
+        Some(artifact) => artifact,
+        None => match state
+            .network_proxy()
+            .fetch_artifact(artifact_id)
+            .await
+        {
+            Ok(artifact) => artifact,
+            Err(ProxyError::ArtifactNotFound | ProxyError::RecvTimeout) => {
+                return Ok(HttpResponse::NotFound().json(ErrorResponse {
+                    error: "Artifact was not found in network",
+                }))
+            },
+            Err(error) => return Err(error.into()),
+        },
+    };
+    Ok(HttpResponse::Ok()
+        .content_type(mime::APPLICATION_OCTET_STREAM)
+        .body(artifact.data))
+}
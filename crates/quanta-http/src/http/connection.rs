@@ -1,12 +1,90 @@
 use actix_web::{web, HttpResponse};
+use libp2p::{Multiaddr, PeerId};
+use quanta_network::ProxyError;
 
-use crate::{http::error::QuantaHttpResponse, state::HttpServerState};
+use crate::{
+    http::{
+        error::QuantaHttpResponse,
+        util::{generate_error_response, ErrorResponse, StatusResponse},
+    },
+    state::HttpServerState,
+};
 
+/// Body of [dial_peer_handler]
+#[derive(serde::Deserialize, Debug)]
+pub struct DialRequest {
+    /// Address of peer that ends with `/p2p/<peer-id>`
+    address: String,
+}
+/// HTTP-API Response of [dial_peer_handler]
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+pub struct DialResponse {
+    /// Id of peer that we are connected to
+    pub peer_id: String,
+}
 /// Returns HashMap of connections that we are receive from proxyservice
 pub async fn get_connections_list(state: web::Data<HttpServerState>) -> QuantaHttpResponse {
     Ok(HttpResponse::Ok().json(
         state
             .network_proxy()
-            .get_connections()?,
+            .get_connections()
+            .await?,
+    ))
+}
+
+/// Returns NAT status and relay addresses of our node that we are receive from proxyservice
+pub async fn get_nat_info(state: web::Data<HttpServerState>) -> QuantaHttpResponse {
+    Ok(HttpResponse::Ok().json(
+        state
+            .network_proxy()
+            .get_nat_info()
+            .await?,
     ))
 }
+
+/// Dial peer by address and wait until connection with it is established. Used for peers that
+/// can not be discovered, for example peers in other subnets
+pub async fn dial_peer_handler(
+    request: web::Json<DialRequest>,
+    state: web::Data<HttpServerState>,
+) -> QuantaHttpResponse {
+    let Ok(address) = request.address.parse::<Multiaddr>() else {
+        return generate_error_response("Invalid address");
+    };
+    match state
+        .network_proxy()
+        .dial(address)
+        .await
+    {
+        Ok(peer_id) => Ok(HttpResponse::Ok().json(DialResponse {
+            peer_id: peer_id.to_string(),
+        })),
+        Err(ProxyError::DialPeerId(_)) => {
+            generate_error_response("Address does not contain /p2p/<peer-id>")
+        },
+        Err(ProxyError::Dial(error)) => {
+            Ok(HttpResponse::BadGateway().json(ErrorResponse { error: &error }))
+        },
+        Err(error) => Err(error.into()),
+    }
+}
+
+/// Close all connections with peer
+pub async fn disconnect_peer_handler(
+    peer_id: web::Path<String>,
+    state: web::Data<HttpServerState>,
+) -> QuantaHttpResponse {
+    let Ok(peer_id) = peer_id.parse::<PeerId>() else {
+        return generate_error_response("Invalid peer id");
+    };
+    if !state
+        .network_proxy()
+        .disconnect(peer_id)
+        .await?
+    {
+        return Ok(HttpResponse::NotFound().json(ErrorResponse {
+            error: "Peer is not connected",
+        }));
+    }
+    Ok(HttpResponse::Ok().json(StatusResponse { status: "Ok" }))
+}
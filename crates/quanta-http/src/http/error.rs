@@ -1,6 +1,12 @@
 use std::num::ParseIntError;
 
-use actix_web::{body::BoxBody, http::header::ToStrError, HttpResponse, ResponseError};
+use actix_web::{
+    body::BoxBody,
+    error::BlockingError,
+    http::header::ToStrError,
+    HttpResponse,
+    ResponseError,
+};
 use quanta_database::DatabaseError;
 use quanta_network::ProxyError;
 
@@ -8,9 +14,21 @@ use quanta_network::ProxyError;
 pub type QuantaHttpResponse = Result<HttpResponse, Error>;
 
 #[derive(thiserror::Error, Debug)]
+#[allow(clippy::enum_variant_names)]
 pub enum Error {
     #[error("Got Internal Server Error")]
     InternalServerError,
+    #[error("Artifact does not belong to magnet link")]
+    /// Error whill occur when artifact received from network does not match merkle root of
+    /// magnet link
+    ArtifactVerification,
+    #[error("Magnet link was not found")]
+    /// Error whill occur when magnet link with given merkle root is not stored and can not be
+    /// fetched from network
+    MagnetNotFound,
+    #[error("Announcement was not published: {0}")]
+    /// Error whill occur when network can not publish announcement in feed
+    Publish(String),
 }
 
 impl From<DatabaseError> for Error {
@@ -18,7 +36,12 @@ impl From<DatabaseError> for Error {
 }
 
 impl From<ProxyError> for Error {
-    fn from(_: ProxyError) -> Self { Error::InternalServerError }
+    fn from(error: ProxyError) -> Self {
+        match error {
+            ProxyError::Publish(error) => Error::Publish(error),
+            _ => Error::InternalServerError,
+        }
+    }
 }
 
 impl From<ParseIntError> for Error {
@@ -33,12 +56,27 @@ impl From<std::io::Error> for Error {
     fn from(_: std::io::Error) -> Self { Error::InternalServerError }
 }
 
+impl From<std::fmt::Error> for Error {
+    fn from(_: std::fmt::Error) -> Self { Error::InternalServerError }
+}
+
+impl From<BlockingError> for Error {
+    fn from(_: BlockingError) -> Self { Error::InternalServerError }
+}
+
 impl ResponseError for Error {
     fn error_response(&self) -> HttpResponse<BoxBody> {
         match self {
             Error::InternalServerError => {
                 HttpResponse::InternalServerError().json("Internal Server Error")
             },
+            Error::ArtifactVerification => {
+                HttpResponse::BadGateway().json("Artifact does not belong to magnet link")
+            },
+            Error::MagnetNotFound => HttpResponse::NotFound().json("Magnet link was not found"),
+            Error::Publish(error) => {
+                HttpResponse::BadGateway().json(format!("Announcement was not published: {error}"))
+            },
         }
     }
 }
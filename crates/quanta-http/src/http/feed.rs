@@ -0,0 +1,58 @@
+use actix_web::{web, HttpResponse};
+
+use crate::{
+    http::{
+        error::QuantaHttpResponse,
+        util::{generate_error_response, StatusResponse},
+    },
+    state::HttpServerState,
+};
+
+/// Check that name of feed can be used as topic and as key prefix in database
+pub(crate) fn is_valid_topic(topic: &str) -> bool {
+    !topic.is_empty() && !topic.chars().any(char::is_control)
+}
+/// Return all announcements that were published in feed and stored in database
+pub async fn get_feed_announcements(
+    topic: web::Path<String>,
+    state: web::Data<HttpServerState>,
+) -> QuantaHttpResponse {
+    if !is_valid_topic(&topic) {
+        return generate_error_response("Invalid feed name");
+    }
+    Ok(HttpResponse::Ok().json(
+        state
+            .database()
+            .get_announcements(&topic)?,
+    ))
+}
+/// Subscribe to feed, so announcements that are published in it are stored in database
+pub async fn feed_subscribe_handler(
+    topic: web::Path<String>,
+    state: web::Data<HttpServerState>,
+) -> QuantaHttpResponse {
+    let topic = topic.into_inner();
+    if !is_valid_topic(&topic) {
+        return generate_error_response("Invalid feed name");
+    }
+    state
+        .network_proxy()
+        .subscribe_feed(topic)
+        .await?;
+    Ok(HttpResponse::Ok().json(StatusResponse { status: "Ok" }))
+}
+/// Unsubscribe from feed. Announcements that were stored are kept in database
+pub async fn feed_unsubscribe_handler(
+    topic: web::Path<String>,
+    state: web::Data<HttpServerState>,
+) -> QuantaHttpResponse {
+    let topic = topic.into_inner();
+    if !is_valid_topic(&topic) {
+        return generate_error_response("Invalid feed name");
+    }
+    state
+        .network_proxy()
+        .unsubscribe_feed(topic)
+        .await?;
+    Ok(HttpResponse::Ok().json(StatusResponse { status: "Ok" }))
+}
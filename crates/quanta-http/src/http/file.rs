@@ -1,11 +1,28 @@
+use std::collections::HashMap;
+
 use actix_multipart::Multipart;
-use actix_web::{web::Data, HttpRequest, HttpResponse};
-use futures::{StreamExt, TryStreamExt};
-use quanta_artifact::{Artifact, MagnetLink};
+use actix_web::{
+    http::header::ContentDisposition,
+    web::{Bytes, Data, Path, Query},
+    HttpRequest,
+    HttpResponse,
+};
+use futures::{channel::mpsc, stream, SinkExt, StreamExt, TryStreamExt};
+use log::{debug, warn};
+use quanta_artifact::{
+    Artifact,
+    ArtifactId,
+    ArtifactStreamReader,
+    Chunker,
+    MagnetLink,
+    MerkleTree,
+};
+use quanta_crypto::HashValue;
 
 use crate::{
     http::{
-        error::QuantaHttpResponse,
+        error::{Error, QuantaHttpResponse},
+        feed::is_valid_topic,
         magnet::MagnetLinkListResponse,
         util::generate_error_response,
     },
@@ -13,12 +30,38 @@ use crate::{
 };
 /// Name of field in [Multipart]
 const FILE_MULTIPART_FORM_FIELD_NAME: &str = "file";
-/// Upload InputFile into Network.
+/// Count of artifacts that are fetched from network with one want list when downloading file
+const DOWNLOAD_BATCH_SIZE: usize = 64;
+/// Query of [network_file_download_handler] and [network_file_download_by_root_handler]
+#[derive(serde::Deserialize, Debug)]
+pub struct FileDownloadQuery {
+    /// Hex-based merkle root of file. It should come from source that we are trust (e.g.
+    /// announcement or upload response), not from magnet link that is downloaded. Without it
+    /// file is verified with merkle root of magnet link itself
+    pub root: Option<String>,
+}
+/// Query of [network_file_upload_handler]
+#[derive(serde::Deserialize, Debug)]
+pub struct FileUploadQuery {
+    /// Name of feed where announcement of uploaded file is published
+    topic: Option<String>,
+}
+/// Upload InputFile into Network. If feed is given in query announcement of file is published in
+/// it
 pub async fn network_file_upload_handler(
     mut payload: Multipart,
     request: HttpRequest,
+    query: Query<FileUploadQuery>,
     state: Data<HttpServerState>,
 ) -> QuantaHttpResponse {
+    // artifacts are not pinned until magnet link is inserted, so garbage collection or eviction
+    // should not remove them while file is uploading
+    let mut hold = state.database().hold_artifacts();
+    if let Some(topic) = &query.topic {
+        if !is_valid_topic(topic) {
+            return generate_error_response("Invalid feed name");
+        }
+    }
     while let Some(Ok(mut field)) = payload.next().await {
         // check if name of field == "file"
         if field.name() == FILE_MULTIPART_FORM_FIELD_NAME {
@@ -36,29 +79,356 @@ pub async fn network_file_upload_handler(
             let file_name = file_name.to_string();
             // Create magnet link which updates when we are read new artifact
             let mut magnet_link = MagnetLink::new(file_name, size);
-            // Start read field with input file
-            while let Ok(Some(bytes)) = field.try_next().await {
-                // vec-based bytes for creating artifact
-                let data = bytes.to_vec();
-                let artifact = Artifact::new(data);
+            // Start read field with input file. Artifacts are cut by content of file, so the
+            // same file uploaded twice gives the same artifacts
+            let mut field_reader = field
+                .by_ref()
+                .map_err(|error| std::io::Error::other(error.to_string()))
+                .into_async_read();
+            let mut artifacts =
+                ArtifactStreamReader::with_chunker(&mut field_reader, Chunker::default());
+            while let Some(artifact) = artifacts.try_next().await? {
                 let artifact_id = artifact.id;
                 magnet_link.new_update_with_artifact_id(artifact_id);
-                state
-                    .database()
-                    .insert_artifact(artifact)?;
+                hold.insert_artifact(artifact)?;
             }
-            // when read is compeleted whe should save magnet link in storage
+            // when read is compeleted we should commit to all artifacts and save magnet link in
+            // storage
+            let merkle_root = magnet_link.update_merkle_root();
             let magnet_string = magnet_link.to_string();
             let index = state
                 .database()
-                .insert_magnet_link(magnet_link)?;
+                .insert_magnet_link(magnet_link.clone())?;
+            provide_magnet_link(&state, &magnet_link).await;
+            // magnet link is stored before announcement is published, so subscribers can fetch
+            // it by merkle root as soon as they receive announcement. Error of publish is
+            // returned, so uploader knows that feed was not notified
+            if let Some(topic) = query.into_inner().topic {
+                state
+                    .network_proxy()
+                    .publish_announcement(topic, magnet_link)
+                    .await?;
+            }
             // return the StatusResponse which indicates that the file was successfully uploaded
             return Ok(HttpResponse::Ok().json(MagnetLinkListResponse {
                 id: index,
                 magnet: magnet_string,
+                merkle_root: merkle_root.to_string(),
+                pinned: true,
             }));
         }
     }
 
     generate_error_response("Field 'file' does not provided in payload")
 }
+
+/// Download file from network by its [MagnetLink]. Artifacts are streamed in order of magnet
+/// link indexes. Artifacts that stored in [quanta_database::Database] are served locally, others
+/// are fetched from network and saved in database if there is space. Magnet link is checked
+/// against merkle root from query, or against its own root if query does not have it, and every
+/// artifact is verified with [quanta_artifact::MerkleProof] against the same root before it is
+/// saved or sent. Download that fails after response is started closes connection, so client
+/// does not get truncated file
+pub async fn network_file_download_handler(
+    magnet: Path<String>,
+    query: Query<FileDownloadQuery>,
+    state: Data<HttpServerState>,
+) -> QuantaHttpResponse {
+    let Ok(magnet_link) = MagnetLink::try_from(magnet.into_inner()) else {
+        return generate_error_response("Invalid magnet link");
+    };
+    let merkle_root = match query
+        .root
+        .as_deref()
+        .map(HashValue::try_from)
+    {
+        Some(Ok(merkle_root)) => merkle_root,
+        Some(Err(_)) => return generate_error_response("Invalid merkle root"),
+        // artifacts are still verified, so file matches at least magnet link
+        None => magnet_link.claimed_merkle_root(),
+    };
+    // artifact ids of magnet link that does not match trusted root can not be trusted
+    if !magnet_link.verify(&merkle_root) {
+        return generate_error_response("Magnet link does not match merkle root");
+    }
+    connect_to_providers(&state, &merkle_root).await;
+    download_file(state, magnet_link, merkle_root).await
+}
+/// Download file by merkle root only, e.g. from announcement of feed. Stored magnet link with
+/// this root is used, otherwise magnet link is fetched from providers of root and verified with
+/// it. Then file is downloaded like in [network_file_download_handler]
+pub async fn network_file_download_by_root_handler(
+    query: Query<FileDownloadQuery>,
+    state: Data<HttpServerState>,
+) -> QuantaHttpResponse {
+    let Some(Ok(merkle_root)) = query
+        .root
+        .as_deref()
+        .map(HashValue::try_from)
+    else {
+        return generate_error_response("Invalid merkle root");
+    };
+    connect_to_providers(&state, &merkle_root).await;
+    let magnet_link = match state
+        .database()
+        .get_magnet_link_by_root(&merkle_root)?
+    {
+        Some((_, magnet_link)) => magnet_link,
+        None => fetch_magnet_link(&state, &merkle_root).await?,
+    };
+    download_file(state, magnet_link, merkle_root).await
+}
+/// Providers of magnet root likely have magnet link and all artifacts of file, so we are wait
+/// until we are connected to them before anything is requested
+async fn connect_to_providers(state: &HttpServerState, merkle_root: &HashValue) {
+    match state
+        .network_proxy()
+        .find_providers(merkle_root.to_bytes())
+        .await
+    {
+        Ok(providers) => debug!("Connected to {} providers of magnet link", providers),
+        Err(error) => warn!(
+            "Got error when trying to find providers of magnet link: {}",
+            error
+        ),
+    }
+}
+/// Fetch [MagnetLink] from network by its merkle root. Peers serve magnet links with merkle root
+/// as key, so magnet link is fetched like artifact and verified with root
+async fn fetch_magnet_link(
+    state: &HttpServerState,
+    merkle_root: &HashValue,
+) -> Result<MagnetLink, Error> {
+    let key = ArtifactId::from_bytes(&merkle_root.to_bytes()).map_err(|_| Error::MagnetNotFound)?;
+    let item = state
+        .network_proxy()
+        .fetch_artifact(key)
+        .await
+        .map_err(|_| Error::MagnetNotFound)?;
+    match MagnetLink::from_bincode(item.data) {
+        Ok(magnet_link) if magnet_link.verify(merkle_root) => Ok(magnet_link),
+        _ => Err(Error::MagnetNotFound),
+    }
+}
+/// Stream file of verified [MagnetLink] batch by batch. The first batch is collected before
+/// response is started, so file that can not be downloaded at all returns error. Content-Length
+/// is sent only when every artifact is stored, otherwise size of file is not known until all
+/// artifacts are fetched
+async fn download_file(
+    state: Data<HttpServerState>,
+    magnet_link: MagnetLink,
+    merkle_root: HashValue,
+) -> QuantaHttpResponse {
+    let merkle_tree = magnet_link.merkle_tree();
+    let file_name = magnet_link.file_name().to_string();
+    let artifact_ids = magnet_link
+        .artifact_ids()
+        .into_iter()
+        .enumerate()
+        .collect::<Vec<(usize, ArtifactId)>>();
+    let file_size = artifact_ids
+        .iter()
+        .map(|(_, artifact_id)| {
+            state
+                .database()
+                .artifact_size(artifact_id)
+        })
+        .sum::<Option<u64>>();
+    let (sender, mut receiver) = mpsc::channel(1);
+    actix_web::rt::spawn(stream_artifacts(
+        state,
+        merkle_root,
+        merkle_tree,
+        artifact_ids,
+        sender,
+    ));
+    let first = match receiver.next().await {
+        Some(Ok(bytes)) => Some(Ok(bytes)),
+        Some(Err(error)) => return Err(error),
+        // file without artifacts is empty
+        None => None,
+    };
+    let mut response = HttpResponse::Ok();
+    response
+        .insert_header(ContentDisposition::attachment(file_name))
+        .content_type(mime::APPLICATION_OCTET_STREAM);
+    if let Some(file_size) = file_size {
+        response.no_chunking(file_size);
+    }
+    Ok(response.streaming(stream::iter(first).chain(receiver)))
+}
+/// Send artifacts of file into body of response in order of magnet link indexes. Artifacts are
+/// held until file is sent, so fetching later batches does not evict earlier ones. Error is
+/// sent when artifact can not be collected, so connection is closed and client does not take
+/// partial file as complete one. Sending stops when client is gone
+async fn stream_artifacts(
+    state: Data<HttpServerState>,
+    merkle_root: HashValue,
+    merkle_tree: MerkleTree,
+    artifact_ids: Vec<(usize, ArtifactId)>,
+    mut body: mpsc::Sender<Result<Bytes, Error>>,
+) {
+    let mut hold = state.database().hold_artifacts();
+    for (_, artifact_id) in &artifact_ids {
+        hold.hold(artifact_id);
+    }
+    for batch in artifact_ids.chunks(DOWNLOAD_BATCH_SIZE) {
+        let artifacts = match collect_artifacts(&state, &merkle_root, &merkle_tree, batch).await {
+            Ok(artifacts) => artifacts,
+            Err(error) => {
+                let _ = body.send(Err(error)).await;
+                return;
+            },
+        };
+        for artifact in artifacts {
+            if body
+                .send(Ok(Bytes::from(artifact)))
+                .await
+                .is_err()
+            {
+                debug!("Client is gone before file was sent");
+                return;
+            }
+        }
+    }
+}
+/// Announce in DHT that we are provide all artifacts and merkle root of magnet link. Keys are
+/// announced in order, so merkle root that downloaders look up goes first
+async fn provide_magnet_link(state: &HttpServerState, magnet_link: &MagnetLink) {
+    let mut keys = magnet_link
+        .merkle_root()
+        .map(|merkle_root| merkle_root.to_bytes())
+        .into_iter()
+        .collect::<Vec<Vec<u8>>>();
+    keys.extend(
+        magnet_link
+            .artifact_ids()
+            .into_iter()
+            .map(|artifact_id| artifact_id.to_bytes()),
+    );
+    provide_keys(state, keys).await;
+}
+/// Announce in DHT that we are provide items with given keys. Announce is not critical for
+/// request, so error is only logged
+async fn provide_keys(state: &HttpServerState, keys: Vec<Vec<u8>>) {
+    if let Err(error) = state
+        .network_proxy()
+        .start_providing(keys)
+        .await
+    {
+        warn!("Got error when trying to provide keys in DHT: {}", error);
+    }
+}
+/// Collect data of batch of artifacts in order. Stored artifacts are read from database, others
+/// are fetched from network with one want list and then one by one. Fetched artifact is sent only
+/// if it belongs to file with given merkle root, and it is saved into database if there is space
+/// for it
+async fn collect_artifacts(
+    state: &HttpServerState,
+    merkle_root: &HashValue,
+    merkle_tree: &MerkleTree,
+    batch: &[(usize, ArtifactId)],
+) -> Result<Vec<Vec<u8>>, Error> {
+    let mut collected = HashMap::new();
+    let mut missing = HashMap::new();
+    for (position, artifact_id) in batch {
+        if collected.contains_key(artifact_id) {
+            continue;
+        }
+        match state
+            .database()
+            .get_artifact(artifact_id)?
+        {
+            Some(artifact) => {
+                collected.insert(*artifact_id, artifact.data);
+            },
+            None => {
+                missing.insert(*artifact_id, *position);
+            },
+        }
+    }
+    let mut fetched = fetch_artifacts(state, merkle_root, merkle_tree, &missing).await;
+    // artifacts that were not fetched with want list are fetched one by one
+    for (artifact_id, position) in &missing {
+        if fetched.contains_key(artifact_id) {
+            continue;
+        }
+        let proof = merkle_tree
+            .proof(*position)
+            .ok_or(Error::ArtifactVerification)?;
+        let artifact = state
+            .network_proxy()
+            .fetch_artifact(*artifact_id)
+            .await?;
+        if artifact.id != *artifact_id || !proof.verify_artifact(merkle_root, &artifact) {
+            return Err(Error::ArtifactVerification);
+        }
+        fetched.insert(*artifact_id, artifact);
+    }
+    cache_artifacts(state, fetched.values()).await;
+    collected.extend(
+        fetched
+            .into_iter()
+            .map(|(artifact_id, artifact)| (artifact_id, artifact.data)),
+    );
+    batch
+        .iter()
+        .map(|(_, artifact_id)| {
+            collected
+                .get(artifact_id)
+                .cloned()
+                .ok_or(Error::ArtifactVerification)
+        })
+        .collect()
+}
+/// Fetch missing artifacts with one want list. Returns only artifacts that belong to file with
+/// given merkle root, others are fetched one by one later in [collect_artifacts]
+async fn fetch_artifacts(
+    state: &HttpServerState,
+    merkle_root: &HashValue,
+    merkle_tree: &MerkleTree,
+    missing: &HashMap<ArtifactId, usize>,
+) -> HashMap<ArtifactId, Artifact> {
+    if missing.is_empty() {
+        return HashMap::new();
+    }
+    let artifacts = match state
+        .network_proxy()
+        .fetch_artifacts(missing.keys().copied().collect())
+        .await
+    {
+        Ok(artifacts) => artifacts,
+        Err(error) => {
+            warn!(
+                "Got error when trying to fetch batch of artifacts: {}",
+                error
+            );
+            return HashMap::new();
+        },
+    };
+    artifacts
+        .into_iter()
+        .filter(|artifact| {
+            missing
+                .get(&artifact.id)
+                .and_then(|position| merkle_tree.proof(*position))
+                .is_some_and(|proof| proof.verify_artifact(merkle_root, artifact))
+        })
+        .map(|artifact| (artifact.id, artifact))
+        .collect()
+}
+/// Save fetched artifacts into database and announce that we are provide them, so other peers
+/// can fetch them from us. Artifacts that do not fit into storage are only sent to client
+async fn cache_artifacts(state: &HttpServerState, artifacts: impl Iterator<Item = &Artifact>) {
+    let mut provided = Vec::new();
+    for artifact in artifacts {
+        match state
+            .database()
+            .insert_artifact(artifact.clone())
+        {
+            Ok(()) => provided.push(artifact.id.to_bytes()),
+            Err(error) => debug!("Fetched artifact is not saved: {}", error),
+        }
+    }
+    provide_keys(state, provided).await;
+}
@@ -1,7 +1,14 @@
 use actix_web::{web, HttpResponse};
+use quanta_database::DatabaseError;
 use serde::{Deserialize, Serialize};
 
-use crate::{http::error::QuantaHttpResponse, state::HttpServerState};
+use crate::{
+    http::{
+        error::QuantaHttpResponse,
+        util::{ErrorResponse, StatusResponse},
+    },
+    state::HttpServerState,
+};
 
 /// HTTP-API Response-item that used in [get_magnet_links_list] handler
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -10,17 +17,77 @@ pub struct MagnetLinkListResponse {
     pub id: u64,
     /// String representation of [MagnetLink]
     pub magnet: String,
+    /// Hex-based merkle root of file that is used to verify magnet link when it is downloaded
+    pub merkle_root: String,
+    /// Pinned magnet link keeps its artifacts, artifacts of unpinned are cached
+    pub pinned: bool,
 }
 /// Return all correct magnets links that stored in database.
 pub async fn get_magnet_links_list(state: web::Data<HttpServerState>) -> QuantaHttpResponse {
-    let database = state.database().get_magnet_links()?;
-    Ok(HttpResponse::Ok().json(
-        database
-            .iter()
-            .map(|(id, magnet)| MagnetLinkListResponse {
+    let database = state.database();
+    let magnet_links = database
+        .get_magnet_links()?
+        .iter()
+        .map(|(id, magnet)| {
+            Ok(MagnetLinkListResponse {
                 id: *id,
                 magnet: magnet.to_string(),
+                merkle_root: magnet.merkle_tree().root().to_string(),
+                pinned: database.is_magnet_link_pinned(*id)?,
             })
-            .collect::<Vec<MagnetLinkListResponse>>(),
-    ))
+        })
+        .collect::<Result<Vec<MagnetLinkListResponse>, DatabaseError>>()?;
+    Ok(HttpResponse::Ok().json(magnet_links))
+}
+/// Delete magnet link with given index from database in thread pool of blocking tasks. Artifacts
+/// that are not used by other magnet links are removed only by garbage collection
+pub async fn delete_magnet_link_handler(
+    id: web::Path<u64>,
+    state: web::Data<HttpServerState>,
+) -> QuantaHttpResponse {
+    let index = id.into_inner();
+    let deleted = web::block(move || {
+        state
+            .database()
+            .delete_magnet_link(index)
+    })
+    .await??;
+    if !deleted {
+        return Ok(HttpResponse::NotFound().json(ErrorResponse {
+            error: "Magnet link was not found",
+        }));
+    }
+    Ok(HttpResponse::Ok().json(StatusResponse { status: "Ok" }))
+}
+/// Pin magnet link with given index, so its artifacts are not evicted or removed by garbage
+/// collection
+pub async fn pin_magnet_link_handler(
+    id: web::Path<u64>,
+    state: web::Data<HttpServerState>,
+) -> QuantaHttpResponse {
+    if !state
+        .database()
+        .pin_magnet_link(id.into_inner())?
+    {
+        return Ok(HttpResponse::NotFound().json(ErrorResponse {
+            error: "Magnet link was not found",
+        }));
+    }
+    Ok(HttpResponse::Ok().json(StatusResponse { status: "Ok" }))
+}
+/// Unpin magnet link with given index. Its artifacts that are not used by other pinned magnet
+/// links are cached: they are evicted when storage quota is reached
+pub async fn unpin_magnet_link_handler(
+    id: web::Path<u64>,
+    state: web::Data<HttpServerState>,
+) -> QuantaHttpResponse {
+    if !state
+        .database()
+        .unpin_magnet_link(id.into_inner())?
+    {
+        return Ok(HttpResponse::NotFound().json(ErrorResponse {
+            error: "Magnet link was not found",
+        }));
+    }
+    Ok(HttpResponse::Ok().json(StatusResponse { status: "Ok" }))
 }
@@ -0,0 +1,17 @@
+use actix_web::{web, HttpResponse};
+
+use crate::{http::error::QuantaHttpResponse, state::HttpServerState};
+
+/// Content type of Prometheus text format that is used by [prometheus_client]
+const METRICS_CONTENT_TYPE: &str = "application/openmetrics-text; version=1.0.0; charset=utf-8";
+
+/// Returns metrics of network, quanta-swap and database in Prometheus text format
+pub async fn get_metrics(state: web::Data<HttpServerState>) -> QuantaHttpResponse {
+    Ok(HttpResponse::Ok()
+        .content_type(METRICS_CONTENT_TYPE)
+        .body(
+            state
+                .metrics()
+                .encode(state.database())?,
+        ))
+}
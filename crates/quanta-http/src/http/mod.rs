@@ -1,6 +1,10 @@
+pub mod artifact;
 pub mod connection;
 mod error;
+pub mod feed;
 pub mod file;
 pub mod index;
 pub mod magnet;
+pub mod metrics;
+pub mod storage;
 mod util;
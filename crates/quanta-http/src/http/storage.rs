@@ -0,0 +1,23 @@
+use actix_web::{web, HttpResponse};
+use serde::{Deserialize, Serialize};
+
+use crate::{http::error::QuantaHttpResponse, state::HttpServerState};
+
+/// HTTP-API Response that used in [storage_gc_handler] handler
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct GarbageCollectionResponse {
+    /// Count of artifacts that were removed
+    pub artifacts: usize,
+    /// Size of artifacts that were removed in bytes
+    pub bytes: u64,
+}
+/// Remove cached artifacts: artifacts that are not used by any pinned magnet link. Artifacts of
+/// files that are uploading are not removed. Artifacts are removed in thread pool of blocking
+/// tasks, so workers of server are not blocked
+pub async fn storage_gc_handler(state: web::Data<HttpServerState>) -> QuantaHttpResponse {
+    let collection = web::block(move || state.database().gc()).await??;
+    Ok(HttpResponse::Ok().json(GarbageCollectionResponse {
+        artifacts: collection.artifacts,
+        bytes: collection.bytes,
+    }))
+}
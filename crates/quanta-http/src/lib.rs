@@ -1,7 +1,10 @@
 #![allow(dead_code)]
 mod http;
+mod metrics;
 mod routes;
 mod run;
 mod state;
+#[cfg(test)]
+mod test;
 
 pub use run::run_http_server;
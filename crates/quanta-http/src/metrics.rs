@@ -0,0 +1,74 @@
+use std::fmt;
+
+use prometheus_client::{
+    encoding::text::encode,
+    metrics::gauge::Gauge,
+    registry::{Registry, Unit},
+};
+use quanta_database::Database;
+
+/// Metrics that are served by HTTP-API. Contains [Registry] with metrics of network and
+/// gauges of [Database]
+pub struct HttpMetrics {
+    /// Registry with metrics of network and [DatabaseMetrics]
+    registry: Registry,
+    /// Gauges of [Database] that are updated before every encoding of [HttpMetrics::registry]
+    database: DatabaseMetrics,
+}
+
+impl HttpMetrics {
+    /// Create new [HttpMetrics] and register gauges of [Database] in given [Registry]
+    pub fn new(mut registry: Registry) -> Self {
+        let database = DatabaseMetrics::new(registry.sub_registry_with_prefix("quanta_database"));
+        HttpMetrics { registry, database }
+    }
+    /// Update gauges of [Database] and encode all metrics in Prometheus text format
+    pub fn encode(&self, database: &Database) -> Result<String, fmt::Error> {
+        self.database.update(database);
+        let mut encoded = String::new();
+        encode(&mut encoded, &self.registry)?;
+        Ok(encoded)
+    }
+}
+/// Gauges of [Database]
+struct DatabaseMetrics {
+    /// Count of artifacts that are stored
+    artifacts: Gauge,
+    /// Size of database in bytes
+    stored: Gauge,
+    /// Count of magnet links that are stored
+    magnets: Gauge,
+}
+
+impl DatabaseMetrics {
+    /// Create new [DatabaseMetrics] and register them in [Registry]
+    fn new(registry: &mut Registry) -> Self {
+        let artifacts = Gauge::default();
+        registry.register(
+            "artifacts",
+            "Number of artifacts that are stored",
+            artifacts.clone(),
+        );
+        let stored = Gauge::default();
+        registry.register_with_unit("stored", "Size of database", Unit::Bytes, stored.clone());
+        let magnets = Gauge::default();
+        registry.register(
+            "magnets",
+            "Number of magnet links that are stored",
+            magnets.clone(),
+        );
+        DatabaseMetrics {
+            artifacts,
+            stored,
+            magnets,
+        }
+    }
+    /// Set gauges to current state of [Database]
+    fn update(&self, database: &Database) {
+        self.artifacts
+            .set(database.artifact_count() as i64);
+        self.stored.set(database.size() as i64);
+        self.magnets
+            .set(database.magnet_count() as i64);
+    }
+}
@@ -1,23 +1,66 @@
-use actix_web::web::{get, post, scope, ServiceConfig};
+use actix_web::web::{delete, get, post, scope, ServiceConfig};
 
 use crate::http::{
-    connection::get_connections_list,
-    file::network_file_upload_handler,
+    artifact::artifact_search_handler,
+    connection::{dial_peer_handler, disconnect_peer_handler, get_connections_list, get_nat_info},
+    feed::{feed_subscribe_handler, feed_unsubscribe_handler, get_feed_announcements},
+    file::{
+        network_file_download_by_root_handler,
+        network_file_download_handler,
+        network_file_upload_handler,
+    },
     index::index,
-    magnet::get_magnet_links_list,
+    magnet::{
+        delete_magnet_link_handler,
+        get_magnet_links_list,
+        pin_magnet_link_handler,
+        unpin_magnet_link_handler,
+    },
+    metrics::get_metrics,
+    storage::storage_gc_handler,
 };
 
 /// Initialize all Quanta HTTP-API Handler-Routes
 pub fn api_routes(application_config: &mut ServiceConfig) {
     application_config
         .route("/", get().to(index))
+        .route("/metrics", get().to(get_metrics))
         .service(
             scope("/api").service(
                 scope("/v1")
-                    .service(scope("/connection").route("/list", get().to(get_connections_list)))
-                    .service(scope("/magnet").route("/list", get().to(get_magnet_links_list)))
                     .service(
-                        scope("/file").route("/upload", post().to(network_file_upload_handler)),
+                        scope("/connection")
+                            .route("/list", get().to(get_connections_list))
+                            .route("/nat", get().to(get_nat_info))
+                            .route("/dial", post().to(dial_peer_handler))
+                            .route("/{peer}", delete().to(disconnect_peer_handler)),
+                    )
+                    .service(
+                        scope("/feed")
+                            .route("/{topic}", get().to(get_feed_announcements))
+                            .route("/{topic}/subscribe", post().to(feed_subscribe_handler))
+                            .route("/{topic}/unsubscribe", post().to(feed_unsubscribe_handler)),
+                    )
+                    .service(
+                        scope("/magnet")
+                            .route("/list", get().to(get_magnet_links_list))
+                            .route("/{id}", delete().to(delete_magnet_link_handler))
+                            .route("/{id}/pin", post().to(pin_magnet_link_handler))
+                            .route("/{id}/unpin", post().to(unpin_magnet_link_handler)),
+                    )
+                    .service(scope("/storage").route("/gc", post().to(storage_gc_handler)))
+                    .service(
+                        scope("/artifact")
+                            .route("/search/{artifact_id}", get().to(artifact_search_handler)),
+                    )
+                    .service(
+                        scope("/file")
+                            .route("/upload", post().to(network_file_upload_handler))
+                            .route("/download", get().to(network_file_download_by_root_handler))
+                            .route(
+                                "/download/{magnet}",
+                                get().to(network_file_download_handler),
+                            ),
                     ),
             ),
         );
@@ -1,10 +1,11 @@
 use std::{net, sync::Arc};
 
 use actix_web::{middleware::Logger, web, App, HttpServer};
+use prometheus_client::registry::Registry;
 use quanta_database::Database;
 use quanta_network::QuantaNetworkServiceProxy;
 
-use crate::{routes::api_routes, state::HttpServerState};
+use crate::{metrics::HttpMetrics, routes::api_routes, state::HttpServerState};
 
 #[derive(thiserror::Error, Debug)]
 pub enum RunError {
@@ -14,18 +15,22 @@ pub enum RunError {
     /// Error whill occur when trying to run new [HttpServer]
     RunServer(std::io::Error),
 }
-/// Create and run new [`HttpServer`] with all specified api-handlers
+/// Create and run new [`HttpServer`] with all specified api-handlers. Metrics from given
+/// [`Registry`] are served together with metrics of [`Database`]
 pub async fn run_http_server<A: net::ToSocketAddrs>(
     addrs: A,
     database: Arc<Database>,
     network_proxy: Arc<QuantaNetworkServiceProxy>,
+    registry: Registry,
 ) -> Result<(), RunError> {
+    let metrics = Arc::new(HttpMetrics::new(registry));
     HttpServer::new(move || {
         App::new()
             .wrap(Logger::default())
             .app_data(web::Data::new(HttpServerState::new(
                 Arc::clone(&database),
                 Arc::clone(&network_proxy),
+                Arc::clone(&metrics),
             )))
             .configure(api_routes)
     })
@@ -0,0 +1,298 @@
+use std::{collections::HashSet, sync::Arc};
+
+use actix_web::{
+    http::{header, Method, StatusCode},
+    test,
+    web,
+    App,
+};
+use libp2p::PeerId;
+use prometheus_client::registry::Registry;
+use quanta_artifact::{Artifact, MagnetLink};
+use quanta_database::{Database, DatabaseConfig, MemoryBackend};
+use quanta_feed::Announcement;
+use quanta_network::{IntoNetworkEvent, QuantaNetworkServiceProxy};
+use tokio::sync::{broadcast, mpsc};
+
+use crate::{
+    http::{connection::DialResponse, storage::GarbageCollectionResponse},
+    metrics::HttpMetrics,
+    routes::api_routes,
+    state::HttpServerState,
+};
+
+/// Database that keeps everything in memory
+fn memory_database() -> Arc<Database> {
+    Arc::new(Database::with_backend(MemoryBackend::default(), DatabaseConfig::default()).unwrap())
+}
+
+/// Start fake network that answers dials and disconnects of proxy. Only peers from `reachable`
+/// can be dialed, peer is connected until it is disconnected
+fn fake_network(reachable: Vec<PeerId>) -> Arc<QuantaNetworkServiceProxy> {
+    let (proxy_tx, _) = broadcast::channel(16);
+    let (network_tx, mut network_rx) = mpsc::channel(16);
+    actix_web::rt::spawn(async move {
+        let mut connected = HashSet::new();
+        while let Some(event) = network_rx.recv().await {
+            match event {
+                IntoNetworkEvent::Dial {
+                    peer_id,
+                    response_channel,
+                    ..
+                } => {
+                    let result = if reachable.contains(&peer_id) {
+                        connected.insert(peer_id);
+                        Ok(())
+                    } else {
+                        Err("Connection refused".to_string())
+                    };
+                    let _ = response_channel.send(result);
+                },
+                IntoNetworkEvent::Disconnect {
+                    peer_id,
+                    response_channel,
+                } => {
+                    let _ = response_channel.send(connected.remove(&peer_id));
+                },
+                event => panic!("fake network got unexpected event {:?}", event),
+            }
+        }
+    });
+    Arc::new(QuantaNetworkServiceProxy::new(proxy_tx, network_tx))
+}
+
+/// State of server with given database and network
+fn server_state(
+    database: Arc<Database>,
+    network_proxy: Arc<QuantaNetworkServiceProxy>,
+) -> web::Data<HttpServerState> {
+    web::Data::new(HttpServerState::new(
+        database,
+        network_proxy,
+        Arc::new(HttpMetrics::new(Registry::default())),
+    ))
+}
+
+/// Insert magnet link of given artifacts, artifacts are stored too. Returns index of magnet link
+fn insert_file(database: &Database, artifacts: Vec<Artifact>) -> u64 {
+    let size = artifacts
+        .iter()
+        .map(|artifact| artifact.data.len())
+        .sum();
+    let mut magnet_link = MagnetLink::new("beep.txt".to_string(), size);
+    for artifact in artifacts {
+        magnet_link.new_update_with_artifact_id(artifact.id);
+        database
+            .insert_artifact(artifact)
+            .unwrap();
+    }
+    magnet_link.update_merkle_root();
+    database
+        .insert_magnet_link(magnet_link)
+        .unwrap()
+}
+
+#[actix_web::test]
+async fn test_dial_and_disconnect_peer() {
+    let reachable = PeerId::random();
+    let unreachable = PeerId::random();
+    let app = test::init_service(
+        App::new()
+            .app_data(server_state(
+                memory_database(),
+                fake_network(vec![reachable]),
+            ))
+            .configure(api_routes),
+    )
+    .await;
+    let dial = |address: String| {
+        test::TestRequest::post()
+            .uri("/api/v1/connection/dial")
+            .set_json(serde_json::json!({ "address": address }))
+            .to_request()
+    };
+    let response: DialResponse = test::call_and_read_body_json(
+        &app,
+        dial(format!("/ip4/127.0.0.1/tcp/4001/p2p/{reachable}")),
+    )
+    .await;
+    assert_eq!(response.peer_id, reachable.to_string());
+    let response = test::call_service(&app, dial("not an address".to_string())).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    // peer id is required to know when dialed peer is connected
+    let response = test::call_service(&app, dial("/ip4/127.0.0.1/tcp/4001".to_string())).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let response = test::call_service(
+        &app,
+        dial(format!("/ip4/127.0.0.1/tcp/4001/p2p/{unreachable}")),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::BAD_GATEWAY);
+
+    let disconnect = |peer: String| {
+        test::TestRequest::delete()
+            .uri(&format!("/api/v1/connection/{peer}"))
+            .to_request()
+    };
+    let response = test::call_service(&app, disconnect(reachable.to_string())).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let response = test::call_service(&app, disconnect(reachable.to_string())).await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    let response = test::call_service(&app, disconnect("peer".to_string())).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[actix_web::test]
+async fn test_pin_unpin_and_delete_magnet_link() {
+    let database = memory_database();
+    let index = insert_file(&database, vec![Artifact::new(vec![1; 100])]);
+    let app = test::init_service(
+        App::new()
+            .app_data(server_state(
+                Arc::clone(&database),
+                fake_network(Vec::new()),
+            ))
+            .configure(api_routes),
+    )
+    .await;
+    let request = |method: Method, uri: String| {
+        test::TestRequest::default()
+            .method(method)
+            .uri(&uri)
+            .to_request()
+    };
+    let response = test::call_service(
+        &app,
+        request(Method::POST, format!("/api/v1/magnet/{index}/unpin")),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(!database
+        .is_magnet_link_pinned(index)
+        .unwrap());
+    let response = test::call_service(
+        &app,
+        request(Method::POST, format!("/api/v1/magnet/{index}/pin")),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(database
+        .is_magnet_link_pinned(index)
+        .unwrap());
+    let response = test::call_service(
+        &app,
+        request(Method::DELETE, format!("/api/v1/magnet/{index}")),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(database.magnet_count(), 0);
+    // magnet link that does not exist is not found by every handler
+    for (method, uri) in [
+        (Method::DELETE, format!("/api/v1/magnet/{index}")),
+        (Method::POST, format!("/api/v1/magnet/{index}/pin")),
+        (Method::POST, format!("/api/v1/magnet/{index}/unpin")),
+    ] {
+        let response = test::call_service(&app, request(method, uri)).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+}
+
+#[actix_web::test]
+async fn test_storage_gc_removes_cached_artifacts() {
+    let database = memory_database();
+    let pinned = Artifact::new(vec![1; 100]);
+    let cached = Artifact::new(vec![2; 50]);
+    let index = insert_file(&database, vec![pinned.clone()]);
+    database
+        .insert_artifact(cached.clone())
+        .unwrap();
+    let app = test::init_service(
+        App::new()
+            .app_data(server_state(
+                Arc::clone(&database),
+                fake_network(Vec::new()),
+            ))
+            .configure(api_routes),
+    )
+    .await;
+    let gc = || {
+        test::TestRequest::post()
+            .uri("/api/v1/storage/gc")
+            .to_request()
+    };
+    let collection: GarbageCollectionResponse = test::call_and_read_body_json(&app, gc()).await;
+    assert_eq!(collection.artifacts, 1);
+    assert_eq!(collection.bytes, 50);
+    assert!(database
+        .get_artifact(&cached.id)
+        .unwrap()
+        .is_none());
+    assert!(database
+        .get_artifact(&pinned.id)
+        .unwrap()
+        .is_some());
+    // artifacts of unpinned magnet link become cached
+    database
+        .unpin_magnet_link(index)
+        .unwrap();
+    let collection: GarbageCollectionResponse = test::call_and_read_body_json(&app, gc()).await;
+    assert_eq!(collection.artifacts, 1);
+    assert_eq!(collection.bytes, 100);
+}
+
+#[actix_web::test]
+async fn test_feed_announcements() {
+    let database = memory_database();
+    let mut magnet_link = MagnetLink::new("beep.txt".to_string(), 100);
+    magnet_link.new_update_with_artifact_id(Artifact::new(vec![1; 100]).id);
+    let announcement = Announcement::new(&magnet_link, PeerId::random());
+    database
+        .insert_announcement("news", &announcement)
+        .unwrap();
+    let app = test::init_service(
+        App::new()
+            .app_data(server_state(database, fake_network(Vec::new())))
+            .configure(api_routes),
+    )
+    .await;
+    let feed = |topic: &str| {
+        test::TestRequest::get()
+            .uri(&format!("/api/v1/feed/{topic}"))
+            .to_request()
+    };
+    let announcements: Vec<Announcement> = test::call_and_read_body_json(&app, feed("news")).await;
+    assert_eq!(announcements, vec![announcement]);
+    let announcements: Vec<Announcement> = test::call_and_read_body_json(&app, feed("other")).await;
+    assert!(announcements.is_empty());
+}
+
+#[actix_web::test]
+async fn test_metrics() {
+    let database = memory_database();
+    insert_file(&database, vec![Artifact::new(vec![1; 100])]);
+    let app = test::init_service(
+        App::new()
+            .app_data(server_state(database, fake_network(Vec::new())))
+            .configure(api_routes),
+    )
+    .await;
+    let response = test::call_service(
+        &app,
+        test::TestRequest::get()
+            .uri("/metrics")
+            .to_request(),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .unwrap()
+        .to_str()
+        .unwrap()
+        .starts_with("application/openmetrics-text"));
+    let body = String::from_utf8(test::read_body(response).await.to_vec()).unwrap();
+    assert!(body.contains("quanta_database_artifacts 1\n"));
+    assert!(body.contains("quanta_database_magnets 1\n"));
+    assert!(body.ends_with("# EOF\n"));
+}
@@ -0,0 +1,122 @@
+use std::path::PathBuf;
+
+use clap::{Parser, Subcommand};
+
+use crate::{client::Client, config::Config, offline, run};
+
+/// Quanta is a peer to peer file sharing network. Without subcommand daemon is started
+#[derive(Parser, Debug)]
+#[command(name = "quanta", version, about)]
+pub struct Cli {
+    /// Path of toml config file. By default `~/.quanta/config.toml` is used if it exists
+    #[arg(long, global = true)]
+    pub(crate) config: Option<PathBuf>,
+    /// Address of HTTP-API of running daemon. By default first HTTP address from config is used
+    #[arg(long, global = true)]
+    pub(crate) api: Option<String>,
+    #[command(subcommand)]
+    pub(crate) command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug)]
+pub(crate) enum Command {
+    /// Run daemon with p2p network and HTTP-API
+    Daemon,
+    /// Add file and print its magnet link and merkle root
+    Add {
+        /// Path of file
+        path: PathBuf,
+        /// Write file straight into database instead of sending it to daemon. Daemon should be
+        /// stopped, because database can not be opened twice
+        #[arg(long)]
+        offline: bool,
+        /// Publish announcement of file in feed with given name
+        #[arg(long, conflicts_with = "offline")]
+        topic: Option<String>,
+    },
+    /// Download file by its magnet link or merkle root
+    Get {
+        /// Magnet link of file. If not set magnet link with given merkle root is taken from
+        /// storage or fetched from network
+        magnet: Option<String>,
+        /// Hex-based merkle root of file that magnet link and artifacts are verified with. If not
+        /// set file is verified with merkle root of magnet link
+        #[arg(long, required_unless_present = "magnet")]
+        root: Option<String>,
+        /// Path where file is saved
+        #[arg(short, long)]
+        output: PathBuf,
+        /// Read file straight from database instead of daemon. All artifacts of file should be
+        /// stored locally
+        #[arg(long)]
+        offline: bool,
+    },
+    /// List magnet links that daemon stores
+    Ls,
+    /// List peers that daemon is connected to
+    Peers,
+    /// Search artifact in network by its id
+    Search {
+        /// Bs58-based id of artifact
+        artifact_id: String,
+        /// Path where artifact is saved. If not set only size of artifact is printed
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// List announcements that daemon received in feed
+    Feed {
+        /// Name of feed
+        topic: String,
+    },
+    /// Subscribe daemon to feed
+    Subscribe {
+        /// Name of feed
+        topic: String,
+    },
+    /// Unsubscribe daemon from feed
+    Unsubscribe {
+        /// Name of feed
+        topic: String,
+    },
+}
+
+impl Cli {
+    /// Execute subcommand that we are get from command line
+    pub async fn execute(self) -> Result<(), Box<dyn std::error::Error>> {
+        let config = Config::load(self.config.as_deref())?;
+        let client = Client::new(
+            self.api
+                .unwrap_or_else(|| config.api_url()),
+        );
+        match self.command.unwrap_or(Command::Daemon) {
+            Command::Daemon => run::run(config).await,
+            Command::Add {
+                path,
+                offline: true,
+                ..
+            } => offline::add(&config, path).await,
+            Command::Add { path, topic, .. } => Ok(client.add(path, topic)?),
+            Command::Get {
+                magnet,
+                root,
+                output,
+                offline: true,
+            } => offline::get(&config, magnet, root, output).await,
+            Command::Get {
+                magnet,
+                root,
+                output,
+                ..
+            } => Ok(client.get(magnet, root, output)?),
+            Command::Ls => Ok(client.ls()?),
+            Command::Peers => Ok(client.peers()?),
+            Command::Search {
+                artifact_id,
+                output,
+            } => Ok(client.search(artifact_id, output)?),
+            Command::Feed { topic } => Ok(client.feed(topic)?),
+            Command::Subscribe { topic } => Ok(client.subscribe(topic)?),
+            Command::Unsubscribe { topic } => Ok(client.unsubscribe(topic)?),
+        }
+    }
+}
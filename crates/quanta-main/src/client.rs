@@ -0,0 +1,237 @@
+use std::{
+    fs::File,
+    io::{Read, Write},
+    path::{Path, PathBuf},
+};
+
+use serde::Deserialize;
+
+/// Boundary of multipart body that is sent in [Client::add]
+const MULTIPART_BOUNDARY: &str = "quanta-multipart-boundary";
+
+#[derive(thiserror::Error, Debug)]
+pub enum ClientError {
+    #[error("Got error when sending request to daemon: {0}")]
+    /// Error whill occur when daemon is not running or returned error status
+    Request(#[from] Box<ureq::Error>),
+    #[error("Got io error: {0}")]
+    /// Error whill occur when reading or writing files
+    Io(#[from] std::io::Error),
+    #[error("File path does not contain file name")]
+    /// Error whill occur when trying to add path without file name
+    FileName,
+}
+
+impl From<ureq::Error> for ClientError {
+    fn from(error: ureq::Error) -> Self { ClientError::Request(Box::new(error)) }
+}
+
+/// Magnet link that daemon returns in `/api/v1/magnet/list` and `/api/v1/file/upload`
+#[derive(Deserialize, Debug)]
+struct MagnetLinkResponse {
+    /// Index of magnet link
+    id: u64,
+    /// String representation of magnet link
+    magnet: String,
+    /// Hex-based merkle root of file
+    merkle_root: String,
+}
+
+/// Announcement that daemon returns in `/api/v1/feed/{topic}`
+#[derive(Deserialize, Debug)]
+struct AnnouncementResponse {
+    /// Hex-based merkle root of file
+    merkle_root: String,
+    /// Name of published file
+    file_name: String,
+    /// Size of published file
+    size: usize,
+    /// Peer that published file
+    publisher: String,
+}
+
+/// Blocking client of HTTP-API of running daemon
+pub struct Client {
+    /// Base url of HTTP-API
+    api: String,
+    /// Agent that used for all requests
+    agent: ureq::Agent,
+}
+
+impl Client {
+    /// Create new [Client] with given base url of HTTP-API
+    pub fn new(api: String) -> Self {
+        Self {
+            api: api.trim_end_matches('/').to_string(),
+            agent: ureq::Agent::new(),
+        }
+    }
+    /// Returns full url of api path
+    fn url(&self, path: &str) -> String { format!("{}{}", self.api, path) }
+    /// Upload file into daemon and print magnet link. If topic is set announcement of file is
+    /// published in feed
+    pub fn add(&self, path: PathBuf, topic: Option<String>) -> Result<(), ClientError> {
+        let file_name = path
+            .file_name()
+            .and_then(|file_name| file_name.to_str())
+            .ok_or(ClientError::FileName)?
+            .replace('"', "");
+        let file = File::open(&path)?;
+        let head = format!(
+            "--{MULTIPART_BOUNDARY}\r\nContent-Disposition: form-data; name=\"file\"; \
+             filename=\"{file_name}\"\r\nContent-Type: application/octet-stream\r\n\r\n"
+        );
+        let tail = format!("\r\n--{MULTIPART_BOUNDARY}--\r\n");
+        let content_length = head.len() as u64 + file.metadata()?.len() + tail.len() as u64;
+        // file is streamed, so we are dont need to keep it in memory
+        let body = head
+            .as_bytes()
+            .chain(file)
+            .chain(tail.as_bytes());
+        let mut request = self
+            .agent
+            .post(&self.url("/api/v1/file/upload"));
+        if let Some(topic) = &topic {
+            request = request.query("topic", topic);
+        }
+        let response: MagnetLinkResponse = request
+            .set(
+                "Content-Type",
+                &format!("multipart/form-data; boundary={MULTIPART_BOUNDARY}"),
+            )
+            .set("Content-Length", &content_length.to_string())
+            .send(body)?
+            .into_json()?;
+        println!("{}\t{}", response.magnet, response.merkle_root);
+        Ok(())
+    }
+    /// Download file by magnet link from daemon and save it into output. Without magnet link
+    /// daemon finds it by merkle root. Daemon verifies file with given merkle root or with root of
+    /// magnet link if it is not given
+    pub fn get(
+        &self,
+        magnet: Option<String>,
+        root: Option<String>,
+        output: PathBuf,
+    ) -> Result<(), ClientError> {
+        let path = match magnet {
+            Some(magnet) => format!("/api/v1/file/download/{magnet}"),
+            None => "/api/v1/file/download".to_string(),
+        };
+        let mut request = self.agent.get(&self.url(&path));
+        if let Some(root) = root {
+            request = request.query("root", &root);
+        }
+        let mut reader = request.call()?.into_reader();
+        let written = save_into(&mut reader, &output)?;
+        println!("Saved {} bytes into {}", written, output.display());
+        Ok(())
+    }
+    /// Print magnet links that daemon stores
+    pub fn ls(&self) -> Result<(), ClientError> {
+        let magnet_links: Vec<MagnetLinkResponse> = self
+            .agent
+            .get(&self.url("/api/v1/magnet/list"))
+            .call()?
+            .into_json()?;
+        for magnet_link in magnet_links {
+            println!(
+                "{}\t{}\t{}",
+                magnet_link.id, magnet_link.magnet, magnet_link.merkle_root
+            );
+        }
+        Ok(())
+    }
+    /// Print peers that daemon is connected to
+    pub fn peers(&self) -> Result<(), ClientError> {
+        let connections: serde_json::Map<String, serde_json::Value> = self
+            .agent
+            .get(&self.url("/api/v1/connection/list"))
+            .call()?
+            .into_json()?;
+        for (peer_id, info) in connections {
+            let agent_version = info
+                .pointer("/identify_info/agent_version")
+                .and_then(|agent_version| agent_version.as_str())
+                .unwrap_or("-");
+            let rtt = info
+                .pointer("/rtt/nanos")
+                .and_then(|nanos| nanos.as_u64())
+                .map(|nanos| format!("{:.2}ms", nanos as f64 / 1_000_000.0))
+                .unwrap_or_else(|| "-".to_string());
+            let is_mdns = info
+                .get("is_mdns")
+                .and_then(|is_mdns| is_mdns.as_bool())
+                .unwrap_or(false);
+            let transport = info
+                .get("transport")
+                .and_then(|transport| transport.as_str())
+                .unwrap_or("-");
+            println!(
+                "{peer_id}\ttransport={transport}\trtt={rtt}\tmdns={is_mdns}\tagent={agent_version}"
+            );
+        }
+        Ok(())
+    }
+    /// Print announcements that daemon received in feed
+    pub fn feed(&self, topic: String) -> Result<(), ClientError> {
+        let announcements: Vec<AnnouncementResponse> = self
+            .agent
+            .get(&self.url(&format!("/api/v1/feed/{topic}")))
+            .call()?
+            .into_json()?;
+        for announcement in announcements {
+            println!(
+                "{}	{}	size={}	publisher={}",
+                announcement.merkle_root,
+                announcement.file_name,
+                announcement.size,
+                announcement.publisher
+            );
+        }
+        Ok(())
+    }
+    /// Subscribe daemon to feed
+    pub fn subscribe(&self, topic: String) -> Result<(), ClientError> {
+        self.agent
+            .post(&self.url(&format!("/api/v1/feed/{topic}/subscribe")))
+            .call()?;
+        println!("Subscribed to {topic}");
+        Ok(())
+    }
+    /// Unsubscribe daemon from feed
+    pub fn unsubscribe(&self, topic: String) -> Result<(), ClientError> {
+        self.agent
+            .post(&self.url(&format!("/api/v1/feed/{topic}/unsubscribe")))
+            .call()?;
+        println!("Unsubscribed from {topic}");
+        Ok(())
+    }
+    /// Search artifact in network and save it into output if it is set
+    pub fn search(&self, artifact_id: String, output: Option<PathBuf>) -> Result<(), ClientError> {
+        let mut reader = self
+            .agent
+            .get(&self.url(&format!("/api/v1/artifact/search/{artifact_id}")))
+            .call()?
+            .into_reader();
+        match output {
+            Some(output) => {
+                let written = save_into(&mut reader, &output)?;
+                println!("Saved {} bytes into {}", written, output.display());
+            },
+            None => {
+                let mut data = Vec::new();
+                reader.read_to_end(&mut data)?;
+                println!("Found artifact {} of {} bytes", artifact_id, data.len());
+            },
+        }
+        Ok(())
+    }
+}
+/// Copy reader into new file. Returns count of written bytes
+fn save_into(reader: &mut impl Read, output: &Path) -> Result<u64, ClientError> {
+    let mut file = File::create(output)?;
+    let written = std::io::copy(reader, &mut file)?;
+    file.flush()?;
+    Ok(written)
+}
@@ -0,0 +1,434 @@
+use std::{
+    net::SocketAddr,
+    num::NonZeroUsize,
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::Duration,
+};
+
+use libp2p::{
+    pnet::{KeyParseError, PreSharedKey},
+    Multiaddr,
+    PeerId,
+};
+use quanta_database::{DatabaseConfig, StorageBackend};
+use quanta_network::QuantaNetworkConfig;
+use serde::Deserialize;
+
+/// Name of application folder in `HOME` dirrectory
+const QUANTA_APPLICATION_PATH_FOLDER_NAME: &str = ".quanta";
+/// Name of config file in application folder. Used when `--config` is not set
+const QUANTA_CONFIG_FILE_NAME: &str = "config.toml";
+/// Default address of HTTP-API
+const QUANTA_HTTP_SERVER_ADDR: &str = "127.0.0.1:51255";
+/// Separator of lists in environment variables
+const ENV_LIST_SEPARATOR: char = ',';
+
+#[derive(thiserror::Error, Debug)]
+pub enum ConfigError {
+    #[error("Got error when trying to read config file {0}: {1}")]
+    /// Error whill occur when config file can not be read
+    Read(PathBuf, std::io::Error),
+    #[error("Got error when trying to parse config file {0}: {1}")]
+    /// Error whill occur when config file is not valid toml or has unknown fields
+    Parse(PathBuf, toml::de::Error),
+    #[error("Invalid value of environment variable {0}: {1}")]
+    /// Error whill occur when `QUANTA_*` environment variable can not be parsed
+    Env(&'static str, String),
+    #[error("Got error when trying to read pre-shared key file {0}: {1}")]
+    /// Error whill occur when key file of private network can not be read
+    PskRead(PathBuf, std::io::Error),
+    #[error("Got error when trying to parse pre-shared key file {0}: {1}")]
+    /// Error whill occur when key file of private network is not in `swarm.key` format
+    PskParse(PathBuf, KeyParseError),
+}
+
+/// Configuration of daemon. Loaded from toml file and then overridden with `QUANTA_*`
+/// environment variables
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Folder where keys and database are stored. Env: `QUANTA_DATA_DIR`
+    pub data_dir: PathBuf,
+    /// HTTP-API settings
+    pub http: HttpConfig,
+    /// P2P network settings
+    pub network: NetworkConfig,
+    /// Database settings
+    pub storage: StorageConfig,
+    /// Quanta-swap settings
+    pub swap: SwapConfig,
+}
+
+/// Settings of HTTP-API
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct HttpConfig {
+    /// Addresses that HTTP-API binds to. Env: `QUANTA_HTTP_BIND`
+    pub bind: Vec<SocketAddr>,
+}
+
+/// Settings of P2P network
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct NetworkConfig {
+    /// Multiaddrs that swarm listens on. Env: `QUANTA_LISTEN`
+    pub listen: Vec<Multiaddr>,
+    /// Addresses that other peers can dial us on, for example public address of relay server.
+    /// Env: `QUANTA_EXTERNAL`
+    pub external: Vec<Multiaddr>,
+    /// Peers that dialed on start, should end with `/p2p/<peer-id>`. Env: `QUANTA_BOOTSTRAP`
+    pub bootstrap: Vec<Multiaddr>,
+    /// Discover peers on local network. Env: `QUANTA_MDNS`
+    pub mdns: bool,
+    /// Relays that used when node is behind NAT, should end with `/p2p/<peer-id>`.
+    /// Env: `QUANTA_RELAYS`
+    pub relays: Vec<Multiaddr>,
+    /// Relay connections of other peers. Env: `QUANTA_RELAY_SERVER`
+    pub relay_server: bool,
+    /// Names of feeds that daemon is subscribed to on start. Env: `QUANTA_FEEDS`
+    pub feeds: Vec<String>,
+    /// Key file of private network in go-libp2p `swarm.key` format. If set only peers with the
+    /// same key can connect and quic is not used. Env: `QUANTA_PSK_FILE`
+    pub psk_file: Option<PathBuf>,
+    /// If not empty only these peers can connect. Env: `QUANTA_ALLOWED_PEERS`
+    pub allowed_peers: Vec<PeerId>,
+    /// Peers that can not connect. Env: `QUANTA_DENIED_PEERS`
+    pub denied_peers: Vec<PeerId>,
+    /// Max count of peers that are remembered to dial them after restart.
+    /// Env: `QUANTA_MAX_KNOWN_PEERS`
+    pub max_known_peers: usize,
+    /// Max count of remembered peers that are dialed on start. Env: `QUANTA_MAX_STARTUP_DIALS`
+    pub max_startup_dials: usize,
+    /// Do not remember loopback, private and link-local addresses of peers.
+    /// Env: `QUANTA_ONLY_GLOBAL_ADDRS`
+    pub only_global_addrs: bool,
+}
+
+/// Settings of database
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct StorageConfig {
+    /// Backend where artifacts and magnet links are stored: `sled`, `memory` or `filesystem`.
+    /// Env: `QUANTA_STORAGE_BACKEND`
+    pub backend: StorageBackend,
+    /// Size of page cache in bytes. Env: `QUANTA_STORAGE_CACHE_CAPACITY`
+    pub cache_capacity: u64,
+    /// Max size of stored artifacts in bytes, not limited if not set. When it is reached least
+    /// recently used artifacts of unpinned magnet links are evicted. Env: `QUANTA_STORAGE_MAX_SIZE`
+    pub max_size: Option<u64>,
+    /// Max count of announcements that are kept in one feed, oldest are removed when it is
+    /// reached. Env: `QUANTA_STORAGE_MAX_FEED_ANNOUNCEMENTS`
+    pub max_feed_announcements: usize,
+}
+
+/// Settings of quanta-swap: rate limits and bans of peers that request artifacts from us
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct SwapConfig {
+    /// Max requests per second that are served to one peer, not limited if not set.
+    /// Env: `QUANTA_SWAP_MAX_REQUESTS_PER_SECOND`
+    pub max_requests_per_second: Option<u32>,
+    /// Max bytes of artifacts per second that are served to one peer, not limited if not set.
+    /// Env: `QUANTA_SWAP_MAX_SERVED_BYTES_PER_SECOND`
+    pub max_served_bytes_per_second: Option<u64>,
+    /// Requests over rate limits after which peer is banned.
+    /// Env: `QUANTA_SWAP_MAX_RATE_LIMIT_VIOLATIONS`
+    pub max_rate_limit_violations: u32,
+    /// Invalid artifacts after which peer is banned. Env: `QUANTA_SWAP_MAX_INVALID_RESPONSES`
+    pub max_invalid_responses: u32,
+    /// Seconds for which peer is banned. Env: `QUANTA_SWAP_BAN_DURATION`
+    pub ban_duration: u64,
+    /// Requests which artifacts are looked up at once, other requests wait and peers that send
+    /// artifacts to us are served first. Env: `QUANTA_SWAP_MAX_CONCURRENT_LOOKUPS`
+    pub max_concurrent_lookups: NonZeroUsize,
+    /// Requests that wait for lookup, requests over it are answered as if we do not have
+    /// artifacts. Env: `QUANTA_SWAP_MAX_QUEUED_REQUESTS`
+    pub max_queued_requests: usize,
+}
+
+impl Config {
+    /// Load config from given path or from `config.toml` in default data dir if it exists. Then
+    /// apply environment overrides
+    pub fn load(path: Option<&Path>) -> Result<Self, ConfigError> {
+        let default_path = default_data_dir().join(QUANTA_CONFIG_FILE_NAME);
+        let mut config = match path {
+            Some(path) => Self::from_file(path)?,
+            None if default_path.exists() => Self::from_file(&default_path)?,
+            None => Self::default(),
+        };
+        config.apply_env(|name| std::env::var(name).ok())?;
+        Ok(config)
+    }
+    /// Read and parse toml config file
+    pub(crate) fn from_file(path: &Path) -> Result<Self, ConfigError> {
+        let content =
+            std::fs::read_to_string(path).map_err(|error| ConfigError::Read(path.into(), error))?;
+        toml::from_str(&content).map_err(|error| ConfigError::Parse(path.into(), error))
+    }
+    /// Override values with `QUANTA_*` variables that returns given function
+    pub(crate) fn apply_env<F>(&mut self, var: F) -> Result<(), ConfigError>
+    where
+        F: Fn(&'static str) -> Option<String>,
+    {
+        if let Some(data_dir) = var("QUANTA_DATA_DIR") {
+            self.data_dir = data_dir.into();
+        }
+        env_list(&var, "QUANTA_HTTP_BIND", &mut self.http.bind)?;
+        env_list(&var, "QUANTA_LISTEN", &mut self.network.listen)?;
+        env_list(&var, "QUANTA_EXTERNAL", &mut self.network.external)?;
+        env_list(&var, "QUANTA_BOOTSTRAP", &mut self.network.bootstrap)?;
+        env_value(&var, "QUANTA_MDNS", &mut self.network.mdns)?;
+        env_list(&var, "QUANTA_RELAYS", &mut self.network.relays)?;
+        env_list(&var, "QUANTA_FEEDS", &mut self.network.feeds)?;
+        env_value(&var, "QUANTA_RELAY_SERVER", &mut self.network.relay_server)?;
+        if let Some(psk_file) = var("QUANTA_PSK_FILE") {
+            self.network.psk_file = Some(psk_file.into());
+        }
+        env_list(
+            &var,
+            "QUANTA_ALLOWED_PEERS",
+            &mut self.network.allowed_peers,
+        )?;
+        env_list(&var, "QUANTA_DENIED_PEERS", &mut self.network.denied_peers)?;
+        env_value(
+            &var,
+            "QUANTA_MAX_KNOWN_PEERS",
+            &mut self.network.max_known_peers,
+        )?;
+        env_value(
+            &var,
+            "QUANTA_MAX_STARTUP_DIALS",
+            &mut self.network.max_startup_dials,
+        )?;
+        env_value(
+            &var,
+            "QUANTA_ONLY_GLOBAL_ADDRS",
+            &mut self.network.only_global_addrs,
+        )?;
+        env_value(&var, "QUANTA_STORAGE_BACKEND", &mut self.storage.backend)?;
+        env_value(
+            &var,
+            "QUANTA_STORAGE_CACHE_CAPACITY",
+            &mut self.storage.cache_capacity,
+        )?;
+        env_option(&var, "QUANTA_STORAGE_MAX_SIZE", &mut self.storage.max_size)?;
+        env_value(
+            &var,
+            "QUANTA_STORAGE_MAX_FEED_ANNOUNCEMENTS",
+            &mut self.storage.max_feed_announcements,
+        )?;
+        env_option(
+            &var,
+            "QUANTA_SWAP_MAX_REQUESTS_PER_SECOND",
+            &mut self.swap.max_requests_per_second,
+        )?;
+        env_option(
+            &var,
+            "QUANTA_SWAP_MAX_SERVED_BYTES_PER_SECOND",
+            &mut self.swap.max_served_bytes_per_second,
+        )?;
+        env_value(
+            &var,
+            "QUANTA_SWAP_MAX_RATE_LIMIT_VIOLATIONS",
+            &mut self.swap.max_rate_limit_violations,
+        )?;
+        env_value(
+            &var,
+            "QUANTA_SWAP_MAX_INVALID_RESPONSES",
+            &mut self.swap.max_invalid_responses,
+        )?;
+        env_value(
+            &var,
+            "QUANTA_SWAP_BAN_DURATION",
+            &mut self.swap.ban_duration,
+        )?;
+        env_value(
+            &var,
+            "QUANTA_SWAP_MAX_CONCURRENT_LOOKUPS",
+            &mut self.swap.max_concurrent_lookups,
+        )?;
+        env_value(
+            &var,
+            "QUANTA_SWAP_MAX_QUEUED_REQUESTS",
+            &mut self.swap.max_queued_requests,
+        )?;
+        Ok(())
+    }
+    /// Returns [QuantaNetworkConfig] for [quanta_network::QuantaNetwork]. Key of private network
+    /// is read from its file here
+    pub fn network_config(&self) -> Result<QuantaNetworkConfig, ConfigError> {
+        let psk = match &self.network.psk_file {
+            Some(path) => Some(
+                std::fs::read_to_string(path)
+                    .map_err(|error| ConfigError::PskRead(path.clone(), error))?
+                    .parse::<PreSharedKey>()
+                    .map_err(|error| ConfigError::PskParse(path.clone(), error))?,
+            ),
+            None => None,
+        };
+        Ok(QuantaNetworkConfig::default()
+            .with_listen_addrs(self.network.listen.to_vec())
+            .with_external_addrs(self.network.external.to_vec())
+            .with_bootstrap_peers(self.network.bootstrap.to_vec())
+            .with_mdns(self.network.mdns)
+            .with_relays(self.network.relays.to_vec())
+            .with_relay_server(self.network.relay_server)
+            .with_feeds(self.network.feeds.to_vec())
+            .with_psk(psk)
+            .with_allowed_peers(self.network.allowed_peers.to_vec())
+            .with_denied_peers(self.network.denied_peers.to_vec())
+            .with_max_known_peers(self.network.max_known_peers)
+            .with_max_startup_dials(self.network.max_startup_dials)
+            .with_only_global_addrs(self.network.only_global_addrs)
+            .with_quanta_swap_config(self.swap_config()))
+    }
+    /// Returns [quanta_swap::Config] with rate limits and bans of peers
+    pub(crate) fn swap_config(&self) -> quanta_swap::Config {
+        quanta_swap::Config::default()
+            .with_max_requests_per_second(self.swap.max_requests_per_second)
+            .with_max_served_bytes_per_second(self.swap.max_served_bytes_per_second)
+            .with_max_rate_limit_violations(self.swap.max_rate_limit_violations)
+            .with_max_invalid_responses(self.swap.max_invalid_responses)
+            .with_ban_duration(Duration::from_secs(self.swap.ban_duration))
+            .with_max_concurrent_lookups(self.swap.max_concurrent_lookups)
+            .with_max_queued_requests(self.swap.max_queued_requests)
+    }
+    /// Returns [DatabaseConfig] for [quanta_database::Database]
+    pub fn database_config(&self) -> DatabaseConfig {
+        DatabaseConfig::default()
+            .with_backend(self.storage.backend)
+            .with_cache_capacity(self.storage.cache_capacity)
+            .with_max_size(self.storage.max_size)
+            .with_max_feed_announcements(self.storage.max_feed_announcements)
+    }
+    /// Returns url of HTTP-API that client commands use
+    pub fn api_url(&self) -> String {
+        match self.http.bind.first() {
+            Some(addr) => format!("http://{}", addr),
+            None => format!("http://{}", QUANTA_HTTP_SERVER_ADDR),
+        }
+    }
+}
+/// Parse environment variable into value if it is set
+fn env_value<F, T>(var: &F, name: &'static str, value: &mut T) -> Result<(), ConfigError>
+where
+    F: Fn(&'static str) -> Option<String>,
+    T: FromStr,
+{
+    if let Some(raw) = var(name) {
+        *value = raw
+            .parse()
+            .map_err(|_| ConfigError::Env(name, raw))?;
+    }
+    Ok(())
+}
+/// Parse environment variable into optional value if it is set
+fn env_option<F, T>(var: &F, name: &'static str, value: &mut Option<T>) -> Result<(), ConfigError>
+where
+    F: Fn(&'static str) -> Option<String>,
+    T: FromStr,
+{
+    if let Some(raw) = var(name) {
+        *value = Some(
+            raw.parse()
+                .map_err(|_| ConfigError::Env(name, raw))?,
+        );
+    }
+    Ok(())
+}
+/// Parse comma-separated environment variable into list if it is set
+fn env_list<F, T>(var: &F, name: &'static str, list: &mut Vec<T>) -> Result<(), ConfigError>
+where
+    F: Fn(&'static str) -> Option<String>,
+    T: FromStr,
+{
+    if let Some(raw) = var(name) {
+        *list = raw
+            .split(ENV_LIST_SEPARATOR)
+            .map(str::trim)
+            .filter(|item| !item.is_empty())
+            .map(|item| item.parse())
+            .collect::<Result<Vec<T>, T::Err>>()
+            .map_err(|_| ConfigError::Env(name, raw))?;
+    }
+    Ok(())
+}
+/// Returns `~/.quanta`
+fn default_data_dir() -> PathBuf {
+    home::home_dir()
+        .expect("Failed to Get `HOME` Dirrectory")
+        .join(QUANTA_APPLICATION_PATH_FOLDER_NAME)
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            data_dir: default_data_dir(),
+            http: HttpConfig::default(),
+            network: NetworkConfig::default(),
+            storage: StorageConfig::default(),
+            swap: SwapConfig::default(),
+        }
+    }
+}
+
+impl Default for HttpConfig {
+    fn default() -> Self {
+        Self {
+            bind: Vec::from([QUANTA_HTTP_SERVER_ADDR
+                .parse()
+                .expect("Default HTTP address is valid")]),
+        }
+    }
+}
+
+impl Default for NetworkConfig {
+    fn default() -> Self {
+        let network_config = QuantaNetworkConfig::default();
+        Self {
+            listen: network_config.listen_addrs().to_vec(),
+            external: network_config.external_addrs().to_vec(),
+            bootstrap: network_config
+                .bootstrap_peers()
+                .to_vec(),
+            mdns: network_config.enable_mdns(),
+            relays: network_config.relays().to_vec(),
+            relay_server: network_config.enable_relay_server(),
+            feeds: network_config.feeds().to_vec(),
+            psk_file: None,
+            allowed_peers: network_config.allowed_peers().to_vec(),
+            denied_peers: network_config.denied_peers().to_vec(),
+            max_known_peers: network_config.max_known_peers(),
+            max_startup_dials: network_config.max_startup_dials(),
+            only_global_addrs: network_config.only_global_addrs(),
+        }
+    }
+}
+
+impl Default for StorageConfig {
+    fn default() -> Self {
+        let database_config = DatabaseConfig::default();
+        Self {
+            backend: database_config.backend(),
+            cache_capacity: database_config.cache_capacity(),
+            max_size: database_config.max_size(),
+            max_feed_announcements: database_config.max_feed_announcements(),
+        }
+    }
+}
+
+impl Default for SwapConfig {
+    fn default() -> Self {
+        let swap_config = quanta_swap::Config::default();
+        Self {
+            max_requests_per_second: swap_config.max_requests_per_second(),
+            max_served_bytes_per_second: swap_config.max_served_bytes_per_second(),
+            max_rate_limit_violations: swap_config.max_rate_limit_violations(),
+            max_invalid_responses: swap_config.max_invalid_responses(),
+            ban_duration: swap_config.ban_duration().as_secs(),
+            max_concurrent_lookups: swap_config.max_concurrent_lookups(),
+            max_queued_requests: swap_config.max_queued_requests(),
+        }
+    }
+}
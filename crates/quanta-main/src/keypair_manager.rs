@@ -52,6 +52,12 @@ async fn load_from_file(mut file: async_std::fs::File, keypair_file_path: PathBu
 async fn generate_and_save_new_keypair(keypair_file_path: PathBuf) -> Keypair {
     info!("Creating new keypair in: {:?}", keypair_file_path);
     let keypair = Keypair::generate_ed25519();
+    // data dir can be set in config, so keys folder may not exist yet
+    if let Some(keypair_store_path) = keypair_file_path.parent() {
+        async_std::fs::create_dir_all(keypair_store_path)
+            .await
+            .expect("Failed to create KeyPair dirrectory");
+    }
 
     let mut file = OpenOptions::new()
         .create(true)
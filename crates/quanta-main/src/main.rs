@@ -1,7 +1,20 @@
 #![allow(clippy::unused_io_amount)]
+mod cli;
+mod client;
+mod config;
 mod keypair_manager;
+mod offline;
 mod run;
 mod storage;
+#[cfg(test)]
+mod test;
+
+use clap::Parser;
 
 #[tokio::main]
-async fn main() -> Result<(), Box<dyn std::error::Error>> { run::run().await }
+async fn main() {
+    if let Err(error) = cli::Cli::parse().execute().await {
+        eprintln!("Error: {}", error);
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,116 @@
+use std::path::PathBuf;
+
+use async_std::io::WriteExt;
+use futures::TryStreamExt;
+use quanta_artifact::{ArtifactStreamReader, Chunker, MagnetLink};
+use quanta_crypto::HashValue;
+
+use crate::{config::Config, run::configure_application_path, storage::open_database};
+
+#[derive(thiserror::Error, Debug)]
+pub enum OfflineError {
+    #[error("File path does not contain file name")]
+    /// Error whill occur when trying to add path without file name
+    FileName,
+    #[error("Magnet link does not match merkle root")]
+    /// Error whill occur when magnet link can not be verified
+    MerkleRoot,
+    #[error("Merkle root is required when magnet link is not given")]
+    /// Error whill occur when neither magnet link nor merkle root is given
+    RootMissing,
+    #[error("Magnet link with merkle root {0} is not stored locally")]
+    /// Error whill occur when magnet link is not given and database does not have it
+    MagnetMissing(String),
+    #[error("Artifact {0} is not stored locally, download file with running daemon")]
+    /// Error whill occur when artifact of file is missing in database
+    ArtifactMissing(String),
+    #[error("Artifact {0} does not belong to magnet link")]
+    /// Error whill occur when stored artifact does not match magnet link
+    ArtifactVerification(String),
+}
+
+/// Split file into artifacts and write them straight into database without daemon
+pub async fn add(config: &Config, path: PathBuf) -> Result<(), Box<dyn std::error::Error>> {
+    let file_name = path
+        .file_name()
+        .and_then(|file_name| file_name.to_str())
+        .ok_or(OfflineError::FileName)?
+        .to_string();
+    let database = open_database(
+        configure_application_path(config).await,
+        config.database_config(),
+    )?;
+    let mut file = async_std::fs::File::open(&path).await?;
+    let size = file.metadata().await?.len() as usize;
+    let mut magnet_link = MagnetLink::new(file_name, size);
+    let mut artifacts = ArtifactStreamReader::with_chunker(&mut file, Chunker::default());
+    // magnet link is stored only after all artifacts, so they are held to not be evicted by
+    // storage limit or garbage collection before it
+    let mut hold = database.hold_artifacts();
+    while let Some(artifact) = artifacts.try_next().await? {
+        magnet_link.new_update_with_artifact_id(artifact.id);
+        hold.insert_artifact(artifact)?;
+    }
+    let merkle_root = magnet_link.update_merkle_root();
+    let magnet_string = magnet_link.to_string();
+    database.insert_magnet_link(magnet_link)?;
+    println!("{}\t{}", magnet_string, merkle_root);
+    Ok(())
+}
+/// Assemble file from artifacts that stored in database without daemon. If magnet link is not
+/// given stored magnet link with given merkle root is used. Magnet link and artifacts are
+/// verified with given merkle root or with root of magnet link if it is not given
+pub async fn get(
+    config: &Config,
+    magnet: Option<String>,
+    root: Option<String>,
+    output: PathBuf,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let trusted_root = root
+        .as_deref()
+        .map(HashValue::try_from)
+        .transpose()?;
+    let database = open_database(
+        configure_application_path(config).await,
+        config.database_config(),
+    )?;
+    let magnet_link = match magnet {
+        Some(magnet) => MagnetLink::try_from(magnet)?,
+        None => {
+            let merkle_root = trusted_root.ok_or(OfflineError::RootMissing)?;
+            database
+                .get_magnet_link_by_root(&merkle_root)?
+                .ok_or_else(|| OfflineError::MagnetMissing(merkle_root.to_string()))?
+                .1
+        },
+    };
+    let merkle_root = trusted_root.unwrap_or_else(|| magnet_link.claimed_merkle_root());
+    if !magnet_link.verify(&merkle_root) {
+        return Err(OfflineError::MerkleRoot.into());
+    }
+    let merkle_tree = magnet_link.merkle_tree();
+    let mut file = async_std::fs::File::create(&output).await?;
+    let mut written = 0;
+    for (position, artifact_id) in magnet_link
+        .artifact_ids()
+        .into_iter()
+        .enumerate()
+    {
+        let artifact = database
+            .get_artifact(&artifact_id)?
+            .ok_or_else(|| OfflineError::ArtifactMissing(artifact_id.to_string()))?;
+        let verified = merkle_tree
+            .proof(position)
+            .map(|proof| proof.verify_artifact(&merkle_root, &artifact))
+            .unwrap_or(false);
+        if !verified {
+            return Err(OfflineError::ArtifactVerification(artifact_id.to_string()).into());
+        }
+        file.write_all(artifact.data.as_slice())
+            .await?;
+        written += artifact.data.len();
+    }
+    file.flush().await?;
+    println!("Saved {} bytes into {}", written, output.display());
+    Ok(())
+}
@@ -1,36 +1,94 @@
-use std::{path::PathBuf, sync::Arc};
+use std::{path::PathBuf, sync::Arc, time::Duration};
 
-use log::info;
+use log::{info, warn};
+use prometheus_client::registry::Registry;
+use quanta_database::Database;
 use quanta_http::run_http_server;
-use quanta_network::QuantaNetwork;
+use quanta_network::{QuantaNetwork, QuantaNetworkServiceProxy};
 
-use crate::{keypair_manager::load_or_generate_new_keypair, storage::load_or_create_new_database};
+use crate::{
+    config::Config,
+    keypair_manager::load_or_generate_new_keypair,
+    storage::load_or_create_new_database,
+};
 
-const QUANTA_APPLICATION_PATH_FOLDER_NAME: &str = ".quanta";
-const QUANTA_HTTP_SERVER_ADDRS: (&str, u16) = ("127.0.0.1", 51255);
+/// Folder in application path where records of DHT and known peers are stored
+const QUANTA_NETWORK_FOLDER_NAME: &str = "network";
+/// How often order of use of artifacts is saved, so least recently used artifacts are evicted
+/// first after restart
+const RECENCY_SAVE_INTERVAL: Duration = Duration::from_secs(60);
 
-async fn configure_application_path() -> PathBuf {
-    let application_path = home::home_dir()
-        .expect("Failed to Get `HOME` Dirrectory")
-        .join(QUANTA_APPLICATION_PATH_FOLDER_NAME);
+/// Returns path of application folder from [Config] and creates it if it does not exist
+pub(crate) async fn configure_application_path(config: &Config) -> PathBuf {
+    let application_path = config.data_dir.to_path_buf();
     async_std::fs::create_dir_all(&application_path)
         .await
         .expect("Failed to create application dirrectory");
     application_path
 }
 
-pub async fn run() -> Result<(), Box<dyn std::error::Error>> {
+/// Announce in DHT all artifacts and magnet roots that we are store, so other peers can find us.
+/// Network announces keys in order with a few at a time, so magnet roots that downloaders look up
+/// go first
+async fn provide_stored_content(
+    storage: &Database,
+    network_proxy: &QuantaNetworkServiceProxy,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut keys = storage
+        .get_magnet_links()?
+        .into_iter()
+        .map(|(_, magnet_link)| {
+            magnet_link
+                .merkle_tree()
+                .root()
+                .to_bytes()
+        })
+        .collect::<Vec<Vec<u8>>>();
+    keys.extend(
+        storage
+            .get_artifact_ids()?
+            .into_iter()
+            .map(|artifact_id| artifact_id.to_bytes()),
+    );
+    info!("Providing {} stored keys in DHT", keys.len());
+    network_proxy
+        .start_providing(keys)
+        .await?;
+    Ok(())
+}
+
+/// Save order of use of artifacts on blocking thread pool, because [Backend] writes to disk
+///
+/// [Backend]: quanta_database::Backend
+async fn save_recency(storage: Arc<Database>) {
+    match tokio::task::spawn_blocking(move || storage.save_recency()).await {
+        Ok(Ok(())) => {},
+        Ok(Err(error)) => warn!("Failed to save order of use of artifacts: {}", error),
+        Err(error) => warn!("Failed to save order of use of artifacts: {}", error),
+    }
+}
+
+pub async fn run(config: Config) -> Result<(), Box<dyn std::error::Error>> {
     pretty_env_logger::init_timed();
-    let application_path = configure_application_path().await;
+    let application_path = configure_application_path(&config).await;
 
     let keypair = load_or_generate_new_keypair(&application_path).await;
     let local_peer_id = libp2p::PeerId::from(keypair.public());
     info!("LocalPeerId={}", local_peer_id);
 
-    let storage = Arc::new(load_or_create_new_database(&application_path).await);
+    let storage =
+        Arc::new(load_or_create_new_database(&application_path, config.database_config()).await);
+    let mut registry = Registry::default();
     info!("Creating QuantaNetwork Service for p2p communications");
-    let (network, network_proxy) =
-        QuantaNetwork::new(&keypair, local_peer_id, Arc::clone(&storage));
+    let (network, network_proxy) = QuantaNetwork::new(
+        &keypair,
+        local_peer_id,
+        Arc::clone(&storage),
+        config
+            .network_config()?
+            .with_store_path(Some(application_path.join(QUANTA_NETWORK_FOLDER_NAME))),
+        &mut registry,
+    )?;
 
     tokio::spawn(async move {
         info!("Running QuantaNetwork Service in new thread");
@@ -40,19 +98,28 @@ pub async fn run() -> Result<(), Box<dyn std::error::Error>> {
             .expect("QuantaNetwork finished with unexpected error")
     });
 
-    info!(
-        "Running HTTP-API Server on: {}",
-        format!(
-            "{}:{}",
-            QUANTA_HTTP_SERVER_ADDRS.0, QUANTA_HTTP_SERVER_ADDRS.1
-        )
-    );
+    provide_stored_content(&storage, &network_proxy).await?;
+
+    let recency_storage = Arc::clone(&storage);
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(RECENCY_SAVE_INTERVAL);
+        // first tick is completed immediately
+        interval.tick().await;
+        loop {
+            interval.tick().await;
+            save_recency(Arc::clone(&recency_storage)).await;
+        }
+    });
+
+    info!("Running HTTP-API Server on: {:?}", config.http.bind);
     run_http_server(
-        QUANTA_HTTP_SERVER_ADDRS,
+        config.http.bind.as_slice(),
         Arc::clone(&storage),
         Arc::new(network_proxy),
+        registry,
     )
     .await?;
+    save_recency(storage).await;
 
     Ok(())
 }
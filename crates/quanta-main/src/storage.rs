@@ -1,12 +1,28 @@
 use async_std::path::Path;
 use log::info;
-use quanta_database::Database;
+use quanta_database::{Database, DatabaseConfig, DatabaseError};
 
 const QUANTA_STORAGE_FOLDER_NAME: &str = "storage";
 
-pub async fn load_or_create_new_database<P: AsRef<Path>>(application_path: P) -> Database {
+pub async fn load_or_create_new_database<P: AsRef<Path>>(
+    application_path: P,
+    config: DatabaseConfig,
+) -> Database {
     let application_path_ref = application_path.as_ref();
     let storage_file_path = application_path_ref.join(QUANTA_STORAGE_FOLDER_NAME);
     info!("Open or Create QuantaDatabase in: {:?}", storage_file_path);
-    Database::new(storage_file_path).expect("Failed to open QuantaDatabase")
+    Database::with_config(storage_file_path, config).expect("Failed to open QuantaDatabase")
+}
+/// Open [Database] in application path. Unlike [load_or_create_new_database] returns error, so
+/// it can be reported to user of command line
+pub fn open_database<P: AsRef<Path>>(
+    application_path: P,
+    config: DatabaseConfig,
+) -> Result<Database, DatabaseError> {
+    Database::with_config(
+        application_path
+            .as_ref()
+            .join(QUANTA_STORAGE_FOLDER_NAME),
+        config,
+    )
 }
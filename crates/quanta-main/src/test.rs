@@ -0,0 +1,409 @@
+use std::{
+    collections::HashMap,
+    io::{Read, Write},
+    net::TcpListener,
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicUsize, Ordering},
+    thread::JoinHandle,
+    time::Duration,
+};
+
+use clap::{error::ErrorKind, Parser};
+use quanta_database::StorageBackend;
+
+use crate::{
+    cli::{Cli, Command},
+    client::{Client, ClientError},
+    config::{Config, ConfigError},
+};
+
+/// Path in temp dir that is unique for every call and removed when it is dropped
+struct TempPath(PathBuf);
+
+impl TempPath {
+    fn new() -> Self {
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+        TempPath(std::env::temp_dir().join(format!(
+            "quanta-main-test-{}-{}",
+            std::process::id(),
+            COUNTER.fetch_add(1, Ordering::Relaxed)
+        )))
+    }
+}
+
+impl Drop for TempPath {
+    fn drop(&mut self) { let _ = std::fs::remove_file(&self.0); }
+}
+
+/// Request that fake daemon received
+struct ReceivedRequest {
+    /// Request line with method and path
+    request_line: String,
+    /// Raw headers of request
+    headers: String,
+    /// Body of request
+    body: Vec<u8>,
+}
+
+/// Start fake daemon that answers one request with given status and body. Returns base url of
+/// daemon and handle that returns received request
+fn serve_once(status: &'static str, body: &'static [u8]) -> (String, JoinHandle<ReceivedRequest>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    let handle = std::thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut received = Vec::new();
+        let mut buf = [0; 4096];
+        // read until end of headers
+        let head_end = loop {
+            let read = stream.read(&mut buf).unwrap();
+            received.extend_from_slice(&buf[..read]);
+            if let Some(position) = received
+                .windows(4)
+                .position(|window| window == b"\r\n\r\n")
+            {
+                break position + 4;
+            }
+        };
+        let head = String::from_utf8(received[..head_end].to_vec()).unwrap();
+        let content_length = head
+            .lines()
+            .find_map(|line| {
+                let (name, value) = line.split_once(':')?;
+                name.eq_ignore_ascii_case("content-length")
+                    .then(|| value.trim().parse::<usize>().unwrap())
+            })
+            .unwrap_or(0);
+        while received.len() < head_end + content_length {
+            let read = stream.read(&mut buf).unwrap();
+            received.extend_from_slice(&buf[..read]);
+        }
+        write!(
+            stream,
+            "HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+            status,
+            body.len()
+        )
+        .unwrap();
+        stream.write_all(body).unwrap();
+        let (request_line, headers) = head.split_once("\r\n").unwrap();
+        ReceivedRequest {
+            request_line: request_line.to_string(),
+            headers: headers.to_string(),
+            body: received[head_end..].to_vec(),
+        }
+    });
+    (url, handle)
+}
+
+#[test]
+fn test_cli_without_subcommand_runs_daemon() {
+    let cli = Cli::try_parse_from(["quanta"]).unwrap();
+    assert!(cli.command.is_none());
+    let cli = Cli::try_parse_from(["quanta", "daemon", "--config", "quanta.toml"]).unwrap();
+    assert!(matches!(cli.command, Some(Command::Daemon)));
+    assert_eq!(cli.config, Some(PathBuf::from("quanta.toml")));
+}
+
+#[test]
+fn test_cli_get_root_is_optional_with_magnet() {
+    let cli = Cli::try_parse_from([
+        "quanta",
+        "get",
+        "magnet",
+        "--root",
+        "abcd",
+        "-o",
+        "file.bin",
+        "--api",
+        "http://api",
+    ])
+    .unwrap();
+    assert_eq!(cli.api.as_deref(), Some("http://api"));
+    assert!(matches!(
+        cli.command,
+        Some(Command::Get { magnet, root, output, offline: false })
+            if magnet.as_deref() == Some("magnet") && root.as_deref() == Some("abcd") && output == Path::new("file.bin")
+    ));
+    // without magnet link file is downloaded by merkle root
+    let cli = Cli::try_parse_from(["quanta", "get", "--root", "abcd", "-o", "file.bin"]).unwrap();
+    assert!(matches!(
+        cli.command,
+        Some(Command::Get { magnet: None, .. })
+    ));
+    // without merkle root file is verified with root of magnet link
+    let cli = Cli::try_parse_from(["quanta", "get", "magnet", "-o", "file.bin"]).unwrap();
+    assert!(matches!(
+        cli.command,
+        Some(Command::Get {
+            magnet: Some(_),
+            root: None,
+            ..
+        })
+    ));
+    let error = Cli::try_parse_from(["quanta", "get", "-o", "file.bin"]).unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::MissingRequiredArgument);
+}
+
+#[test]
+fn test_cli_add_offline_conflicts_with_topic() {
+    let cli = Cli::try_parse_from(["quanta", "add", "file.bin", "--topic", "news"]).unwrap();
+    assert!(matches!(
+        cli.command,
+        Some(Command::Add { path, offline: false, topic: Some(topic) })
+            if path == Path::new("file.bin") && topic == "news"
+    ));
+    let error = Cli::try_parse_from(["quanta", "add", "file.bin", "--offline", "--topic", "news"])
+        .unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::ArgumentConflict);
+}
+
+#[test]
+fn test_cli_search_output_is_optional() {
+    let cli = Cli::try_parse_from(["quanta", "search", "artifact"]).unwrap();
+    assert!(matches!(
+        cli.command,
+        Some(Command::Search { artifact_id, output: None }) if artifact_id == "artifact"
+    ));
+    let error = Cli::try_parse_from(["quanta", "unknown"]).unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::InvalidSubcommand);
+}
+
+#[test]
+fn test_client_add_uploads_multipart() {
+    let (url, handle) = serve_once(
+        "200 OK",
+        br#"{"id":1,"magnet":"magnet","merkle_root":"abcd","pinned":true}"#,
+    );
+    let path = TempPath::new();
+    std::fs::write(&path.0, b"beep boop").unwrap();
+    // trailing slash of api url is ignored
+    Client::new(format!("{url}/"))
+        .add(path.0.clone(), Some("news".to_string()))
+        .unwrap();
+    let request = handle.join().unwrap();
+    assert_eq!(
+        request.request_line,
+        "POST /api/v1/file/upload?topic=news HTTP/1.1"
+    );
+    assert!(request
+        .headers
+        .contains("multipart/form-data; boundary=quanta-multipart-boundary"));
+    let body = String::from_utf8(request.body).unwrap();
+    let file_name = path
+        .0
+        .file_name()
+        .unwrap()
+        .to_str()
+        .unwrap()
+        .to_string();
+    assert!(body.contains(&format!("name=\"file\"; filename=\"{file_name}\"")));
+    assert!(body.contains("\r\n\r\nbeep boop\r\n--quanta-multipart-boundary--\r\n"));
+}
+
+#[test]
+fn test_client_get_sends_root() {
+    let (url, handle) = serve_once("200 OK", b"beep boop");
+    let output = TempPath::new();
+    Client::new(url)
+        .get(
+            Some("magnet".to_string()),
+            Some("abcd".to_string()),
+            output.0.clone(),
+        )
+        .unwrap();
+    let request = handle.join().unwrap();
+    assert_eq!(
+        request.request_line,
+        "GET /api/v1/file/download/magnet?root=abcd HTTP/1.1"
+    );
+    assert_eq!(std::fs::read(&output.0).unwrap(), b"beep boop");
+}
+
+#[test]
+fn test_client_get_without_root() {
+    let (url, handle) = serve_once("200 OK", b"beep boop");
+    let output = TempPath::new();
+    Client::new(url)
+        .get(Some("magnet".to_string()), None, output.0.clone())
+        .unwrap();
+    let request = handle.join().unwrap();
+    assert_eq!(
+        request.request_line,
+        "GET /api/v1/file/download/magnet HTTP/1.1"
+    );
+    assert_eq!(std::fs::read(&output.0).unwrap(), b"beep boop");
+}
+
+#[test]
+fn test_client_get_by_root() {
+    let (url, handle) = serve_once("200 OK", b"beep boop");
+    let output = TempPath::new();
+    Client::new(url)
+        .get(None, Some("abcd".to_string()), output.0.clone())
+        .unwrap();
+    let request = handle.join().unwrap();
+    assert_eq!(
+        request.request_line,
+        "GET /api/v1/file/download?root=abcd HTTP/1.1"
+    );
+    assert_eq!(std::fs::read(&output.0).unwrap(), b"beep boop");
+}
+
+#[test]
+fn test_client_returns_error_status() {
+    let (url, handle) = serve_once("400 Bad Request", br#""Invalid merkle root""#);
+    let output = TempPath::new();
+    let error = Client::new(url)
+        .get(
+            Some("magnet".to_string()),
+            Some("abcd".to_string()),
+            output.0.clone(),
+        )
+        .unwrap_err();
+    handle.join().unwrap();
+    assert!(matches!(
+        error,
+        ClientError::Request(error) if matches!(*error, ureq::Error::Status(400, _))
+    ));
+    // file is not created when daemon returned error
+    assert!(!output.0.exists());
+}
+
+/// Apply given environment variables to config
+fn apply_env(config: &mut Config, vars: &[(&str, &str)]) -> Result<(), ConfigError> {
+    let vars = vars
+        .iter()
+        .map(|(name, value)| (name.to_string(), value.to_string()))
+        .collect::<HashMap<String, String>>();
+    config.apply_env(|name| vars.get(name).cloned())
+}
+
+#[test]
+fn test_config_parses_toml() {
+    let path = TempPath::new();
+    std::fs::write(
+        &path.0,
+        r#"
+data_dir = "/var/lib/quanta"
+
+[http]
+bind = ["127.0.0.1:8080"]
+
+[network]
+listen = ["/ip4/0.0.0.0/tcp/4001"]
+mdns = false
+feeds = ["news"]
+
+[storage]
+backend = "memory"
+max_size = 1024
+max_feed_announcements = 10
+
+[swap]
+max_requests_per_second = 10
+ban_duration = 60
+"#,
+    )
+    .unwrap();
+    let config = Config::from_file(&path.0).unwrap();
+    assert_eq!(config.data_dir, PathBuf::from("/var/lib/quanta"));
+    assert_eq!(config.api_url(), "http://127.0.0.1:8080");
+    assert_eq!(
+        config.network.listen,
+        Vec::from(["/ip4/0.0.0.0/tcp/4001".parse().unwrap()])
+    );
+    assert!(!config.network.mdns);
+    assert_eq!(config.network.feeds, ["news"]);
+    // missing values are defaults
+    assert!(config.network.bootstrap.is_empty());
+    assert_eq!(config.storage.backend, StorageBackend::Memory);
+    assert_eq!(config.database_config().max_size(), Some(1024));
+    assert_eq!(
+        config
+            .database_config()
+            .max_feed_announcements(),
+        10
+    );
+    let swap_config = config.swap_config();
+    assert_eq!(swap_config.max_requests_per_second(), Some(10));
+    assert_eq!(swap_config.ban_duration(), Duration::from_secs(60));
+    assert_eq!(
+        swap_config.max_invalid_responses(),
+        quanta_swap::Config::default().max_invalid_responses()
+    );
+}
+
+#[test]
+fn test_config_rejects_invalid_file() {
+    let path = TempPath::new();
+    std::fs::write(&path.0, "[network]\nunknown = true\n").unwrap();
+    assert!(matches!(
+        Config::from_file(&path.0),
+        Err(ConfigError::Parse(..))
+    ));
+    std::fs::write(&path.0, "[storage]\nbackend = \"tape\"\n").unwrap();
+    assert!(matches!(
+        Config::from_file(&path.0),
+        Err(ConfigError::Parse(..))
+    ));
+    let missing = TempPath::new();
+    assert!(matches!(
+        Config::from_file(&missing.0),
+        Err(ConfigError::Read(..))
+    ));
+}
+
+#[test]
+fn test_config_env_overrides() {
+    let mut config = Config::default();
+    apply_env(&mut config, &[
+        ("QUANTA_DATA_DIR", "/tmp/quanta"),
+        ("QUANTA_HTTP_BIND", "127.0.0.1:1, 127.0.0.1:2"),
+        ("QUANTA_BOOTSTRAP", ""),
+        ("QUANTA_MDNS", "false"),
+        ("QUANTA_FEEDS", "news,,music"),
+        ("QUANTA_MAX_STARTUP_DIALS", "8"),
+        ("QUANTA_ONLY_GLOBAL_ADDRS", "false"),
+        ("QUANTA_STORAGE_BACKEND", "filesystem"),
+        ("QUANTA_STORAGE_MAX_SIZE", "2048"),
+        ("QUANTA_SWAP_MAX_CONCURRENT_LOOKUPS", "4"),
+        ("QUANTA_SWAP_MAX_QUEUED_REQUESTS", "16"),
+    ])
+    .unwrap();
+    assert_eq!(config.data_dir, PathBuf::from("/tmp/quanta"));
+    assert_eq!(config.http.bind.len(), 2);
+    assert_eq!(config.api_url(), "http://127.0.0.1:1");
+    // empty list clears value from file
+    assert!(config.network.bootstrap.is_empty());
+    assert!(!config.network.mdns);
+    assert_eq!(config.network.feeds, ["news", "music"]);
+    assert_eq!(config.network.max_startup_dials, 8);
+    assert!(!config.network.only_global_addrs);
+    assert_eq!(config.storage.backend, StorageBackend::Filesystem);
+    assert_eq!(config.storage.max_size, Some(2048));
+    assert_eq!(config.swap.max_concurrent_lookups.get(), 4);
+    assert_eq!(config.swap.max_queued_requests, 16);
+    // variables that are not set do not change config
+    let mut unchanged = config.clone();
+    apply_env(&mut unchanged, &[]).unwrap();
+    assert_eq!(unchanged.data_dir, config.data_dir);
+    assert_eq!(unchanged.storage.max_size, config.storage.max_size);
+}
+
+#[test]
+fn test_config_rejects_invalid_env() {
+    for (name, value) in [
+        ("QUANTA_MDNS", "maybe"),
+        ("QUANTA_LISTEN", "/ip4/127.0.0.1/tcp/1,not-multiaddr"),
+        ("QUANTA_STORAGE_MAX_SIZE", "-1"),
+        ("QUANTA_ALLOWED_PEERS", "peer"),
+        ("QUANTA_SWAP_MAX_CONCURRENT_LOOKUPS", "0"),
+    ] {
+        let error = apply_env(&mut Config::default(), &[(name, value)]).unwrap_err();
+        assert!(matches!(
+            error,
+            ConfigError::Env(variable, raw) if variable == name && raw == value
+        ));
+    }
+}
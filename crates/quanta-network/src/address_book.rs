@@ -0,0 +1,225 @@
+use std::{
+    net::{Ipv4Addr, Ipv6Addr},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use libp2p::{multiaddr::Protocol, Multiaddr, PeerId};
+use serde::{Deserialize, Serialize};
+
+const PEER_TREE_NAME: &str = "peers";
+/// Max count of addresses that we are remember for one peer. When there are more addresses,
+/// addresses that were seen first are forgotten
+const MAX_ADDRESSES_PER_PEER: usize = 8;
+/// Count of dials in a row that should fail before peer is forgotten
+const MAX_DIAL_FAILURES: u32 = 5;
+
+/// Address book of peers that we were connected to. It is stored in [sled], so we can dial them
+/// again after restart
+pub struct AddressBook {
+    /// Tree of peers. Key is a peer id, value is an encoded [StoredPeer]
+    peer_tree: sled::Tree,
+    /// Max count of peers in address book. When there are more peers, peer that was connected
+    /// the longest time ago is forgotten
+    max_peers: usize,
+    /// If true loopback, private and link-local addresses are not remembered
+    only_global_addrs: bool,
+}
+
+/// Peer as it is stored in [sled]
+#[derive(Serialize, Deserialize, Default)]
+struct StoredPeer {
+    /// Known addresses, the most recent address is the last
+    addresses: Vec<Vec<u8>>,
+    /// Time of the last successful dial in milliseconds since [UNIX_EPOCH]. Zero if peer was
+    /// never dialed
+    last_connected: u64,
+    /// Count of dials in a row that failed
+    failures: u32,
+}
+
+impl AddressBook {
+    /// Open tree of address book in [sled::Db]
+    pub fn open(
+        db: &sled::Db,
+        max_peers: usize,
+        only_global_addrs: bool,
+    ) -> Result<Self, sled::Error> {
+        Ok(AddressBook {
+            peer_tree: db.open_tree(PEER_TREE_NAME)?,
+            max_peers,
+            only_global_addrs,
+        })
+    }
+    /// Remember address of peer. Address that is already known becomes the most recent. Returns
+    /// false if address is not global and only global addresses are remembered
+    pub fn insert(&self, peer_id: &PeerId, address: &Multiaddr) -> Result<bool, sled::Error> {
+        if self.only_global_addrs && !is_global_address(address) {
+            return Ok(false);
+        }
+        let address = address.to_vec();
+        let previous = self
+            .peer_tree
+            .fetch_and_update(peer_id.to_bytes(), |stored| {
+                let mut peer = stored
+                    .and_then(decode_peer)
+                    .unwrap_or_default();
+                peer.addresses
+                    .retain(|stored| stored != &address);
+                peer.addresses.push(address.clone());
+                if peer.addresses.len() > MAX_ADDRESSES_PER_PEER {
+                    peer.addresses
+                        .drain(..peer.addresses.len() - MAX_ADDRESSES_PER_PEER);
+                }
+                Some(encode_peer(&peer))
+            })?;
+        if previous.is_none() {
+            self.forget_oldest(peer_id)?;
+        }
+        Ok(true)
+    }
+    /// Remember that peer was dialed successfully
+    pub fn connected(&self, peer_id: &PeerId) -> Result<(), sled::Error> {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis() as u64;
+        self.peer_tree
+            .update_and_fetch(peer_id.to_bytes(), |stored| {
+                let mut peer = stored.and_then(decode_peer)?;
+                peer.last_connected = now;
+                peer.failures = 0;
+                Some(encode_peer(&peer))
+            })?;
+        Ok(())
+    }
+    /// Remember that dial of peer failed. Peer is forgotten when too many dials in a row failed.
+    /// Returns true if peer was forgotten
+    pub fn failed(&self, peer_id: &PeerId) -> Result<bool, sled::Error> {
+        let stored = self
+            .peer_tree
+            .update_and_fetch(peer_id.to_bytes(), |stored| {
+                let mut peer = stored.and_then(decode_peer)?;
+                peer.failures += 1;
+                (peer.failures < MAX_DIAL_FAILURES).then(|| encode_peer(&peer))
+            })?;
+        Ok(stored.is_none())
+    }
+    /// Returns known addresses of peer, the most recent address is the last
+    pub fn addresses(&self, peer_id: &PeerId) -> Result<Vec<Multiaddr>, sled::Error> {
+        Ok(self
+            .peer_tree
+            .get(peer_id.to_bytes())?
+            .and_then(|stored| decode_peer(&stored))
+            .map(|peer| into_multiaddrs(peer.addresses))
+            .unwrap_or_default())
+    }
+    /// Returns all known peers with their addresses. Peers that were connected recently are the
+    /// first. Broken entries are skipped
+    pub fn peers(&self) -> Result<Vec<(PeerId, Vec<Multiaddr>)>, sled::Error> {
+        let mut peers = Vec::new();
+        for entry in self.peer_tree.iter() {
+            let (peer_id, stored) = entry?;
+            let (Ok(peer_id), Some(peer)) = (PeerId::from_bytes(&peer_id), decode_peer(&stored))
+            else {
+                continue;
+            };
+            peers.push((
+                peer.last_connected,
+                peer_id,
+                into_multiaddrs(peer.addresses),
+            ));
+        }
+        peers.sort_by(|(first, ..), (second, ..)| second.cmp(first));
+        Ok(peers
+            .into_iter()
+            .map(|(_, peer_id, addresses)| (peer_id, addresses))
+            .collect())
+    }
+    /// Forget peers that were connected the longest time ago while there are more peers than
+    /// [AddressBook::max_peers]. Peer that was just inserted is kept
+    fn forget_oldest(&self, inserted: &PeerId) -> Result<(), sled::Error> {
+        let count = self.peer_tree.len();
+        if count <= self.max_peers {
+            return Ok(());
+        }
+        let inserted = inserted.to_bytes();
+        let mut peers = Vec::with_capacity(count);
+        for entry in self.peer_tree.iter() {
+            let (peer_id, stored) = entry?;
+            if peer_id == inserted {
+                continue;
+            }
+            // broken entries are forgotten first
+            let last_connected = decode_peer(&stored)
+                .map(|peer| peer.last_connected)
+                .unwrap_or_default();
+            peers.push((last_connected, peer_id));
+        }
+        peers.sort();
+        for (_, peer_id) in peers
+            .into_iter()
+            .take(count - self.max_peers)
+        {
+            self.peer_tree.remove(peer_id)?;
+        }
+        Ok(())
+    }
+}
+
+/// Encode peer into bytes
+fn encode_peer(peer: &StoredPeer) -> Vec<u8> {
+    bincode::serialize(peer).expect("Peer is always serializable")
+}
+
+/// Decode peer in bytes. Address book that was stored before peers had dial stats contains only
+/// list of addresses
+fn decode_peer(stored: &[u8]) -> Option<StoredPeer> {
+    bincode::deserialize(stored)
+        .ok()
+        .or_else(|| {
+            let addresses = bincode::deserialize(stored).ok()?;
+            Some(StoredPeer {
+                addresses,
+                ..Default::default()
+            })
+        })
+}
+
+/// Convert addresses in bytes into [Multiaddr]. Addresses that can not be decoded are skipped
+fn into_multiaddrs(addresses: Vec<Vec<u8>>) -> Vec<Multiaddr> {
+    addresses
+        .into_iter()
+        .filter_map(|address| Multiaddr::try_from(address).ok())
+        .collect()
+}
+
+/// Returns false if address is loopback, private or link-local address, so it can not be dialed
+/// from other networks
+fn is_global_address(address: &Multiaddr) -> bool {
+    match address.iter().next() {
+        Some(Protocol::Ip4(ip)) => is_global_ipv4(&ip),
+        Some(Protocol::Ip6(ip)) => is_global_ipv6(&ip),
+        Some(Protocol::Dns(host) | Protocol::Dns4(host) | Protocol::Dns6(host)) => {
+            host != "localhost"
+        },
+        _ => true,
+    }
+}
+
+/// Returns false if ip is loopback, private, link-local or unspecified
+fn is_global_ipv4(ip: &Ipv4Addr) -> bool {
+    !(ip.is_loopback() ||
+        ip.is_private() ||
+        ip.is_link_local() ||
+        ip.is_unspecified() ||
+        ip.is_broadcast())
+}
+
+/// Returns false if ip is loopback, unique local, link-local or unspecified
+fn is_global_ipv6(ip: &Ipv6Addr) -> bool {
+    let first = ip.segments()[0];
+    !(ip.is_loopback() ||
+        ip.is_unspecified() ||
+        (first & 0xfe00) == 0xfc00 ||
+        (first & 0xffc0) == 0xfe80)
+}
@@ -1,6 +1,28 @@
 use std::sync::Arc;
 
-use libp2p::{identify, identity::PublicKey, kad, mdns, ping, swarm::NetworkBehaviour, PeerId};
+use libp2p::{
+    allow_block_list,
+    autonat,
+    dcutr,
+    gossipsub,
+    identify,
+    identity::Keypair,
+    kad,
+    mdns,
+    multiaddr::Protocol,
+    ping,
+    relay,
+    swarm::{behaviour::toggle::Toggle, NetworkBehaviour},
+    PeerId,
+};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    config::QuantaNetworkConfig,
+    ledger::SledLedgerStorage,
+    store::PersistentStore,
+    validator::ArtifactValidator,
+};
 
 const QUANTA_IDENTIFY_PROTOCOL_VERSION: &str = "/quanta/identify/0.0.1";
 
@@ -10,10 +32,12 @@ pub struct QuantaBehaviour<S>
 where
     S: quanta_swap::Storage + 'static,
 {
-    /// [kad::Kademlia] is a DHT that used for peers discovery for more info see
-    /// https://en.wikipedia.org/wiki/Kademlia
-    pub(crate) kademlia: kad::Kademlia<kad::store::MemoryStore>,
+    /// [kad::Kademlia] is a DHT that used for peers discovery and for provider records of
+    /// artifacts and magnet roots. Records are kept in [PersistentStore], so they are not lost on
+    /// restart. For more info see https://en.wikipedia.org/wiki/Kademlia
+    pub(crate) kademlia: kad::Kademlia<PersistentStore>,
     /// [quanta_swap::Behaviour] is a custom protocol that used for searching artifacts in network.
+    /// Received artifacts are checked with [ArtifactValidator]
     pub(crate) quanta_swap: quanta_swap::Behaviour<S>,
     /// [identify::Behaviour] is a protocol that used for peers-identification. If we got this
     /// behaviour we know some [identify::Info] about peer that we can use in HTTP-API
@@ -21,30 +45,112 @@ where
     /// [ping::Behaviour] is a protocol that used for check rtt delay to peers.
     pub(crate) ping: ping::Behaviour,
     /// [mdns::async_io::Behaviour] is a protool that used for local-peers identification.
-    /// using this protocol we can discover peers on the local network
-    pub(crate) mdns: mdns::async_io::Behaviour,
+    /// using this protocol we can discover peers on the local network. Can be disabled in
+    /// [crate::QuantaNetworkConfig]
+    pub(crate) mdns: Toggle<mdns::async_io::Behaviour>,
+    /// [autonat::Behaviour] is a protocol that used for probing if we are reachable from outside
+    /// (dial-back from other peers). If we are not reachable we are listen over relays
+    pub(crate) autonat: autonat::Behaviour,
+    /// [relay::client::Behaviour] is a protocol that used for reservations on relays, so peers
+    /// behind NAT can be reached over `/p2p-circuit` addresses
+    pub(crate) relay_client: relay::client::Behaviour,
+    /// [relay::Behaviour] is a protocol that used for relaying connections of other peers. Can be
+    /// enabled in [crate::QuantaNetworkConfig]
+    pub(crate) relay_server: Toggle<relay::Behaviour>,
+    /// [dcutr::Behaviour] is a protocol that used for hole punching. Upgrades relayed connection
+    /// into direct connection
+    pub(crate) dcutr: dcutr::Behaviour,
+    /// [allow_block_list::Behaviour] that denies connections of peers that are not in allowlist.
+    /// Enabled when allowlist in [crate::QuantaNetworkConfig] is not empty
+    pub(crate) allowed_peers: Toggle<allow_block_list::Behaviour<allow_block_list::AllowedPeers>>,
+    /// [allow_block_list::Behaviour] that denies connections of peers from denylist
+    pub(crate) denied_peers: allow_block_list::Behaviour<allow_block_list::BlockedPeers>,
+    /// [gossipsub::Behaviour] is a protocol that used for publishing announcements in feeds.
+    /// Messages are signed and validated before they are forwarded to other peers
+    pub(crate) gossipsub: gossipsub::Behaviour,
 }
 
 impl<S> QuantaBehaviour<S>
 where
     S: quanta_swap::Storage + 'static,
 {
-    /// Creates new [QuantaBehaviour]
+    /// Creates new [QuantaBehaviour]. [relay::client::Behaviour] should be created together with
+    /// transport that is used by swarm, see [relay::client::new]
     pub fn new(
         local_peer_id: PeerId,
-        public_key: PublicKey,
+        keypair: &Keypair,
         storage: Arc<S>,
+        kademlia_store: PersistentStore,
+        ledger_storage: SledLedgerStorage,
+        relay_client: relay::client::Behaviour,
+        config: &QuantaNetworkConfig,
     ) -> QuantaBehaviour<S> {
-        let kademlia =
-            kad::Kademlia::new(local_peer_id, kad::store::MemoryStore::new(local_peer_id));
-        let quanta_swap = quanta_swap::Behaviour::new(storage);
+        let kademlia = kad::Kademlia::new(local_peer_id, kademlia_store);
+        let quanta_swap = quanta_swap::Behaviour::with_config(
+            storage,
+            ArtifactValidator,
+            config.quanta_swap_config().clone(),
+        )
+        .with_ledger_storage(Arc::new(ledger_storage));
         let identify = identify::Behaviour::new(identify::Config::new(
             QUANTA_IDENTIFY_PROTOCOL_VERSION.to_string(),
-            public_key,
+            keypair.public(),
         ));
         let ping = ping::Behaviour::new(ping::Config::default());
-        let mdns = mdns::async_io::Behaviour::new(mdns::Config::default(), local_peer_id)
-            .expect("Got error when trying to create mdns::Behaviour");
+        let mdns = Toggle::from(config.enable_mdns().then(|| {
+            mdns::async_io::Behaviour::new(mdns::Config::default(), local_peer_id)
+                .expect("Got error when trying to create mdns::Behaviour")
+        }));
+        let mut autonat = autonat::Behaviour::new(local_peer_id, config.autonat_config().clone());
+        // relays and bootstrap peers are known to be reachable, so they are used as servers for
+        // probes
+        for address in config
+            .relays()
+            .iter()
+            .chain(config.bootstrap_peers())
+        {
+            if let Some(Protocol::P2p(multihash)) = address.iter().last() {
+                if let Ok(peer_id) = PeerId::from_multihash(multihash) {
+                    autonat.add_server(peer_id, Some(address.clone()));
+                }
+            }
+        }
+        let relay_server = Toggle::from(
+            config
+                .enable_relay_server()
+                .then(|| relay::Behaviour::new(local_peer_id, relay::Config::default())),
+        );
+        let dcutr = dcutr::Behaviour::new(local_peer_id);
+        let allowed_peers = Toggle::from((!config.allowed_peers().is_empty()).then(|| {
+            let mut allowed_peers = allow_block_list::Behaviour::default();
+            for peer_id in config.allowed_peers() {
+                allowed_peers.allow_peer(*peer_id);
+            }
+            allowed_peers
+        }));
+        let mut denied_peers = allow_block_list::Behaviour::default();
+        for peer_id in config.denied_peers() {
+            denied_peers.block_peer(*peer_id);
+        }
+        let gossipsub = gossipsub::Behaviour::new(
+            gossipsub::MessageAuthenticity::Signed(keypair.clone()),
+            gossipsub::ConfigBuilder::default()
+                // the same announcement that published twice is one message. Id is sha2-256 of
+                // data in topic, so it is the same on every peer and the same announcement is
+                // still delivered in every feed
+                .message_id_fn(|message| {
+                    gossipsub::MessageId::new(
+                        &Sha256::new()
+                            .chain_update(message.topic.as_str())
+                            .chain_update(&message.data)
+                            .finalize(),
+                    )
+                })
+                .validate_messages()
+                .build()
+                .expect("Got error when trying to create gossipsub::Config"),
+        )
+        .expect("Got error when trying to create gossipsub::Behaviour");
 
         QuantaBehaviour {
             kademlia,
@@ -52,6 +158,13 @@ where
             identify,
             ping,
             mdns,
+            autonat,
+            relay_client,
+            relay_server,
+            dcutr,
+            allowed_peers,
+            denied_peers,
+            gossipsub,
         }
     }
 }
@@ -0,0 +1,202 @@
+use std::path::{Path, PathBuf};
+
+use libp2p::{autonat, pnet::PreSharedKey, Multiaddr, PeerId};
+
+/// Default addresses that swarm listens on with tcp and quic transports. Port is chosen by OS
+const DEFAULT_LISTEN_ADDRS: [&str; 2] = ["/ip4/0.0.0.0/tcp/0", "/ip4/0.0.0.0/udp/0/quic-v1"];
+/// Default max count of peers in address book
+const DEFAULT_MAX_KNOWN_PEERS: usize = 1024;
+/// Default max count of peers from address book that we are dial on start
+const DEFAULT_MAX_STARTUP_DIALS: usize = 32;
+
+/// Configuration of [crate::QuantaNetwork]
+#[derive(Debug, Clone)]
+pub struct QuantaNetworkConfig {
+    /// Addresses that swarm listens on. Tcp and quic (`/udp/<port>/quic-v1`) addresses are
+    /// supported
+    listen_addrs: Vec<Multiaddr>,
+    /// Addresses that other peers can dial us on. Used when they are known, for example on relay
+    /// server with public address. Other addresses are confirmed by [autonat]
+    external_addrs: Vec<Multiaddr>,
+    /// Peers that we are dial on start and add into [libp2p::kad::Kademlia]. Address should end
+    /// with `/p2p/<peer-id>`
+    bootstrap_peers: Vec<Multiaddr>,
+    /// If false peers on local network are not discovered with [libp2p::mdns]
+    enable_mdns: bool,
+    /// Relays that we are use for reservations when [autonat] reports that we are not reachable.
+    /// Address should end with `/p2p/<peer-id>`
+    relays: Vec<Multiaddr>,
+    /// If true we are relay connections of other peers with [libp2p::relay::Behaviour]
+    enable_relay_server: bool,
+    /// Config of [autonat::Behaviour]
+    autonat_config: autonat::Config,
+    /// Names of feeds that we are subscribed to on start
+    feeds: Vec<String>,
+    /// Directory of [sled] database where records of [libp2p::kad::Kademlia] and address book of
+    /// known peers are stored. If None they are stored in temporary database and are lost on
+    /// restart
+    store_path: Option<PathBuf>,
+    /// Max count of peers in address book. Peers that were connected the longest time ago are
+    /// forgotten first
+    max_known_peers: usize,
+    /// Max count of peers from address book that we are dial on start. Peers that were
+    /// connected recently are dialed first, others are only added into [libp2p::kad::Kademlia]
+    max_startup_dials: usize,
+    /// If true loopback, private and link-local addresses of peers are not saved into address
+    /// book
+    only_global_addrs: bool,
+    /// Key of private network. If set only peers with the same key can connect to us, see
+    /// [crate::QuantaNetwork]
+    psk: Option<PreSharedKey>,
+    /// If not empty only these peers can be connected to us
+    allowed_peers: Vec<PeerId>,
+    /// Peers that can not be connected to us
+    denied_peers: Vec<PeerId>,
+    /// Configuration of [quanta_swap::Behaviour]: timeouts, rate limits and bans of peers
+    quanta_swap_config: quanta_swap::Config,
+}
+
+impl QuantaNetworkConfig {
+    /// Set addresses that swarm listens on
+    pub fn with_listen_addrs(mut self, listen_addrs: Vec<Multiaddr>) -> Self {
+        self.listen_addrs = listen_addrs;
+        self
+    }
+    /// Set addresses that other peers can dial us on
+    pub fn with_external_addrs(mut self, external_addrs: Vec<Multiaddr>) -> Self {
+        self.external_addrs = external_addrs;
+        self
+    }
+    /// Set peers that we are dial on start
+    pub fn with_bootstrap_peers(mut self, bootstrap_peers: Vec<Multiaddr>) -> Self {
+        self.bootstrap_peers = bootstrap_peers;
+        self
+    }
+    /// Enable or disable [libp2p::mdns]
+    pub fn with_mdns(mut self, enable_mdns: bool) -> Self {
+        self.enable_mdns = enable_mdns;
+        self
+    }
+    /// Set relays that we are use when we are not reachable
+    pub fn with_relays(mut self, relays: Vec<Multiaddr>) -> Self {
+        self.relays = relays;
+        self
+    }
+    /// Enable or disable relaying connections of other peers
+    pub fn with_relay_server(mut self, enable_relay_server: bool) -> Self {
+        self.enable_relay_server = enable_relay_server;
+        self
+    }
+    /// Set config of [autonat::Behaviour]
+    pub fn with_autonat_config(mut self, autonat_config: autonat::Config) -> Self {
+        self.autonat_config = autonat_config;
+        self
+    }
+    /// Set names of feeds that we are subscribed to on start
+    pub fn with_feeds(mut self, feeds: Vec<String>) -> Self {
+        self.feeds = feeds;
+        self
+    }
+    /// Set directory where records of [libp2p::kad::Kademlia] and known peers are stored
+    pub fn with_store_path(mut self, store_path: Option<PathBuf>) -> Self {
+        self.store_path = store_path;
+        self
+    }
+    /// Set max count of peers in address book
+    pub fn with_max_known_peers(mut self, max_known_peers: usize) -> Self {
+        self.max_known_peers = max_known_peers;
+        self
+    }
+    /// Set max count of peers from address book that we are dial on start
+    pub fn with_max_startup_dials(mut self, max_startup_dials: usize) -> Self {
+        self.max_startup_dials = max_startup_dials;
+        self
+    }
+    /// Enable or disable saving of loopback, private and link-local addresses into address book
+    pub fn with_only_global_addrs(mut self, only_global_addrs: bool) -> Self {
+        self.only_global_addrs = only_global_addrs;
+        self
+    }
+    /// Set key of private network
+    pub fn with_psk(mut self, psk: Option<PreSharedKey>) -> Self {
+        self.psk = psk;
+        self
+    }
+    /// Set peers that only can be connected to us. Empty list allows every peer
+    pub fn with_allowed_peers(mut self, allowed_peers: Vec<PeerId>) -> Self {
+        self.allowed_peers = allowed_peers;
+        self
+    }
+    /// Set peers that can not be connected to us
+    pub fn with_denied_peers(mut self, denied_peers: Vec<PeerId>) -> Self {
+        self.denied_peers = denied_peers;
+        self
+    }
+    /// Set configuration of [quanta_swap::Behaviour]
+    pub fn with_quanta_swap_config(mut self, quanta_swap_config: quanta_swap::Config) -> Self {
+        self.quanta_swap_config = quanta_swap_config;
+        self
+    }
+    /// returns addresses that swarm listens on
+    pub fn listen_addrs(&self) -> &[Multiaddr] { &self.listen_addrs }
+    /// returns addresses that other peers can dial us on
+    pub fn external_addrs(&self) -> &[Multiaddr] { &self.external_addrs }
+    /// returns peers that we are dial on start
+    pub fn bootstrap_peers(&self) -> &[Multiaddr] { &self.bootstrap_peers }
+    /// returns true if [libp2p::mdns] is enabled
+    pub fn enable_mdns(&self) -> bool { self.enable_mdns }
+    /// returns relays that we are use when we are not reachable
+    pub fn relays(&self) -> &[Multiaddr] { &self.relays }
+    /// returns true if we are relay connections of other peers
+    pub fn enable_relay_server(&self) -> bool { self.enable_relay_server }
+    /// returns config of [autonat::Behaviour]
+    pub fn autonat_config(&self) -> &autonat::Config { &self.autonat_config }
+    /// returns names of feeds that we are subscribed to on start
+    pub fn feeds(&self) -> &[String] { &self.feeds }
+    /// returns directory where records of [libp2p::kad::Kademlia] and known peers are stored
+    pub fn store_path(&self) -> Option<&Path> { self.store_path.as_deref() }
+    /// returns max count of peers in address book
+    pub fn max_known_peers(&self) -> usize { self.max_known_peers }
+    /// returns max count of peers from address book that we are dial on start
+    pub fn max_startup_dials(&self) -> usize { self.max_startup_dials }
+    /// returns true if only global addresses are saved into address book
+    pub fn only_global_addrs(&self) -> bool { self.only_global_addrs }
+    /// returns key of private network
+    pub fn psk(&self) -> Option<PreSharedKey> { self.psk }
+    /// returns peers that only can be connected to us
+    pub fn allowed_peers(&self) -> &[PeerId] { &self.allowed_peers }
+    /// returns peers that can not be connected to us
+    pub fn denied_peers(&self) -> &[PeerId] { &self.denied_peers }
+    /// returns configuration of [quanta_swap::Behaviour]
+    pub fn quanta_swap_config(&self) -> &quanta_swap::Config { &self.quanta_swap_config }
+}
+
+impl Default for QuantaNetworkConfig {
+    fn default() -> Self {
+        Self {
+            listen_addrs: DEFAULT_LISTEN_ADDRS
+                .iter()
+                .map(|address| {
+                    address
+                        .parse()
+                        .expect("Default listen address is valid multiaddr")
+                })
+                .collect(),
+            external_addrs: Vec::new(),
+            bootstrap_peers: Vec::new(),
+            enable_mdns: true,
+            relays: Vec::new(),
+            enable_relay_server: false,
+            autonat_config: autonat::Config::default(),
+            feeds: Vec::new(),
+            store_path: None,
+            max_known_peers: DEFAULT_MAX_KNOWN_PEERS,
+            max_startup_dials: DEFAULT_MAX_STARTUP_DIALS,
+            only_global_addrs: true,
+            psk: None,
+            allowed_peers: Vec::new(),
+            denied_peers: Vec::new(),
+            quanta_swap_config: quanta_swap::Config::default(),
+        }
+    }
+}
@@ -0,0 +1,15 @@
+use libp2p::gossipsub;
+
+/// Prefix of [gossipsub] topics of feeds. Named topic of user is appended to it
+const FEED_TOPIC_PREFIX: &str = "/quanta/feed/";
+
+/// Returns [gossipsub::IdentTopic] of feed with given name
+pub(crate) fn feed_topic(topic: &str) -> gossipsub::IdentTopic {
+    gossipsub::IdentTopic::new(format!("{FEED_TOPIC_PREFIX}{topic}"))
+}
+/// Returns name of feed from [gossipsub::TopicHash] or None if topic is not a feed
+pub(crate) fn feed_name(topic: &gossipsub::TopicHash) -> Option<&str> {
+    topic
+        .as_str()
+        .strip_prefix(FEED_TOPIC_PREFIX)
+}
@@ -1,6 +1,6 @@
 use std::time::Duration;
 
-use libp2p::{identify, Multiaddr};
+use libp2p::{identify, multiaddr::Protocol, Multiaddr};
 
 /// [identify::Info] but with serde derives
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
@@ -32,6 +32,63 @@ impl From<identify::Info> for IdentifyInfoSerde {
     }
 }
 
+/// Transport that is used for connection with peer
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum ConnectionTransport {
+    /// Tcp with noise and yamux
+    Tcp,
+    /// Quic with built-in tls and multiplexing
+    Quic,
+    /// Circuit through relay, noise and yamux over stream of relay connection
+    Relay,
+}
+
+impl From<&Multiaddr> for ConnectionTransport {
+    fn from(value: &Multiaddr) -> Self {
+        // address of relayed connection contains transport of relay connection too, so circuit is
+        // checked first
+        if value
+            .iter()
+            .any(|protocol| matches!(protocol, Protocol::P2pCircuit))
+        {
+            return ConnectionTransport::Relay;
+        }
+        match value
+            .iter()
+            .any(|protocol| matches!(protocol, Protocol::QuicV1 | Protocol::Quic))
+        {
+            true => ConnectionTransport::Quic,
+            false => ConnectionTransport::Tcp,
+        }
+    }
+}
+
+/// Reachability of our node that we are get from [libp2p::autonat::Behaviour]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum NatStatus {
+    /// Other peers can dial us on public address
+    Public,
+    /// Other peers can not dial us, we are reachable only over relays
+    Private,
+    /// Not enough probes to determine status
+    Unknown,
+}
+
+/// Information about NAT traversal of our node
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct NatInfo {
+    /// Reachability of our node
+    pub(crate) status: NatStatus,
+    /// Address that was confirmed by probes if status is [NatStatus::Public]
+    pub(crate) public_address: Option<Multiaddr>,
+    /// Count of probes that confirmed current status
+    pub(crate) confidence: usize,
+    /// `/p2p-circuit` addresses that we are listen on over relays
+    pub(crate) relayed_addrs: Vec<Multiaddr>,
+    /// If true we are relay connections of other peers
+    pub(crate) relay_server: bool,
+}
+
 /// Base information about connection
 #[derive(Default, Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct ConnectionInfo {
@@ -41,4 +98,29 @@ pub struct ConnectionInfo {
     pub(crate) rtt: Option<Duration>,
     /// if peer_id discovered from mdns
     pub(crate) is_mdns: bool,
+    /// transport of last established connection with peer
+    pub(crate) transport: Option<ConnectionTransport>,
+    /// bytes that we are exchanged with peer in [quanta_swap::Behaviour]
+    pub(crate) ledger: Option<LedgerInfo>,
+}
+
+/// [quanta_swap::Ledger] but with serde derives and debt ratio of peer
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct LedgerInfo {
+    /// Size in bytes of artifacts that we are sent to peer
+    pub bytes_sent: u64,
+    /// Size in bytes of artifacts that peer sent to us
+    pub bytes_received: u64,
+    /// Bytes sent divided by bytes received, peers with lower ratio are served first
+    pub debt_ratio: f64,
+}
+
+impl From<&quanta_swap::Ledger> for LedgerInfo {
+    fn from(value: &quanta_swap::Ledger) -> Self {
+        LedgerInfo {
+            bytes_sent: value.bytes_sent,
+            bytes_received: value.bytes_received,
+            debt_ratio: value.debt_ratio(),
+        }
+    }
 }
@@ -0,0 +1,75 @@
+use std::sync::Arc;
+
+use libp2p::PeerId;
+use log::warn;
+use quanta_swap::{Ledger, LedgerStorage};
+use serde::{Deserialize, Serialize};
+
+const LEDGER_TREE_NAME: &str = "swap_ledgers";
+
+/// [LedgerStorage] of [quanta_swap::Behaviour] that keeps ledgers of peers in [sled], so debt
+/// ratio of peers is not lost on restart
+pub struct SledLedgerStorage {
+    /// Tree of ledgers. Key is a peer id, value is an encoded [StoredLedger]
+    ledger_tree: sled::Tree,
+}
+
+/// [Ledger] as it is stored in [sled]
+#[derive(Serialize, Deserialize)]
+struct StoredLedger {
+    bytes_sent: u64,
+    bytes_received: u64,
+}
+
+impl SledLedgerStorage {
+    /// Open tree of ledgers in [sled::Db]
+    pub fn open(db: &sled::Db) -> Result<Self, sled::Error> {
+        Ok(SledLedgerStorage {
+            ledger_tree: db.open_tree(LEDGER_TREE_NAME)?,
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl LedgerStorage for SledLedgerStorage {
+    /// Load ledger in blocking task, so [quanta_swap::Behaviour] is not blocked by disk
+    async fn load(self: Arc<Self>, peer: PeerId) -> Option<Ledger> {
+        async_std::task::spawn_blocking(move || self.load_ledger(&peer)).await
+    }
+    /// Save ledger in blocking task, so [quanta_swap::Behaviour] is not blocked by disk
+    async fn save(self: Arc<Self>, peer: PeerId, ledger: Ledger) {
+        async_std::task::spawn_blocking(move || self.save_ledger(&peer, ledger)).await
+    }
+}
+
+impl SledLedgerStorage {
+    /// Read ledger of peer from [sled] for [LedgerStorage::load]
+    fn load_ledger(&self, peer: &PeerId) -> Option<Ledger> {
+        let stored = match self.ledger_tree.get(peer.to_bytes()) {
+            Ok(stored) => stored?,
+            Err(error) => {
+                warn!("Got error when trying to load ledger of peer: {}", error);
+                return None;
+            },
+        };
+        // broken ledger is replaced with new one on next save
+        let stored = bincode::deserialize::<StoredLedger>(&stored).ok()?;
+        Some(Ledger {
+            bytes_sent: stored.bytes_sent,
+            bytes_received: stored.bytes_received,
+        })
+    }
+    /// Write ledger of peer into [sled] for [LedgerStorage::save]
+    fn save_ledger(&self, peer: &PeerId, ledger: Ledger) {
+        let stored = StoredLedger {
+            bytes_sent: ledger.bytes_sent,
+            bytes_received: ledger.bytes_received,
+        };
+        if let Err(error) = self.ledger_tree.insert(
+            peer.to_bytes(),
+            bincode::serialize(&stored).expect("Ledger is always serializable"),
+        ) {
+            warn!("Got error when trying to save ledger of peer: {}", error);
+        }
+    }
+}
@@ -1,8 +1,19 @@
 #![allow(dead_code)]
+mod address_book;
 mod behaviour;
+mod config;
+mod feed;
 mod info;
+mod ledger;
+mod metrics;
 mod proxy;
 mod service;
+mod store;
+#[cfg(test)]
+mod test;
+mod transport;
+mod validator;
 
-pub use proxy::{FromNetworkEvent, ProxyError, QuantaNetworkServiceProxy};
+pub use config::QuantaNetworkConfig;
+pub use proxy::{FromNetworkEvent, IntoNetworkEvent, ProxyError, QuantaNetworkServiceProxy};
 pub use service::{Error, QuantaNetwork};
@@ -0,0 +1,209 @@
+use libp2p::{
+    metrics::{self, Recorder},
+    PeerId,
+};
+use prometheus_client::{
+    encoding::{EncodeLabelSet, EncodeLabelValue},
+    metrics::{counter::Counter, family::Family},
+    registry::{Registry, Unit},
+};
+
+/// Metrics of [crate::QuantaNetwork]. Contains libp2p metrics of swarm and protocols and
+/// counters of [quanta_swap::Behaviour]
+pub(crate) struct Metrics {
+    /// Metrics of swarm, kademlia, identify, ping and other libp2p protocols
+    libp2p: metrics::Metrics,
+    /// Metrics of [quanta_swap::Behaviour]
+    quanta_swap: QuantaSwapMetrics,
+}
+
+impl Metrics {
+    /// Create new [Metrics] and register them in [Registry]
+    pub(crate) fn new(registry: &mut Registry) -> Self {
+        Metrics {
+            libp2p: metrics::Metrics::new(registry),
+            quanta_swap: QuantaSwapMetrics::new(registry.sub_registry_with_prefix("quanta_swap")),
+        }
+    }
+    /// Record that we are started new search in [quanta_swap::Behaviour]
+    pub(crate) fn record_search_started(&self, kind: SearchKind) {
+        self.quanta_swap
+            .started
+            .get_or_create(&SearchLabels { kind })
+            .inc();
+    }
+    /// Record event of [quanta_swap::Behaviour]
+    pub(crate) fn record_quanta_swap(&self, event: &quanta_swap::Event) {
+        let swap = &self.quanta_swap;
+        match event {
+            quanta_swap::Event::QueryCompleted { .. } => {
+                swap.completed
+                    .get_or_create(&SearchLabels {
+                        kind: SearchKind::Query,
+                    })
+                    .inc();
+            },
+            quanta_swap::Event::WantListCompleted { .. } => {
+                swap.completed
+                    .get_or_create(&SearchLabels {
+                        kind: SearchKind::WantList,
+                    })
+                    .inc();
+            },
+            quanta_swap::Event::QueryTimedOut { .. } => {
+                swap.timed_out
+                    .get_or_create(&SearchLabels {
+                        kind: SearchKind::Query,
+                    })
+                    .inc();
+            },
+            quanta_swap::Event::WantListTimedOut { .. } => {
+                swap.timed_out
+                    .get_or_create(&SearchLabels {
+                        kind: SearchKind::WantList,
+                    })
+                    .inc();
+            },
+            quanta_swap::Event::ItemsServed { peer, items, bytes } => {
+                let labels = PeerLabels::from(peer);
+                swap.items_served
+                    .get_or_create(&labels)
+                    .inc_by(*items as u64);
+                swap.bytes_served
+                    .get_or_create(&labels)
+                    .inc_by(*bytes as u64);
+            },
+            quanta_swap::Event::PeerBanned { reason, .. } => {
+                swap.peers_banned
+                    .get_or_create(&BanLabels {
+                        reason: (*reason).into(),
+                    })
+                    .inc();
+            },
+            _ => {},
+        }
+    }
+}
+/// Record events of libp2p swarm and protocols with [metrics::Metrics]
+impl<E> Recorder<E> for Metrics
+where
+    metrics::Metrics: Recorder<E>,
+{
+    fn record(&self, event: &E) { self.libp2p.record(event) }
+}
+/// Counters of [quanta_swap::Behaviour]
+struct QuantaSwapMetrics {
+    /// Searches that we are started
+    started: Family<SearchLabels, Counter>,
+    /// Searches that were completed
+    completed: Family<SearchLabels, Counter>,
+    /// Searches that were removed after deadline
+    timed_out: Family<SearchLabels, Counter>,
+    /// Items that we are sent to peer
+    items_served: Family<PeerLabels, Counter>,
+    /// Bytes of items that we are sent to peer
+    bytes_served: Family<PeerLabels, Counter>,
+    /// Peers that were banned
+    peers_banned: Family<BanLabels, Counter>,
+}
+
+impl QuantaSwapMetrics {
+    /// Create new [QuantaSwapMetrics] and register them in [Registry]
+    fn new(registry: &mut Registry) -> Self {
+        let started = Family::default();
+        registry.register(
+            "searches_started",
+            "Number of searches that were started",
+            started.clone(),
+        );
+        let completed = Family::default();
+        registry.register(
+            "searches_completed",
+            "Number of searches that received all items",
+            completed.clone(),
+        );
+        let timed_out = Family::default();
+        registry.register(
+            "searches_timed_out",
+            "Number of searches that were not completed before deadline",
+            timed_out.clone(),
+        );
+        let items_served = Family::default();
+        registry.register(
+            "items_served",
+            "Number of items that were sent to peer",
+            items_served.clone(),
+        );
+        let bytes_served = Family::default();
+        registry.register_with_unit(
+            "served",
+            "Size of items that were sent to peer",
+            Unit::Bytes,
+            bytes_served.clone(),
+        );
+        let peers_banned = Family::default();
+        registry.register(
+            "peers_banned",
+            "Number of peers that were banned",
+            peers_banned.clone(),
+        );
+        QuantaSwapMetrics {
+            started,
+            completed,
+            timed_out,
+            items_served,
+            bytes_served,
+            peers_banned,
+        }
+    }
+}
+/// Kind of search in [quanta_swap::Behaviour]
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, EncodeLabelValue)]
+pub(crate) enum SearchKind {
+    /// Search of one item
+    Query,
+    /// Search of many items with one want list
+    WantList,
+}
+
+/// Labels of search counters
+#[derive(Debug, Clone, Hash, PartialEq, Eq, EncodeLabelSet)]
+struct SearchLabels {
+    kind: SearchKind,
+}
+/// Reason of ban in [quanta_swap::Behaviour]
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, EncodeLabelValue)]
+enum BanReason {
+    /// Peer sent too many invalid items
+    InvalidResponses,
+    /// Peer sent too many requests over rate limits
+    RateLimited,
+}
+
+impl From<quanta_swap::BanReason> for BanReason {
+    fn from(reason: quanta_swap::BanReason) -> Self {
+        match reason {
+            quanta_swap::BanReason::InvalidResponses => BanReason::InvalidResponses,
+            quanta_swap::BanReason::RateLimited => BanReason::RateLimited,
+        }
+    }
+}
+
+/// Labels of ban counters
+#[derive(Debug, Clone, Hash, PartialEq, Eq, EncodeLabelSet)]
+struct BanLabels {
+    reason: BanReason,
+}
+/// Labels of counters that are collected per peer
+#[derive(Debug, Clone, Hash, PartialEq, Eq, EncodeLabelSet)]
+struct PeerLabels {
+    peer: String,
+}
+
+impl From<&PeerId> for PeerLabels {
+    fn from(peer: &PeerId) -> Self {
+        PeerLabels {
+            peer: peer.to_string(),
+        }
+    }
+}
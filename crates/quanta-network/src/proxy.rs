@@ -1,27 +1,55 @@
-use std::{
-    collections::HashMap,
-    pin::Pin,
-    task::{Context, Poll},
-    time::Duration,
-};
+use std::{collections::HashMap, time::Duration};
 
-use futures::Stream;
-use libp2p::PeerId;
-use log::error;
-use quanta_artifact::{Artifact, ArtifactId};
+use futures::{
+    future,
+    stream::{self, BoxStream},
+    Stream,
+    StreamExt,
+};
+use libp2p::{multiaddr::Protocol, Multiaddr, PeerId};
+use log::{debug, warn};
+use quanta_artifact::{Artifact, ArtifactId, MagnetLink};
 use quanta_swap::SearchID;
 use tokio::sync;
 
-use crate::info::ConnectionInfo;
+use crate::info::{ConnectionInfo, NatInfo};
+
+/// How long we are wait for response from [crate::service::QuantaNetwork] longer than search in
+/// [quanta_swap::Behaviour], so search is timed out in network before proxy stops waiting
+pub(crate) const RESPONSE_TIMEOUT_MARGIN: Duration = Duration::from_secs(10);
+/// How long we are wait for response from [crate::service::QuantaNetwork] by default. It is
+/// default [quanta_swap::Config::query_timeout] with [RESPONSE_TIMEOUT_MARGIN]
+const DEFAULT_RESPONSE_TIMEOUT: Duration = Duration::from_secs(40);
 
 #[derive(thiserror::Error, Debug)]
 pub enum ProxyError {
-    #[error("Got Recv timeout error when trying to recv response from network")]
-    /// Error whill occur when trying to recv response from network
+    #[error("Got timeout error when trying to get response from network")]
+    /// Error whill occur when sending event into network or receiving response from it takes
+    /// longer than timeout
     RecvTimeout,
     #[error("Got error when trying to recv response from network: {0}")]
-    /// Error whill occur in [timeout_oneshot_recv]
+    /// Error whill occur in [QuantaNetworkServiceProxy::request] when network drops response
+    /// channel
     Recv(sync::oneshot::error::RecvError),
+    #[error("Subscription skipped {0} events from network")]
+    /// Error whill occur when subscription to search was too slow and result of search could be
+    /// skipped
+    Lagged(u64),
+    #[error("Network service is stopped")]
+    /// Error whill occur when [crate::service::QuantaNetwork] is stopped before search is finished
+    Closed,
+    #[error("Artifact was not found in network")]
+    /// Error whill occur when search is timed out or every peer does not have artifact
+    ArtifactNotFound,
+    #[error("Address {0} does not contain /p2p/<peer-id>")]
+    /// Error whill occur when address that we are dial has no peer id
+    DialPeerId(Multiaddr),
+    #[error("Got error when trying to dial peer: {0}")]
+    /// Error whill occur when [crate::service::QuantaNetwork] can not connect to peer
+    Dial(String),
+    #[error("Got error when trying to publish announcement: {0}")]
+    /// Error whill occur when [crate::service::QuantaNetwork] can not publish announcement
+    Publish(String),
     #[error("Got error when sending event into network: {0}")]
     /// Error whill occur when trying to send event into network
     Send(#[from] sync::mpsc::error::SendError<IntoNetworkEvent>),
@@ -38,15 +66,38 @@ pub enum FromNetworkEvent {
         /// Artifact that user search
         artifact: Artifact,
     },
+    /// When receive [quanta_swap::Event::QueryTimedOut] or [quanta_swap::Event::QueryNotFound]
+    /// we are send this event to proxy
+    QuantaSwapNotFound {
+        /// Unique id of search that we are receive from [quanta_swap::Behaviour::search_item_with]
+        search_id: SearchID,
+        /// Id of Artifact that user search
+        searching: ArtifactId,
+    },
+}
+
+impl FromNetworkEvent {
+    /// Returns [SearchID] of search that event belongs to
+    pub fn search_id(&self) -> SearchID {
+        match self {
+            FromNetworkEvent::QuantaSwapSearched { search_id, .. } |
+            FromNetworkEvent::QuantaSwapNotFound { search_id, .. } => *search_id,
+        }
+    }
 }
 /// [`QuantaNetworkServiceProxy`] is a way to communicate with a service that is
 /// running on a different thread [crate::service::QuantaNetwork].
-/// Used in conjunication with various services (http-api e.t.c)
+/// Used in conjunication with various services (http-api e.t.c). Proxy can be cloned, so every
+/// service can have its own proxy
+#[derive(Clone)]
 pub struct QuantaNetworkServiceProxy {
-    /// Receive events from [crate::service::QuantaNetwork]
-    proxy_rx: sync::mpsc::Receiver<FromNetworkEvent>,
+    /// Sender of events from [crate::service::QuantaNetwork]. Used for creating subscriptions
+    /// with [QuantaNetworkServiceProxy::subscribe]
+    proxy_tx: sync::broadcast::Sender<FromNetworkEvent>,
     /// Send events into [crate::service::QuantaNetwork]
     network_tx: sync::mpsc::Sender<IntoNetworkEvent>,
+    /// How long we are wait for response from [crate::service::QuantaNetwork]
+    timeout: Duration,
 }
 /// Events that we are send from [`QuantaNetworkServiceProxy`] into [`crate::service::QuantaNetwork`]
 #[derive(Debug)]
@@ -56,6 +107,27 @@ pub enum IntoNetworkEvent {
         /// Over this channel network sends connections
         response_channel: sync::oneshot::Sender<HashMap<PeerId, ConnectionInfo>>,
     },
+    /// Get NAT status and relay addresses of our node
+    GetNatInfo {
+        /// Over this channel network sends NAT info
+        response_channel: sync::oneshot::Sender<NatInfo>,
+    },
+    /// Dial peer and wait until connection with it is established
+    Dial {
+        /// Id of peer that dialed
+        peer_id: PeerId,
+        /// Address of peer
+        address: Multiaddr,
+        /// Over this channel network sends Ok when we are connected to peer or error of dial
+        response_channel: sync::oneshot::Sender<Result<(), String>>,
+    },
+    /// Close all connections with peer
+    Disconnect {
+        /// Id of peer that disconnected
+        peer_id: PeerId,
+        /// Over this channel network sends false if we were not connected to peer
+        response_channel: sync::oneshot::Sender<bool>,
+    },
     /// Send new search into [quanta_swap::Behaviour] and get unique id of search
     CreateSearch {
         /// Artifact id that searched
@@ -63,60 +135,428 @@ pub enum IntoNetworkEvent {
         /// Unique id of search
         response_channel: sync::oneshot::Sender<SearchID>,
     },
+    /// Send new want list into [quanta_swap::Behaviour] and get all found [Artifact]s when want
+    /// list is finished
+    FetchArtifacts {
+        /// Artifact ids that searched
+        searching: Vec<ArtifactId>,
+        /// Over this channel network sends artifacts that were found. Artifacts that were not
+        /// found are missing in response
+        response_channel: sync::oneshot::Sender<Vec<Artifact>>,
+    },
+    /// Cancel active search in [quanta_swap::Behaviour]
+    CancelSearch {
+        /// Unique id of search
+        search_id: SearchID,
+    },
+    /// Announce in [libp2p::kad::Kademlia] that we are provide items with given keys
+    StartProviding {
+        /// Keys of items (artifact ids, magnet roots) in bytes
+        keys: Vec<Vec<u8>>,
+    },
+    /// Find providers of key in [libp2p::kad::Kademlia] and connect to them
+    FindProviders {
+        /// Key of item in bytes
+        key: Vec<u8>,
+        /// Over this channel network sends count of connected providers when lookup and dials
+        /// to found providers are finished
+        response_channel: sync::oneshot::Sender<usize>,
+    },
+    /// Subscribe to feed and store announcements that are published in it
+    SubscribeFeed {
+        /// Name of feed
+        topic: String,
+    },
+    /// Unsubscribe from feed
+    UnsubscribeFeed {
+        /// Name of feed
+        topic: String,
+    },
+    /// Publish announcement of magnet link in feed. We are publisher of announcement
+    PublishAnnouncement {
+        /// Name of feed
+        topic: String,
+        /// Magnet link that announced. Boxed because it is much larger than other events
+        magnet_link: Box<MagnetLink>,
+        /// Over this channel network sends Ok when announcement is published or error of publish
+        response_channel: sync::oneshot::Sender<Result<(), String>>,
+    },
 }
 
 impl QuantaNetworkServiceProxy {
     /// Create new [QuantaNetworkServiceProxy]
     pub fn new(
-        proxy_rx: sync::mpsc::Receiver<FromNetworkEvent>,
+        proxy_tx: sync::broadcast::Sender<FromNetworkEvent>,
         network_tx: sync::mpsc::Sender<IntoNetworkEvent>,
     ) -> Self {
         QuantaNetworkServiceProxy {
-            proxy_rx,
+            proxy_tx,
             network_tx,
+            timeout: DEFAULT_RESPONSE_TIMEOUT,
         }
     }
+    /// Set how long we are wait for response from [crate::service::QuantaNetwork] in calls that
+    /// are not called with explicit timeout
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+    /// Returns how long we are wait for response from [crate::service::QuantaNetwork]
+    pub fn timeout(&self) -> Duration { self.timeout }
+    /// Subscribe to all [FromNetworkEvent]s that are sent after subscription was created. Every
+    /// subscription receives its own copy of event
+    pub fn subscribe(&self) -> impl Stream<Item = FromNetworkEvent> + Send + 'static {
+        from_network_events(self.proxy_tx.subscribe())
+    }
+    /// Subscribe to result of search with given [SearchID]. Stream ends after the only
+    /// [FromNetworkEvent] of search or yields [ProxyError::Lagged] if result could be skipped.
+    /// Subscription should be created before search can be finished, otherwise use
+    /// [QuantaNetworkServiceProxy::search]
+    pub fn subscribe_search(
+        &self,
+        search_id: SearchID,
+    ) -> impl Stream<Item = Result<FromNetworkEvent, ProxyError>> + Send + 'static {
+        search_events(self.proxy_tx.subscribe(), search_id, None)
+    }
     /// Send event into [crate::service::QuantaNetwork] that we are want all identified connections
     /// and for response from [crate::service::QuantaNetwork]
-    pub fn get_connections(&self) -> Result<HashMap<PeerId, ConnectionInfo>, ProxyError> {
-        futures::executor::block_on(async move {
-            let (response_channel, response_channel_rx) = sync::oneshot::channel();
-            self.network_tx
-                .send(IntoNetworkEvent::GetConnections { response_channel })
-                .await?;
-            timeout_oneshot_recv(response_channel_rx).await
-        })
+    pub async fn get_connections(&self) -> Result<HashMap<PeerId, ConnectionInfo>, ProxyError> {
+        self.get_connections_with_timeout(self.timeout)
+            .await
+    }
+    /// Same as [QuantaNetworkServiceProxy::get_connections] but wait response with given timeout
+    pub async fn get_connections_with_timeout(
+        &self,
+        timeout: Duration,
+    ) -> Result<HashMap<PeerId, ConnectionInfo>, ProxyError> {
+        self.request(
+            |response_channel| IntoNetworkEvent::GetConnections { response_channel },
+            timeout,
+        )
+        .await
+    }
+    /// Send event into [crate::service::QuantaNetwork] that we are want NAT status of our node
+    /// and wait for response from [crate::service::QuantaNetwork]
+    pub async fn get_nat_info(&self) -> Result<NatInfo, ProxyError> {
+        self.get_nat_info_with_timeout(self.timeout)
+            .await
+    }
+    /// Same as [QuantaNetworkServiceProxy::get_nat_info] but wait response with given timeout
+    pub async fn get_nat_info_with_timeout(
+        &self,
+        timeout: Duration,
+    ) -> Result<NatInfo, ProxyError> {
+        self.request(
+            |response_channel| IntoNetworkEvent::GetNatInfo { response_channel },
+            timeout,
+        )
+        .await
+    }
+    /// Dial peer by address that ends with `/p2p/<peer-id>` and wait until connection with it is
+    /// established. Returns id of connected peer
+    pub async fn dial(&self, address: Multiaddr) -> Result<PeerId, ProxyError> {
+        self.dial_with_timeout(address, self.timeout)
+            .await
+    }
+    /// Same as [QuantaNetworkServiceProxy::dial] but wait connection with given timeout
+    pub async fn dial_with_timeout(
+        &self,
+        address: Multiaddr,
+        timeout: Duration,
+    ) -> Result<PeerId, ProxyError> {
+        let Some(Protocol::P2p(multihash)) = address.iter().last() else {
+            return Err(ProxyError::DialPeerId(address));
+        };
+        let peer_id = PeerId::from_multihash(multihash)
+            .map_err(|_| ProxyError::DialPeerId(address.clone()))?;
+        self.request(
+            |response_channel| IntoNetworkEvent::Dial {
+                peer_id,
+                address,
+                response_channel,
+            },
+            timeout,
+        )
+        .await?
+        .map_err(ProxyError::Dial)?;
+        Ok(peer_id)
+    }
+    /// Close all connections with peer. Returns false if we were not connected to it
+    pub async fn disconnect(&self, peer_id: PeerId) -> Result<bool, ProxyError> {
+        self.request(
+            |response_channel| IntoNetworkEvent::Disconnect {
+                peer_id,
+                response_channel,
+            },
+            self.timeout,
+        )
+        .await
     }
     /// Create and send new search into [crate::service::QuantaNetwork] and wait [SearchID] that be
     /// send as response from [crate::service::QuantaNetwork]
-    pub fn create_search(&self, searching: ArtifactId) -> Result<SearchID, ProxyError> {
-        futures::executor::block_on(async move {
-            let (response_channel, response_channel_rx) = sync::oneshot::channel();
+    pub async fn create_search(&self, searching: ArtifactId) -> Result<SearchID, ProxyError> {
+        self.create_search_with_timeout(searching, self.timeout)
+            .await
+    }
+    /// Same as [QuantaNetworkServiceProxy::create_search] but wait response with given timeout
+    pub async fn create_search_with_timeout(
+        &self,
+        searching: ArtifactId,
+        timeout: Duration,
+    ) -> Result<SearchID, ProxyError> {
+        self.request(
+            |response_channel| IntoNetworkEvent::CreateSearch {
+                searching,
+                response_channel,
+            },
+            timeout,
+        )
+        .await
+    }
+    /// Create new search and subscribe to its result like
+    /// [QuantaNetworkServiceProxy::subscribe_search]. Subscription is created before search is
+    /// sent into [crate::service::QuantaNetwork], so result of search can not be missed. Search is
+    /// cancelled when stream is dropped before result is received
+    pub async fn search(
+        &self,
+        searching: ArtifactId,
+    ) -> Result<
+        (
+            SearchID,
+            impl Stream<Item = Result<FromNetworkEvent, ProxyError>> + Send + 'static,
+        ),
+        ProxyError,
+    > {
+        let proxy_rx = self.proxy_tx.subscribe();
+        let search_id = self.create_search(searching).await?;
+        let guard = SearchGuard {
+            search_id,
+            network_tx: self.network_tx.clone(),
+            completed: false,
+        };
+        Ok((search_id, search_events(proxy_rx, search_id, Some(guard))))
+    }
+    /// Create new search like [QuantaNetworkServiceProxy::search] but wait until
+    /// [crate::service::QuantaNetwork] receive [Artifact] from network and return it
+    pub async fn fetch_artifact(&self, searching: ArtifactId) -> Result<Artifact, ProxyError> {
+        self.fetch_artifact_with_timeout(searching, self.timeout)
+            .await
+    }
+    /// Same as [QuantaNetworkServiceProxy::fetch_artifact] but wait artifact with given timeout
+    pub async fn fetch_artifact_with_timeout(
+        &self,
+        searching: ArtifactId,
+        timeout: Duration,
+    ) -> Result<Artifact, ProxyError> {
+        // search is cancelled by dropped stream when timeout is expired
+        tokio::time::timeout(timeout, async move {
+            let (_, events) = self.search(searching).await?;
+            match Box::pin(events).next().await {
+                Some(Ok(FromNetworkEvent::QuantaSwapSearched { artifact, .. })) => Ok(artifact),
+                Some(Ok(FromNetworkEvent::QuantaSwapNotFound { .. })) => {
+                    Err(ProxyError::ArtifactNotFound)
+                },
+                Some(Err(error)) => Err(error),
+                None => Err(ProxyError::Closed),
+            }
+        })
+        .await
+        .map_err(|_| ProxyError::RecvTimeout)?
+    }
+    /// Fetch many artifacts with one want list. Returns only artifacts that were found, so
+    /// caller should check which ones are missing
+    pub async fn fetch_artifacts(
+        &self,
+        searching: Vec<ArtifactId>,
+    ) -> Result<Vec<Artifact>, ProxyError> {
+        self.fetch_artifacts_with_timeout(searching, self.timeout)
+            .await
+    }
+    /// Same as [QuantaNetworkServiceProxy::fetch_artifacts] but wait artifacts with given timeout
+    pub async fn fetch_artifacts_with_timeout(
+        &self,
+        searching: Vec<ArtifactId>,
+        timeout: Duration,
+    ) -> Result<Vec<Artifact>, ProxyError> {
+        self.request(
+            |response_channel| IntoNetworkEvent::FetchArtifacts {
+                searching,
+                response_channel,
+            },
+            timeout,
+        )
+        .await
+    }
+    /// Announce that we are provide items with given keys, so other peers can find us in DHT
+    pub async fn start_providing(&self, keys: Vec<Vec<u8>>) -> Result<(), ProxyError> {
+        self.send(IntoNetworkEvent::StartProviding { keys })
+            .await
+    }
+    /// Find providers of key in DHT and wait until we are connected to them. Returns count of
+    /// connected providers
+    pub async fn find_providers(&self, key: Vec<u8>) -> Result<usize, ProxyError> {
+        self.find_providers_with_timeout(key, self.timeout)
+            .await
+    }
+    /// Same as [QuantaNetworkServiceProxy::find_providers] but wait providers with given timeout
+    pub async fn find_providers_with_timeout(
+        &self,
+        key: Vec<u8>,
+        timeout: Duration,
+    ) -> Result<usize, ProxyError> {
+        self.request(
+            |response_channel| IntoNetworkEvent::FindProviders {
+                key,
+                response_channel,
+            },
+            timeout,
+        )
+        .await
+    }
+    /// Subscribe to feed. Announcements that are published in it are stored in
+    /// [quanta_feed::FeedStorage]
+    pub async fn subscribe_feed(&self, topic: String) -> Result<(), ProxyError> {
+        self.send(IntoNetworkEvent::SubscribeFeed { topic })
+            .await
+    }
+    /// Unsubscribe from feed
+    pub async fn unsubscribe_feed(&self, topic: String) -> Result<(), ProxyError> {
+        self.send(IntoNetworkEvent::UnsubscribeFeed { topic })
+            .await
+    }
+    /// Publish announcement of magnet link in feed. Announcement is stored in our
+    /// [quanta_feed::FeedStorage] too
+    pub async fn publish_announcement(
+        &self,
+        topic: String,
+        magnet_link: MagnetLink,
+    ) -> Result<(), ProxyError> {
+        self.publish_announcement_with_timeout(topic, magnet_link, self.timeout)
+            .await
+    }
+    /// Same as [QuantaNetworkServiceProxy::publish_announcement] but wait result of publish with
+    /// given timeout
+    pub async fn publish_announcement_with_timeout(
+        &self,
+        topic: String,
+        magnet_link: MagnetLink,
+        timeout: Duration,
+    ) -> Result<(), ProxyError> {
+        self.request(
+            |response_channel| IntoNetworkEvent::PublishAnnouncement {
+                topic,
+                magnet_link: Box::new(magnet_link),
+                response_channel,
+            },
+            timeout,
+        )
+        .await?
+        .map_err(ProxyError::Publish)
+    }
+    /// Cancel search that was created with [QuantaNetworkServiceProxy::create_search]
+    pub async fn cancel_search(&self, search_id: SearchID) -> Result<(), ProxyError> {
+        self.send(IntoNetworkEvent::CancelSearch { search_id })
+            .await
+    }
+    /// Send event that does not wait for response into [crate::service::QuantaNetwork]. Sending
+    /// waits for free place in channel not longer than [QuantaNetworkServiceProxy::timeout]
+    async fn send(&self, event: IntoNetworkEvent) -> Result<(), ProxyError> {
+        tokio::time::timeout(self.timeout, self.network_tx.send(event))
+            .await
+            .map_err(|_| ProxyError::RecvTimeout)??;
+        Ok(())
+    }
+    /// Send event with response channel into [crate::service::QuantaNetwork] and read response
+    /// from it. Timeout bounds the whole call, so caller is not blocked when channel of network
+    /// is full
+    async fn request<R>(
+        &self,
+        event: impl FnOnce(sync::oneshot::Sender<R>) -> IntoNetworkEvent,
+        timeout: Duration,
+    ) -> Result<R, ProxyError> {
+        let (response_channel, response_channel_rx) = sync::oneshot::channel();
+        tokio::time::timeout(timeout, async move {
             self.network_tx
-                .send(IntoNetworkEvent::CreateSearch {
-                    searching,
-                    response_channel,
-                })
+                .send(event(response_channel))
                 .await?;
-            timeout_oneshot_recv(response_channel_rx).await
+            response_channel_rx
+                .await
+                .map_err(ProxyError::Recv)
         })
-    }
-}
-/// Read response from oneshot channel that we are get when sending specific events into network
-async fn timeout_oneshot_recv<R>(
-    response_channel_rx: sync::oneshot::Receiver<R>,
-) -> Result<R, ProxyError> {
-    let duration = Duration::from_secs(10);
-    tokio::time::timeout(duration, response_channel_rx)
         .await
         .map_err(|_| ProxyError::RecvTimeout)?
-        .map_err(ProxyError::Recv)
+    }
+}
+/// Create stream of [FromNetworkEvent]s from broadcast receiver. Events that were missed because
+/// subscription was too slow are skipped
+fn from_network_events(
+    proxy_rx: sync::broadcast::Receiver<FromNetworkEvent>,
+) -> BoxStream<'static, FromNetworkEvent> {
+    stream::unfold(proxy_rx, |mut proxy_rx| async move {
+        loop {
+            match proxy_rx.recv().await {
+                Ok(event) => return Some((event, proxy_rx)),
+                Err(sync::broadcast::error::RecvError::Lagged(skipped)) => {
+                    warn!("Subscription skipped {} events from network", skipped)
+                },
+                Err(sync::broadcast::error::RecvError::Closed) => return None,
+            }
+        }
+    })
+    .boxed()
+}
+/// Create stream of the only [FromNetworkEvent] of search with given [SearchID]. Stream ends
+/// after event of search, when network is stopped or with [ProxyError::Lagged] when subscription
+/// was too slow, because skipped events could contain result of search
+fn search_events(
+    mut proxy_rx: sync::broadcast::Receiver<FromNetworkEvent>,
+    search_id: SearchID,
+    mut guard: Option<SearchGuard>,
+) -> BoxStream<'static, Result<FromNetworkEvent, ProxyError>> {
+    stream::once(async move {
+        loop {
+            match proxy_rx.recv().await {
+                Ok(event) if event.search_id() == search_id => {
+                    if let Some(guard) = guard.as_mut() {
+                        guard.completed = true;
+                    }
+                    return Some(Ok(event));
+                },
+                Ok(_) => continue,
+                Err(sync::broadcast::error::RecvError::Lagged(skipped)) => {
+                    return Some(Err(ProxyError::Lagged(skipped)))
+                },
+                Err(sync::broadcast::error::RecvError::Closed) => return None,
+            }
+        }
+    })
+    .filter_map(future::ready)
+    .boxed()
+}
+/// Cancels search in [crate::service::QuantaNetwork] when nobody waits for its result anymore
+struct SearchGuard {
+    /// Unique id of search
+    search_id: SearchID,
+    /// Send [IntoNetworkEvent::CancelSearch] into [crate::service::QuantaNetwork]
+    network_tx: sync::mpsc::Sender<IntoNetworkEvent>,
+    /// Search is finished, so it is not cancelled
+    completed: bool,
 }
 
-impl Stream for QuantaNetworkServiceProxy {
-    type Item = FromNetworkEvent;
-
-    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
-        self.proxy_rx.poll_recv(cx)
+impl Drop for SearchGuard {
+    fn drop(&mut self) {
+        if self.completed {
+            return;
+        }
+        // drop can not wait for free place in channel, search is timed out in network anyway
+        if self
+            .network_tx
+            .try_send(IntoNetworkEvent::CancelSearch {
+                search_id: self.search_id,
+            })
+            .is_err()
+        {
+            debug!("Got error when cancelling search {}", self.search_id);
+        }
     }
 }
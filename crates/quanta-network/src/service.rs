@@ -1,130 +1,396 @@
-use std::{collections::HashMap, sync::Arc};
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    sync::Arc,
+};
 
-use either::Either;
 use libp2p::{
+    autonat,
+    core::transport::ListenerId,
+    dcutr,
     futures::StreamExt,
+    gossipsub,
     identify,
     identity::Keypair,
     kad,
     mdns,
-    noise,
+    metrics::Recorder,
+    multiaddr::Protocol,
     ping,
-    ping::Failure,
-    swarm,
-    swarm::ConnectionHandlerUpgrErr,
-    tcp,
-    yamux,
+    relay,
+    swarm::{
+        self,
+        dial_opts::{DialOpts, PeerCondition},
+    },
+    Multiaddr,
     PeerId,
     Swarm,
-    Transport,
 };
-use log::{debug, error, info};
+use log::{debug, error, info, warn};
+use prometheus_client::registry::Registry;
 use quanta_artifact::{Artifact, ArtifactId};
-use quanta_swap::Storage;
+use quanta_feed::{Announcement, FeedStorage};
+use quanta_swap::{SearchID, Storage};
 use tokio::sync;
-use void::Void;
 
 use crate::{
+    address_book::AddressBook,
     behaviour::{QuantaBehaviour, QuantaBehaviourEvent},
-    info::{ConnectionInfo, IdentifyInfoSerde},
-    proxy::{FromNetworkEvent, IntoNetworkEvent, QuantaNetworkServiceProxy},
+    config::QuantaNetworkConfig,
+    feed::{feed_name, feed_topic},
+    info::{
+        ConnectionInfo,
+        ConnectionTransport,
+        IdentifyInfoSerde,
+        LedgerInfo,
+        NatInfo,
+        NatStatus,
+    },
+    ledger::SledLedgerStorage,
+    metrics::{Metrics, SearchKind},
+    proxy::{
+        FromNetworkEvent,
+        IntoNetworkEvent,
+        QuantaNetworkServiceProxy,
+        RESPONSE_TIMEOUT_MARGIN,
+    },
+    store::PersistentStore,
+    transport::build_transport,
 };
 
 const CHANNELS_BUF_SIZE: usize = 2048 * 2;
+/// Max count of keys that are announced in [kad::Kademlia] at the same time. Other keys wait in
+/// queue, so providing of all stored keys on start does not flood DHT
+const MAX_PROVIDING_QUERIES: usize = 16;
+/// Max count of missing keys of want list which providers are looked up in [kad::Kademlia].
+/// Artifacts of one want list usually belong to the same file, so they have the same providers
+const MAX_WANT_LIST_PROVIDER_LOOKUPS: usize = 4;
 #[derive(thiserror::Error, Debug)]
 pub enum Error {
     #[error("Got error when trying to decode bytes into ArtifactId: {0}")]
     /// Error whill occur when trying to decode bytes into [ArtifactId]
     ArtifactId(quanta_artifact::ArtifactIdError),
-    #[error("Got error when trying to send event into proxy: {0}")]
-    FromNetworkSend(#[from] sync::mpsc::error::SendError<FromNetworkEvent>),
     #[error("Gto error when trying to deal addr: {0}")]
     /// Error whill occur when we are trying [Swarm::dial]
     Dial(swarm::DialError),
+    #[error("Got error when trying to listen on addr: {0}")]
+    /// Error whill occur when swarm can not listen on address from [QuantaNetworkConfig]
+    Listen(libp2p::TransportError<std::io::Error>),
+    #[error("Bootstrap peer address {0} does not contain /p2p/<peer-id>")]
+    /// Error whill occur when bootstrap peer address from [QuantaNetworkConfig] has no peer id
+    BootstrapPeerId(Multiaddr),
+    #[error("Got error when trying to subscribe to feed: {0}")]
+    /// Error whill occur when we are subscribe to feed in [gossipsub::Behaviour]
+    Subscribe(gossipsub::SubscriptionError),
+    #[error("Got error when trying to publish into feed: {0}")]
+    /// Error whill occur when we are publish announcement or unsubscribe from feed in
+    /// [gossipsub::Behaviour]
+    Publish(gossipsub::PublishError),
+    #[error("Got error when trying to encode announcement: {0}")]
+    /// Error whill occur when announcement can not be converted into bytes
+    Announcement(quanta_feed::AnnouncementError),
+    #[error("Got error when trying to open network store: {0}")]
+    /// Error whill occur when we are open [sled] database with records of [kad::Kademlia] and
+    /// address book of known peers
+    Store(sled::Error),
 }
 /// QuantaNetwork is the backbone of the networking service on the quanta network. It defines
 /// the swarm that [QuantaBehaviour] uses. Storing information about connected peers. to our node
 /// And define several channels for interacting with the proxy, which in turn is used in the HTTP-API
 pub struct QuantaNetwork<S>
 where
-    S: Storage + 'static,
+    S: Storage + FeedStorage + 'static,
 {
     /// Swarm is used to communicate between peers on a network using protocols that have been
     /// defined in [QuantaBehaviour]
     swarm: Swarm<QuantaBehaviour<S>>,
+    /// Storage of artifacts that shared with [quanta_swap::Behaviour]. Announcements from feeds
+    /// are stored here too
+    storage: Arc<S>,
     /// We store information about the connection with peers. Not all connections can be stored
     /// here, because we only store those that gave at least some response from protocols:
     /// [ping::Behaviour], [identify::Behaviour]
     connections: HashMap<PeerId, ConnectionInfo>,
-    /// Proxy sender. Send [FromNetworkEvent] into proxy
-    proxy_tx: sync::mpsc::Sender<FromNetworkEvent>,
+    /// Proxy sender. Send [FromNetworkEvent] into every clone of proxy
+    proxy_tx: sync::broadcast::Sender<FromNetworkEvent>,
     /// Proxy receiver. Receive [IntoNetworkEvent] from proxy
     network_rx: sync::mpsc::Receiver<IntoNetworkEvent>,
+    /// Want lists that were created by [IntoNetworkEvent::FetchArtifacts] with artifacts that
+    /// were already received
+    pending_want_lists: HashMap<SearchID, PendingWantList>,
+    /// Active lookups of providers in [kad::Kademlia]
+    provider_lookups: HashMap<kad::QueryId, ProviderLookup>,
+    /// Searches and want lists that were not found in connected peers and were continued with
+    /// providers from [kad::Kademlia]. If they are not found again they are finished
+    provider_searches: HashSet<SearchID>,
+    /// Want lists that wait until lookups of providers of their missing keys are finished
+    provider_want_lists: HashMap<SearchID, ProviderWantList>,
+    /// Lookups that were created by [IntoNetworkEvent::FindProviders] and wait until dials to
+    /// found providers are finished
+    provider_dials: Vec<ProviderDials>,
+    /// Keys that wait to be announced in [kad::Kademlia], see [MAX_PROVIDING_QUERIES]
+    providing_queue: VecDeque<Vec<u8>>,
+    /// Active announcements of keys in [kad::Kademlia]
+    providing_queries: HashSet<kad::QueryId>,
+    /// Relays from [QuantaNetworkConfig] that we are listen on when [autonat::Behaviour] reports
+    /// that we are not reachable
+    relays: Vec<Multiaddr>,
+    /// Listeners of `/p2p-circuit` addresses over relays. Removed when we are reachable again
+    relay_listeners: Vec<ListenerId>,
+    /// Metrics of swarm, protocols and [quanta_swap::Behaviour] that are registered in
+    /// [Registry] given in [QuantaNetwork::new]
+    metrics: Metrics,
+    /// Addresses of peers that we were connected to. They are dialed again on start
+    address_book: AddressBook,
+    /// Dials that were created by [IntoNetworkEvent::Dial]. When connection with peer is
+    /// established or dial is failed we are send result over channels
+    pending_dials: HashMap<PeerId, Vec<sync::oneshot::Sender<Result<(), String>>>>,
+}
+/// Lookup of providers of key in [kad::Kademlia]
+struct ProviderLookup {
+    /// Who waits for providers
+    target: ProviderLookupTarget,
+    /// Key in bytes
+    searching: Vec<u8>,
+    /// Providers that were found
+    providers: HashSet<PeerId>,
+}
+/// Who waits for lookup of providers in [kad::Kademlia]
+enum ProviderLookupTarget {
+    /// Search that is continued with found providers
+    Search(SearchID),
+    /// Want list that is continued with found providers when lookups of all its keys are finished
+    WantList(SearchID),
+    /// We are just connect to providers. Count of connected providers is sent over channel
+    Connect(sync::oneshot::Sender<usize>),
+}
+/// Want list that waits for lookups of providers of its missing keys
+struct ProviderWantList {
+    /// Keys that were not found in connected peers
+    missing: Vec<Vec<u8>>,
+    /// Count of lookups that are not finished yet
+    lookups: usize,
+    /// Providers that were found
+    providers: HashSet<PeerId>,
+}
+/// Dials to providers that were found for [IntoNetworkEvent::FindProviders]
+struct ProviderDials {
+    /// Providers that we are dialing
+    dialing: HashSet<PeerId>,
+    /// Count of providers that we are connected to
+    connected: usize,
+    /// Over this channel we are send count of connected providers when all dials are finished
+    response_channel: sync::oneshot::Sender<usize>,
+}
+/// Want list that waits for artifacts from network
+struct PendingWantList {
+    /// Artifacts that were received
+    artifacts: Vec<Artifact>,
+    /// Over this channel we are send artifacts when want list is finished
+    response_channel: sync::oneshot::Sender<Vec<Artifact>>,
 }
 /// Create custom type for more code readability
-type CustomSwarmEvent<S> = swarm::SwarmEvent<
-    QuantaBehaviourEvent<S>,
-    Either<
-        Either<
-            Either<
-                Either<std::io::Error, ConnectionHandlerUpgrErr<std::io::Error>>,
-                std::io::Error,
-            >,
-            Failure,
-        >,
-        Void,
-    >,
->;
+type CustomSwarmEvent<S> =
+    swarm::SwarmEvent<QuantaBehaviourEvent<S>, swarm::THandlerErr<QuantaBehaviour<S>>>;
+
+/// Add bootstrap peers and peers from [AddressBook] into [kad::Kademlia], dial them and start
+/// bootstrap of routing table. Only `max_known_dials` peers from [AddressBook] that were connected
+/// recently are dialed, kademlia dials others when it needs them
+fn bootstrap<S>(
+    swarm: &mut Swarm<QuantaBehaviour<S>>,
+    peers: &[Multiaddr],
+    address_book: &AddressBook,
+    max_known_dials: usize,
+) -> Result<(), Error>
+where
+    S: Storage + FeedStorage + 'static,
+{
+    for address in peers {
+        let Some(Protocol::P2p(multihash)) = address.iter().last() else {
+            return Err(Error::BootstrapPeerId(address.clone()));
+        };
+        let peer_id = PeerId::from_multihash(multihash)
+            .map_err(|_| Error::BootstrapPeerId(address.clone()))?;
+        swarm
+            .behaviour_mut()
+            .kademlia
+            .add_address(&peer_id, address.clone());
+        // bootstrap peer can be offline now, it is not a reason to stop
+        if let Err(error) = swarm.dial(address.clone()) {
+            warn!(
+                "Got error when trying to dial bootstrap peer {}: {}",
+                address, error
+            );
+        }
+    }
+    let known_peers = address_book
+        .peers()
+        .map_err(Error::Store)?;
+    for (index, (peer_id, addresses)) in known_peers.iter().enumerate() {
+        for address in addresses {
+            swarm
+                .behaviour_mut()
+                .kademlia
+                .add_address(peer_id, address.clone());
+        }
+        if index >= max_known_dials {
+            continue;
+        }
+        // bootstrap peers can be known already, they are dialed only once
+        let dial_opts = DialOpts::peer_id(*peer_id)
+            .addresses(addresses.clone())
+            .condition(PeerCondition::Disconnected)
+            .build();
+        if let Err(error) = swarm.dial(dial_opts) {
+            debug!(
+                "Got error when trying to dial known peer {}: {}",
+                peer_id, error
+            );
+        }
+    }
+    if !peers.is_empty() || !known_peers.is_empty() {
+        if let Err(error) = swarm
+            .behaviour_mut()
+            .kademlia
+            .bootstrap()
+        {
+            warn!("Got error when trying to bootstrap kademlia: {}", error);
+        }
+    }
+    Ok(())
+}
 
 impl<S> QuantaNetwork<S>
 where
-    S: Storage + 'static,
+    S: Storage + FeedStorage + 'static,
 {
-    /// Create new [QuantaNetwork]
+    /// Create new [QuantaNetwork] that listens on addresses from [QuantaNetworkConfig] and dials
+    /// bootstrap peers. Metrics of network are registered in given [Registry]
     pub fn new(
         keypair: &Keypair,
         local_peer_id: PeerId,
         storage: Arc<S>,
-    ) -> (QuantaNetwork<S>, QuantaNetworkServiceProxy) {
-        // create new swarm with async_io tcp transport, yamux multiplexer, noise authenticate
-        // and quanta behaviour
+        config: QuantaNetworkConfig,
+        registry: &mut Registry,
+    ) -> Result<(QuantaNetwork<S>, QuantaNetworkServiceProxy), Error> {
+        // relay client transport and behaviour are connected with each other, so they are
+        // created together
+        let (relay_transport, relay_client) = relay::client::new(local_peer_id);
+        // records of kademlia, known peers and ledgers of quanta swap are stored in the same
+        // database
+        let db = match config.store_path() {
+            Some(path) => sled::open(path),
+            None => sled::Config::new()
+                .temporary(true)
+                .open(),
+        }
+        .map_err(Error::Store)?;
+        let kademlia_store = PersistentStore::open(local_peer_id, &db).map_err(Error::Store)?;
+        let address_book =
+            AddressBook::open(&db, config.max_known_peers(), config.only_global_addrs())
+                .map_err(Error::Store)?;
+        let ledger_storage = SledLedgerStorage::open(&db).map_err(Error::Store)?;
+        // create new swarm with quic, tcp or relay transport and quanta behaviour
         let mut swarm = swarm::SwarmBuilder::with_async_std_executor(
-            tcp::async_io::Transport::new(tcp::Config::default().port_reuse(true))
-                .upgrade(libp2p::core::upgrade::Version::V1)
-                .authenticate(
-                    noise::Config::new(keypair)
-                        .expect("Got unexpected error when trying to create libp2p::noise::Config"),
-                )
-                .multiplex(yamux::Config::default())
-                .boxed(),
-            QuantaBehaviour::new(local_peer_id, keypair.public(), storage),
+            build_transport(keypair, relay_transport, config.psk()),
+            QuantaBehaviour::new(
+                local_peer_id,
+                keypair,
+                Arc::clone(&storage),
+                kademlia_store,
+                ledger_storage,
+                relay_client,
+                &config,
+            ),
             local_peer_id,
         )
         .build();
-        swarm
-            .listen_on("/ip4/0.0.0.0/tcp/0".parse().unwrap())
-            .unwrap();
-        let (proxy_tx, proxy_rx) = sync::mpsc::channel(CHANNELS_BUF_SIZE);
+        if let Some(psk) = config.psk() {
+            info!("Private Network With PSK Fingerprint={}", psk.fingerprint());
+        }
+        for address in config.listen_addrs() {
+            if config.psk().is_some() &&
+                address
+                    .iter()
+                    .any(|protocol| protocol == Protocol::QuicV1)
+            {
+                warn!(
+                    "Skip listen address {} because private network does not use quic",
+                    address
+                );
+                continue;
+            }
+            swarm
+                .listen_on(address.clone())
+                .map_err(Error::Listen)?;
+        }
+        for address in config.external_addrs() {
+            swarm.add_external_address(address.clone(), swarm::AddressScore::Infinite);
+        }
+        bootstrap(
+            &mut swarm,
+            config.bootstrap_peers(),
+            &address_book,
+            config.max_startup_dials(),
+        )?;
+        for topic in config.feeds() {
+            swarm
+                .behaviour_mut()
+                .gossipsub
+                .subscribe(&feed_topic(topic))
+                .map_err(Error::Subscribe)?;
+        }
+        let (proxy_tx, _) = sync::broadcast::channel(CHANNELS_BUF_SIZE);
         let (network_tx, network_rx) = sync::mpsc::channel(CHANNELS_BUF_SIZE);
+        let proxy = QuantaNetworkServiceProxy::new(proxy_tx.clone(), network_tx).with_timeout(
+            config
+                .quanta_swap_config()
+                .query_timeout() +
+                RESPONSE_TIMEOUT_MARGIN,
+        );
         let connections = HashMap::default();
-        (
+        let pending_want_lists = HashMap::default();
+        let provider_lookups = HashMap::default();
+        let provider_searches = HashSet::default();
+        let provider_want_lists = HashMap::default();
+        let provider_dials = Vec::default();
+        let providing_queue = VecDeque::default();
+        let providing_queries = HashSet::default();
+        let relays = config.relays().to_vec();
+        let relay_listeners = Vec::default();
+        let pending_dials = HashMap::default();
+        let metrics = Metrics::new(registry);
+        Ok((
             QuantaNetwork {
                 swarm,
+                storage,
                 connections,
                 proxy_tx,
                 network_rx,
+                pending_want_lists,
+                provider_lookups,
+                provider_searches,
+                provider_want_lists,
+                provider_dials,
+                providing_queue,
+                providing_queries,
+                relays,
+                relay_listeners,
+                metrics,
+                address_book,
+                pending_dials,
             },
-            QuantaNetworkServiceProxy::new(proxy_rx, network_tx),
-        )
+            proxy,
+        ))
     }
     /// Handle events from [ping::Behaviour]. We are intersted only in Ok events.
     /// Result of event we are use for compile information about connections with peer
     async fn handle_ping(&mut self, event: ping::Event) -> Result<(), Error> {
+        self.metrics.record(&event);
         if let Ok(ping::Success::Ping { rtt }) = event.result {
             // update or create new info about connection with peer which id given in ping::Event
             self.connections
                 .entry(event.peer)
-                .or_insert(ConnectionInfo::default())
+                .or_default()
                 .rtt = Some(rtt)
         };
         Ok(())
@@ -132,15 +398,72 @@ where
     /// Handle events from [identify::Behaviour]. We are interested only in [identify::Event::Received]
     /// events. Result of event we are use for compile info about connection with peer
     async fn handle_identify(&mut self, event: identify::Event) -> Result<(), Error> {
+        self.metrics.record(&event);
         if let identify::Event::Received { peer_id, info } = event {
+            // kademlia should know addresses of peer, so other peers can dial it when it is
+            // returned as provider
+            for address in &info.listen_addrs {
+                self.swarm
+                    .behaviour_mut()
+                    .kademlia
+                    .add_address(&peer_id, address.clone());
+                self.remember_address(&peer_id, address.clone());
+            }
             // update or create new info about connection with peer which id given in event
             self.connections
                 .entry(peer_id)
-                .or_insert(ConnectionInfo::default())
+                .or_default()
                 .identify_info = Some(IdentifyInfoSerde::from(info))
         };
         Ok(())
     }
+    /// Send result of dial into every proxy that waits for connection with peer
+    fn complete_dials(&mut self, peer_id: &PeerId, result: Result<(), String>) {
+        for response_channel in self
+            .pending_dials
+            .remove(peer_id)
+            .unwrap_or_default()
+        {
+            // proxy can stop waiting for dial (e.g. after timeout)
+            let _ = response_channel.send(result.clone());
+        }
+    }
+    /// Save address of peer into [AddressBook], so peer is dialed again after restart
+    fn remember_address(&self, peer_id: &PeerId, mut address: Multiaddr) {
+        if let Some(Protocol::P2p(_)) = address.iter().last() {
+            address.pop();
+        }
+        if let Err(error) = self
+            .address_book
+            .insert(peer_id, &address)
+        {
+            warn!(
+                "Got error when trying to save address of peer {}: {}",
+                peer_id, error
+            );
+        }
+    }
+    /// Save result of dial of peer into [AddressBook]. Peers that can not be dialed many times in
+    /// a row are forgotten
+    fn remember_dial(&self, peer_id: &PeerId, connected: bool) {
+        let result = match connected {
+            true => self.address_book.connected(peer_id),
+            false => self
+                .address_book
+                .failed(peer_id)
+                .map(|forgotten| {
+                    if forgotten {
+                        debug!("Forget peer {} that can not be dialed", peer_id);
+                    }
+                }),
+        };
+        if let Err(error) = result {
+            warn!(
+                "Got error when trying to save dial of peer {}: {}",
+                peer_id, error
+            );
+        }
+    }
     /// Handle events from [mdns::Behaviour]. Here we interested only in [mdns::Event::Discovered]
     /// event.
     async fn handle_mdns(&mut self, event: mdns::Event) -> Result<(), Error> {
@@ -151,56 +474,644 @@ where
                     "MDNS Discovered Local Device with PeerId={} and Address={}",
                     peer, address
                 );
+                // peer id is given, so peers that are not allowed are denied before dial
+                let dial_opts = DialOpts::peer_id(peer)
+                    .addresses(Vec::from([address.clone()]))
+                    .condition(PeerCondition::Always)
+                    .build();
+                match self.swarm.dial(dial_opts) {
+                    Ok(()) => {},
+                    Err(swarm::DialError::Denied { .. }) => {
+                        debug!("Skip Local Device with PeerId={} that is not allowed", peer);
+                        continue;
+                    },
+                    Err(error) => return Err(Error::Dial(error)),
+                }
                 self.connections
                     .entry(peer)
-                    .or_insert(ConnectionInfo::default())
+                    .or_default()
                     .is_mdns = true;
                 self.swarm
                     .behaviour_mut()
                     .kademlia
-                    .add_address(&peer, address.clone());
-                self.swarm
-                    .dial(address)
-                    .map_err(Error::Dial)?;
+                    .add_address(&peer, address);
+            }
+        };
+        Ok(())
+    }
+    /// Handle [autonat::Event]. When we are not reachable we are listen over relays, so other
+    /// peers can dial us over `/p2p-circuit`. When we are reachable again relay listeners are
+    /// removed
+    async fn handle_autonat(&mut self, event: autonat::Event) -> Result<(), Error> {
+        debug!("Received new AutoNAT event from swarm: {:?}", event);
+        if let autonat::Event::StatusChanged { old, new } = event {
+            info!("NAT Status Changed From {:?} To {:?}", old, new);
+            match new {
+                autonat::NatStatus::Private if self.relay_listeners.is_empty() => {
+                    for relay in &self.relays {
+                        match self
+                            .swarm
+                            .listen_on(relay.clone().with(Protocol::P2pCircuit))
+                        {
+                            Ok(listener_id) => self.relay_listeners.push(listener_id),
+                            Err(error) => warn!(
+                                "Got error when trying to listen over relay {}: {}",
+                                relay, error
+                            ),
+                        }
+                    }
+                },
+                autonat::NatStatus::Public(_) => {
+                    for listener_id in self.relay_listeners.drain(..) {
+                        self.swarm.remove_listener(listener_id);
+                    }
+                },
+                _ => {},
             }
+        }
+        Ok(())
+    }
+    /// Handle [relay::client::Event]. Events are only logged
+    async fn handle_relay_client(&mut self, event: relay::client::Event) -> Result<(), Error> {
+        match event {
+            relay::client::Event::ReservationReqAccepted { relay_peer_id, .. } => {
+                info!("Reservation Accepted by Relay={}", relay_peer_id)
+            },
+            relay::client::Event::ReservationReqFailed {
+                relay_peer_id,
+                error,
+                ..
+            } => warn!("Reservation Failed on Relay={}: {}", relay_peer_id, error),
+            event => debug!("Received new Relay client event from swarm: {:?}", event),
+        }
+        Ok(())
+    }
+    /// Handle [relay::Event] when we are relay server. Events are only logged
+    async fn handle_relay_server(&mut self, event: relay::Event) -> Result<(), Error> {
+        self.metrics.record(&event);
+        debug!("Received new Relay server event from swarm: {:?}", event);
+        Ok(())
+    }
+    /// Handle [dcutr::Event]. Events are only logged
+    async fn handle_dcutr(&mut self, event: dcutr::Event) -> Result<(), Error> {
+        self.metrics.record(&event);
+        match event {
+            dcutr::Event::DirectConnectionUpgradeSucceeded { remote_peer_id } => {
+                info!("Hole Punching Succeeded with PeerId={}", remote_peer_id)
+            },
+            dcutr::Event::DirectConnectionUpgradeFailed {
+                remote_peer_id,
+                error,
+            } => warn!(
+                "Hole Punching Failed with PeerId={}: {}",
+                remote_peer_id, error
+            ),
+            event => debug!("Received new DCUtR event from swarm: {:?}", event),
+        }
+        Ok(())
+    }
+    /// Returns [NatInfo] that we are compile from [autonat::Behaviour] and relay listeners
+    fn nat_info(&self) -> NatInfo {
+        let autonat = &self.swarm.behaviour().autonat;
+        let (status, public_address) = match autonat.nat_status() {
+            autonat::NatStatus::Public(address) => (NatStatus::Public, Some(address)),
+            autonat::NatStatus::Private => (NatStatus::Private, None),
+            autonat::NatStatus::Unknown => (NatStatus::Unknown, None),
         };
+        let relayed_addrs = self
+            .swarm
+            .listeners()
+            .filter(|address| {
+                address
+                    .iter()
+                    .any(|protocol| protocol == Protocol::P2pCircuit)
+            })
+            .cloned()
+            .collect();
+        NatInfo {
+            status,
+            public_address,
+            confidence: autonat.confidence(),
+            relayed_addrs,
+            relay_server: self
+                .swarm
+                .behaviour()
+                .relay_server
+                .is_enabled(),
+        }
+    }
+    /// Handle [gossipsub::Event]. Announcements from feeds are validated: publisher should be
+    /// source of signed message that carries announcement. Only valid announcements are stored
+    /// and forwarded to other peers. Announcements are stored in blocking task, so event loop does
+    /// not wait for storage
+    async fn handle_gossipsub(&mut self, event: gossipsub::Event) -> Result<(), Error> {
+        self.metrics.record(&event);
+        if let gossipsub::Event::Message {
+            propagation_source,
+            message_id,
+            message,
+        } = event
+        {
+            let acceptance = match (
+                feed_name(&message.topic),
+                Announcement::from_bincode(&message.data),
+            ) {
+                (Some(topic), Ok(announcement))
+                    if announcement.validate(message.source.as_ref()) =>
+                {
+                    info!(
+                        "Received Announcement of {} in Feed={} from PeerId={}",
+                        announcement.file_name, topic, announcement.publisher
+                    );
+                    let storage = Arc::clone(&self.storage);
+                    let topic = topic.to_string();
+                    async_std::task::spawn_blocking(move || {
+                        storage.insert_announcement(&topic, &announcement)
+                    });
+                    gossipsub::MessageAcceptance::Accept
+                },
+                _ => {
+                    warn!(
+                        "Received invalid announcement from PeerId={}",
+                        propagation_source
+                    );
+                    gossipsub::MessageAcceptance::Reject
+                },
+            };
+            self.swarm
+                .behaviour_mut()
+                .gossipsub
+                .report_message_validation_result(&message_id, &propagation_source, acceptance)
+                .map_err(Error::Publish)?;
+        }
         Ok(())
     }
-    /// Just log [kad::KademliaEvent] and do nothing. We are not intersted in kademlia events
+    /// Store announcement of magnet link in our storage and publish it in feed
+    fn publish_announcement(
+        &mut self,
+        topic: String,
+        magnet_link: quanta_artifact::MagnetLink,
+    ) -> Result<(), Error> {
+        let announcement = Announcement::new(&magnet_link, *self.swarm.local_peer_id());
+        self.storage
+            .insert_announcement(&topic, &announcement);
+        let data = announcement
+            .to_bincode()
+            .map_err(Error::Announcement)?;
+        match self
+            .swarm
+            .behaviour_mut()
+            .gossipsub
+            .publish(feed_topic(&topic), data)
+        {
+            Ok(_) => Ok(()),
+            // announcement is stored, so it is not an error that nobody receives it now
+            Err(gossipsub::PublishError::InsufficientPeers) => {
+                warn!("Nobody is subscribed to Feed={}", topic);
+                Ok(())
+            },
+            // the same announcement was already published recently
+            Err(gossipsub::PublishError::Duplicate) => Ok(()),
+            Err(error) => Err(Error::Publish(error)),
+        }
+    }
+    /// Handle [kad::KademliaEvent]. We are intersted only in results of providers lookups and
+    /// announcements of provided keys
     async fn handle_kademlia(&mut self, event: kad::KademliaEvent) -> Result<(), Error> {
         debug!("Received new Kademlia event from swarm: {:?}", event);
+        self.metrics.record(&event);
+        if let kad::KademliaEvent::OutboundQueryProgressed {
+            id,
+            result: kad::QueryResult::StartProviding(_),
+            step,
+            ..
+        } = &event
+        {
+            if step.last && self.providing_queries.remove(id) {
+                self.start_queued_providing();
+            }
+            return Ok(());
+        }
+        if let kad::KademliaEvent::OutboundQueryProgressed {
+            id,
+            result: kad::QueryResult::GetProviders(result),
+            step,
+            ..
+        } = event
+        {
+            let Some(lookup) = self.provider_lookups.get_mut(&id) else {
+                return Ok(());
+            };
+            if let Ok(kad::GetProvidersOk::FoundProviders { providers, .. }) = result {
+                lookup.providers.extend(providers);
+            }
+            if step.last {
+                if let Some(lookup) = self.provider_lookups.remove(&id) {
+                    return self
+                        .finish_provider_lookup(lookup)
+                        .await;
+                }
+            }
+        }
         Ok(())
     }
-    /// Handle event from [quanta_swap::Event]. QuantaSwap got only one event.
+    /// Announce queued keys in [kad::Kademlia] while count of active announcements is lower than
+    /// [MAX_PROVIDING_QUERIES]
+    fn start_queued_providing(&mut self) {
+        while self.providing_queries.len() < MAX_PROVIDING_QUERIES {
+            let Some(key) = self.providing_queue.pop_front() else {
+                return;
+            };
+            match self
+                .swarm
+                .behaviour_mut()
+                .kademlia
+                .start_providing(kad::RecordKey::new(&key))
+            {
+                Ok(query_id) => {
+                    self.providing_queries.insert(query_id);
+                },
+                Err(error) => warn!("Got error when trying to start providing key: {}", error),
+            }
+        }
+    }
+    /// Start lookup of providers of key in [kad::Kademlia]
+    fn start_provider_lookup(&mut self, target: ProviderLookupTarget, searching: Vec<u8>) {
+        let query_id = self
+            .swarm
+            .behaviour_mut()
+            .kademlia
+            .get_providers(kad::RecordKey::new(&searching));
+        self.provider_lookups
+            .insert(query_id, ProviderLookup {
+                target,
+                searching,
+                providers: HashSet::default(),
+            });
+    }
+    /// Continue search with found providers or just connect to them if nobody waits for lookup
+    async fn finish_provider_lookup(&mut self, mut lookup: ProviderLookup) -> Result<(), Error> {
+        let local_peer_id = *self.swarm.local_peer_id();
+        lookup.providers.remove(&local_peer_id);
+        info!(
+            "Found {} providers for lookup of {} bytes key",
+            lookup.providers.len(),
+            lookup.searching.len()
+        );
+        match lookup.target {
+            // search was cancelled while we are looked for providers
+            ProviderLookupTarget::Search(search_id) | ProviderLookupTarget::WantList(search_id)
+                if !self
+                    .provider_searches
+                    .contains(&search_id) =>
+            {
+                self.provider_want_lists
+                    .remove(&search_id);
+                Ok(())
+            },
+            ProviderLookupTarget::Search(search_id) if lookup.providers.is_empty() => {
+                self.complete_search(search_id, lookup.searching, None)
+                    .await
+            },
+            ProviderLookupTarget::Search(search_id) => {
+                self.swarm
+                    .behaviour_mut()
+                    .quanta_swap
+                    .search_item_with_providers(
+                        search_id,
+                        lookup.searching,
+                        lookup.providers.into_iter().collect(),
+                    );
+                Ok(())
+            },
+            ProviderLookupTarget::WantList(search_id) => {
+                self.finish_want_list_provider_lookup(search_id, lookup.providers);
+                Ok(())
+            },
+            ProviderLookupTarget::Connect(response_channel) => {
+                let mut dialing = HashSet::new();
+                let mut connected = 0;
+                for provider in lookup.providers {
+                    if self.swarm.is_connected(&provider) {
+                        connected += 1;
+                        continue;
+                    }
+                    // provider address can be unknown, so dial error is not critical
+                    match self.swarm.dial(provider) {
+                        Ok(()) => {
+                            dialing.insert(provider);
+                        },
+                        Err(error) => warn!(
+                            "Got error when trying to dial provider {}: {}",
+                            provider, error
+                        ),
+                    }
+                }
+                let dials = ProviderDials {
+                    dialing,
+                    connected,
+                    response_channel,
+                };
+                if dials.dialing.is_empty() {
+                    // proxy can stop waiting for providers (e.g. after timeout)
+                    let _ = dials
+                        .response_channel
+                        .send(dials.connected);
+                    return Ok(());
+                }
+                self.provider_dials.push(dials);
+                Ok(())
+            },
+        }
+    }
+    /// Look up providers of missing keys of want list. Want list is continued with found
+    /// providers when all lookups are finished
+    fn start_want_list_provider_lookup(&mut self, search_id: SearchID, missing: Vec<Vec<u8>>) {
+        let keys = missing
+            .iter()
+            .take(MAX_WANT_LIST_PROVIDER_LOOKUPS)
+            .cloned()
+            .collect::<Vec<Vec<u8>>>();
+        self.provider_want_lists
+            .insert(search_id, ProviderWantList {
+                missing,
+                lookups: keys.len(),
+                providers: HashSet::default(),
+            });
+        for key in keys {
+            self.start_provider_lookup(ProviderLookupTarget::WantList(search_id), key);
+        }
+    }
+    /// Save providers of one missing key of want list. When lookups of all keys are finished
+    /// want list is continued with providers or finished if nobody provides its keys
+    fn finish_want_list_provider_lookup(
+        &mut self,
+        search_id: SearchID,
+        providers: HashSet<PeerId>,
+    ) {
+        let Some(pending) = self
+            .provider_want_lists
+            .get_mut(&search_id)
+        else {
+            return;
+        };
+        pending.providers.extend(providers);
+        pending.lookups -= 1;
+        if pending.lookups > 0 {
+            return;
+        }
+        let Some(pending) = self
+            .provider_want_lists
+            .remove(&search_id)
+        else {
+            return;
+        };
+        if pending.providers.is_empty() {
+            self.complete_want_list(search_id);
+            return;
+        }
+        self.swarm
+            .behaviour_mut()
+            .quanta_swap
+            .search_items_with_providers(
+                search_id,
+                pending.missing,
+                pending.providers.into_iter().collect(),
+            );
+    }
+    /// Save result of dial to provider. When all dials of lookup are finished we are send count of
+    /// connected providers to whoever waits for them
+    fn complete_provider_dials(&mut self, peer_id: &PeerId, connected: bool) {
+        let mut idx = 0;
+        while idx < self.provider_dials.len() {
+            let dials = &mut self.provider_dials[idx];
+            if dials.dialing.remove(peer_id) && connected {
+                dials.connected += 1;
+            }
+            if !dials.dialing.is_empty() {
+                idx += 1;
+                continue;
+            }
+            let dials = self.provider_dials.swap_remove(idx);
+            // proxy can stop waiting for providers (e.g. after timeout)
+            let _ = dials
+                .response_channel
+                .send(dials.connected);
+        }
+    }
+    /// Handle event from [quanta_swap::Event]
     async fn handle_quanta_swap(&mut self, event: quanta_swap::Event) -> Result<(), Error> {
-        let quanta_swap::Event::QueryCompleted {
-            search_id,
-            searching,
-            item,
-            ..
-        } = event;
-        // just send FromNetworkEvent into proxy
-        Ok(self
-            .proxy_tx
-            .send(FromNetworkEvent::QuantaSwapSearched {
+        self.metrics.record_quanta_swap(&event);
+        match event {
+            quanta_swap::Event::QueryCompleted {
                 search_id,
-                searching: ArtifactId::from_bytes(searching.as_slice())
-                    .map_err(Error::ArtifactId)?,
-                artifact: Artifact::new(item),
-            })
-            .await?)
+                searching,
+                item,
+                ..
+            } => {
+                self.complete_search(search_id, searching, Some(Artifact::new(item)))
+                    .await
+            },
+            // quanta swap already closed connection with peer. Search stays active so we are
+            // just wait for artifact from other peers
+            quanta_swap::Event::QueryValidationFailed {
+                peer, search_id, ..
+            } => {
+                warn!(
+                    "Peer {} sent invalid artifact for search {}. Connection closed",
+                    peer, search_id
+                );
+                Ok(())
+            },
+            quanta_swap::Event::QueryNotFound {
+                search_id,
+                searching,
+            } => {
+                // connected peers does not have artifact, so we are try to find providers in DHT
+                // once
+                if self.provider_searches.insert(search_id) {
+                    debug!(
+                        "Search {} was not found in connected peers, looking for providers",
+                        search_id
+                    );
+                    self.start_provider_lookup(ProviderLookupTarget::Search(search_id), searching);
+                    return Ok(());
+                }
+                info!("Artifact for search {} was not found in network", search_id);
+                self.complete_search(search_id, searching, None)
+                    .await
+            },
+            quanta_swap::Event::QueryTimedOut {
+                search_id,
+                searching,
+            } => {
+                info!("Artifact for search {} was not found in network", search_id);
+                self.complete_search(search_id, searching, None)
+                    .await
+            },
+            quanta_swap::Event::WantListProgress {
+                search_id, items, ..
+            } => {
+                if let Some(pending) = self
+                    .pending_want_lists
+                    .get_mut(&search_id)
+                {
+                    pending.artifacts.extend(
+                        items
+                            .into_iter()
+                            .map(|(_, item)| Artifact::new(item)),
+                    );
+                }
+                Ok(())
+            },
+            quanta_swap::Event::WantListCompleted { search_id } => {
+                self.complete_want_list(search_id);
+                Ok(())
+            },
+            quanta_swap::Event::WantListNotFound { search_id, missing } => {
+                // connected peers does not have artifacts, so we are try to find providers in
+                // DHT once
+                if self
+                    .pending_want_lists
+                    .contains_key(&search_id) &&
+                    self.provider_searches.insert(search_id)
+                {
+                    debug!(
+                        "{} artifacts for want list {} were not found in connected peers, looking for providers",
+                        missing.len(),
+                        search_id
+                    );
+                    self.start_want_list_provider_lookup(search_id, missing);
+                    return Ok(());
+                }
+                info!(
+                    "{} artifacts for want list {} were not found in network",
+                    missing.len(),
+                    search_id
+                );
+                self.complete_want_list(search_id);
+                Ok(())
+            },
+            quanta_swap::Event::WantListTimedOut { search_id, missing } => {
+                info!(
+                    "{} artifacts for want list {} were not found in network",
+                    missing.len(),
+                    search_id
+                );
+                self.complete_want_list(search_id);
+                Ok(())
+            },
+            quanta_swap::Event::ItemsServed { peer, items, bytes } => {
+                debug!("Sent {} items ({} bytes) to PeerId={}", items, bytes, peer);
+                Ok(())
+            },
+            // quanta swap already closed connections with peer and denies new ones. Peer is
+            // removed from routing table, so we are not ask it about providers while it is banned
+            quanta_swap::Event::PeerBanned {
+                peer,
+                reason,
+                duration,
+            } => {
+                warn!("PeerId={} is banned for {:?}: {:?}", peer, duration, reason);
+                self.swarm
+                    .behaviour_mut()
+                    .kademlia
+                    .remove_peer(&peer);
+                Ok(())
+            },
+            quanta_swap::Event::PeerUnbanned { peer } => {
+                info!("Ban of PeerId={} is expired", peer);
+                Ok(())
+            },
+        }
+    }
+    /// Send artifacts that were received for want list to whoever waits for them
+    fn complete_want_list(&mut self, search_id: SearchID) {
+        self.provider_searches
+            .remove(&search_id);
+        self.provider_want_lists
+            .remove(&search_id);
+        if let Some(pending) = self
+            .pending_want_lists
+            .remove(&search_id)
+        {
+            if pending
+                .response_channel
+                .send(pending.artifacts)
+                .is_err()
+            {
+                error!("Got SendError when sending fetched Artifacts from network to proxy");
+            }
+        }
+    }
+    /// Send result of search into proxy. None means that artifact was not found
+    async fn complete_search(
+        &mut self,
+        search_id: SearchID,
+        searching: Vec<u8>,
+        artifact: Option<Artifact>,
+    ) -> Result<(), Error> {
+        self.provider_searches
+            .remove(&search_id);
+        let searching = ArtifactId::from_bytes(searching.as_slice()).map_err(Error::ArtifactId)?;
+        // it is fine if nobody listens for events
+        let event = match artifact {
+            Some(artifact) => FromNetworkEvent::QuantaSwapSearched {
+                search_id,
+                searching,
+                artifact,
+            },
+            None => FromNetworkEvent::QuantaSwapNotFound {
+                search_id,
+                searching,
+            },
+        };
+        if self.proxy_tx.send(event).is_err() {
+            debug!("Nobody listens for events from network in proxy");
+        }
+        Ok(())
     }
     /// Handle events that we are accept from [Swarm]. Events based on [QuantaBehaviour]
     async fn handle_swarm(&mut self, event: CustomSwarmEvent<S>) -> Result<(), Error> {
+        self.metrics.record(&event);
         match event {
-            swarm::SwarmEvent::ConnectionEstablished { peer_id, .. } => {
+            swarm::SwarmEvent::ConnectionEstablished {
+                peer_id, endpoint, ..
+            } => {
                 self.connections
                     .entry(peer_id)
-                    .or_insert(ConnectionInfo::default());
+                    .or_default()
+                    .transport = Some(ConnectionTransport::from(endpoint.get_remote_address()));
+                // address that we are dialed is known to work, listener address of inbound
+                // connection is usually an ephemeral port
+                if endpoint.is_dialer() {
+                    self.remember_address(&peer_id, endpoint.get_remote_address().clone());
+                    self.remember_dial(&peer_id, true);
+                }
+                self.complete_dials(&peer_id, Ok(()));
+                self.complete_provider_dials(&peer_id, true);
                 Ok(())
             },
-            swarm::SwarmEvent::ConnectionClosed { peer_id, .. } => {
-                self.connections.remove(&peer_id);
+            swarm::SwarmEvent::OutgoingConnectionError {
+                peer_id: Some(peer_id),
+                error,
+            } => {
+                debug!("Got error when trying to dial peer {}: {}", peer_id, error);
+                self.remember_dial(&peer_id, false);
+                self.complete_dials(&peer_id, Err(error.to_string()));
+                self.complete_provider_dials(&peer_id, false);
+                Ok(())
+            },
+            swarm::SwarmEvent::ConnectionClosed {
+                peer_id,
+                num_established,
+                ..
+            } => {
+                // peer can stay connected with other connection, e.g. QUIC next to TCP or
+                // direct connection after hole punch closed relayed one
+                if num_established == 0 {
+                    self.connections.remove(&peer_id);
+                }
                 Ok(())
             },
             swarm::SwarmEvent::NewListenAddr { address, .. } => {
@@ -213,26 +1124,107 @@ where
                 QuantaBehaviourEvent::Identify(event) => self.handle_identify(event).await,
                 QuantaBehaviourEvent::Ping(event) => self.handle_ping(event).await,
                 QuantaBehaviourEvent::Mdns(event) => self.handle_mdns(event).await,
+                QuantaBehaviourEvent::Autonat(event) => self.handle_autonat(event).await,
+                QuantaBehaviourEvent::RelayClient(event) => self.handle_relay_client(event).await,
+                QuantaBehaviourEvent::RelayServer(event) => self.handle_relay_server(event).await,
+                QuantaBehaviourEvent::Dcutr(event) => self.handle_dcutr(event).await,
+                QuantaBehaviourEvent::Gossipsub(event) => self.handle_gossipsub(event).await,
+                QuantaBehaviourEvent::AllowedPeers(event) => match event {},
+                QuantaBehaviourEvent::DeniedPeers(event) => match event {},
             },
             _ => Ok(()),
         }
     }
+    /// Returns connections with bytes that we are exchanged with peers in [quanta_swap::Behaviour]
+    fn connections_with_ledgers(&self) -> HashMap<PeerId, ConnectionInfo> {
+        let quanta_swap = &self.swarm.behaviour().quanta_swap;
+        self.connections
+            .iter()
+            .map(|(peer_id, info)| {
+                let mut info = info.clone();
+                info.ledger = quanta_swap
+                    .ledger(peer_id)
+                    .map(LedgerInfo::from);
+                (*peer_id, info)
+            })
+            .collect()
+    }
     /// Handle events that we are accept from [QuantaNetworkServiceProxy]
     async fn handle_proxy(&mut self, event: IntoNetworkEvent) -> Result<(), Error> {
         match event {
             IntoNetworkEvent::GetConnections { response_channel } => {
                 if response_channel
-                    .send(self.connections.clone())
+                    .send(self.connections_with_ledgers())
                     .is_err()
                 {
                     error!("Got SendError when sending connections from network to proxy")
                 }
                 Ok(())
             },
+            IntoNetworkEvent::GetNatInfo { response_channel } => {
+                if response_channel
+                    .send(self.nat_info())
+                    .is_err()
+                {
+                    error!("Got SendError when sending NAT info from network to proxy")
+                }
+                Ok(())
+            },
+            IntoNetworkEvent::Dial {
+                peer_id,
+                address,
+                response_channel,
+            } => {
+                if self.swarm.is_connected(&peer_id) {
+                    let _ = response_channel.send(Ok(()));
+                    return Ok(());
+                }
+                self.swarm
+                    .behaviour_mut()
+                    .kademlia
+                    .add_address(&peer_id, address.clone());
+                let dial_opts = DialOpts::peer_id(peer_id)
+                    .addresses(Vec::from([address]))
+                    .condition(PeerCondition::Disconnected)
+                    .build();
+                if let Err(error) = self.swarm.dial(dial_opts) {
+                    let _ = response_channel.send(Err(error.to_string()));
+                    return Ok(());
+                }
+                // drop dials which proxy stopped waiting for (e.g. after timeout)
+                self.pending_dials
+                    .retain(|_, response_channels| {
+                        response_channels.retain(|response_channel| !response_channel.is_closed());
+                        !response_channels.is_empty()
+                    });
+                self.pending_dials
+                    .entry(peer_id)
+                    .or_default()
+                    .push(response_channel);
+                Ok(())
+            },
+            IntoNetworkEvent::Disconnect {
+                peer_id,
+                response_channel,
+            } => {
+                let disconnected = self
+                    .swarm
+                    .disconnect_peer_id(peer_id)
+                    .is_ok();
+                if response_channel
+                    .send(disconnected)
+                    .is_err()
+                {
+                    error!("Got SendError when sending result of disconnect from network to proxy")
+                }
+                Ok(())
+            },
             IntoNetworkEvent::CreateSearch {
                 searching,
                 response_channel,
             } => {
+                self.metrics
+                    .record_search_started(SearchKind::Query);
                 if response_channel
                     .send(
                         self.swarm
@@ -246,6 +1238,100 @@ where
                 }
                 Ok(())
             },
+            IntoNetworkEvent::FetchArtifacts {
+                searching,
+                response_channel,
+            } => {
+                self.metrics
+                    .record_search_started(SearchKind::WantList);
+                let search_id = self
+                    .swarm
+                    .behaviour_mut()
+                    .quanta_swap
+                    .search_items_with(
+                        searching
+                            .iter()
+                            .map(|artifact_id| artifact_id.to_bytes())
+                            .collect(),
+                    );
+                // cancel want lists which proxy stopped waiting for (e.g. after timeout)
+                let quanta_swap = &mut self.swarm.behaviour_mut().quanta_swap;
+                let provider_searches = &mut self.provider_searches;
+                let provider_want_lists = &mut self.provider_want_lists;
+                self.pending_want_lists
+                    .retain(|search_id, pending| {
+                        if pending.response_channel.is_closed() {
+                            quanta_swap.cancel_search(search_id);
+                            provider_searches.remove(search_id);
+                            provider_want_lists.remove(search_id);
+                            return false;
+                        }
+                        true
+                    });
+                self.pending_want_lists
+                    .insert(search_id, PendingWantList {
+                        artifacts: Vec::with_capacity(searching.len()),
+                        response_channel,
+                    });
+                Ok(())
+            },
+            IntoNetworkEvent::StartProviding { keys } => {
+                self.providing_queue.extend(keys);
+                self.start_queued_providing();
+                Ok(())
+            },
+            IntoNetworkEvent::FindProviders {
+                key,
+                response_channel,
+            } => {
+                self.start_provider_lookup(ProviderLookupTarget::Connect(response_channel), key);
+                Ok(())
+            },
+            IntoNetworkEvent::SubscribeFeed { topic } => {
+                self.swarm
+                    .behaviour_mut()
+                    .gossipsub
+                    .subscribe(&feed_topic(&topic))
+                    .map_err(Error::Subscribe)?;
+                Ok(())
+            },
+            IntoNetworkEvent::UnsubscribeFeed { topic } => {
+                self.swarm
+                    .behaviour_mut()
+                    .gossipsub
+                    .unsubscribe(&feed_topic(&topic))
+                    .map_err(Error::Publish)?;
+                Ok(())
+            },
+            IntoNetworkEvent::PublishAnnouncement {
+                topic,
+                magnet_link,
+                response_channel,
+            } => {
+                let published = self
+                    .publish_announcement(topic, *magnet_link)
+                    .map_err(|error| error.to_string());
+                if response_channel
+                    .send(published)
+                    .is_err()
+                {
+                    error!("Got SendError when sending result of publish from network to proxy");
+                }
+                Ok(())
+            },
+            IntoNetworkEvent::CancelSearch { search_id } => {
+                self.pending_want_lists
+                    .remove(&search_id);
+                self.provider_searches
+                    .remove(&search_id);
+                self.provider_want_lists
+                    .remove(&search_id);
+                self.swarm
+                    .behaviour_mut()
+                    .quanta_swap
+                    .cancel_search(&search_id);
+                Ok(())
+            },
         }
     }
     /// Run [QuantaNetwork] that check [Swarm] for new events and handle
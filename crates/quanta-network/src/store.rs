@@ -0,0 +1,357 @@
+use std::{
+    borrow::Cow,
+    iter,
+    sync::mpsc::{self, Receiver, SyncSender, TrySendError},
+    thread::{self, JoinHandle},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use libp2p::{
+    kad::{
+        store::{MemoryStore, MemoryStoreConfig, RecordStore},
+        ProviderRecord,
+        Record,
+        RecordKey as Key,
+    },
+    Multiaddr,
+    PeerId,
+};
+use log::warn;
+use serde::{Deserialize, Serialize};
+
+const RECORD_TREE_NAME: &str = "kad_records";
+const PROVIDER_TREE_NAME: &str = "kad_providers";
+/// Max count of keys that we are provide in [libp2p::kad::Kademlia]. Every stored artifact and
+/// magnet root is provided, so default limit of [MemoryStore] is too small
+const MAX_PROVIDED_KEYS: usize = 1024 * 1024;
+/// Max count of writes that wait for background thread. When there are more writes, new writes
+/// are dropped: records are republished by [libp2p::kad::Kademlia], so they are written later
+const MAX_PENDING_WRITES: usize = 64 * 1024;
+/// Max count of writes that are applied to [sled] in one batch
+const MAX_BATCH_WRITES: usize = 1024;
+
+/// [RecordStore] of [libp2p::kad::Kademlia] that keeps records and provider records in
+/// [MemoryStore] and writes them through into [sled], so they are not lost on restart. Reads are
+/// served from memory, records that are expired when store is opened are skipped. Writes are
+/// applied in batches by background thread, so [libp2p::kad::Kademlia] is not blocked by disk
+pub struct PersistentStore {
+    /// Store that [libp2p::kad::Kademlia] reads from. It also checks limits of records
+    memory: MemoryStore,
+    /// Over this channel we are send writes into background thread
+    writes: SyncSender<StoreWrite>,
+    /// Background thread that writes into [sled]. It is joined on drop, so pending writes are
+    /// not lost
+    writer: Option<JoinHandle<()>>,
+}
+
+/// Write into [sled] that is applied by background thread of [PersistentStore]. Value is None
+/// when stored entry is removed
+enum StoreWrite {
+    /// Write into tree of records
+    Record(Vec<u8>, Option<Vec<u8>>),
+    /// Write into tree of provider records
+    Provider(Vec<u8>, Option<Vec<u8>>),
+}
+
+/// [Record] as it is stored in [sled]
+#[derive(Serialize, Deserialize)]
+struct StoredRecord {
+    key: Vec<u8>,
+    value: Vec<u8>,
+    publisher: Option<Vec<u8>>,
+    /// Time of expiration in milliseconds since [UNIX_EPOCH]
+    expires: Option<u64>,
+}
+
+/// [ProviderRecord] as it is stored in [sled]
+#[derive(Serialize, Deserialize)]
+struct StoredProviderRecord {
+    key: Vec<u8>,
+    provider: Vec<u8>,
+    /// Time of expiration in milliseconds since [UNIX_EPOCH]
+    expires: Option<u64>,
+    addresses: Vec<Vec<u8>>,
+}
+
+impl PersistentStore {
+    /// Open trees of records in [sled::Db] and load records that are not expired into memory
+    pub fn open(local_peer_id: PeerId, db: &sled::Db) -> Result<Self, sled::Error> {
+        let mut memory = MemoryStore::with_config(local_peer_id, MemoryStoreConfig {
+            max_provided_keys: MAX_PROVIDED_KEYS,
+            ..Default::default()
+        });
+        let record_tree = db.open_tree(RECORD_TREE_NAME)?;
+        let provider_tree = db.open_tree(PROVIDER_TREE_NAME)?;
+        load(&mut memory, &record_tree, &provider_tree)?;
+        let (writes, writes_rx) = mpsc::sync_channel(MAX_PENDING_WRITES);
+        let writer = thread::Builder::new()
+            .name("kad-store-writer".to_string())
+            .spawn(move || write_loop(&record_tree, &provider_tree, writes_rx))
+            .map_err(sled::Error::Io)?;
+        Ok(PersistentStore {
+            memory,
+            writes,
+            writer: Some(writer),
+        })
+    }
+    /// Send write into background thread. Write is dropped when thread is behind
+    fn write(&self, write: StoreWrite) {
+        match self.writes.try_send(write) {
+            Ok(()) => {},
+            Err(TrySendError::Full(_)) => {
+                warn!("Kademlia store is behind, record is not persisted");
+            },
+            Err(TrySendError::Disconnected(_)) => {
+                warn!("Kademlia store writer is stopped, record is not persisted");
+            },
+        }
+    }
+    /// Persist provider record that is kept in memory
+    fn persist_provider(&self, record: &ProviderRecord) {
+        let stored_key = provider_key(&record.key, &record.provider);
+        // expired record is not needed after restart
+        let stored = StoredProviderRecord::from_provider_record(record).map(|stored| {
+            bincode::serialize(&stored).expect("Provider record is always serializable")
+        });
+        self.write(StoreWrite::Provider(stored_key, stored));
+    }
+}
+
+impl Drop for PersistentStore {
+    fn drop(&mut self) {
+        // writer stops when channel is closed and all writes are applied
+        let (closed, _) = mpsc::sync_channel(0);
+        drop(std::mem::replace(&mut self.writes, closed));
+        if let Some(writer) = self.writer.take() {
+            if writer.join().is_err() {
+                warn!("Kademlia store writer panicked");
+            }
+        }
+    }
+}
+
+/// Load records from [sled] into memory. Expired and broken records are removed
+fn load(
+    memory: &mut MemoryStore,
+    record_tree: &sled::Tree,
+    provider_tree: &sled::Tree,
+) -> Result<(), sled::Error> {
+    for entry in record_tree.iter() {
+        let (key, value) = entry?;
+        let loaded = bincode::deserialize::<StoredRecord>(&value)
+            .ok()
+            .and_then(StoredRecord::into_record)
+            .filter(|record| memory.put(record.clone()).is_ok());
+        if loaded.is_none() {
+            record_tree.remove(key)?;
+        }
+    }
+    for entry in provider_tree.iter() {
+        let (key, value) = entry?;
+        let loaded = bincode::deserialize::<StoredProviderRecord>(&value)
+            .ok()
+            .and_then(StoredProviderRecord::into_provider_record)
+            .filter(|record| {
+                memory
+                    .add_provider(record.clone())
+                    .is_ok()
+            });
+        if loaded.is_none() {
+            provider_tree.remove(key)?;
+        }
+    }
+    Ok(())
+}
+
+/// Apply writes of [PersistentStore] to [sled] until channel is closed. Writes that are sent
+/// while previous batch is applied are applied together
+fn write_loop(record_tree: &sled::Tree, provider_tree: &sled::Tree, writes: Receiver<StoreWrite>) {
+    while let Ok(write) = writes.recv() {
+        let mut records = sled::Batch::default();
+        let mut providers = sled::Batch::default();
+        for write in iter::once(write)
+            .chain(writes.try_iter())
+            .take(MAX_BATCH_WRITES)
+        {
+            let (batch, key, value) = match write {
+                StoreWrite::Record(key, value) => (&mut records, key, value),
+                StoreWrite::Provider(key, value) => (&mut providers, key, value),
+            };
+            match value {
+                Some(value) => batch.insert(key, value),
+                None => batch.remove(key),
+            }
+        }
+        if let Err(error) = record_tree.apply_batch(records) {
+            warn!(
+                "Got error when trying to persist kademlia records: {}",
+                error
+            );
+        }
+        if let Err(error) = provider_tree.apply_batch(providers) {
+            warn!(
+                "Got error when trying to persist provider records: {}",
+                error
+            );
+        }
+    }
+}
+
+impl RecordStore for PersistentStore {
+    type ProvidedIter<'a> = <MemoryStore as RecordStore>::ProvidedIter<'a>;
+    type RecordsIter<'a> = <MemoryStore as RecordStore>::RecordsIter<'a>;
+
+    fn get(&self, k: &Key) -> Option<Cow<'_, Record>> { self.memory.get(k) }
+
+    fn put(&mut self, r: Record) -> libp2p::kad::store::Result<()> {
+        self.memory.put(r.clone())?;
+        // expired record is not needed after restart
+        let stored = StoredRecord::from_record(&r)
+            .map(|stored| bincode::serialize(&stored).expect("Record is always serializable"));
+        self.write(StoreWrite::Record(r.key.to_vec(), stored));
+        Ok(())
+    }
+
+    fn remove(&mut self, k: &Key) {
+        self.memory.remove(k);
+        self.write(StoreWrite::Record(k.to_vec(), None));
+    }
+
+    fn records(&self) -> Self::RecordsIter<'_> { self.memory.records() }
+
+    fn add_provider(&mut self, record: ProviderRecord) -> libp2p::kad::store::Result<()> {
+        // memory store keeps only providers that are closest to key, so provider that is added
+        // can replace other one
+        let previous: Vec<_> = self
+            .memory
+            .providers(&record.key)
+            .into_iter()
+            .map(|previous| previous.provider)
+            .collect();
+        let key = record.key.clone();
+        let provider = record.provider;
+        self.memory.add_provider(record)?;
+        let current = self.memory.providers(&key);
+        for replaced in previous.iter().filter(|previous| {
+            !current
+                .iter()
+                .any(|record| &record.provider == *previous)
+        }) {
+            self.write(StoreWrite::Provider(provider_key(&key, replaced), None));
+        }
+        if let Some(added) = current
+            .iter()
+            .find(|record| record.provider == provider)
+        {
+            self.persist_provider(added);
+        }
+        Ok(())
+    }
+
+    fn providers(&self, key: &Key) -> Vec<ProviderRecord> { self.memory.providers(key) }
+
+    fn provided(&self) -> Self::ProvidedIter<'_> { self.memory.provided() }
+
+    fn remove_provider(&mut self, k: &Key, p: &PeerId) {
+        self.memory.remove_provider(k, p);
+        self.write(StoreWrite::Provider(provider_key(k, p), None));
+    }
+}
+
+impl StoredRecord {
+    /// Returns None if record is already expired
+    fn from_record(record: &Record) -> Option<Self> {
+        Some(StoredRecord {
+            key: record.key.to_vec(),
+            value: record.value.clone(),
+            publisher: record
+                .publisher
+                .map(|peer_id| peer_id.to_bytes()),
+            expires: expires_to_unix(record.expires)?,
+        })
+    }
+    /// Returns None if record is expired or can not be decoded
+    fn into_record(self) -> Option<Record> {
+        Some(Record {
+            key: Key::from(self.key),
+            value: self.value,
+            publisher: match self.publisher {
+                Some(bytes) => Some(PeerId::from_bytes(&bytes).ok()?),
+                None => None,
+            },
+            expires: expires_from_unix(self.expires)?,
+        })
+    }
+}
+
+impl StoredProviderRecord {
+    /// Returns None if record is already expired
+    fn from_provider_record(record: &ProviderRecord) -> Option<Self> {
+        Some(StoredProviderRecord {
+            key: record.key.to_vec(),
+            provider: record.provider.to_bytes(),
+            expires: expires_to_unix(record.expires)?,
+            addresses: record
+                .addresses
+                .iter()
+                .map(|address| address.to_vec())
+                .collect(),
+        })
+    }
+    /// Returns None if record is expired or can not be decoded
+    fn into_provider_record(self) -> Option<ProviderRecord> {
+        Some(ProviderRecord {
+            key: Key::from(self.key),
+            provider: PeerId::from_bytes(&self.provider).ok()?,
+            expires: expires_from_unix(self.expires)?,
+            addresses: self
+                .addresses
+                .into_iter()
+                .filter_map(|address| Multiaddr::try_from(address).ok())
+                .collect(),
+        })
+    }
+}
+
+/// Convert expiration on monotonic clock into milliseconds since [UNIX_EPOCH]. Returns None if
+/// it is already expired
+fn expires_to_unix(expires: Option<Instant>) -> Option<Option<u64>> {
+    let Some(expires) = expires else {
+        return Some(None);
+    };
+    let remaining = expires.checked_duration_since(Instant::now())?;
+    let unix = (SystemTime::now() + remaining)
+        .duration_since(UNIX_EPOCH)
+        .ok()?;
+    Some(Some(unix.as_millis() as u64))
+}
+
+/// Convert milliseconds since [UNIX_EPOCH] into expiration on monotonic clock. Returns None if
+/// it is already expired
+fn expires_from_unix(expires: Option<u64>) -> Option<Option<Instant>> {
+    let Some(expires) = expires else {
+        return Some(None);
+    };
+    let remaining = (UNIX_EPOCH + Duration::from_millis(expires))
+        .duration_since(SystemTime::now())
+        .ok()?;
+    Some(Some(Instant::now() + remaining))
+}
+
+/// Returns prefix of keys of provider records of given key. Length of key is written first, so
+/// prefix of one key is never a prefix of other
+fn provider_key_prefix(key: &Key) -> Vec<u8> {
+    let key = key.to_vec();
+    let mut prefix = (key.len() as u32)
+        .to_be_bytes()
+        .to_vec();
+    prefix.extend_from_slice(&key);
+    prefix
+}
+
+/// Returns key of provider record in [sled]
+fn provider_key(key: &Key, provider: &PeerId) -> Vec<u8> {
+    let mut stored_key = provider_key_prefix(key);
+    stored_key.extend_from_slice(&provider.to_bytes());
+    stored_key
+}
@@ -0,0 +1,881 @@
+use std::{
+    collections::HashSet,
+    net::{TcpListener, UdpSocket},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+        Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use futures::StreamExt;
+use libp2p::{
+    autonat,
+    identity::Keypair,
+    kad::{store::RecordStore, ProviderRecord, Record, RecordKey},
+    multiaddr::Protocol,
+    pnet::PreSharedKey,
+    Multiaddr,
+    PeerId,
+};
+use prometheus_client::{encoding::text::encode, registry::Registry};
+use quanta_artifact::{Artifact, MagnetLink};
+use quanta_feed::{Announcement, FeedStorage};
+use quanta_swap::{Ledger, LedgerStorage, SearchID, Storage, Validator};
+
+use crate::{
+    address_book::AddressBook,
+    info::{ConnectionTransport, LedgerInfo, NatInfo, NatStatus},
+    ledger::SledLedgerStorage,
+    metrics::{Metrics, SearchKind},
+    store::PersistentStore,
+    validator::ArtifactValidator,
+    FromNetworkEvent,
+    ProxyError,
+    QuantaNetwork,
+    QuantaNetworkConfig,
+    QuantaNetworkServiceProxy,
+};
+
+/// Storage that does not have any items, but keeps announcements from feeds
+#[derive(Default)]
+struct MemoryStorage(Mutex<Vec<(String, Announcement)>>);
+
+#[async_trait::async_trait]
+impl Storage for MemoryStorage {
+    async fn exists(self: Arc<Self>, _key: Vec<u8>) -> bool { false }
+
+    async fn get(self: Arc<Self>, _key: Vec<u8>) -> Option<Vec<u8>> { None }
+}
+
+impl FeedStorage for MemoryStorage {
+    fn insert_announcement(&self, topic: &str, announcement: &Announcement) -> bool {
+        self.0
+            .lock()
+            .unwrap()
+            .push((topic.to_string(), announcement.clone()));
+        true
+    }
+}
+
+/// Directory in temp dir that is removed when it is dropped
+struct TempDir(PathBuf);
+
+impl TempDir {
+    fn new() -> Self {
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+        let path = std::env::temp_dir().join(format!(
+            "quanta-network-test-{}-{}",
+            std::process::id(),
+            COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        std::fs::create_dir_all(&path).unwrap();
+        TempDir(path)
+    }
+
+    fn path(&self) -> &Path { &self.0 }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) { let _ = std::fs::remove_dir_all(&self.0); }
+}
+
+/// Returns loopback tcp address with port that is free now
+fn free_tcp_addr() -> Multiaddr {
+    let port = TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port();
+    Multiaddr::empty()
+        .with(Protocol::Ip4([127, 0, 0, 1].into()))
+        .with(Protocol::Tcp(port))
+}
+
+/// Returns loopback quic address with port that is free now
+fn free_quic_addr() -> Multiaddr {
+    let port = UdpSocket::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port();
+    Multiaddr::empty()
+        .with(Protocol::Ip4([127, 0, 0, 1].into()))
+        .with(Protocol::Udp(port))
+        .with(Protocol::QuicV1)
+}
+
+/// Config of local stand-in node: listens only on given addresses, without mdns and with
+/// [autonat::Behaviour] that accepts loopback addresses and starts probes quickly
+fn local_config(listen_addrs: Vec<Multiaddr>) -> QuantaNetworkConfig {
+    QuantaNetworkConfig::default()
+        .with_listen_addrs(listen_addrs)
+        .with_mdns(false)
+        .with_only_global_addrs(false)
+        .with_autonat_config(autonat::Config {
+            boot_delay: Duration::from_millis(500),
+            retry_interval: Duration::from_secs(1),
+            refresh_interval: Duration::from_secs(1),
+            throttle_server_period: Duration::ZERO,
+            only_global_ips: false,
+            ..Default::default()
+        })
+}
+
+/// Spawn [QuantaNetwork] in background and returns id of node with proxy and storage
+fn spawn_network(
+    config: QuantaNetworkConfig,
+) -> (PeerId, QuantaNetworkServiceProxy, Arc<MemoryStorage>) {
+    spawn_network_with_keypair(Keypair::generate_ed25519(), config)
+}
+
+/// Same as [spawn_network] but node has given [Keypair]
+fn spawn_network_with_keypair(
+    keypair: Keypair,
+    config: QuantaNetworkConfig,
+) -> (PeerId, QuantaNetworkServiceProxy, Arc<MemoryStorage>) {
+    let local_peer_id = PeerId::from(keypair.public());
+    let storage = Arc::new(MemoryStorage::default());
+    let (network, proxy) = QuantaNetwork::new(
+        &keypair,
+        local_peer_id,
+        Arc::clone(&storage),
+        config,
+        &mut Registry::default(),
+    )
+    .unwrap();
+    tokio::spawn(network.run_and_handle());
+    (local_peer_id, proxy, storage)
+}
+
+/// Returns magnet link of file with one artifact
+fn magnet_link(file_name: &str, data: &[u8]) -> MagnetLink {
+    let mut magnet_link = MagnetLink::new(file_name.to_string(), data.len());
+    magnet_link.new_update_with_artifact_id(Artifact::new(data.to_vec()).id);
+    magnet_link.update_merkle_root();
+    magnet_link
+}
+
+/// Request [NatInfo] from node until it matches given predicate
+async fn wait_nat_info<F>(proxy: QuantaNetworkServiceProxy, predicate: F) -> NatInfo
+where
+    F: Fn(&NatInfo) -> bool,
+{
+    let wait = async {
+        loop {
+            let nat_info = proxy.get_nat_info().await.unwrap();
+            if predicate(&nat_info) {
+                return nat_info;
+            }
+            tokio::time::sleep(Duration::from_millis(200)).await;
+        }
+    };
+    tokio::time::timeout(Duration::from_secs(20), wait)
+        .await
+        .expect("expected NAT info was not received")
+}
+
+#[test]
+fn test_connection_transport_from_address() {
+    let tcp: Multiaddr = "/ip4/127.0.0.1/tcp/4001"
+        .parse()
+        .unwrap();
+    let quic: Multiaddr = "/ip4/127.0.0.1/udp/4001/quic-v1"
+        .parse()
+        .unwrap();
+    assert_eq!(ConnectionTransport::from(&tcp), ConnectionTransport::Tcp);
+    assert_eq!(ConnectionTransport::from(&quic), ConnectionTransport::Quic);
+    // circuit through relay that is connected over quic is relay connection
+    let relay: Multiaddr = "/ip4/127.0.0.1/udp/4001/quic-v1/p2p/12D3KooWDpJ7As7BWAwRMfu1VU2WCqNjvq387JEYKDBj4kx6nXTN/p2p-circuit"
+        .parse()
+        .unwrap();
+    assert_eq!(
+        ConnectionTransport::from(&relay),
+        ConnectionTransport::Relay
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_reachable_node_is_public() {
+    // tcp connection reuses listen port, so dial-back over tcp would use the same ports as
+    // connection that already exists. Quic connections are not bound to ports
+    let server_addr = free_quic_addr();
+    let (server_id, _server_proxy, _) =
+        spawn_network(local_config(Vec::from([server_addr.clone()])));
+    // server from relays is used for probes
+    let (_, proxy, _) = spawn_network(local_config(Vec::from([free_quic_addr()])).with_relays(
+        Vec::from([server_addr.with(Protocol::P2p(server_id.into()))]),
+    ));
+
+    let nat_info = wait_nat_info(proxy, |nat_info| nat_info.status == NatStatus::Public).await;
+    assert!(nat_info.public_address.is_some());
+    assert!(nat_info.relayed_addrs.is_empty());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_private_node_listens_over_relay() {
+    let relay_addr = free_tcp_addr();
+    let (relay_id, _relay_proxy, _) = spawn_network(
+        local_config(Vec::from([relay_addr.clone()]))
+            .with_external_addrs(Vec::from([relay_addr.clone()]))
+            .with_relay_server(true),
+    );
+    // node does not listen, so probe of its external address fails and node is private
+    let (_, proxy, _) = spawn_network(
+        local_config(Vec::new())
+            .with_external_addrs(Vec::from([free_tcp_addr()]))
+            .with_relays(Vec::from([relay_addr.with(Protocol::P2p(relay_id.into()))])),
+    );
+
+    let nat_info = wait_nat_info(proxy, |nat_info| !nat_info.relayed_addrs.is_empty()).await;
+    assert_eq!(nat_info.status, NatStatus::Private);
+    assert!(nat_info
+        .relayed_addrs
+        .iter()
+        .all(|address| {
+            address
+                .iter()
+                .any(|protocol| protocol == Protocol::P2p(relay_id.into()))
+        }));
+}
+
+#[test]
+fn test_validator_accepts_artifacts_and_magnet_links() {
+    let artifact = Artifact::new(b"beep".to_vec());
+    let beep = magnet_link("beep.txt", b"beep");
+    let merkle_root = beep.merkle_root().unwrap().to_bytes();
+    let magnet_bytes = beep.to_bincode().unwrap();
+    assert!(ArtifactValidator.validate(&artifact.id.to_bytes(), &artifact.data));
+    assert!(ArtifactValidator.validate(&merkle_root, &magnet_bytes));
+    // magnet link of other file is not valid for root
+    let other_magnet_bytes = magnet_link("boop.txt", b"boop")
+        .to_bincode()
+        .unwrap();
+    assert!(!ArtifactValidator.validate(&merkle_root, &other_magnet_bytes));
+    assert!(!ArtifactValidator.validate(&merkle_root, &artifact.data));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_announcement_is_received_by_subscriber() {
+    let publisher_addr = free_tcp_addr();
+    let (publisher_id, proxy, _) = spawn_network(
+        local_config(Vec::from([publisher_addr.clone()])).with_feeds(Vec::from(["channel".into()])),
+    );
+    let (_, _subscriber_proxy, subscriber_storage) = spawn_network(
+        local_config(Vec::from([free_tcp_addr()]))
+            .with_feeds(Vec::from(["channel".into()]))
+            .with_bootstrap_peers(Vec::from([
+                publisher_addr.with(Protocol::P2p(publisher_id.into()))
+            ])),
+    );
+
+    let magnet_link = magnet_link("beep.txt", b"beep");
+    let wait = async {
+        loop {
+            // subscription of peer is known after connection, so announcement is published
+            // until it is received. The same announcement is one message, so it is not duplicated
+            proxy
+                .publish_announcement("channel".into(), magnet_link.clone())
+                .await
+                .unwrap();
+            if let Some(received) = subscriber_storage
+                .0
+                .lock()
+                .unwrap()
+                .first()
+                .cloned()
+            {
+                return received;
+            }
+            tokio::time::sleep(Duration::from_millis(200)).await;
+        }
+    };
+    let (topic, announcement) = tokio::time::timeout(Duration::from_secs(20), wait)
+        .await
+        .expect("announcement was not received");
+    assert_eq!(topic, "channel");
+    assert_eq!(announcement, Announcement::new(&magnet_link, publisher_id));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_cloned_proxy_shares_network() {
+    let (_, proxy, _) = spawn_network(local_config(Vec::from([free_tcp_addr()])));
+    let cloned_proxy = proxy.clone();
+    drop(proxy);
+    assert!(cloned_proxy
+        .get_connections()
+        .await
+        .unwrap()
+        .is_empty());
+    assert_eq!(
+        cloned_proxy
+            .get_nat_info()
+            .await
+            .unwrap()
+            .status,
+        NatStatus::Unknown
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_search_subscriptions_receive_own_results() {
+    let peer_addr = free_tcp_addr();
+    let (peer_id, _peer_proxy, _) = spawn_network(local_config(Vec::from([peer_addr.clone()])));
+    let (_, proxy, _) = spawn_network(
+        local_config(Vec::from([free_tcp_addr()]))
+            .with_bootstrap_peers(Vec::from([peer_addr.with(Protocol::P2p(peer_id.into()))])),
+    );
+    let connected = async {
+        while proxy
+            .get_connections()
+            .await
+            .unwrap()
+            .is_empty()
+        {
+            tokio::time::sleep(Duration::from_millis(200)).await;
+        }
+    };
+    tokio::time::timeout(Duration::from_secs(20), connected)
+        .await
+        .expect("peer was not connected");
+    let mut all_events = proxy.subscribe();
+    // connected peer does not have artifacts, so searches are finished without them
+    let (first_id, first_events) = proxy
+        .search(Artifact::new(b"beep".to_vec()).id)
+        .await
+        .unwrap();
+    let (second_id, second_events) = proxy
+        .search(Artifact::new(b"boop".to_vec()).id)
+        .await
+        .unwrap();
+    let (mut first_events, mut second_events) = (Box::pin(first_events), Box::pin(second_events));
+    let wait = async { futures::join!(first_events.next(), second_events.next()) };
+    let (first, second) = tokio::time::timeout(Duration::from_secs(20), wait)
+        .await
+        .expect("searches were not finished");
+    assert!(matches!(
+        first,
+        Some(Ok(FromNetworkEvent::QuantaSwapNotFound { search_id, .. })) if search_id == first_id
+    ));
+    assert!(matches!(
+        second,
+        Some(Ok(FromNetworkEvent::QuantaSwapNotFound { search_id, .. })) if search_id == second_id
+    ));
+    // streams of searches end after result
+    assert!(first_events.next().await.is_none());
+    assert!(second_events.next().await.is_none());
+    // subscription without filter receives events of both searches
+    let search_ids = HashSet::from([
+        all_events
+            .next()
+            .await
+            .unwrap()
+            .search_id(),
+        all_events
+            .next()
+            .await
+            .unwrap()
+            .search_id(),
+    ]);
+    assert_eq!(search_ids, HashSet::from([first_id, second_id]));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_find_providers_returns_connected_providers() {
+    let provider_addr = free_tcp_addr();
+    let (provider_id, provider_proxy, _) =
+        spawn_network(local_config(Vec::from([provider_addr.clone()])));
+    let (_, proxy, _) = spawn_network(
+        local_config(Vec::from([free_tcp_addr()])).with_bootstrap_peers(Vec::from([
+            provider_addr.with(Protocol::P2p(provider_id.into()))
+        ])),
+    );
+    // nobody provides key yet
+    assert_eq!(
+        proxy
+            .find_providers(b"beep".to_vec())
+            .await
+            .unwrap(),
+        0
+    );
+    provider_proxy
+        .start_providing(Vec::from([b"beep".to_vec()]))
+        .await
+        .unwrap();
+    let found = async {
+        while proxy
+            .find_providers(b"beep".to_vec())
+            .await
+            .unwrap() ==
+            0
+        {
+            tokio::time::sleep(Duration::from_millis(200)).await;
+        }
+    };
+    tokio::time::timeout(Duration::from_secs(20), found)
+        .await
+        .expect("provider was not found");
+}
+
+#[tokio::test]
+async fn test_proxy_call_timeout() {
+    let keypair = Keypair::generate_ed25519();
+    let local_peer_id = PeerId::from(keypair.public());
+    // network is not running, so nobody answers proxy
+    let (_network, proxy) = QuantaNetwork::new(
+        &keypair,
+        local_peer_id,
+        Arc::new(MemoryStorage::default()),
+        local_config(Vec::from([free_tcp_addr()])),
+        &mut Registry::default(),
+    )
+    .unwrap();
+    // proxy waits for network longer than search in quanta-swap
+    assert!(proxy.timeout() > quanta_swap::Config::default().query_timeout());
+    let proxy = proxy.with_timeout(Duration::from_millis(100));
+    assert!(matches!(
+        proxy.get_connections().await,
+        Err(ProxyError::RecvTimeout)
+    ));
+    assert!(matches!(
+        proxy
+            .get_nat_info_with_timeout(Duration::from_millis(100))
+            .await,
+        Err(ProxyError::RecvTimeout)
+    ));
+    // timeout bounds sending into full channel of network too
+    let (proxy_tx, _) = tokio::sync::broadcast::channel(1);
+    let (network_tx, _network_rx) = tokio::sync::mpsc::channel(1);
+    let proxy = QuantaNetworkServiceProxy::new(proxy_tx, network_tx)
+        .with_timeout(Duration::from_millis(100));
+    proxy
+        .start_providing(Vec::from([b"beep".to_vec()]))
+        .await
+        .unwrap();
+    assert!(matches!(
+        proxy
+            .get_connections_with_timeout(Duration::from_millis(100))
+            .await,
+        Err(ProxyError::RecvTimeout)
+    ));
+    assert!(matches!(
+        proxy
+            .start_providing(Vec::from([b"beep".to_vec()]))
+            .await,
+        Err(ProxyError::RecvTimeout)
+    ));
+}
+
+#[tokio::test]
+async fn test_search_subscription_lagged() {
+    let (proxy_tx, _) = tokio::sync::broadcast::channel(1);
+    let (network_tx, _network_rx) = tokio::sync::mpsc::channel(1);
+    let proxy = QuantaNetworkServiceProxy::new(proxy_tx.clone(), network_tx);
+    let searching = Artifact::new(b"beep".to_vec()).id;
+    let search_id = SearchID::random();
+    let mut events = Box::pin(proxy.subscribe_search(search_id));
+    // result of search is overwritten before subscription reads it
+    for search_id in [search_id, SearchID::random()] {
+        proxy_tx
+            .send(FromNetworkEvent::QuantaSwapNotFound {
+                search_id,
+                searching,
+            })
+            .unwrap();
+    }
+    assert!(matches!(
+        events.next().await,
+        Some(Err(ProxyError::Lagged(1)))
+    ));
+    assert!(events.next().await.is_none());
+}
+
+#[test]
+fn test_quanta_swap_metrics() {
+    let mut registry = Registry::default();
+    let metrics = Metrics::new(&mut registry);
+    let peer = PeerId::random();
+    metrics.record_search_started(SearchKind::WantList);
+    metrics.record_quanta_swap(&quanta_swap::Event::ItemsServed {
+        peer,
+        items: 2,
+        bytes: 1024,
+    });
+    let mut encoded = String::new();
+    encode(&mut encoded, &registry).unwrap();
+    assert!(encoded.contains("quanta_swap_searches_started_total{kind=\"WantList\"} 1"));
+    assert!(encoded.contains(&format!(
+        "quanta_swap_items_served_total{{peer=\"{peer}\"}} 2"
+    )));
+    assert!(encoded.contains(&format!(
+        "quanta_swap_served_bytes_total{{peer=\"{peer}\"}} 1024"
+    )));
+    assert!(encoded.contains("libp2p_swarm_connections_established"));
+}
+
+#[test]
+fn test_persistent_store_reopen() {
+    let dir = TempDir::new();
+    let local_peer_id = PeerId::random();
+    let provider = PeerId::random();
+    let peer_addr = free_tcp_addr();
+    let other_addr = free_quic_addr();
+    let expires = Some(Instant::now() + Duration::from_secs(3600));
+    {
+        let db = sled::open(dir.path()).unwrap();
+        let mut store = PersistentStore::open(local_peer_id, &db).unwrap();
+        let mut record = Record::new(RecordKey::new(b"record"), b"value".to_vec());
+        record.expires = expires;
+        store.put(record).unwrap();
+        let mut expired = Record::new(RecordKey::new(b"expired"), b"value".to_vec());
+        expired.expires = Some(Instant::now());
+        store.put(expired).unwrap();
+        store
+            .add_provider(ProviderRecord {
+                key: RecordKey::new(b"artifact"),
+                provider,
+                expires,
+                addresses: Vec::from([peer_addr.clone()]),
+            })
+            .unwrap();
+        store
+            .add_provider(ProviderRecord::new(
+                RecordKey::new(b"removed"),
+                provider,
+                Vec::new(),
+            ))
+            .unwrap();
+        store.remove_provider(&RecordKey::new(b"removed"), &provider);
+        let address_book = AddressBook::open(&db, 1024, false).unwrap();
+        address_book
+            .insert(&provider, &peer_addr)
+            .unwrap();
+        // known address becomes the most recent instead of being duplicated
+        address_book
+            .insert(&provider, &other_addr)
+            .unwrap();
+        address_book
+            .insert(&provider, &peer_addr)
+            .unwrap();
+    }
+    let db = sled::open(dir.path()).unwrap();
+    let store = PersistentStore::open(local_peer_id, &db).unwrap();
+    let record = store
+        .get(&RecordKey::new(b"record"))
+        .unwrap();
+    assert_eq!(record.value, b"value");
+    assert!(record.expires.unwrap() > Instant::now());
+    assert!(store
+        .get(&RecordKey::new(b"expired"))
+        .is_none());
+    let providers = store.providers(&RecordKey::new(b"artifact"));
+    assert_eq!(providers.len(), 1);
+    assert_eq!(providers[0].provider, provider);
+    assert_eq!(providers[0].addresses, Vec::from([peer_addr.clone()]));
+    assert!(store
+        .providers(&RecordKey::new(b"removed"))
+        .is_empty());
+    let address_book = AddressBook::open(&db, 1024, false).unwrap();
+    assert_eq!(address_book.peers().unwrap().len(), 1);
+    assert_eq!(
+        address_book
+            .addresses(&provider)
+            .unwrap(),
+        Vec::from([other_addr, peer_addr])
+    );
+}
+
+#[test]
+fn test_persistent_store_keeps_closest_providers() {
+    let dir = TempDir::new();
+    let key = RecordKey::new(b"artifact");
+    let providers: Vec<_> = (0..30)
+        .map(|_| PeerId::random())
+        .collect();
+    let kept = {
+        let db = sled::open(dir.path()).unwrap();
+        let mut store = PersistentStore::open(PeerId::random(), &db).unwrap();
+        for provider in &providers {
+            store
+                .add_provider(ProviderRecord::new(key.clone(), *provider, Vec::new()))
+                .unwrap();
+        }
+        store.providers(&key)
+    };
+    // providers that were replaced by closer ones are removed from disk too
+    let db = sled::open(dir.path()).unwrap();
+    assert_eq!(
+        db.open_tree("kad_providers")
+            .unwrap()
+            .len(),
+        kept.len()
+    );
+    let store = PersistentStore::open(PeerId::random(), &db).unwrap();
+    let reopened: HashSet<_> = store
+        .providers(&key)
+        .into_iter()
+        .map(|record| record.provider)
+        .collect();
+    assert!(kept.len() < providers.len());
+    assert_eq!(
+        reopened,
+        kept.into_iter()
+            .map(|record| record.provider)
+            .collect()
+    );
+}
+
+#[test]
+fn test_address_book_limits() {
+    let dir = TempDir::new();
+    let db = sled::open(dir.path()).unwrap();
+    let address_book = AddressBook::open(&db, 2, true).unwrap();
+    let public: Multiaddr = "/ip4/1.2.3.4/tcp/4001".parse().unwrap();
+    let [connected, old, new] = [PeerId::random(), PeerId::random(), PeerId::random()];
+    for address in [
+        "/ip4/127.0.0.1/tcp/4001",
+        "/ip4/192.168.1.2/tcp/4001",
+        "/ip6/fe80::1/udp/4001/quic-v1",
+        "/dns4/localhost/tcp/4001",
+    ] {
+        assert!(!address_book
+            .insert(&connected, &address.parse().unwrap())
+            .unwrap());
+    }
+    assert!(address_book.peers().unwrap().is_empty());
+    for peer_id in [&connected, &old] {
+        assert!(address_book
+            .insert(peer_id, &public)
+            .unwrap());
+    }
+    address_book
+        .connected(&connected)
+        .unwrap();
+    // peer that was never connected is forgotten before peer that was connected
+    assert!(address_book
+        .insert(&new, &public)
+        .unwrap());
+    let peers: Vec<_> = address_book
+        .peers()
+        .unwrap()
+        .into_iter()
+        .map(|(peer_id, _)| peer_id)
+        .collect();
+    assert_eq!(peers, [connected, new]);
+    // successful dial resets failures, so only dials in a row forget peer
+    for _ in 0..4 {
+        assert!(!address_book.failed(&connected).unwrap());
+    }
+    address_book
+        .connected(&connected)
+        .unwrap();
+    for _ in 0..4 {
+        assert!(!address_book.failed(&connected).unwrap());
+    }
+    assert!(address_book.failed(&connected).unwrap());
+    assert!(address_book
+        .addresses(&connected)
+        .unwrap()
+        .is_empty());
+    // address book that was stored before dial stats is still read
+    db.open_tree("peers")
+        .unwrap()
+        .insert(
+            old.to_bytes(),
+            bincode::serialize(&Vec::from([public.to_vec()])).unwrap(),
+        )
+        .unwrap();
+    assert_eq!(address_book.addresses(&old).unwrap(), [public]);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_known_peer_is_dialed_on_start() {
+    let peer_addr = free_tcp_addr();
+    let (peer_id, _peer_proxy, _) = spawn_network(local_config(Vec::from([peer_addr.clone()])));
+    let dir = TempDir::new();
+    {
+        let db = sled::open(dir.path()).unwrap();
+        AddressBook::open(&db, 1024, false)
+            .unwrap()
+            .insert(&peer_id, &peer_addr)
+            .unwrap();
+    }
+    // peer is not in bootstrap peers, it is known only from address book
+    let (_, proxy, _) = spawn_network(
+        local_config(Vec::from([free_tcp_addr()])).with_store_path(Some(dir.path().to_path_buf())),
+    );
+    let connected = async {
+        while !proxy
+            .get_connections()
+            .await
+            .unwrap()
+            .contains_key(&peer_id)
+        {
+            tokio::time::sleep(Duration::from_millis(200)).await;
+        }
+    };
+    tokio::time::timeout(Duration::from_secs(20), connected)
+        .await
+        .expect("known peer was not dialed");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_dial_and_disconnect_peer() {
+    let peer_addr = free_tcp_addr();
+    let (peer_id, _peer_proxy, _) = spawn_network(local_config(Vec::from([peer_addr.clone()])));
+    let (_, proxy, _) = spawn_network(local_config(Vec::from([free_tcp_addr()])));
+    assert!(matches!(
+        proxy.dial(peer_addr.clone()).await,
+        Err(ProxyError::DialPeerId(_))
+    ));
+    // nobody listens on this address
+    assert!(matches!(
+        proxy
+            .dial(free_tcp_addr().with(Protocol::P2p(PeerId::random().into())))
+            .await,
+        Err(ProxyError::Dial(_))
+    ));
+    assert_eq!(
+        proxy
+            .dial(peer_addr.with(Protocol::P2p(peer_id.into())))
+            .await
+            .unwrap(),
+        peer_id
+    );
+    assert!(proxy.disconnect(peer_id).await.unwrap());
+    let disconnected = async {
+        while proxy
+            .get_connections()
+            .await
+            .unwrap()
+            .contains_key(&peer_id)
+        {
+            tokio::time::sleep(Duration::from_millis(200)).await;
+        }
+    };
+    tokio::time::timeout(Duration::from_secs(20), disconnected)
+        .await
+        .expect("peer was not disconnected");
+    assert!(!proxy.disconnect(peer_id).await.unwrap());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_private_network_requires_same_key() {
+    let psk = PreSharedKey::new([7; 32]);
+    let peer_addr = free_tcp_addr();
+    // quic address is skipped, because quic can not be used in private network
+    let (peer_id, peer_proxy, _) = spawn_network(
+        local_config(Vec::from([peer_addr.clone(), free_quic_addr()])).with_psk(Some(psk)),
+    );
+    let peer_addr = peer_addr.with(Protocol::P2p(peer_id.into()));
+    // handshake of peer without key or with other key can hang, so dial fails or times out
+    let (stranger_id, stranger_proxy, _) =
+        spawn_network(local_config(Vec::from([free_tcp_addr()])));
+    assert!(stranger_proxy
+        .dial_with_timeout(peer_addr.clone(), Duration::from_secs(3))
+        .await
+        .is_err());
+    let (other_key_id, other_key_proxy, _) = spawn_network(
+        local_config(Vec::from([free_tcp_addr()])).with_psk(Some(PreSharedKey::new([8; 32]))),
+    );
+    assert!(other_key_proxy
+        .dial_with_timeout(peer_addr.clone(), Duration::from_secs(3))
+        .await
+        .is_err());
+    let (member_id, proxy, _) =
+        spawn_network(local_config(Vec::from([free_tcp_addr()])).with_psk(Some(psk)));
+    assert_eq!(proxy.dial(peer_addr).await.unwrap(), peer_id);
+    let connections = peer_proxy
+        .get_connections()
+        .await
+        .unwrap();
+    assert!(connections.contains_key(&member_id));
+    assert!(!connections.contains_key(&stranger_id));
+    assert!(!connections.contains_key(&other_key_id));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_allowed_and_denied_peers() {
+    let allowed_keypair = Keypair::generate_ed25519();
+    let denied_keypair = Keypair::generate_ed25519();
+    let stranger_keypair = Keypair::generate_ed25519();
+    let (allowed_addr, denied_addr, stranger_addr) =
+        (free_tcp_addr(), free_tcp_addr(), free_tcp_addr());
+    let (allowed_id, _allowed_proxy, _) = spawn_network_with_keypair(
+        allowed_keypair,
+        local_config(Vec::from([allowed_addr.clone()])),
+    );
+    let (denied_id, _denied_proxy, _) = spawn_network_with_keypair(
+        denied_keypair,
+        local_config(Vec::from([denied_addr.clone()])),
+    );
+    let (stranger_id, stranger_proxy, _) = spawn_network_with_keypair(
+        stranger_keypair,
+        local_config(Vec::from([stranger_addr.clone()])),
+    );
+    let node_addr = free_tcp_addr();
+    let (node_id, proxy, _) = spawn_network(
+        local_config(Vec::from([node_addr.clone()]))
+            .with_allowed_peers(Vec::from([allowed_id, denied_id]))
+            .with_denied_peers(Vec::from([denied_id])),
+    );
+    assert_eq!(
+        proxy
+            .dial(allowed_addr.with(Protocol::P2p(allowed_id.into())))
+            .await
+            .unwrap(),
+        allowed_id
+    );
+    // denylist wins over allowlist
+    assert!(matches!(
+        proxy
+            .dial(denied_addr.with(Protocol::P2p(denied_id.into())))
+            .await,
+        Err(ProxyError::Dial(_))
+    ));
+    assert!(matches!(
+        proxy
+            .dial(stranger_addr.with(Protocol::P2p(stranger_id.into())))
+            .await,
+        Err(ProxyError::Dial(_))
+    ));
+    // inbound connection of peer that is not allowed is closed when it is established
+    let _ = stranger_proxy
+        .dial(node_addr.with(Protocol::P2p(node_id.into())))
+        .await;
+    let connections = proxy.get_connections().await.unwrap();
+    assert!(connections.contains_key(&allowed_id));
+    assert!(!connections.contains_key(&denied_id));
+    assert!(!connections.contains_key(&stranger_id));
+}
+
+#[tokio::test]
+async fn test_ledger_storage_reopen() {
+    let dir = TempDir::new();
+    let peer = PeerId::random();
+    let ledger = Ledger {
+        bytes_sent: 300,
+        bytes_received: 99,
+    };
+    {
+        let db = sled::open(dir.path()).unwrap();
+        let ledger_storage = Arc::new(SledLedgerStorage::open(&db).unwrap());
+        assert_eq!(
+            Arc::clone(&ledger_storage)
+                .load(peer)
+                .await,
+            None
+        );
+        Arc::clone(&ledger_storage)
+            .save(peer, ledger)
+            .await;
+        db.flush().unwrap();
+    }
+    let db = sled::open(dir.path()).unwrap();
+    let ledger_storage = Arc::new(SledLedgerStorage::open(&db).unwrap());
+    assert_eq!(ledger_storage.load(peer).await, Some(ledger));
+    let info = LedgerInfo::from(&ledger);
+    assert_eq!(info.debt_ratio, 3.0);
+}
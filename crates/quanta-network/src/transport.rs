@@ -0,0 +1,71 @@
+use libp2p::{
+    core::{
+        muxing::StreamMuxerBox,
+        transport::{Boxed, OrTransport},
+        upgrade,
+    },
+    futures::{future, AsyncRead, AsyncWrite},
+    identity::Keypair,
+    noise,
+    pnet::{PnetConfig, PreSharedKey},
+    relay,
+    tcp,
+    yamux,
+    PeerId,
+    Transport,
+};
+use libp2p_quic as quic;
+
+/// Build transport of quanta-network. Address that we are dial or listen on chooses between:
+/// quic, tcp and relay circuit (`/p2p-circuit`) that goes through [relay::client::Transport].
+/// Tcp and relay connections are authenticated with noise and multiplexed with yamux, quic has
+/// its own encryption and multiplexing, so it does not need upgrades.
+///
+/// If [PreSharedKey] is given tcp and relay connections are encrypted with it before noise, so
+/// only peers that know the key can connect. Handshake of quic can not be wrapped with the key,
+/// so quic is not used in private network
+pub(crate) fn build_transport(
+    keypair: &Keypair,
+    relay_transport: relay::client::Transport,
+    psk: Option<PreSharedKey>,
+) -> Boxed<(PeerId, StreamMuxerBox)> {
+    let tcp_transport = tcp::async_io::Transport::new(tcp::Config::default().port_reuse(true));
+    let relay_or_tcp_transport = OrTransport::new(relay_transport, tcp_transport);
+    if let Some(psk) = psk {
+        return upgrade_transport(
+            keypair,
+            relay_or_tcp_transport
+                .and_then(move |socket, _| PnetConfig::new(psk).handshake(socket)),
+        );
+    }
+    let quic_transport = quic::async_std::Transport::new(quic::Config::new(keypair));
+    OrTransport::new(
+        quic_transport,
+        upgrade_transport(keypair, relay_or_tcp_transport),
+    )
+    .map(|output, _| match output {
+        future::Either::Left((peer_id, muxer)) => (peer_id, StreamMuxerBox::new(muxer)),
+        future::Either::Right(output) => output,
+    })
+    .boxed()
+}
+
+/// Authenticate connections of transport with noise and multiplex them with yamux
+fn upgrade_transport<T>(keypair: &Keypair, transport: T) -> Boxed<(PeerId, StreamMuxerBox)>
+where
+    T: Transport + Send + Unpin + 'static,
+    T::Output: AsyncRead + AsyncWrite + Send + Unpin + 'static,
+    T::Error: Send + Sync + 'static,
+    T::Dial: Send + 'static,
+    T::ListenerUpgrade: Send + 'static,
+{
+    transport
+        .upgrade(upgrade::Version::V1)
+        .authenticate(
+            noise::Config::new(keypair)
+                .expect("Got unexpected error when trying to create libp2p::noise::Config"),
+        )
+        .multiplex(yamux::Config::default())
+        .map(|(peer_id, muxer), _| (peer_id, StreamMuxerBox::new(muxer)))
+        .boxed()
+}
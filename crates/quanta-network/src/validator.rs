@@ -0,0 +1,27 @@
+use quanta_artifact::{ArtifactId, MagnetLink};
+use quanta_crypto::HashValue;
+
+/// [quanta_swap::Validator] for artifacts and magnet links. Key of artifact is its [ArtifactId],
+/// so artifact is valid only if its hash equals the key that we are searched. Magnet links are
+/// searched by their merkle root, so magnet link is valid only if it has the same merkle root
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ArtifactValidator;
+
+impl quanta_swap::Validator for ArtifactValidator {
+    fn validate(&self, key: &[u8], item: &[u8]) -> bool {
+        if ArtifactId::new(item)
+            .to_bytes()
+            .as_slice() ==
+            key
+        {
+            return true;
+        }
+        match (
+            HashValue::try_from(key),
+            MagnetLink::from_bincode(item.to_vec()),
+        ) {
+            (Ok(merkle_root), Ok(magnet_link)) => magnet_link.verify(&merkle_root),
+            _ => false,
+        }
+    }
+}
@@ -1,5 +1,4 @@
 use std::{
-    collections::HashMap,
     error::Error,
     str::{from_utf8, FromStr},
     sync::Arc,
@@ -19,32 +18,12 @@ use libp2p::{
     Swarm,
     Transport,
 };
-use quanta_swap::Storage;
-use tokio::sync::Mutex;
-
-/// MemoryStorage
-struct MemoryStorage(Arc<Mutex<HashMap<Vec<u8>, Vec<u8>>>>);
-
-impl Storage for MemoryStorage {
-    fn exists(&self, key: Vec<u8>) -> bool {
-        let guard = futures::executor::block_on(self.0.lock());
-        guard.contains_key(&key)
-    }
-
-    fn get(&self, key: Vec<u8>) -> Option<Vec<u8>> {
-        let guard = futures::executor::block_on(self.0.lock());
-        guard.get(&key).cloned()
-    }
-}
-
-impl MemoryStorage {
-    /// Create new storage
-    pub fn new(hm: Arc<Mutex<HashMap<Vec<u8>, Vec<u8>>>>) -> Self { Self(hm) }
-}
+use quanta_artifact::{Artifact, ArtifactId};
+use quanta_database::{Database, DatabaseConfig, MemoryBackend};
 
 #[derive(NetworkBehaviour)]
 struct Behaviour {
-    quanta_swap: quanta_swap::Behaviour<MemoryStorage>,
+    quanta_swap: quanta_swap::Behaviour<Database>,
 }
 
 #[tokio::main]
@@ -55,11 +34,14 @@ async fn main() -> Result<(), Box<dyn Error>> {
     let id_keys = identity::Keypair::generate_ed25519();
     let local_peer_id = PeerId::from(id_keys.public());
     println!("Local peer id: {local_peer_id}");
-    let mut hm = Arc::new(Mutex::new(HashMap::new()));
-    let storage = MemoryStorage::new(Arc::clone(&hm));
+    // Artifacts are kept in memory, so nothing is left when example is stopped
+    let database = Arc::new(Database::with_backend(
+        MemoryBackend::default(),
+        DatabaseConfig::default(),
+    )?);
     // Create behaviour
     let behaviour = Behaviour {
-        quanta_swap: quanta_swap::Behaviour::new(Arc::new(storage)),
+        quanta_swap: quanta_swap::Behaviour::new(Arc::clone(&database)),
     };
     // transport
     let tcp_transport = tcp::async_io::Transport::new(tcp::Config::default().nodelay(true))
@@ -82,7 +64,7 @@ async fn main() -> Result<(), Box<dyn Error>> {
         tokio::select! {
             line = stdin.select_next_some() => handle_input_line(
                 &mut swarm,
-                &mut hm,
+                &database,
                 line.expect("Stdin not to close")
             ),
             event = swarm.select_next_some() => {
@@ -94,7 +76,9 @@ async fn main() -> Result<(), Box<dyn Error>> {
                         ..
                     })) => {
                         let result_str = from_utf8(item.as_slice()).unwrap();
-                        let searching_str = from_utf8(searching.as_slice()).unwrap();
+                        let searching_str = ArtifactId::from_bytes(searching.as_slice())
+                            .map(|artifact_id| artifact_id.to_string())
+                            .unwrap_or_default();
                         println!(
                            "Search completed. ID={}, SEARCHING={}, RESULT={}",
                             search_id, searching_str, result_str
@@ -110,42 +94,35 @@ async fn main() -> Result<(), Box<dyn Error>> {
     }
 }
 
-fn handle_input_line(
-    swarm: &mut Swarm<Behaviour>,
-    hm: &mut Arc<Mutex<HashMap<Vec<u8>, Vec<u8>>>>,
-    line: String,
-) {
+fn handle_input_line(swarm: &mut Swarm<Behaviour>, database: &Database, line: String) {
     let mut args = line.split(' ');
 
     match args.next() {
         Some("INSERT") => {
-            let key = {
+            let artifact = {
                 match args.next() {
-                    Some(key) => key.as_bytes().to_vec(),
-                    None => {
-                        eprintln!("Expected key");
-                        return;
-                    },
-                }
-            };
-            let value = {
-                match args.next() {
-                    Some(value) => value.as_bytes().to_vec(),
+                    Some(value) => Artifact::new(value.as_bytes().to_vec()),
                     None => {
                         eprintln!("Expected value");
                         return;
                     },
                 }
             };
-            let mut guard = futures::executor::block_on(hm.lock());
-            guard.insert(key, value);
+            let artifact_id = artifact.id;
+            match database.insert_artifact(artifact) {
+                Ok(()) => println!("ARTIFACT_ID={}", artifact_id),
+                Err(error) => eprintln!("Failed to insert artifact: {}", error),
+            }
         },
         Some("SEARCH") => {
-            let key = {
-                match args.next() {
-                    Some(key) => key.as_bytes().to_vec(),
-                    None => {
-                        eprintln!("Expected key");
+            let artifact_id = {
+                match args
+                    .next()
+                    .map(ArtifactId::from_bs58_string)
+                {
+                    Some(Ok(artifact_id)) => artifact_id,
+                    _ => {
+                        eprintln!("Expected artifact id");
                         return;
                     },
                 }
@@ -153,7 +130,7 @@ fn handle_input_line(
             let search_id = swarm
                 .behaviour_mut()
                 .quanta_swap
-                .search_item_with(key);
+                .search_item_with(artifact_id.to_bytes());
             println!("SEARCH_ID={}", search_id);
         },
         Some(_) => {},
@@ -1,15 +1,21 @@
 use std::{
     collections::{HashMap, VecDeque},
+    future::Future,
+    pin::Pin,
     sync::Arc,
     task::{Context, Poll},
+    time::{Duration, Instant},
 };
 
 use fnv::FnvHashSet;
+use futures::{future, stream::FuturesUnordered, StreamExt};
 use libp2p::{
     core::Endpoint,
-    request_response::{self, ProtocolSupport, ResponseChannel},
+    request_response::{self, ProtocolSupport, RequestId, ResponseChannel},
     swarm::{
-        behaviour::ConnectionEstablished,
+        behaviour::{ConnectionEstablished, DialFailure},
+        dial_opts::{DialOpts, PeerCondition},
+        CloseConnection,
         ConnectionClosed as RequestResponseConnectionClosed,
         ConnectionDenied,
         ConnectionId,
@@ -27,23 +33,75 @@ use libp2p::{
 use log::debug;
 
 use crate::{
-    codec::QuantaSwapCodec,
+    codec::{NegotiatedRequest, NegotiatedResponse, QuantaSwapCodec},
+    config::Config,
+    ledger::{Ledger, LedgerStorage, Ledgers},
     protocol::QuantaSwapProtocol,
+    query::Query,
+    queue::LookupQueue,
     request::QuantaSwapRequest,
     response::QuantaSwapRespone,
+    score::{BanReason, PeerBannedError, PeerScores, PeerStats, RequestVerdict},
     searchid::SearchID,
+    validator::{AcceptAllValidator, Validator},
+    want::{self, WantList, MAX_BLOCKS_RESPONSE_SIZE, MAX_WANT_LIST_KEYS},
 };
 
+/// How often we are check queries deadlines
+const QUERY_TIMEOUT_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+/// How often changed ledgers are saved into [`LedgerStorage`]. Ledger of peer is also saved
+/// when connection with it is closed
+const LEDGER_SAVE_INTERVAL: Duration = Duration::from_secs(10);
+/// Time after which request that we are sent fails if peer does not answer. Requests of other
+/// peers that wait in queue longer are dropped, because peer does not wait for them anymore
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
 /// Base storage of QuantaSwap protocol. Any database can be used as storage (even in memory),
 /// but I recommend using something like Rocksdb
-pub trait Storage {
+///
+/// Lookups are not awaited in place: [`Behaviour`] keeps them and sends responses to peers
+/// when they are resolved, so slow lookup does not stall the swarm. Lookups own the storage,
+/// so storage that reads from disk can move reads onto blocking thread pool
+#[async_trait::async_trait]
+pub trait Storage: Send + Sync {
     /// Check if value with key exists
-    fn exists(&self, key: Vec<u8>) -> bool;
+    async fn exists(self: Arc<Self>, key: Vec<u8>) -> bool;
     /// Get value by key
-    fn get(&self, key: Vec<u8>) -> Option<Vec<u8>>;
+    async fn get(self: Arc<Self>, key: Vec<u8>) -> Option<Vec<u8>>;
+    /// Check which of keys exist. By default keys are checked concurrently with
+    /// [`Storage::exists`], storage that reads from disk can check all of them with one read
+    async fn exists_many(self: Arc<Self>, keys: Vec<Vec<u8>>) -> Vec<bool> {
+        future::join_all(
+            keys.into_iter()
+                .map(|key| Arc::clone(&self).exists(key)),
+        )
+        .await
+    }
+    /// Get values of keys in order of keys while their total size is not larger than `max_size`.
+    /// First found value is returned whatever its size, missing keys are skipped. By default
+    /// values are got one by one with [`Storage::get`]
+    async fn get_many(
+        self: Arc<Self>,
+        keys: Vec<Vec<u8>>,
+        max_size: usize,
+    ) -> Vec<(Vec<u8>, Vec<u8>)> {
+        let mut items = Vec::new();
+        let mut size = 0;
+        for key in keys {
+            let Some(item) = Arc::clone(&self).get(key.clone()).await else {
+                continue;
+            };
+            if !items.is_empty() && size + item.len() > max_size {
+                break;
+            }
+            size += item.len();
+            items.push((key, item));
+        }
+        items
+    }
 }
 
-/// Events that we are send out of this behaviour Here we got only 1 event - QueryCompleted
+/// Events that we are send out of this behaviour
 #[derive(Debug)]
 pub enum Event {
     QueryCompleted {
@@ -57,6 +115,86 @@ pub enum Event {
         /// Result of Query
         item: Vec<u8>,
     },
+    /// Peer sent item that was rejected by [`Validator`]. Query stays active so other peers can
+    /// still answer, and connection with peer that sent invalid item is closed
+    QueryValidationFailed {
+        /// Who send invalid item
+        peer: PeerId,
+        /// Unqiue ID
+        search_id: SearchID,
+        /// Key in bytes of value
+        /// that we are searched
+        searching: Vec<u8>,
+    },
+    /// Nobody sent item before query deadline. Query is removed
+    QueryTimedOut {
+        /// Unqiue ID
+        search_id: SearchID,
+        /// Key in bytes of value
+        /// that we are searched
+        searching: Vec<u8>,
+    },
+    /// Every connected peer answered that it does not have item. Query is removed
+    QueryNotFound {
+        /// Unqiue ID
+        search_id: SearchID,
+        /// Key in bytes of value
+        /// that we are searched
+        searching: Vec<u8>,
+    },
+    /// Peer sent batch of valid items for want list created with
+    /// [`Behaviour::search_items_with`]
+    WantListProgress {
+        /// Who send items
+        peer: PeerId,
+        /// Unqiue ID
+        search_id: SearchID,
+        /// Keys with items
+        items: Vec<(Vec<u8>, Vec<u8>)>,
+    },
+    /// All items of want list are received. Want list is removed
+    WantListCompleted {
+        /// Unqiue ID
+        search_id: SearchID,
+    },
+    /// Every connected peer answered and nobody have remaining keys. Want list is removed
+    WantListNotFound {
+        /// Unqiue ID
+        search_id: SearchID,
+        /// Keys which items were not received
+        missing: Vec<Vec<u8>>,
+    },
+    /// Not all items were received before want list deadline. Want list is removed
+    WantListTimedOut {
+        /// Unqiue ID
+        search_id: SearchID,
+        /// Keys which items were not received
+        missing: Vec<Vec<u8>>,
+    },
+    /// We are sent items from [`Storage`] to peer
+    ItemsServed {
+        /// Who requested items
+        peer: PeerId,
+        /// Count of items that were sent
+        items: usize,
+        /// Size in bytes of items that were sent
+        bytes: usize,
+    },
+    /// Peer sent too many invalid items or requests over rate limits. Connections with peer are
+    /// closed and new connections are denied until ban expires
+    PeerBanned {
+        /// Who is banned
+        peer: PeerId,
+        /// Why peer is banned
+        reason: BanReason,
+        /// Time for which peer is banned
+        duration: Duration,
+    },
+    /// Ban of peer is expired, so it can connect again
+    PeerUnbanned {
+        /// Who was banned
+        peer: PeerId,
+    },
 }
 
 /// [`request_response::Behaviour`] with [`QuantaSwapCodec`]
@@ -65,13 +203,41 @@ pub enum Event {
 type RequestResponse = request_response::Behaviour<QuantaSwapCodec>;
 /// Create this type for better code readability
 type RequestResponseMessage =
-    request_response::Message<QuantaSwapRequest, QuantaSwapRespone, QuantaSwapRespone>;
+    request_response::Message<NegotiatedRequest, NegotiatedResponse, NegotiatedResponse>;
 /// Create this type for better code readability
 type ConnectionClosed<'l> =
     RequestResponseConnectionClosed<'l, <RequestResponse as NetworkBehaviour>::ConnectionHandler>;
 /// Create this type for better code readability
 type OutEventsQueue<S> =
     VecDeque<ToSwarm<<Behaviour<S> as NetworkBehaviour>::OutEvent, THandlerInEvent<Behaviour<S>>>>;
+/// Create this type for better code readability
+type Delay = Pin<Box<dyn Future<Output = ()> + Send>>;
+/// Response to request of peer that is sent when lookups in [`Storage`] are resolved
+struct PendingResponse {
+    /// Channel where response is sent
+    channel: ResponseChannel<NegotiatedResponse>,
+    /// Response to request. If None peer does not get response
+    response: Option<QuantaSwapRespone>,
+    /// Event that is sent out of [`Behaviour`] with response
+    event: Option<Event>,
+}
+/// Create this type for better code readability
+type PendingResponses = FuturesUnordered<Pin<Box<dyn Future<Output = PendingResponse> + Send>>>;
+/// Request of peer that waits for free slot of lookups in [`Storage`]
+struct QueuedRequest {
+    /// Request that should be answered
+    request: QuantaSwapRequest,
+    /// Channel where response is sent
+    channel: ResponseChannel<NegotiatedResponse>,
+}
+/// Finished load or save of [`Ledger`] of peer
+enum LedgerTask {
+    /// Ledger that was loaded, None if it was never saved
+    Loaded(PeerId, Option<Ledger>),
+    Saved(PeerId),
+}
+/// Loads and saves of [`Ledger`]s
+type LedgerTasks = FuturesUnordered<Pin<Box<dyn Future<Output = LedgerTask> + Send>>>;
 /// [`NetworkBehaviour`] for Quanta-swap
 pub struct Behaviour<S>
 where
@@ -81,43 +247,120 @@ where
     request_response: RequestResponse,
     /// Storage is needed to check or receive data that will be sent later to other network members
     storage: Arc<S>,
+    /// Validator checks items that we are receive from other network members
+    validator: Box<dyn Validator + Send + 'static>,
     /// All active connections
-    connections: FnvHashSet<PeerId>,
+    pub(crate) connections: FnvHashSet<PeerId>,
     /// All active queries.
     ///
     /// [`SearchID`] - Unique ID of query.
-    /// Vec<u8> - Key in [`Storage`]
-    /// that peer looking for
-    queries: HashMap<SearchID, Vec<u8>>,
+    /// [`Query`] - Key in [`Storage`] that peer looking for and query state
+    queries: HashMap<SearchID, Query>,
+    /// All active want lists. Unlike queries want list searches many keys with one request
+    want_lists: HashMap<SearchID, WantList>,
+    /// Outgoing want list requests that are waiting for response. Indexes of keys in
+    /// [`WantList`] that were sent in request
+    want_requests: HashMap<RequestId, (SearchID, Vec<usize>)>,
+    /// Versions of protocol that connected peers negotiated. Want list messages are sent only
+    /// to peers that speak [`QuantaSwapProtocol::V2`], other peers are asked with queries
+    pub(crate) peer_protocols: HashMap<PeerId, QuantaSwapProtocol>,
+    /// First want list requests of peers which version of protocol is not known yet. They are
+    /// supported by all versions and version is learned from their responses
+    protocol_probes: HashMap<RequestId, PeerId>,
     /// Out events queue that we are send out of [`Behaviour`]
     out_evenets_queue: OutEventsQueue<S>,
+    /// Responses to requests of peers that are waiting for lookups in [`Storage`]
+    pending_responses: PendingResponses,
+    /// Requests of peers that wait until count of resolving lookups is lower than
+    /// [`Config::max_concurrent_lookups`]
+    queued_requests: LookupQueue<QueuedRequest>,
+    /// Bytes that we are exchanged with peers
+    ledgers: Ledgers,
+    /// Storage where ledgers are saved, ledgers are kept only in memory if None
+    ledger_storage: Option<Arc<dyn LedgerStorage>>,
+    /// Loads and saves of ledgers that are resolving
+    ledger_tasks: LedgerTasks,
+    /// Last time when changed ledgers were saved
+    last_ledger_save: Instant,
+    /// Accounting of requests, served items and invalid items of peers
+    peer_scores: PeerScores,
+    /// Configuration of [`Behaviour`]
+    config: Config,
+    /// Timer that wakes [`Behaviour`] to check queries deadlines
+    timeout_check: Delay,
 }
 
 impl<S> Behaviour<S>
 where
     S: Storage + 'static,
 {
-    /// Create new [`Behaviour`]
-    pub fn new(storage: Arc<S>) -> Self {
+    /// Create new [`Behaviour`] that accept any item from network
+    pub fn new(storage: Arc<S>) -> Self { Self::with_validator(storage, AcceptAllValidator) }
+    /// Create new [`Behaviour`] that checks received items with given [`Validator`]
+    pub fn with_validator<V>(storage: Arc<S>, validator: V) -> Self
+    where
+        V: Validator + Send + 'static,
+    {
+        Self::with_config(storage, validator, Config::default())
+    }
+    /// Create new [`Behaviour`] with given [`Validator`] and [`Config`]
+    pub fn with_config<V>(storage: Arc<S>, validator: V, config: Config) -> Self
+    where
+        V: Validator + Send + 'static,
+    {
+        let mut request_response_config = request_response::Config::default();
+        request_response_config.set_request_timeout(REQUEST_TIMEOUT);
         let request_response = RequestResponse::new(
-            QuantaSwapCodec,
-            std::iter::once((QuantaSwapProtocol, ProtocolSupport::Full)),
-            Default::default(),
+            QuantaSwapCodec::new(config.max_message_size()),
+            QuantaSwapProtocol::supported()
+                .into_iter()
+                .map(|protocol| (protocol, ProtocolSupport::Full)),
+            request_response_config,
         );
         let connections = FnvHashSet::default();
         let queries = HashMap::default();
+        let want_lists = HashMap::default();
+        let want_requests = HashMap::default();
         let out_evenets_queue = OutEventsQueue::<S>::default();
         Self {
             request_response,
             storage,
+            validator: Box::new(validator),
             connections,
             queries,
+            want_lists,
+            want_requests,
+            peer_protocols: HashMap::default(),
+            protocol_probes: HashMap::default(),
             out_evenets_queue,
+            pending_responses: PendingResponses::default(),
+            queued_requests: LookupQueue::default(),
+            ledgers: Ledgers::default(),
+            ledger_storage: None,
+            ledger_tasks: LedgerTasks::default(),
+            last_ledger_save: Instant::now(),
+            peer_scores: PeerScores::default(),
+            config,
+            timeout_check: Box::pin(async_std::task::sleep(QUERY_TIMEOUT_CHECK_INTERVAL)),
         }
     }
+    /// Save [`Ledger`]s of peers in given storage. Ledger of peer is loaded when connection with
+    /// it is established, so debt ratio of peer is kept after restart
+    pub fn with_ledger_storage<L>(mut self, ledger_storage: Arc<L>) -> Self
+    where
+        L: LedgerStorage + 'static,
+    {
+        self.ledger_storage = Some(ledger_storage);
+        self
+    }
     /// Call this function if you need create new search query. Search query create new
-    /// random [`SearchID`] and sends [`QuantaSwapRequest::Query`] to all connections
+    /// random [`SearchID`] and sends [`QuantaSwapRequest::Query`] to all connections. Query
+    /// expires after [`Config::query_timeout`]
     pub fn search_item_with(&mut self, searching: Vec<u8>) -> SearchID {
+        self.search_item_with_timeout(searching, self.config.query_timeout())
+    }
+    /// Same as [`Behaviour::search_item_with`] but query expires after given timeout
+    pub fn search_item_with_timeout(&mut self, searching: Vec<u8>, timeout: Duration) -> SearchID {
         let search_id = SearchID::random();
         debug!(
             "[`QuantaBehaviour`]: Strarted new search with id: {}",
@@ -125,26 +368,650 @@ where
         );
         self.queries
             .entry(search_id)
-            .or_insert(searching.clone());
+            .or_insert(Query::new(searching.clone(), timeout));
         for peer in &self.connections {
-            self.request_response
-                .send_request(peer, QuantaSwapRequest::Query {
+            self.request_response.send_request(
+                peer,
+                QuantaSwapRequest::Query {
                     search_id,
                     searching: searching.to_vec(),
+                }
+                .into(),
+            );
+        }
+        search_id
+    }
+    /// Continue search with peers that provide item (e.g. providers that were found in DHT).
+    /// Providers that are not connected are dialed and asked as soon as connection is
+    /// established. If search with given [`SearchID`] is already finished it is started again
+    /// with the same id
+    pub fn search_item_with_providers(
+        &mut self,
+        search_id: SearchID,
+        searching: Vec<u8>,
+        providers: Vec<PeerId>,
+    ) {
+        let timeout = self.config.query_timeout();
+        let connections = &self.connections;
+        let query = self
+            .queries
+            .entry(search_id)
+            .or_insert_with(|| {
+                // connected peers already answered before search was finished, so only
+                // providers are asked again
+                let mut query = Query::new(searching.to_vec(), timeout);
+                query.not_found.extend(
+                    connections
+                        .iter()
+                        .filter(|peer| !providers.contains(peer)),
+                );
+                query
+            });
+        for provider in providers {
+            if query.not_found.contains(&provider) {
+                continue;
+            }
+            if self.connections.contains(&provider) {
+                self.request_response.send_request(
+                    &provider,
+                    QuantaSwapRequest::Query {
+                        search_id,
+                        searching: searching.to_vec(),
+                    }
+                    .into(),
+                );
+                continue;
+            }
+            debug!(
+                "[`QuantaBehaviour`]: Dialing provider {} for search {}",
+                provider, search_id
+            );
+            query.pending_peers.insert(provider);
+            self.out_evenets_queue
+                .push_back(ToSwarm::Dial {
+                    opts: DialOpts::peer_id(provider)
+                        .condition(PeerCondition::Disconnected)
+                        .build(),
                 });
         }
+        self.check_query_not_found(search_id);
+    }
+    /// Finish query with [`Event::QueryNotFound`] if every connected peer does not have item and
+    /// we are not waiting for connection with any provider
+    fn check_query_not_found(&mut self, search_id: SearchID) {
+        let Some(query) = self.queries.get(&search_id) else {
+            return;
+        };
+        // if every connected peer does not have item there is no reason to wait deadline
+        if query.pending_peers.is_empty() &&
+            self.connections
+                .iter()
+                .all(|peer| query.not_found.contains(peer))
+        {
+            if let Some(query) = self.queries.remove(&search_id) {
+                self.out_evenets_queue
+                    .push_back(ToSwarm::GenerateEvent(Event::QueryNotFound {
+                        search_id,
+                        searching: query.searching,
+                    }));
+            }
+        }
+    }
+    /// Check every active query with [`Behaviour::check_query_not_found`]. Peer that did not
+    /// answer can be gone, so queries should not wait for deadline
+    fn check_queries_not_found(&mut self) {
+        let search_ids = self
+            .queries
+            .keys()
+            .copied()
+            .collect::<Vec<SearchID>>();
+        for search_id in search_ids {
+            self.check_query_not_found(search_id);
+        }
+    }
+    /// Search many items with one want list instead of query per item. Peers are asked which
+    /// keys they have with [`QuantaSwapRequest::WantHave`] and then items are requested in
+    /// batches with [`QuantaSwapRequest::WantBlocks`]. Received items are sent out in
+    /// [`Event::WantListProgress`]. Want list expires after [`Config::query_timeout`]
+    pub fn search_items_with(&mut self, keys: Vec<Vec<u8>>) -> SearchID {
+        self.search_items_with_timeout(keys, self.config.query_timeout())
+    }
+    /// Same as [`Behaviour::search_items_with`] but want list expires after given timeout
+    pub fn search_items_with_timeout(&mut self, keys: Vec<Vec<u8>>, timeout: Duration) -> SearchID {
+        let search_id = SearchID::random();
+        debug!(
+            "[`QuantaBehaviour`]: Strarted new want list with id: {} and {} keys",
+            search_id,
+            keys.len()
+        );
+        self.want_lists
+            .insert(search_id, WantList::new(keys, timeout));
+        let peers = self
+            .connections
+            .iter()
+            .copied()
+            .collect::<Vec<PeerId>>();
+        for peer in peers {
+            self.send_want_have(&peer, search_id);
+        }
         search_id
     }
+    /// Continue want list with peers that provide its keys (e.g. providers that were found in
+    /// DHT). Providers that are not connected are dialed and asked as soon as connection is
+    /// established. If want list with given [`SearchID`] is already finished it is started again
+    /// with the same id and given keys
+    pub fn search_items_with_providers(
+        &mut self,
+        search_id: SearchID,
+        keys: Vec<Vec<u8>>,
+        providers: Vec<PeerId>,
+    ) {
+        let timeout = self.config.query_timeout();
+        let connections = &self.connections;
+        let want_list = self
+            .want_lists
+            .entry(search_id)
+            .or_insert_with(|| {
+                // connected peers already answered before want list was finished, so only
+                // providers are asked again
+                let mut want_list = WantList::new(keys, timeout);
+                for peer in connections
+                    .iter()
+                    .filter(|peer| !providers.contains(peer))
+                {
+                    want_list.on_have(*peer, []);
+                }
+                want_list
+            });
+        let mut connected = Vec::new();
+        for provider in providers {
+            if self.connections.contains(&provider) {
+                connected.push(provider);
+                continue;
+            }
+            debug!(
+                "[`QuantaBehaviour`]: Dialing provider {} for want list {}",
+                provider, search_id
+            );
+            want_list.pending_peers.insert(provider);
+            self.out_evenets_queue
+                .push_back(ToSwarm::Dial {
+                    opts: DialOpts::peer_id(provider)
+                        .condition(PeerCondition::Disconnected)
+                        .build(),
+                });
+        }
+        for provider in connected {
+            self.send_want_have(&provider, search_id);
+        }
+        self.check_want_list(search_id);
+    }
+    /// Returns accounting of peer or None if peer did not send anything to us
+    pub fn peer_stats(&self, peer: &PeerId) -> Option<&PeerStats> { self.peer_scores.stats(peer) }
+    /// Returns bytes that we are exchanged with peer or None if we are never exchanged items
+    pub fn ledger(&self, peer: &PeerId) -> Option<&Ledger> { self.ledgers.get(peer) }
+    /// Load ledger of peer from [`LedgerStorage`] if it is not loaded yet
+    fn load_ledger(&mut self, peer: PeerId) {
+        let Some(ledger_storage) = &self.ledger_storage else {
+            return;
+        };
+        if !self.ledgers.start_loading(peer) {
+            return;
+        }
+        let ledger_storage = Arc::clone(ledger_storage);
+        self.ledger_tasks
+            .push(Box::pin(async move {
+                LedgerTask::Loaded(peer, ledger_storage.load(peer).await)
+            }));
+    }
+    /// Save ledger of peer into [`LedgerStorage`] if it was changed since last save. If previous
+    /// save of ledger is not finished, ledger is saved after it
+    fn save_ledger(&mut self, peer: PeerId) {
+        if self.ledger_storage.is_none() {
+            return;
+        }
+        if let Some(ledger) = self.ledgers.take_dirty(&peer) {
+            self.push_ledger_save(peer, ledger);
+        }
+    }
+    /// Save all ledgers that were changed since last save
+    fn save_ledgers(&mut self) {
+        if self.ledger_storage.is_none() {
+            return;
+        }
+        for (peer, ledger) in self.ledgers.take_all_dirty() {
+            self.push_ledger_save(peer, ledger);
+        }
+        self.last_ledger_save = Instant::now();
+    }
+    /// Start save of ledger into [`LedgerStorage`]
+    fn push_ledger_save(&mut self, peer: PeerId, ledger: Ledger) {
+        let Some(ledger_storage) = &self.ledger_storage else {
+            return;
+        };
+        let ledger_storage = Arc::clone(ledger_storage);
+        self.ledger_tasks
+            .push(Box::pin(async move {
+                ledger_storage.save(peer, ledger).await;
+                LedgerTask::Saved(peer)
+            }));
+    }
+    /// Returns true if peer is banned, connections with banned peer are denied
+    pub fn is_banned(&self, peer: &PeerId) -> bool {
+        self.peer_scores
+            .is_banned(peer, Instant::now())
+    }
+    /// Ban peer for [`Config::ban_duration`]: close connections with it, deny new connections and
+    /// send [`Event::PeerBanned`]
+    fn ban_peer(&mut self, peer: PeerId, reason: BanReason) {
+        let duration = self.config.ban_duration();
+        debug!(
+            "[`QuantaBehaviour`]: Peer {} is banned for {:?}: {:?}",
+            peer, duration, reason
+        );
+        self.peer_scores
+            .ban(peer, &self.config, Instant::now() + duration);
+        self.queued_requests.remove_peer(&peer);
+        self.out_evenets_queue
+            .push_back(ToSwarm::CloseConnection {
+                peer_id: peer,
+                connection: CloseConnection::All,
+            });
+        self.out_evenets_queue
+            .push_back(ToSwarm::GenerateEvent(Event::PeerBanned {
+                peer,
+                reason,
+                duration,
+            }));
+    }
+    /// Penalise peer that sent invalid item by closing connection with it. Peer that sent too
+    /// many invalid items is banned
+    fn on_invalid_item(&mut self, peer: PeerId) {
+        if self
+            .peer_scores
+            .on_invalid_response(peer, &self.config)
+        {
+            self.ban_peer(peer, BanReason::InvalidResponses);
+            return;
+        }
+        self.out_evenets_queue
+            .push_back(ToSwarm::CloseConnection {
+                peer_id: peer,
+                connection: CloseConnection::All,
+            });
+    }
+    /// Returns error if peer is banned, so connection with it is denied
+    fn deny_banned(&self, peer: &PeerId) -> Result<(), ConnectionDenied> {
+        if self.is_banned(peer) {
+            return Err(ConnectionDenied::new(PeerBannedError(*peer)));
+        }
+        Ok(())
+    }
+    /// Cancel active search. Answers that we are receive later for this search are ignored.
+    /// Returns false if there is no active search with given [`SearchID`]
+    pub fn cancel_search(&mut self, search_id: &SearchID) -> bool {
+        debug!(
+            "[`QuantaBehaviour`]: Cancelled search with id: {}",
+            search_id
+        );
+        self.queries.remove(search_id).is_some() ||
+            self.want_lists
+                .remove(search_id)
+                .is_some()
+    }
+    /// Ask peer which of remaining keys of want list it have. Peers that do not support want lists
+    /// are asked with one [`QuantaSwapRequest::Query`] per key. Peer which version of protocol is
+    /// not known yet is asked about one key with [`QuantaSwapRequest::Query`], other keys are
+    /// asked when it answers, see [`Behaviour::on_peer_protocol`]
+    fn send_want_have(&mut self, peer: &PeerId, search_id: SearchID) {
+        let Some(want_list) = self.want_lists.get(&search_id) else {
+            return;
+        };
+        let Some(protocol) = self.peer_protocols.get(peer) else {
+            if self
+                .protocol_probes
+                .values()
+                .any(|probing| probing == peer)
+            {
+                return;
+            }
+            let Some(idx) = want_list.remaining().first().copied() else {
+                return;
+            };
+            let request_id = self.request_response.send_request(
+                peer,
+                QuantaSwapRequest::Query {
+                    search_id,
+                    searching: want_list.keys[idx].to_vec(),
+                }
+                .into(),
+            );
+            self.want_requests
+                .insert(request_id, (search_id, Vec::from([idx])));
+            self.protocol_probes
+                .insert(request_id, *peer);
+            return;
+        };
+        if !protocol.supports_want_lists() {
+            for idx in want_list.remaining() {
+                let request_id = self.request_response.send_request(
+                    peer,
+                    QuantaSwapRequest::Query {
+                        search_id,
+                        searching: want_list.keys[idx].to_vec(),
+                    }
+                    .into(),
+                );
+                self.want_requests
+                    .insert(request_id, (search_id, Vec::from([idx])));
+            }
+            return;
+        }
+        for indexes in want_list
+            .remaining()
+            .chunks(MAX_WANT_LIST_KEYS)
+        {
+            let keys = indexes
+                .iter()
+                .map(|idx| want_list.keys[*idx].to_vec())
+                .collect();
+            let request_id = self
+                .request_response
+                .send_request(peer, QuantaSwapRequest::WantHave { search_id, keys }.into());
+            self.want_requests
+                .insert(request_id, (search_id, indexes.to_vec()));
+        }
+    }
+    /// Request items from peer that it have and that are not requested from other peers. Peers
+    /// that do not support want lists are asked with one [`QuantaSwapRequest::QueryWant`] per key
+    fn send_want_blocks(&mut self, peer: &PeerId, search_id: SearchID) {
+        let Some(want_list) = self.want_lists.get_mut(&search_id) else {
+            return;
+        };
+        let assigned = want_list.assign(*peer);
+        if !self
+            .peer_protocols
+            .get(peer)
+            .is_some_and(|protocol| protocol.supports_want_lists())
+        {
+            for idx in assigned {
+                let request_id = self.request_response.send_request(
+                    peer,
+                    QuantaSwapRequest::QueryWant {
+                        search_id,
+                        searching: want_list.keys[idx].to_vec(),
+                    }
+                    .into(),
+                );
+                self.want_requests
+                    .insert(request_id, (search_id, Vec::from([idx])));
+            }
+            return;
+        }
+        for indexes in assigned.chunks(MAX_WANT_LIST_KEYS) {
+            let keys = indexes
+                .iter()
+                .map(|idx| want_list.keys[*idx].to_vec())
+                .collect();
+            let request_id = self.request_response.send_request(
+                peer,
+                QuantaSwapRequest::WantBlocks { search_id, keys }.into(),
+            );
+            self.want_requests
+                .insert(request_id, (search_id, indexes.to_vec()));
+        }
+    }
+    /// Request released items of want list from all peers that have them
+    fn reassign_want_list(&mut self, search_id: SearchID) {
+        let Some(want_list) = self.want_lists.get(&search_id) else {
+            return;
+        };
+        for peer in want_list.providers() {
+            if self.connections.contains(&peer) {
+                self.send_want_blocks(&peer, search_id);
+            }
+        }
+    }
+    /// Finish want list if all items are received or nobody can send remaining items
+    fn check_want_list(&mut self, search_id: SearchID) {
+        let Some(want_list) = self.want_lists.get(&search_id) else {
+            return;
+        };
+        let event = if want_list.is_completed() {
+            Event::WantListCompleted { search_id }
+        } else if want_list.is_exhausted(&self.connections) && !self.has_want_requests(search_id) {
+            Event::WantListNotFound {
+                search_id,
+                missing: want_list.missing_keys(),
+            }
+        } else {
+            return;
+        };
+        self.want_lists.remove(&search_id);
+        self.out_evenets_queue
+            .push_back(ToSwarm::GenerateEvent(event));
+    }
+    /// Check if want list has requests that are waiting for response. Peer that did not answer
+    /// which keys it have yet can still have remaining items
+    pub(crate) fn has_want_requests(&self, search_id: SearchID) -> bool {
+        self.want_requests
+            .values()
+            .any(|(requested_search_id, _)| *requested_search_id == search_id)
+    }
+    /// Want list request was failed, so items that were requested from peer should be
+    /// requested from other peers
+    fn on_want_request_failed(&mut self, peer: PeerId, request_id: RequestId) {
+        let Some((search_id, indexes)) = self.want_requests.remove(&request_id) else {
+            return;
+        };
+        if let Some(want_list) = self.want_lists.get_mut(&search_id) {
+            want_list.forget_peer(&peer);
+            want_list.release(&peer, indexes);
+        }
+        self.reassign_want_list(search_id);
+        self.check_want_list(search_id);
+    }
+    /// Handle [`QuantaSwapRespone::Have`] for want list
+    fn handle_have_response(&mut self, peer: PeerId, request_id: RequestId, have: Vec<u8>) {
+        let Some((search_id, indexes)) = self.want_requests.remove(&request_id) else {
+            return;
+        };
+        let Some(want_list) = self.want_lists.get_mut(&search_id) else {
+            return;
+        };
+        want_list.on_have(
+            peer,
+            indexes
+                .into_iter()
+                .enumerate()
+                .filter(|(bit, _)| want::is_set(have.as_slice(), *bit))
+                .map(|(_, idx)| idx),
+        );
+        self.send_want_blocks(&peer, search_id);
+        self.check_want_list(search_id);
+    }
+    /// Handle [`QuantaSwapRespone::Blocks`] for want list
+    fn handle_blocks_response(
+        &mut self,
+        peer: PeerId,
+        request_id: RequestId,
+        blocks: Vec<(Vec<u8>, Vec<u8>)>,
+    ) {
+        let Some((search_id, indexes)) = self.want_requests.remove(&request_id) else {
+            return;
+        };
+        let Some(want_list) = self.want_lists.get_mut(&search_id) else {
+            return;
+        };
+        let mut requested = indexes
+            .into_iter()
+            .map(|idx| (want_list.keys[idx].to_vec(), idx))
+            .collect::<HashMap<Vec<u8>, usize>>();
+        let requested_count = requested.len();
+        let mut items = Vec::new();
+        let mut invalid_key = None;
+        for (key, item) in blocks {
+            // ignore items that we are not requested from peer
+            let Some(idx) = requested.remove(&key) else {
+                continue;
+            };
+            if !self
+                .validator
+                .validate(key.as_slice(), item.as_slice())
+            {
+                debug!(
+                    "[`QuantaBehaviour`]: Peer {} sent invalid item for want list {}",
+                    peer, search_id
+                );
+                want_list.forget_peer(&peer);
+                invalid_key = Some(key);
+                break;
+            }
+            if want_list.on_received(idx) {
+                self.ledgers
+                    .on_received(peer, item.len());
+                items.push((key, item));
+            }
+        }
+        // peer that answered without requested items is asked again after backoff, so we are
+        // not looping requests over its rate limits
+        if requested.len() == requested_count && requested_count > 0 {
+            want_list.back_off(peer, Instant::now());
+        } else {
+            want_list.on_answered(&peer);
+        }
+        // keys that did not fit into response can be requested again
+        want_list.release(&peer, requested.into_values());
+        if let Some(key) = invalid_key {
+            self.on_invalid_item(peer);
+            self.out_evenets_queue
+                .push_back(ToSwarm::GenerateEvent(Event::QueryValidationFailed {
+                    peer,
+                    search_id,
+                    searching: key,
+                }));
+        }
+        if !items.is_empty() {
+            self.out_evenets_queue
+                .push_back(ToSwarm::GenerateEvent(Event::WantListProgress {
+                    peer,
+                    search_id,
+                    items,
+                }));
+        }
+        self.reassign_want_list(search_id);
+        self.check_want_list(search_id);
+    }
+    /// Remove all queries which deadline is reached and send [`Event::QueryTimedOut`] for them
+    fn remove_expired_queries(&mut self) {
+        let now = Instant::now();
+        let expired = self
+            .queries
+            .iter()
+            .filter(|(_, query)| query.is_expired(now))
+            .map(|(search_id, _)| *search_id)
+            .collect::<Vec<SearchID>>();
+        for search_id in expired {
+            if let Some(query) = self.queries.remove(&search_id) {
+                debug!(
+                    "[`QuantaBehaviour`]: Search with id: {} timed out",
+                    search_id
+                );
+                self.out_evenets_queue
+                    .push_back(ToSwarm::GenerateEvent(Event::QueryTimedOut {
+                        search_id,
+                        searching: query.searching,
+                    }));
+            }
+        }
+    }
+    /// Remove all want lists which deadline is reached and send [`Event::WantListTimedOut`]
+    /// for them
+    fn remove_expired_want_lists(&mut self) {
+        let now = Instant::now();
+        let expired = self
+            .want_lists
+            .iter()
+            .filter(|(_, want_list)| want_list.is_expired(now))
+            .map(|(search_id, _)| *search_id)
+            .collect::<Vec<SearchID>>();
+        for search_id in expired {
+            if let Some(want_list) = self.want_lists.remove(&search_id) {
+                debug!(
+                    "[`QuantaBehaviour`]: Want list with id: {} timed out",
+                    search_id
+                );
+                self.out_evenets_queue
+                    .push_back(ToSwarm::GenerateEvent(Event::WantListTimedOut {
+                        search_id,
+                        missing: want_list.missing_keys(),
+                    }));
+            }
+        }
+    }
+    /// Drop queued requests of peers that wait longer than [`REQUEST_TIMEOUT`], peers already
+    /// gave up waiting for responses to them
+    fn remove_expired_requests(&mut self) {
+        let Some(moment) = Instant::now().checked_sub(REQUEST_TIMEOUT) else {
+            return;
+        };
+        let removed = self
+            .queued_requests
+            .remove_queued_before(moment);
+        if removed > 0 {
+            debug!(
+                "[`QuantaBehaviour`]: Dropped {} queued requests that are timed out",
+                removed
+            );
+        }
+    }
+    /// Request blocks of want lists again from peers which backoff is over
+    fn resume_want_lists(&mut self) {
+        let now = Instant::now();
+        let resumed = self
+            .want_lists
+            .iter_mut()
+            .filter_map(|(search_id, want_list)| {
+                want_list
+                    .resume(now)
+                    .then_some(*search_id)
+            })
+            .collect::<Vec<SearchID>>();
+        for search_id in resumed {
+            self.reassign_want_list(search_id);
+        }
+    }
+    /// Remove bans which time is over and send [`Event::PeerUnbanned`] for them
+    fn remove_expired_bans(&mut self) {
+        for peer in self
+            .peer_scores
+            .remove_expired(&self.connections, Instant::now())
+        {
+            debug!("[`QuantaBehaviour`]: Ban of peer {} is expired", peer);
+            self.out_evenets_queue
+                .push_back(ToSwarm::GenerateEvent(Event::PeerUnbanned { peer }));
+        }
+    }
     /// Handle [`FromSwarm::ConnectionEstablished`] event and send it into [`RequestResponse`]
     fn on_connection_established(&mut self, connection_established: ConnectionEstablished) {
+        // peer that is connected with other connection already knows about our searches
+        if connection_established.other_established > 0 {
+            self.request_response
+                .on_swarm_event(FromSwarm::ConnectionEstablished(connection_established));
+            return;
+        }
         // Send swarm connection_established event into request_response behaviour
-        for (search_id, searching) in &self.queries {
+        for (search_id, query) in &mut self.queries {
+            query
+                .pending_peers
+                .remove(&connection_established.peer_id);
             self.request_response.send_request(
                 &connection_established.peer_id,
                 QuantaSwapRequest::Query {
                     search_id: *search_id,
-                    searching: searching.to_vec(),
-                },
+                    searching: query.searching.to_vec(),
+                }
+                .into(),
             );
         }
         self.request_response
@@ -152,89 +1019,335 @@ where
         // Insert new peer into connections
         self.connections
             .insert(connection_established.peer_id);
-        // Send all active queries to new peer
+        self.load_ledger(connection_established.peer_id);
+        // provider is not answered until it is asked, so want list keeps waiting for it
+        for want_list in self.want_lists.values_mut() {
+            want_list
+                .pending_peers
+                .remove(&connection_established.peer_id);
+        }
+        let search_ids = self
+            .want_lists
+            .keys()
+            .copied()
+            .collect::<Vec<SearchID>>();
+        for search_id in search_ids {
+            self.send_want_have(&connection_established.peer_id, search_id);
+        }
+    }
+    /// Save version of protocol that peer speaks. Active want lists are sent to peer when its
+    /// version becomes known
+    pub(crate) fn on_peer_protocol(&mut self, peer: PeerId, protocol: QuantaSwapProtocol) {
+        if self
+            .peer_protocols
+            .insert(peer, protocol)
+            .is_some()
+        {
+            return;
+        }
+        let search_ids = self
+            .want_lists
+            .keys()
+            .copied()
+            .collect::<Vec<SearchID>>();
+        for search_id in search_ids {
+            self.send_want_have(&peer, search_id);
+        }
+    }
+    /// Request was failed. Peer that did not answer its first request is asked with queries,
+    /// they are supported by all versions of protocol
+    fn on_request_failed(&mut self, peer: PeerId, request_id: RequestId) {
+        let probe = self
+            .protocol_probes
+            .remove(&request_id)
+            .is_some();
+        self.on_want_request_failed(peer, request_id);
+        if probe && self.connections.contains(&peer) {
+            self.on_peer_protocol(peer, QuantaSwapProtocol::V1);
+        }
+    }
+    /// Handle [`FromSwarm::DialFailure`] event and send it into [`RequestResponse`]. Provider
+    /// that we could not dial will never answer, so queries should not wait for it
+    fn on_dial_failure(&mut self, dial_failure: DialFailure) {
+        if let Some(peer) = dial_failure.peer_id {
+            let search_ids = self
+                .queries
+                .iter_mut()
+                .filter_map(|(search_id, query)| {
+                    query
+                        .pending_peers
+                        .remove(&peer)
+                        .then_some(*search_id)
+                })
+                .collect::<Vec<SearchID>>();
+            for search_id in search_ids {
+                self.check_query_not_found(search_id);
+            }
+            let search_ids = self
+                .want_lists
+                .iter_mut()
+                .filter_map(|(search_id, want_list)| {
+                    want_list
+                        .pending_peers
+                        .remove(&peer)
+                        .then_some(*search_id)
+                })
+                .collect::<Vec<SearchID>>();
+            for search_id in search_ids {
+                self.check_want_list(search_id);
+            }
+        }
+        self.request_response
+            .on_swarm_event(FromSwarm::DialFailure(dial_failure));
     }
     /// Handle [`FromSwarm::ConnectionClosed`] event and send it into [`RequestResponse`]
     fn on_connection_closed(&mut self, connection_closed: ConnectionClosed) {
+        let peer = connection_closed.peer_id;
+        let remaining_established = connection_closed.remaining_established;
         // Send swarm connection_closed event into request_response behaviour
-        self.connections
-            .remove(&connection_closed.peer_id);
         self.request_response
             .on_swarm_event(FromSwarm::ConnectionClosed(connection_closed));
+        // peer is still connected with other connections
+        if remaining_established > 0 {
+            return;
+        }
+        self.connections.remove(&peer);
+        self.peer_protocols.remove(&peer);
+        // responses to requests of peer can not be sent anymore
+        self.queued_requests.remove_peer(&peer);
+        self.save_ledger(peer);
+        self.ledgers
+            .on_disconnected(peer, Instant::now());
+        // peer that is gone will never answer queries
+        self.check_queries_not_found();
+        // Items that were requested from peer should be requested from other peers
+        let search_ids = self
+            .want_lists
+            .keys()
+            .copied()
+            .collect::<Vec<SearchID>>();
+        for search_id in search_ids {
+            if let Some(want_list) = self.want_lists.get_mut(&search_id) {
+                want_list.forget_peer(&peer);
+            }
+            self.reassign_want_list(search_id);
+            self.check_want_list(search_id);
+        }
     }
-    /// handle ch err
-    fn handle_err_and_sent_response(
-        &mut self,
-        channel: ResponseChannel<QuantaSwapRespone>,
-        response: QuantaSwapRespone,
-    ) {
-        self.request_response
-            .send_response(channel, response)
-            .expect("got unexpected err when trying to send response")
+    /// Send response that was resolved. Peer that has gone while lookups were resolving does
+    /// not get response
+    fn send_pending_response(&mut self, pending_response: PendingResponse) {
+        let Some(response) = pending_response.response else {
+            return;
+        };
+        if self
+            .request_response
+            .send_response(pending_response.channel, response.into())
+            .is_err()
+        {
+            debug!("[`QuantaBehaviour`]: Response was not sent, peer is gone");
+            return;
+        }
+        if let Some(event) = pending_response.event {
+            if let Event::ItemsServed { peer, items, bytes } = &event {
+                self.peer_scores
+                    .on_served(*peer, *items, *bytes, &self.config);
+                self.ledgers.on_sent(*peer, *bytes);
+            }
+            self.out_evenets_queue
+                .push_back(ToSwarm::GenerateEvent(event));
+        }
     }
-    /// Handle [`QuantaSwapRequest`]
+    /// Handle [`QuantaSwapRequest`]. Request is queued until there is free slot of lookups, see
+    /// [`Behaviour::start_queued_lookups`]. Requests over rate limits or over
+    /// [`Config::max_queued_requests`] are answered as if we do not have items, so peer asks other
+    /// peers and connection stays open
     fn handle_request_message(
         &mut self,
+        peer: PeerId,
         request: QuantaSwapRequest,
-        channel: ResponseChannel<QuantaSwapRespone>,
-    ) -> Option<Event> {
+        channel: ResponseChannel<NegotiatedResponse>,
+    ) {
         debug!("[`QuantaBehaviour`]: New Request={}", request);
-        match request {
-            QuantaSwapRequest::Query {
-                search_id,
-                searching,
-            } => {
-                let exists = self.storage.exists(searching);
-                let response = QuantaSwapRespone::Query { search_id, exists };
-                self.handle_err_and_sent_response(channel, response);
-                None
-            },
-            QuantaSwapRequest::QueryWant {
-                search_id,
-                searching,
-            } => {
-                if let Some(item) = self.storage.get(searching) {
-                    let response = QuantaSwapRespone::QueryWant { search_id, item };
-                    self.handle_err_and_sent_response(channel, response);
+        match self
+            .peer_scores
+            .on_request(peer, &self.config, Instant::now())
+        {
+            RequestVerdict::Allowed => {},
+            RequestVerdict::Limited => {
+                debug!(
+                    "[`QuantaBehaviour`]: Request of peer {} is limited by rate limit",
+                    peer
+                );
+                if let Some(response) = limited_response(request) {
+                    let _ = self
+                        .request_response
+                        .send_response(channel, response.into());
                 }
-                None
+                return;
             },
+            RequestVerdict::Ban => {
+                self.ban_peer(peer, BanReason::RateLimited);
+                return;
+            },
+        }
+        if self.queued_requests.len() >= self.config.max_queued_requests() {
+            debug!(
+                "[`QuantaBehaviour`]: Request of peer {} is dropped because queue of lookups is \
+                 full",
+                peer
+            );
+            if let Some(response) = limited_response(request) {
+                let _ = self
+                    .request_response
+                    .send_response(channel, response.into());
+            }
+            return;
         }
+        self.queued_requests
+            .push(peer, QueuedRequest { request, channel }, Instant::now());
     }
-    /// Handle [`QuantaSwapRespone`]
+    /// Start lookups of queued requests while count of resolving lookups is lower than
+    /// [`Config::max_concurrent_lookups`]. Requests of peers with lower debt ratio in [`Ledger`]
+    /// are started first, requests of peers with the same ratio in order of arrival
+    fn start_queued_lookups(&mut self) {
+        while self.pending_responses.len() <
+            self.config
+                .max_concurrent_lookups()
+                .get()
+        {
+            let Some((peer, queued)) = self.queued_requests.pop(&self.ledgers) else {
+                return;
+            };
+            self.start_lookup(peer, queued);
+        }
+    }
+    /// Lookup items of request in [`Storage`]. Lookups are resolved later, so response is sent
+    /// when they are finished
+    fn start_lookup(&mut self, peer: PeerId, queued: QueuedRequest) {
+        let QueuedRequest { request, channel } = queued;
+        let storage = Arc::clone(&self.storage);
+        // leave space for keys and framing, so response fits into one message
+        let max_size = MAX_BLOCKS_RESPONSE_SIZE.min(self.config.max_message_size() / 2);
+        self.pending_responses
+            .push(Box::pin(async move {
+                let (response, event) = lookup_response(storage, peer, request, max_size).await;
+                PendingResponse {
+                    channel,
+                    response,
+                    event,
+                }
+            }));
+    }
+    /// Handle [`QuantaSwapRespone`]. Every response tells which version of protocol peer speaks
     fn handle_response_message(
         &mut self,
         peer: PeerId,
-        response: QuantaSwapRespone,
+        request_id: RequestId,
+        response: NegotiatedResponse,
     ) -> Option<Event> {
+        let NegotiatedResponse { response, protocol } = response;
         debug!("[`QuantaBehaviour`]: New Response={}", response);
+        self.protocol_probes.remove(&request_id);
+        if let Some(protocol) = protocol {
+            self.on_peer_protocol(peer, protocol);
+        }
+        if self
+            .want_requests
+            .contains_key(&request_id)
+        {
+            self.handle_want_list_response(peer, request_id, response);
+            return None;
+        }
         match response {
             QuantaSwapRespone::Query { search_id, exists } => {
+                let query = self.queries.get_mut(&search_id)?;
                 if exists {
-                    if let Some(searching) = self.queries.get(&search_id) {
-                        self.request_response
-                            .send_request(&peer, QuantaSwapRequest::QueryWant {
-                                search_id,
-                                searching: searching.to_vec(),
-                            });
-                    };
+                    self.request_response.send_request(
+                        &peer,
+                        QuantaSwapRequest::QueryWant {
+                            search_id,
+                            searching: query.searching.to_vec(),
+                        }
+                        .into(),
+                    );
+                    return None;
                 };
+                query.not_found.insert(peer);
+                self.check_query_not_found(search_id);
                 None
             },
             QuantaSwapRespone::QueryWant { search_id, item } => {
-                if let Some(searching) = self.queries.remove(&search_id) {
-                    return Some(Event::QueryCompleted {
+                let searching = &self.queries.get(&search_id)?.searching;
+                if !self
+                    .validator
+                    .validate(searching.as_slice(), item.as_slice())
+                {
+                    debug!(
+                        "[`QuantaBehaviour`]: Peer {} sent invalid item for search {}",
+                        peer, search_id
+                    );
+                    let searching = searching.to_vec();
+                    self.on_invalid_item(peer);
+                    return Some(Event::QueryValidationFailed {
                         peer,
                         search_id,
                         searching,
-                        item,
                     });
                 }
-                None
+                let searching = self
+                    .queries
+                    .remove(&search_id)?
+                    .searching;
+                self.ledgers
+                    .on_received(peer, item.len());
+                Some(Event::QueryCompleted {
+                    peer,
+                    search_id,
+                    searching,
+                    item,
+                })
+            },
+            // want list responses to requests that we are not waiting for
+            QuantaSwapRespone::Have { .. } | QuantaSwapRespone::Blocks { .. } => None,
+        }
+    }
+    /// Handle response to want list request. Peers that do not support want lists answer
+    /// queries, they are handled as want list responses with one key
+    fn handle_want_list_response(
+        &mut self,
+        peer: PeerId,
+        request_id: RequestId,
+        response: QuantaSwapRespone,
+    ) {
+        match response {
+            QuantaSwapRespone::Query { exists, .. } => {
+                self.handle_have_response(peer, request_id, want::to_bitmap(&[exists]));
+            },
+            QuantaSwapRespone::QueryWant { item, .. } => {
+                let Some(key) = self
+                    .want_requests
+                    .get(&request_id)
+                    .and_then(|(search_id, indexes)| {
+                        let want_list = self.want_lists.get(search_id)?;
+                        Some(want_list.keys[*indexes.first()?].to_vec())
+                    })
+                else {
+                    self.want_requests.remove(&request_id);
+                    return;
+                };
+                self.handle_blocks_response(peer, request_id, Vec::from([(key, item)]));
+            },
+            QuantaSwapRespone::Have { have, .. } => {
+                self.handle_have_response(peer, request_id, have);
+            },
+            QuantaSwapRespone::Blocks { blocks, .. } => {
+                self.handle_blocks_response(peer, request_id, blocks);
             },
         }
     }
-    /// Handle all [`RequestResponse`] messages([`QuantaSwapRequest`], [`QuantaSwapRespone`])
+    /// Handle all [`RequestResponse`] messages([`QuantaSwapRequest`], [`QuantaSwapRespone`]).
+    /// Every message tells which version of protocol peer speaks
     fn handle_request_response_message(
         &mut self,
         peer: PeerId,
@@ -243,10 +1356,18 @@ where
         match message {
             RequestResponseMessage::Request {
                 request, channel, ..
-            } => self.handle_request_message(request, channel),
-            RequestResponseMessage::Response { response, .. } => {
-                self.handle_response_message(peer, response)
+            } => {
+                let NegotiatedRequest { request, protocol } = request;
+                if let Some(protocol) = protocol {
+                    self.on_peer_protocol(peer, protocol);
+                }
+                self.handle_request_message(peer, request, channel);
+                None
             },
+            RequestResponseMessage::Response {
+                request_id,
+                response,
+            } => self.handle_response_message(peer, request_id, response),
         }
     }
 }
@@ -265,6 +1386,7 @@ where
         local_addr: &Multiaddr,
         remote_addr: &Multiaddr,
     ) -> Result<THandler<Self>, ConnectionDenied> {
+        self.deny_banned(&peer)?;
         self.request_response
             .handle_established_inbound_connection(_connection_id, peer, local_addr, remote_addr)
     }
@@ -276,6 +1398,9 @@ where
         _addresses: &[Multiaddr],
         _effective_role: Endpoint,
     ) -> Result<Vec<Multiaddr>, ConnectionDenied> {
+        if let Some(peer) = maybe_peer {
+            self.deny_banned(&peer)?;
+        }
         self.request_response
             .handle_pending_outbound_connection(
                 _connection_id,
@@ -292,6 +1417,7 @@ where
         addr: &Multiaddr,
         role_override: Endpoint,
     ) -> Result<THandler<Self>, ConnectionDenied> {
+        self.deny_banned(&peer)?;
         self.request_response
             .handle_established_outbound_connection(_connection_id, peer, addr, role_override)
     }
@@ -306,6 +1432,10 @@ where
                 debug!("[`QuantaBehaviour`]: New ConnectionClosed swarm event");
                 self.on_connection_closed(connection_closed)
             },
+            FromSwarm::DialFailure(dial_failure) => {
+                debug!("[`QuantaBehaviour`]: New DialFailure swarm event");
+                self.on_dial_failure(dial_failure)
+            },
             // All events that we are need we are handle.
             // Other we just send into [`RequestReponse`]
             _ => {
@@ -328,15 +1458,54 @@ where
     /// Poll
     fn poll(
         &mut self,
-        _cx: &mut Context<'_>,
-        _params: &mut impl PollParameters,
+        cx: &mut Context<'_>,
+        params: &mut impl PollParameters,
     ) -> Poll<ToSwarm<Self::OutEvent, THandlerInEvent<Self>>> {
+        // check deadlines of queries and register timer in context for next check
+        while self
+            .timeout_check
+            .as_mut()
+            .poll(cx)
+            .is_ready()
+        {
+            self.timeout_check = Box::pin(async_std::task::sleep(QUERY_TIMEOUT_CHECK_INTERVAL));
+            self.remove_expired_queries();
+            self.check_queries_not_found();
+            self.remove_expired_want_lists();
+            self.resume_want_lists();
+            self.remove_expired_bans();
+            if self.last_ledger_save.elapsed() >= LEDGER_SAVE_INTERVAL {
+                self.save_ledgers();
+            }
+            self.ledgers
+                .forget_idle(Instant::now(), self.ledger_storage.is_some());
+            self.remove_expired_requests();
+        }
         loop {
+            self.start_queued_lookups();
+            // send responses which lookups are resolved
+            while let Poll::Ready(Some(pending_response)) = self
+                .pending_responses
+                .poll_next_unpin(cx)
+            {
+                self.send_pending_response(pending_response);
+                self.start_queued_lookups();
+            }
+            while let Poll::Ready(Some(task)) = self.ledger_tasks.poll_next_unpin(cx) {
+                match task {
+                    LedgerTask::Loaded(peer, ledger) => self.ledgers.on_loaded(peer, ledger),
+                    // changes that were made while ledger was saving are saved now
+                    LedgerTask::Saved(peer) => {
+                        self.ledgers.on_saved(peer);
+                        self.save_ledger(peer);
+                    },
+                }
+            }
             if let Some(event) = self.out_evenets_queue.pop_front() {
                 return Poll::Ready(event);
             };
 
-            let event = self.request_response.poll(_cx, _params);
+            let event = self.request_response.poll(cx, params);
             match event {
                 Poll::Ready(event) => {
                     if let ToSwarm::NotifyHandler {
@@ -366,9 +1535,100 @@ where
                         };
                         continue;
                     }
+                    if let ToSwarm::GenerateEvent(request_response::Event::OutboundFailure {
+                        peer,
+                        request_id,
+                        ..
+                    }) = event
+                    {
+                        self.on_request_failed(peer, request_id);
+                        continue;
+                    }
                 },
                 Poll::Pending => return Poll::Pending,
             }
         }
     }
 }
+
+/// Returns response to request that is over rate limits. It tells that we do not have items,
+/// item of [`QuantaSwapRequest::QueryWant`] can not be answered so peer does not get response
+fn limited_response(request: QuantaSwapRequest) -> Option<QuantaSwapRespone> {
+    match request {
+        QuantaSwapRequest::Query { search_id, .. } => Some(QuantaSwapRespone::Query {
+            search_id,
+            exists: false,
+        }),
+        QuantaSwapRequest::QueryWant { .. } => None,
+        QuantaSwapRequest::WantHave { search_id, keys } => Some(QuantaSwapRespone::Have {
+            search_id,
+            have: want::to_bitmap(vec![false; keys.len()].as_slice()),
+        }),
+        QuantaSwapRequest::WantBlocks { search_id, .. } => Some(QuantaSwapRespone::Blocks {
+            search_id,
+            blocks: Vec::new(),
+        }),
+    }
+}
+
+/// Lookup items in [`Storage`] for request of peer. Returns response that should be sent to
+/// peer and [`Event::ItemsServed`] if items were sent
+async fn lookup_response<S>(
+    storage: Arc<S>,
+    peer: PeerId,
+    request: QuantaSwapRequest,
+    max_size: usize,
+) -> (Option<QuantaSwapRespone>, Option<Event>)
+where
+    S: Storage + 'static,
+{
+    match request {
+        QuantaSwapRequest::Query {
+            search_id,
+            searching,
+        } => {
+            let exists = storage.exists(searching).await;
+            (Some(QuantaSwapRespone::Query { search_id, exists }), None)
+        },
+        QuantaSwapRequest::QueryWant {
+            search_id,
+            searching,
+        } => {
+            let Some(item) = storage.get(searching).await else {
+                return (None, None);
+            };
+            let bytes = item.len();
+            (
+                Some(QuantaSwapRespone::QueryWant { search_id, item }),
+                Some(Event::ItemsServed {
+                    peer,
+                    items: 1,
+                    bytes,
+                }),
+            )
+        },
+        QuantaSwapRequest::WantHave { search_id, keys } => {
+            let have = storage.exists_many(keys).await;
+            let response = QuantaSwapRespone::Have {
+                search_id,
+                have: want::to_bitmap(have.as_slice()),
+            };
+            (Some(response), None)
+        },
+        QuantaSwapRequest::WantBlocks { search_id, keys } => {
+            // the rest of keys will be requested again
+            let blocks = storage.get_many(keys, max_size).await;
+            let size = blocks
+                .iter()
+                .map(|(_, item)| item.len())
+                .sum();
+            let items = blocks.len();
+            let event = (items > 0).then_some(Event::ItemsServed {
+                peer,
+                items,
+                bytes: size,
+            });
+            (Some(QuantaSwapRespone::Blocks { search_id, blocks }), event)
+        },
+    }
+}
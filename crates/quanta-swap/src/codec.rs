@@ -8,66 +8,162 @@ use crate::{
     response::QuantaSwapRespone,
 };
 
+/// [`QuantaSwapRequest`] with version of protocol that was negotiated on its substream. Version
+/// is known only for requests that we are read, requests that we are send have None
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NegotiatedRequest {
+    /// Request itself
+    pub(crate) request: QuantaSwapRequest,
+    /// Version of protocol that request was received over
+    pub(crate) protocol: Option<QuantaSwapProtocol>,
+}
+
+impl From<QuantaSwapRequest> for NegotiatedRequest {
+    fn from(request: QuantaSwapRequest) -> Self {
+        Self {
+            request,
+            protocol: None,
+        }
+    }
+}
+
+/// [`QuantaSwapRespone`] with version of protocol that was negotiated on its substream. Version
+/// is known only for responses that we are read, responses that we are send have None
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NegotiatedResponse {
+    /// Response itself
+    pub(crate) response: QuantaSwapRespone,
+    /// Version of protocol that response was received over
+    pub(crate) protocol: Option<QuantaSwapProtocol>,
+}
+
+impl From<QuantaSwapRespone> for NegotiatedResponse {
+    fn from(response: QuantaSwapRespone) -> Self {
+        Self {
+            response,
+            protocol: None,
+        }
+    }
+}
+
+/// Codec of quanta swap messages. Messages are length-prefixed protobufs, max size of message
+/// depends on negotiated [`QuantaSwapProtocol`] version
 #[derive(Debug, Clone)]
-pub struct QuantaSwapCodec;
+pub struct QuantaSwapCodec {
+    /// Max size of one message in [`QuantaSwapProtocol::V2`]
+    max_message_size: usize,
+}
 
-const MAX_BUFFER_SIZE: usize = 512 * 4;
+impl QuantaSwapCodec {
+    /// Create new [`QuantaSwapCodec`] with given max size of message
+    pub fn new(max_message_size: usize) -> Self { Self { max_message_size } }
+    /// Write message into io if it fits into negotiated version of protocol. Otherwise remote
+    /// peer would reject it anyway, so we are fail before sending it
+    async fn write_message<T>(
+        &self,
+        protocol: &QuantaSwapProtocol,
+        io: &mut T,
+        bytes: Vec<u8>,
+    ) -> std::io::Result<()>
+    where
+        T: AsyncWrite + Unpin + Send,
+    {
+        let max_message_size = protocol.max_message_size(self.max_message_size);
+        if bytes.len() > max_message_size {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!(
+                    "message of {} bytes exceeds limit of {} bytes for {:?}",
+                    bytes.len(),
+                    max_message_size,
+                    protocol
+                ),
+            ));
+        }
+        upgrade::write_length_prefixed(io, bytes.as_slice()).await
+    }
+}
+/// Returns error if message is a part of want list and negotiated version of protocol does not
+/// support want lists. Peers that speak only first version can not parse them
+fn ensure_supported(protocol: &QuantaSwapProtocol, is_want_list: bool) -> std::io::Result<()> {
+    if is_want_list && !protocol.supports_want_lists() {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!("want lists are not supported in {:?}", protocol),
+        ));
+    }
+    Ok(())
+}
 
 #[async_trait::async_trait]
 impl Codec for QuantaSwapCodec {
     type Protocol = QuantaSwapProtocol;
-    type Request = QuantaSwapRequest;
-    type Response = QuantaSwapRespone;
+    type Request = NegotiatedRequest;
+    type Response = NegotiatedResponse;
 
     async fn read_request<T>(
         &mut self,
-        _protocol: &Self::Protocol,
+        protocol: &Self::Protocol,
         io: &mut T,
     ) -> std::io::Result<Self::Request>
     where
         T: AsyncRead + Unpin + Send,
     {
-        let bytes = upgrade::read_length_prefixed(io, MAX_BUFFER_SIZE).await?;
-        QuantaSwapRequest::from_proto(bytes)
-            .map_err(|error| std::io::Error::new(std::io::ErrorKind::InvalidData, error))
+        let max_message_size = protocol.max_message_size(self.max_message_size);
+        let bytes = upgrade::read_length_prefixed(io, max_message_size).await?;
+        let request = QuantaSwapRequest::from_proto(bytes)
+            .map_err(|error| std::io::Error::new(std::io::ErrorKind::InvalidData, error))?;
+        ensure_supported(protocol, request.is_want_list())?;
+        Ok(NegotiatedRequest {
+            request,
+            protocol: Some(*protocol),
+        })
     }
 
     async fn read_response<T>(
         &mut self,
-        _protocol: &Self::Protocol,
+        protocol: &Self::Protocol,
         io: &mut T,
     ) -> std::io::Result<Self::Response>
     where
         T: AsyncRead + Unpin + Send,
     {
-        let bytes = upgrade::read_length_prefixed(io, MAX_BUFFER_SIZE).await?;
-        QuantaSwapRespone::from_proto(bytes)
-            .map_err(|error| std::io::Error::new(std::io::ErrorKind::InvalidData, error))
+        let max_message_size = protocol.max_message_size(self.max_message_size);
+        let bytes = upgrade::read_length_prefixed(io, max_message_size).await?;
+        let response = QuantaSwapRespone::from_proto(bytes)
+            .map_err(|error| std::io::Error::new(std::io::ErrorKind::InvalidData, error))?;
+        ensure_supported(protocol, response.is_want_list())?;
+        Ok(NegotiatedResponse {
+            response,
+            protocol: Some(*protocol),
+        })
     }
 
     async fn write_request<T>(
         &mut self,
-        _protocol: &Self::Protocol,
+        protocol: &Self::Protocol,
         io: &mut T,
         req: Self::Request,
     ) -> std::io::Result<()>
     where
         T: AsyncWrite + Unpin + Send,
     {
-        let bytes = req.to_proto();
-        upgrade::write_length_prefixed(io, bytes.as_slice()).await
+        ensure_supported(protocol, req.request.is_want_list())?;
+        self.write_message(protocol, io, req.request.to_proto())
+            .await
     }
 
     async fn write_response<T>(
         &mut self,
-        _protocol: &Self::Protocol,
+        protocol: &Self::Protocol,
         io: &mut T,
         res: Self::Response,
     ) -> std::io::Result<()>
     where
         T: AsyncWrite + Unpin + Send,
     {
-        let bytes = res.to_proto();
-        upgrade::write_length_prefixed(io, bytes.as_slice()).await
+        ensure_supported(protocol, res.response.is_want_list())?;
+        self.write_message(protocol, io, res.response.to_proto())
+            .await
     }
 }
@@ -0,0 +1,135 @@
+use std::{num::NonZeroUsize, time::Duration};
+
+/// Default time after which query is finished with [`crate::Event::QueryTimedOut`]
+const DEFAULT_QUERY_TIMEOUT: Duration = Duration::from_secs(30);
+/// Default max size of one message in [`crate::protocol::QuantaSwapProtocol::V2`]
+const DEFAULT_MAX_MESSAGE_SIZE: usize = 4 * 1024 * 1024;
+/// Default max count of requests per second that we are serve to one peer
+const DEFAULT_MAX_REQUESTS_PER_SECOND: u32 = 256;
+/// Default count of requests over rate limits after which peer is banned
+const DEFAULT_MAX_RATE_LIMIT_VIOLATIONS: u32 = 256;
+/// Default count of invalid items after which peer is banned
+const DEFAULT_MAX_INVALID_RESPONSES: u32 = 3;
+/// Default max count of requests which lookups in [`crate::Storage`] are resolving at once
+const DEFAULT_MAX_CONCURRENT_LOOKUPS: NonZeroUsize = NonZeroUsize::new(32).unwrap();
+/// Default max count of requests that wait for free slot of lookups
+const DEFAULT_MAX_QUEUED_REQUESTS: usize = 1024;
+/// Default time for which peer is banned
+const DEFAULT_BAN_DURATION: Duration = Duration::from_secs(10 * 60);
+
+/// Configuration of [`crate::Behaviour`]
+#[derive(Debug, Clone)]
+pub struct Config {
+    /// Time after which query is finished with [`crate::Event::QueryTimedOut`]
+    query_timeout: Duration,
+    /// Max size of one message that we are send or receive. Items bigger than this limit can
+    /// not be transferred
+    max_message_size: usize,
+    /// Max count of requests per second that we are serve to one peer. Requests over limit are
+    /// answered as if we do not have items. Not limited if None
+    max_requests_per_second: Option<u32>,
+    /// Max size in bytes of items per second that we are serve to one peer. Requests over limit
+    /// are answered as if we do not have items. Not limited if None
+    max_served_bytes_per_second: Option<u64>,
+    /// Count of requests over rate limits after which peer is banned
+    max_rate_limit_violations: u32,
+    /// Count of invalid items after which peer is banned
+    max_invalid_responses: u32,
+    /// Time for which peer is banned
+    ban_duration: Duration,
+    /// Max count of requests which lookups in [`crate::Storage`] are resolving at once. Other
+    /// requests wait in queue, requests of peers with lower debt ratio in [`crate::Ledger`] are
+    /// started first
+    max_concurrent_lookups: NonZeroUsize,
+    /// Max count of requests that wait for free slot of lookups. Requests over limit are
+    /// answered as if we do not have items
+    max_queued_requests: usize,
+}
+
+impl Config {
+    /// Set time after which query is finished with [`crate::Event::QueryTimedOut`]
+    pub fn with_query_timeout(mut self, query_timeout: Duration) -> Self {
+        self.query_timeout = query_timeout;
+        self
+    }
+    /// Set max size of one message. Peers that speak only first version of protocol always use
+    /// 2 KB limit
+    pub fn with_max_message_size(mut self, max_message_size: usize) -> Self {
+        self.max_message_size = max_message_size;
+        self
+    }
+    /// Set max count of requests per second that we are serve to one peer, None disables limit
+    pub fn with_max_requests_per_second(mut self, max_requests_per_second: Option<u32>) -> Self {
+        self.max_requests_per_second = max_requests_per_second;
+        self
+    }
+    /// Set max size in bytes of items per second that we are serve to one peer, None disables
+    /// limit
+    pub fn with_max_served_bytes_per_second(
+        mut self,
+        max_served_bytes_per_second: Option<u64>,
+    ) -> Self {
+        self.max_served_bytes_per_second = max_served_bytes_per_second;
+        self
+    }
+    /// Set count of requests over rate limits after which peer is banned. Violations that are
+    /// more than 10 seconds apart are not counted together
+    pub fn with_max_rate_limit_violations(mut self, max_rate_limit_violations: u32) -> Self {
+        self.max_rate_limit_violations = max_rate_limit_violations;
+        self
+    }
+    /// Set count of invalid items after which peer is banned
+    pub fn with_max_invalid_responses(mut self, max_invalid_responses: u32) -> Self {
+        self.max_invalid_responses = max_invalid_responses;
+        self
+    }
+    /// Set time for which peer is banned
+    pub fn with_ban_duration(mut self, ban_duration: Duration) -> Self {
+        self.ban_duration = ban_duration;
+        self
+    }
+    /// Set max count of requests which lookups in [`crate::Storage`] are resolving at once
+    pub fn with_max_concurrent_lookups(mut self, max_concurrent_lookups: NonZeroUsize) -> Self {
+        self.max_concurrent_lookups = max_concurrent_lookups;
+        self
+    }
+    /// Set max count of requests that wait for free slot of lookups
+    pub fn with_max_queued_requests(mut self, max_queued_requests: usize) -> Self {
+        self.max_queued_requests = max_queued_requests;
+        self
+    }
+    /// returns query timeout
+    pub fn query_timeout(&self) -> Duration { self.query_timeout }
+    /// returns max size of one message
+    pub fn max_message_size(&self) -> usize { self.max_message_size }
+    /// returns max count of requests per second of one peer
+    pub fn max_requests_per_second(&self) -> Option<u32> { self.max_requests_per_second }
+    /// returns max size of served items per second of one peer
+    pub fn max_served_bytes_per_second(&self) -> Option<u64> { self.max_served_bytes_per_second }
+    /// returns count of rate limit violations after which peer is banned
+    pub fn max_rate_limit_violations(&self) -> u32 { self.max_rate_limit_violations }
+    /// returns count of invalid items after which peer is banned
+    pub fn max_invalid_responses(&self) -> u32 { self.max_invalid_responses }
+    /// returns time for which peer is banned
+    pub fn ban_duration(&self) -> Duration { self.ban_duration }
+    /// returns max count of lookups that are resolving at once
+    pub fn max_concurrent_lookups(&self) -> NonZeroUsize { self.max_concurrent_lookups }
+    /// returns max count of requests that wait for free slot of lookups
+    pub fn max_queued_requests(&self) -> usize { self.max_queued_requests }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            query_timeout: DEFAULT_QUERY_TIMEOUT,
+            max_message_size: DEFAULT_MAX_MESSAGE_SIZE,
+            max_requests_per_second: Some(DEFAULT_MAX_REQUESTS_PER_SECOND),
+            max_served_bytes_per_second: None,
+            max_rate_limit_violations: DEFAULT_MAX_RATE_LIMIT_VIOLATIONS,
+            max_invalid_responses: DEFAULT_MAX_INVALID_RESPONSES,
+            ban_duration: DEFAULT_BAN_DURATION,
+            max_concurrent_lookups: DEFAULT_MAX_CONCURRENT_LOOKUPS,
+            max_queued_requests: DEFAULT_MAX_QUEUED_REQUESTS,
+        }
+    }
+}
@@ -0,0 +1,162 @@
+use std::{
+    collections::HashMap,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use fnv::FnvHashSet;
+use libp2p::PeerId;
+
+/// Ledger of peer that is disconnected is forgotten after this interval. It is saved into
+/// [`LedgerStorage`] before and is loaded again when peer connects
+const IDLE_LEDGER_RETENTION: Duration = Duration::from_secs(10 * 60);
+
+/// Bytes that we are exchanged with peer. Like ledger of Bitswap it is used to serve peers that
+/// send items to us before peers that only download from us
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Ledger {
+    /// Size in bytes of items that we are sent to peer
+    pub bytes_sent: u64,
+    /// Size in bytes of valid items that peer sent to us
+    pub bytes_received: u64,
+}
+
+impl Ledger {
+    /// Returns debt ratio of peer: bytes that we are sent to peer divided by bytes that peer sent
+    /// to us. Requests of peer with lower ratio are served first
+    pub fn debt_ratio(&self) -> f64 { self.bytes_sent as f64 / (self.bytes_received as f64 + 1.0) }
+    /// Add bytes of other ledger
+    fn merge(&mut self, other: Ledger) {
+        self.bytes_sent += other.bytes_sent;
+        self.bytes_received += other.bytes_received;
+    }
+}
+
+/// Storage of [`Ledger`]s, so peers keep their debt ratio after restart. Like [`crate::Storage`]
+/// it is called from futures that [`crate::Behaviour`] polls, so it should not block for long
+#[async_trait::async_trait]
+pub trait LedgerStorage: Send + Sync {
+    /// Load ledger of peer. Returns None if ledger of peer was never saved
+    async fn load(self: Arc<Self>, peer: PeerId) -> Option<Ledger>;
+    /// Save ledger of peer
+    async fn save(self: Arc<Self>, peer: PeerId, ledger: Ledger);
+}
+
+/// Ledgers of all peers that we are exchanged items with
+#[derive(Debug, Default)]
+pub(crate) struct Ledgers {
+    ledgers: HashMap<PeerId, Ledger>,
+    /// Peers which ledgers are loading from [`LedgerStorage`]. They are not saved until load is
+    /// finished, otherwise saved ledger would be overwritten with partial one
+    loading: FnvHashSet<PeerId>,
+    /// Peers which ledgers were loaded or are loading, so they are not loaded again
+    loaded: FnvHashSet<PeerId>,
+    /// Peers which ledgers were changed since last save
+    dirty: FnvHashSet<PeerId>,
+    /// Peers which ledgers are saving into [`LedgerStorage`]. Ledger is not saved again until
+    /// save is finished, so older save can not overwrite newer one
+    saving: FnvHashSet<PeerId>,
+    /// Peers that are disconnected and since when
+    disconnected: HashMap<PeerId, Instant>,
+}
+
+impl Ledgers {
+    /// Returns ledger of peer
+    pub(crate) fn get(&self, peer: &PeerId) -> Option<&Ledger> { self.ledgers.get(peer) }
+    /// Returns debt ratio of peer, peer without ledger has no debt
+    pub(crate) fn debt_ratio(&self, peer: &PeerId) -> f64 {
+        self.ledgers
+            .get(peer)
+            .map_or(0.0, Ledger::debt_ratio)
+    }
+    /// Account items that we are sent to peer
+    pub(crate) fn on_sent(&mut self, peer: PeerId, bytes: usize) {
+        self.ledgers
+            .entry(peer)
+            .or_default()
+            .bytes_sent += bytes as u64;
+        self.dirty.insert(peer);
+    }
+    /// Account valid items that peer sent to us
+    pub(crate) fn on_received(&mut self, peer: PeerId, bytes: usize) {
+        self.ledgers
+            .entry(peer)
+            .or_default()
+            .bytes_received += bytes as u64;
+        self.dirty.insert(peer);
+    }
+    /// Returns true if ledger of peer should be loaded from [`LedgerStorage`]. Peer is marked as
+    /// loading until [`Ledgers::on_loaded`] is called
+    pub(crate) fn start_loading(&mut self, peer: PeerId) -> bool {
+        self.disconnected.remove(&peer);
+        if !self.loaded.insert(peer) {
+            return false;
+        }
+        self.loading.insert(peer);
+        true
+    }
+    /// Add loaded ledger to bytes that were accounted while it was loading
+    pub(crate) fn on_loaded(&mut self, peer: PeerId, ledger: Option<Ledger>) {
+        self.loading.remove(&peer);
+        if let Some(ledger) = ledger {
+            self.ledgers
+                .entry(peer)
+                .or_default()
+                .merge(ledger);
+        }
+    }
+    /// Remember when peer was disconnected, its ledger is forgotten later with
+    /// [`Ledgers::forget_idle`]
+    pub(crate) fn on_disconnected(&mut self, peer: PeerId, now: Instant) {
+        self.disconnected.insert(peer, now);
+    }
+    /// Mark save of ledger of peer as finished, changes that were made while it was saving are
+    /// returned by [`Ledgers::take_dirty`] again
+    pub(crate) fn on_saved(&mut self, peer: PeerId) { self.saving.remove(&peer); }
+    /// Forget ledgers of peers that are disconnected longer than retention interval. Ledgers
+    /// that are loading or saving are kept, as well as ledgers that are not saved yet if they are
+    /// `saved` into [`LedgerStorage`]
+    pub(crate) fn forget_idle(&mut self, now: Instant, saved: bool) {
+        let idle = self
+            .disconnected
+            .iter()
+            .filter(|(peer, since)| {
+                let kept = self.loading.contains(*peer) ||
+                    self.saving.contains(*peer) ||
+                    (saved && self.dirty.contains(*peer));
+                now.saturating_duration_since(**since) >= IDLE_LEDGER_RETENTION && !kept
+            })
+            .map(|(peer, _)| *peer)
+            .collect::<Vec<PeerId>>();
+        for peer in idle {
+            self.disconnected.remove(&peer);
+            self.ledgers.remove(&peer);
+            self.loaded.remove(&peer);
+            self.dirty.remove(&peer);
+        }
+    }
+    /// Returns ledger of peer if it was changed since last save and it is not loading or saving.
+    /// Peer is marked as saving until [`Ledgers::on_saved`] is called
+    pub(crate) fn take_dirty(&mut self, peer: &PeerId) -> Option<Ledger> {
+        if self.loading.contains(peer) || self.saving.contains(peer) || !self.dirty.remove(peer) {
+            return None;
+        }
+        self.saving.insert(*peer);
+        self.ledgers.get(peer).copied()
+    }
+    /// Returns all ledgers that were changed since last save and are not loading or saving
+    pub(crate) fn take_all_dirty(&mut self) -> Vec<(PeerId, Ledger)> {
+        let peers = self
+            .dirty
+            .iter()
+            .copied()
+            .collect::<Vec<PeerId>>();
+        peers
+            .into_iter()
+            .filter_map(|peer| {
+                self.take_dirty(&peer)
+                    .map(|ledger| (peer, ledger))
+            })
+            .collect()
+    }
+}
@@ -1,17 +1,30 @@
 #![allow(dead_code)]
 mod behaviour;
 mod codec;
+mod config;
+mod ledger;
 mod protobuffable;
 mod protocol;
+mod query;
+mod queue;
 mod request;
 mod response;
+mod score;
 mod searchid;
 #[cfg(test)]
 mod test;
+mod validator;
+mod want;
 
 pub use behaviour::{Behaviour, Event, Storage};
+pub use config::Config;
+pub use ledger::{Ledger, LedgerStorage};
+pub use score::{BanReason, PeerStats};
 pub use searchid::SearchID;
+pub use validator::{AcceptAllValidator, Validator};
 
+// generated code, variants names come from swap_pb.proto
+#[allow(clippy::enum_variant_names)]
 mod swap_pb {
     include!(concat!(env!("OUT_DIR"), "/swap_pb.rs"));
 }
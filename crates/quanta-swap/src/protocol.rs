@@ -1,10 +1,50 @@
 use libp2p::request_response::ProtocolName;
 
-const PROTOCOL_NAME: &[u8] = b"/quanta/swap/0.0.1";
+/// First version of protocol. Messages are limited with [`LEGACY_MAX_MESSAGE_SIZE`] and want
+/// lists are not supported
+const PROTOCOL_NAME_V1: &[u8] = b"/quanta/swap/0.0.1";
+/// Second version of protocol. Messages are limited with [`crate::Config::max_message_size`] and
+/// want lists are supported
+const PROTOCOL_NAME_V2: &[u8] = b"/quanta/swap/0.0.2";
 
-#[derive(Debug, Clone)]
-pub struct QuantaSwapProtocol;
+/// Max size of one message in [`QuantaSwapProtocol::V1`]. Peers that speak only first version
+/// reject bigger messages
+pub(crate) const LEGACY_MAX_MESSAGE_SIZE: usize = 512 * 4;
+
+/// Versions of quanta swap protocol. Version is negotiated on every substream, so peers that
+/// support only first version can still exchange small items
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QuantaSwapProtocol {
+    /// Messages are limited with [`LEGACY_MAX_MESSAGE_SIZE`], only queries are supported
+    V1,
+    /// Max size of message is set in [`crate::Config`], queries and want lists are supported
+    V2,
+}
+
+impl QuantaSwapProtocol {
+    /// All supported versions in order of preference
+    pub(crate) fn supported() -> [Self; 2] { [Self::V2, Self::V1] }
+    /// Max size of one message in this version of protocol
+    pub(crate) fn max_message_size(&self, configured: usize) -> usize {
+        match self {
+            Self::V1 => LEGACY_MAX_MESSAGE_SIZE,
+            Self::V2 => configured,
+        }
+    }
+    /// Check if want list messages can be sent in this version of protocol
+    pub(crate) fn supports_want_lists(&self) -> bool {
+        match self {
+            Self::V1 => false,
+            Self::V2 => true,
+        }
+    }
+}
 
 impl ProtocolName for QuantaSwapProtocol {
-    fn protocol_name(&self) -> &[u8] { PROTOCOL_NAME }
+    fn protocol_name(&self) -> &[u8] {
+        match self {
+            Self::V1 => PROTOCOL_NAME_V1,
+            Self::V2 => PROTOCOL_NAME_V2,
+        }
+    }
 }
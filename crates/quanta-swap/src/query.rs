@@ -0,0 +1,32 @@
+use std::time::{Duration, Instant};
+
+use fnv::FnvHashSet;
+use libp2p::PeerId;
+
+/// Active search of item in network
+#[derive(Debug, Clone)]
+pub(crate) struct Query {
+    /// Key in [`crate::Storage`] that peer looking for
+    pub(crate) searching: Vec<u8>,
+    /// After this moment query is finished with [`crate::Event::QueryTimedOut`]
+    pub(crate) deadline: Instant,
+    /// Peers that answered that they does not have item
+    pub(crate) not_found: FnvHashSet<PeerId>,
+    /// Providers of item that we are dialing. Query is not finished with
+    /// [`crate::Event::QueryNotFound`] until they answer or dial fails
+    pub(crate) pending_peers: FnvHashSet<PeerId>,
+}
+
+impl Query {
+    /// Create new [`Query`] that expires after timeout
+    pub(crate) fn new(searching: Vec<u8>, timeout: Duration) -> Self {
+        Self {
+            searching,
+            deadline: Instant::now() + timeout,
+            not_found: FnvHashSet::default(),
+            pending_peers: FnvHashSet::default(),
+        }
+    }
+    /// Check if deadline of query is reached
+    pub(crate) fn is_expired(&self, now: Instant) -> bool { self.deadline <= now }
+}
@@ -0,0 +1,103 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    time::Instant,
+};
+
+use libp2p::PeerId;
+
+use crate::ledger::Ledgers;
+
+/// Request that waits in [`LookupQueue`]
+#[derive(Debug)]
+struct Queued<T> {
+    /// Order of arrival of request among all requests
+    seq: u64,
+    /// When request was queued
+    queued_at: Instant,
+    request: T,
+}
+
+/// Requests of peers that wait for free slot of lookups in [`crate::Storage`]. Requests of one
+/// peer are kept in order of arrival, so next request is chosen among peers and not among all
+/// queued requests
+#[derive(Debug)]
+pub(crate) struct LookupQueue<T> {
+    /// Queued requests of every peer, peers without requests are removed
+    peers: HashMap<PeerId, VecDeque<Queued<T>>>,
+    /// Count of queued requests of all peers
+    len: usize,
+    /// Order of arrival of the next request
+    next_seq: u64,
+}
+
+impl<T> Default for LookupQueue<T> {
+    fn default() -> Self {
+        Self {
+            peers: HashMap::default(),
+            len: 0,
+            next_seq: 0,
+        }
+    }
+}
+
+impl<T> LookupQueue<T> {
+    /// Returns count of queued requests
+    pub(crate) fn len(&self) -> usize { self.len }
+    /// Add request of peer to the end of its queue
+    pub(crate) fn push(&mut self, peer: PeerId, request: T, now: Instant) {
+        self.peers
+            .entry(peer)
+            .or_default()
+            .push_back(Queued {
+                seq: self.next_seq,
+                queued_at: now,
+                request,
+            });
+        self.next_seq += 1;
+        self.len += 1;
+    }
+    /// Take the first request of peer with the lowest debt ratio in [`Ledgers`]. Requests of
+    /// peers with the same ratio are taken in order of arrival
+    pub(crate) fn pop(&mut self, ledgers: &Ledgers) -> Option<(PeerId, T)> {
+        let (peer, _) = self
+            .peers
+            .iter()
+            .filter_map(|(peer, queued)| Some((*peer, queued.front()?.seq)))
+            .min_by(|(first, first_seq), (second, second_seq)| {
+                ledgers
+                    .debt_ratio(first)
+                    .total_cmp(&ledgers.debt_ratio(second))
+                    .then(first_seq.cmp(second_seq))
+            })?;
+        let queued = self.peers.get_mut(&peer)?;
+        let request = queued.pop_front()?.request;
+        if queued.is_empty() {
+            self.peers.remove(&peer);
+        }
+        self.len -= 1;
+        Some((peer, request))
+    }
+    /// Remove all requests of peer
+    pub(crate) fn remove_peer(&mut self, peer: &PeerId) {
+        if let Some(queued) = self.peers.remove(peer) {
+            self.len -= queued.len();
+        }
+    }
+    /// Remove requests that were queued before given moment. Returns count of removed requests
+    pub(crate) fn remove_queued_before(&mut self, moment: Instant) -> usize {
+        let mut removed = 0;
+        self.peers.retain(|_, queued| {
+            // requests of peer are in order of arrival, so expired requests are the first
+            while queued
+                .front()
+                .is_some_and(|first| first.queued_at < moment)
+            {
+                queued.pop_front();
+                removed += 1;
+            }
+            !queued.is_empty()
+        });
+        self.len -= removed;
+        removed
+    }
+}
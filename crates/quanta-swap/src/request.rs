@@ -10,6 +10,8 @@ use crate::{
 
 const QUERY_MESSAGE_TYPE: i32 = 0;
 const QUERY_WANT_MESSAGE_TYPE: i32 = 1;
+const WANT_HAVE_MESSAGE_TYPE: i32 = 2;
+const WANT_BLOCKS_MESSAGE_TYPE: i32 = 3;
 
 /// Requests which sends over network
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
@@ -28,6 +30,31 @@ pub enum QuantaSwapRequest {
         /// Key in [`crate::storage::Storage`]
         searching: Vec<u8>,
     },
+    /// Check which of keys peer have
+    WantHave {
+        /// Unique ID
+        search_id: SearchID,
+        /// Keys in [`crate::storage::Storage`]
+        keys: Vec<Vec<u8>>,
+    },
+    /// Get items of keys from peer
+    WantBlocks {
+        /// Unique ID
+        search_id: SearchID,
+        /// Keys in [`crate::storage::Storage`]
+        keys: Vec<Vec<u8>>,
+    },
+}
+
+impl QuantaSwapRequest {
+    /// Check if request is a part of want list. Want lists are sent only over
+    /// [`crate::protocol::QuantaSwapProtocol::V2`]
+    pub(crate) fn is_want_list(&self) -> bool {
+        matches!(
+            self,
+            QuantaSwapRequest::WantHave { .. } | QuantaSwapRequest::WantBlocks { .. }
+        )
+    }
 }
 
 impl Protobuffable for QuantaSwapRequest {
@@ -53,6 +80,22 @@ impl Protobuffable for QuantaSwapRequest {
                     searching: query_want.searching,
                 })
             },
+            WANT_HAVE_MESSAGE_TYPE => {
+                let want_have =
+                    swap_pb::proto_request::ProtoWantHave::decode(proto.message.as_slice())?;
+                Ok(Self::WantHave {
+                    search_id: SearchID::from_proto(want_have.search_id)?,
+                    keys: want_have.keys,
+                })
+            },
+            WANT_BLOCKS_MESSAGE_TYPE => {
+                let want_blocks =
+                    swap_pb::proto_request::ProtoWantBlocks::decode(proto.message.as_slice())?;
+                Ok(Self::WantBlocks {
+                    search_id: SearchID::from_proto(want_blocks.search_id)?,
+                    keys: want_blocks.keys,
+                })
+            },
             _ => Err(ProtobuffableError::InvalidProtoMessageType),
         }
     }
@@ -76,10 +119,26 @@ impl Protobuffable for QuantaSwapRequest {
                     searching: searching.to_vec(),
                 }
                 .encode_to_vec(),
+                QuantaSwapRequest::WantHave { search_id, keys } => {
+                    swap_pb::proto_request::ProtoWantHave {
+                        search_id: search_id.to_proto(),
+                        keys: keys.to_vec(),
+                    }
+                    .encode_to_vec()
+                },
+                QuantaSwapRequest::WantBlocks { search_id, keys } => {
+                    swap_pb::proto_request::ProtoWantBlocks {
+                        search_id: search_id.to_proto(),
+                        keys: keys.to_vec(),
+                    }
+                    .encode_to_vec()
+                },
             },
             pb_type: match self {
                 QuantaSwapRequest::Query { .. } => QUERY_MESSAGE_TYPE,
                 QuantaSwapRequest::QueryWant { .. } => QUERY_WANT_MESSAGE_TYPE,
+                QuantaSwapRequest::WantHave { .. } => WANT_HAVE_MESSAGE_TYPE,
+                QuantaSwapRequest::WantBlocks { .. } => WANT_BLOCKS_MESSAGE_TYPE,
             },
         }
         .encode_to_vec()
@@ -95,6 +154,22 @@ impl Display for QuantaSwapRequest {
             QuantaSwapRequest::QueryWant { search_id, .. } => {
                 write!(f, "[QuantaSwapRequest::QueryWant], SEARCH_ID={}", search_id)
             },
+            QuantaSwapRequest::WantHave { search_id, keys } => {
+                write!(
+                    f,
+                    "[QuantaSwapRequest::WantHave], SEARCH_ID={}, KEYS={}",
+                    search_id,
+                    keys.len()
+                )
+            },
+            QuantaSwapRequest::WantBlocks { search_id, keys } => {
+                write!(
+                    f,
+                    "[QuantaSwapRequest::WantBlocks], SEARCH_ID={}, KEYS={}",
+                    search_id,
+                    keys.len()
+                )
+            },
         }
     }
 }
@@ -10,9 +10,11 @@ use crate::{
 
 const QUERY_RESPONSE_MESSAGE_TYPE: i32 = 0;
 const QUERY_WANT_RESPONSE_MESSAGE_TYPE: i32 = 1;
+const HAVE_RESPONSE_MESSAGE_TYPE: i32 = 2;
+const BLOCKS_RESPONSE_MESSAGE_TYPE: i32 = 3;
 
 /// Responses which sends over network
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum QuantaSwapRespone {
     /// Response of [`crate::request::QuantaSwapRequest`]
     Query {
@@ -28,6 +30,32 @@ pub enum QuantaSwapRespone {
         /// Item that peer searching
         item: Vec<u8>,
     },
+    /// Response on [`crate::request::QuantaSwapRequest::WantHave`]
+    Have {
+        /// Unique ID
+        search_id: SearchID,
+        /// Bitmap where bit with index of key in request is set if peer have it
+        have: Vec<u8>,
+    },
+    /// Response on [`crate::request::QuantaSwapRequest::WantBlocks`]. Contains only items that
+    /// peer have and that fits into one message
+    Blocks {
+        /// Unique ID
+        search_id: SearchID,
+        /// Keys with items
+        blocks: Vec<(Vec<u8>, Vec<u8>)>,
+    },
+}
+
+impl QuantaSwapRespone {
+    /// Check if response is a part of want list. Want lists are sent only over
+    /// [`crate::protocol::QuantaSwapProtocol::V2`]
+    pub(crate) fn is_want_list(&self) -> bool {
+        matches!(
+            self,
+            QuantaSwapRespone::Have { .. } | QuantaSwapRespone::Blocks { .. }
+        )
+    }
 }
 
 impl Protobuffable for QuantaSwapRespone {
@@ -55,6 +83,26 @@ impl Protobuffable for QuantaSwapRespone {
                     item: query_want_response.item,
                 })
             },
+            HAVE_RESPONSE_MESSAGE_TYPE => {
+                let have_response =
+                    swap_pb::proto_response::ProtoHaveResponse::decode(proto.message.as_slice())?;
+                Ok(Self::Have {
+                    search_id: SearchID::from_proto(have_response.search_id)?,
+                    have: have_response.have,
+                })
+            },
+            BLOCKS_RESPONSE_MESSAGE_TYPE => {
+                let blocks_response =
+                    swap_pb::proto_response::ProtoBlocksResponse::decode(proto.message.as_slice())?;
+                Ok(Self::Blocks {
+                    search_id: SearchID::from_proto(blocks_response.search_id)?,
+                    blocks: blocks_response
+                        .blocks
+                        .into_iter()
+                        .map(|block| (block.key, block.item))
+                        .collect(),
+                })
+            },
             _ => Err(ProtobuffableError::InvalidProtoMessageType),
         }
     }
@@ -76,10 +124,32 @@ impl Protobuffable for QuantaSwapRespone {
                     }
                     .encode_to_vec()
                 },
+                QuantaSwapRespone::Have { search_id, have } => {
+                    swap_pb::proto_response::ProtoHaveResponse {
+                        search_id: search_id.to_proto(),
+                        have: have.to_vec(),
+                    }
+                    .encode_to_vec()
+                },
+                QuantaSwapRespone::Blocks { search_id, blocks } => {
+                    swap_pb::proto_response::ProtoBlocksResponse {
+                        search_id: search_id.to_proto(),
+                        blocks: blocks
+                            .iter()
+                            .map(|(key, item)| swap_pb::proto_response::ProtoBlock {
+                                key: key.to_vec(),
+                                item: item.to_vec(),
+                            })
+                            .collect(),
+                    }
+                    .encode_to_vec()
+                },
             },
             pb_type: match self {
                 QuantaSwapRespone::Query { .. } => QUERY_RESPONSE_MESSAGE_TYPE,
                 QuantaSwapRespone::QueryWant { .. } => QUERY_WANT_RESPONSE_MESSAGE_TYPE,
+                QuantaSwapRespone::Have { .. } => HAVE_RESPONSE_MESSAGE_TYPE,
+                QuantaSwapRespone::Blocks { .. } => BLOCKS_RESPONSE_MESSAGE_TYPE,
             },
         }
         .encode_to_vec()
@@ -103,6 +173,17 @@ impl Display for QuantaSwapRespone {
                     search_id
                 )
             },
+            QuantaSwapRespone::Have { search_id, .. } => {
+                write!(f, "[QuantaSwapResponse::Have], SEARCH_ID={}", search_id)
+            },
+            QuantaSwapRespone::Blocks { search_id, blocks } => {
+                write!(
+                    f,
+                    "[QuantaSwapResponse::Blocks], SEARCH_ID={}, BLOCKS={}",
+                    search_id,
+                    blocks.len()
+                )
+            },
         }
     }
 }
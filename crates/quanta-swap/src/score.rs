@@ -0,0 +1,258 @@
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+use fnv::FnvHashSet;
+use libp2p::PeerId;
+
+use crate::config::Config;
+
+/// Rate limit violations that are further apart than this interval are not counted together,
+/// so peer that sometimes sends burst of requests is not banned
+const VIOLATION_RESET_INTERVAL: Duration = Duration::from_secs(10);
+/// Accounting of peer that is not connected and not banned is forgotten after this interval
+const IDLE_PEER_RETENTION: Duration = Duration::from_secs(60 * 60);
+
+/// Reason of ban in [`crate::Event::PeerBanned`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BanReason {
+    /// Peer sent too many items that were rejected by [`crate::Validator`]
+    InvalidResponses,
+    /// Peer sent too many requests over [`Config::max_requests_per_second`] or
+    /// [`Config::max_served_bytes_per_second`]
+    RateLimited,
+}
+
+/// Accounting of one peer in [`crate::Behaviour`]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PeerStats {
+    /// Count of requests that peer sent to us
+    pub requests: u64,
+    /// Count of requests that were not served because peer exceeded rate limits
+    pub rate_limited: u64,
+    /// Count of items that we are sent to peer
+    pub items_served: u64,
+    /// Size in bytes of items that we are sent to peer
+    pub bytes_served: u64,
+    /// Count of items from peer that were rejected by [`crate::Validator`]
+    pub invalid_responses: u64,
+}
+
+/// What [`crate::Behaviour`] should do with request of peer
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum RequestVerdict {
+    /// Request is within limits and should be served
+    Allowed,
+    /// Request is over limits or peer is banned, so request is not served
+    Limited,
+    /// Request is over limits and peer reached max count of violations, so it should be banned
+    Ban,
+}
+
+/// Token bucket that is refilled with constant rate. Capacity of bucket is one second of rate
+#[derive(Debug, Clone)]
+struct TokenBucket {
+    /// Tokens that can be taken now. Can be negative when more was taken than there was
+    tokens: f64,
+    /// Tokens that are added per second
+    rate: f64,
+    /// Last time when tokens were added
+    updated: Instant,
+}
+
+impl TokenBucket {
+    /// Create full bucket
+    fn new(rate: u64, now: Instant) -> Self {
+        TokenBucket {
+            tokens: rate as f64,
+            rate: rate as f64,
+            updated: now,
+        }
+    }
+    /// Add tokens for time that is passed since last refill
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now
+            .saturating_duration_since(self.updated)
+            .as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.rate).min(self.rate);
+        self.updated = now;
+    }
+    /// Returns true if there are tokens that can be taken
+    fn has_tokens(&mut self, now: Instant) -> bool {
+        self.refill(now);
+        self.tokens >= 1.0
+    }
+    /// Take tokens. Bucket goes into debt if there are not enough tokens, so big item is
+    /// served but next requests wait until debt is paid
+    fn take(&mut self, amount: u64) { self.tokens -= amount as f64; }
+}
+
+/// Accounting and limits of one peer
+#[derive(Debug, Clone)]
+struct PeerScore {
+    stats: PeerStats,
+    /// Limit of requests per second, None if not limited
+    requests: Option<TokenBucket>,
+    /// Limit of served bytes per second, None if not limited
+    bytes: Option<TokenBucket>,
+    /// Violations of rate limits since last reset
+    rate_limit_violations: u32,
+    /// Last time when peer exceeded rate limits
+    last_violation: Option<Instant>,
+    /// Invalid items that peer sent since last ban
+    invalid_responses: u32,
+    /// Time until peer is banned
+    banned_until: Option<Instant>,
+    /// Last time when peer sent request or response to us
+    last_seen: Instant,
+}
+
+impl PeerScore {
+    fn new(config: &Config, now: Instant) -> Self {
+        PeerScore {
+            stats: PeerStats::default(),
+            requests: config
+                .max_requests_per_second()
+                .map(|rate| TokenBucket::new(rate.into(), now)),
+            bytes: config
+                .max_served_bytes_per_second()
+                .map(|rate| TokenBucket::new(rate, now)),
+            rate_limit_violations: 0,
+            last_violation: None,
+            invalid_responses: 0,
+            banned_until: None,
+            last_seen: now,
+        }
+    }
+    fn is_banned(&self, now: Instant) -> bool {
+        self.banned_until
+            .is_some_and(|banned_until| banned_until > now)
+    }
+}
+
+/// Accounting of all peers that sent requests or responses to [`crate::Behaviour`]. It decides
+/// which requests are served and when peer should be banned
+#[derive(Debug, Default)]
+pub(crate) struct PeerScores {
+    peers: HashMap<PeerId, PeerScore>,
+}
+
+impl PeerScores {
+    /// Returns score of peer, new score is created if peer is not known
+    fn score_mut(&mut self, peer: PeerId, config: &Config, now: Instant) -> &mut PeerScore {
+        let score = self
+            .peers
+            .entry(peer)
+            .or_insert_with(|| PeerScore::new(config, now));
+        score.last_seen = now;
+        score
+    }
+    /// Account request of peer and check it against rate limits
+    pub(crate) fn on_request(
+        &mut self,
+        peer: PeerId,
+        config: &Config,
+        now: Instant,
+    ) -> RequestVerdict {
+        let score = self.score_mut(peer, config, now);
+        score.stats.requests += 1;
+        if score.is_banned(now) {
+            score.stats.rate_limited += 1;
+            return RequestVerdict::Limited;
+        }
+        let has_requests = score
+            .requests
+            .as_mut()
+            .is_none_or(|bucket| bucket.has_tokens(now));
+        let has_bytes = score
+            .bytes
+            .as_mut()
+            .is_none_or(|bucket| bucket.has_tokens(now));
+        if has_requests && has_bytes {
+            if let Some(bucket) = &mut score.requests {
+                bucket.take(1);
+            }
+            return RequestVerdict::Allowed;
+        }
+        score.stats.rate_limited += 1;
+        let is_burst = score
+            .last_violation
+            .is_some_and(|last| now.saturating_duration_since(last) < VIOLATION_RESET_INTERVAL);
+        if !is_burst {
+            score.rate_limit_violations = 0;
+        }
+        score.rate_limit_violations += 1;
+        score.last_violation = Some(now);
+        if score.rate_limit_violations >= config.max_rate_limit_violations() {
+            return RequestVerdict::Ban;
+        }
+        RequestVerdict::Limited
+    }
+    /// Account items that we are sent to peer
+    pub(crate) fn on_served(&mut self, peer: PeerId, items: usize, bytes: usize, config: &Config) {
+        let score = self.score_mut(peer, config, Instant::now());
+        score.stats.items_served += items as u64;
+        score.stats.bytes_served += bytes as u64;
+        if let Some(bucket) = &mut score.bytes {
+            bucket.take(bytes as u64);
+        }
+    }
+    /// Account invalid item that peer sent. Returns true if peer reached max count of invalid
+    /// items and should be banned
+    pub(crate) fn on_invalid_response(&mut self, peer: PeerId, config: &Config) -> bool {
+        let score = self.score_mut(peer, config, Instant::now());
+        score.stats.invalid_responses += 1;
+        score.invalid_responses += 1;
+        score.invalid_responses >= config.max_invalid_responses()
+    }
+    /// Ban peer until given time. Counters of violations are reset, so peer starts from scratch
+    /// when ban expires
+    pub(crate) fn ban(&mut self, peer: PeerId, config: &Config, until: Instant) {
+        let now = Instant::now();
+        let score = self.score_mut(peer, config, now);
+        score.banned_until = Some(until);
+        score.rate_limit_violations = 0;
+        score.last_violation = None;
+        score.invalid_responses = 0;
+    }
+    /// Returns true if peer is banned now
+    pub(crate) fn is_banned(&self, peer: &PeerId, now: Instant) -> bool {
+        self.peers
+            .get(peer)
+            .is_some_and(|score| score.is_banned(now))
+    }
+    /// Returns accounting of peer
+    pub(crate) fn stats(&self, peer: &PeerId) -> Option<&PeerStats> {
+        self.peers
+            .get(peer)
+            .map(|score| &score.stats)
+    }
+    /// Remove bans that are expired and returns peers which bans were removed. Accounting of
+    /// peers that are not connected for a long time is forgotten
+    pub(crate) fn remove_expired(
+        &mut self,
+        connections: &FnvHashSet<PeerId>,
+        now: Instant,
+    ) -> Vec<PeerId> {
+        let mut unbanned = Vec::new();
+        self.peers.retain(|peer, score| {
+            if score
+                .banned_until
+                .is_some_and(|banned_until| banned_until <= now)
+            {
+                score.banned_until = None;
+                unbanned.push(*peer);
+            }
+            score.banned_until.is_some() ||
+                connections.contains(peer) ||
+                now.saturating_duration_since(score.last_seen) < IDLE_PEER_RETENTION
+        });
+        unbanned
+    }
+}
+
+/// Cause of [`libp2p::swarm::ConnectionDenied`] for peer that is banned
+#[derive(thiserror::Error, Debug)]
+#[error("Peer {0} is banned")]
+pub(crate) struct PeerBannedError(pub(crate) PeerId);
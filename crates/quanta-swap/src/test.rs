@@ -1,6 +1,49 @@
-use libp2p::PeerId;
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
 
-use crate::{protobuffable::Protobuffable, request::QuantaSwapRequest, searchid::SearchID};
+use futures::{io::Cursor, FutureExt, StreamExt};
+use libp2p::{
+    core::{transport::MemoryTransport, upgrade, ConnectedPoint, Endpoint},
+    identity::Keypair,
+    noise,
+    request_response::{self, Codec, ProtocolSupport},
+    swarm::{
+        behaviour::{ConnectionClosed, ConnectionEstablished},
+        ConnectionId,
+        FromSwarm,
+        NetworkBehaviour,
+        SwarmBuilder,
+        SwarmEvent,
+    },
+    yamux,
+    Multiaddr,
+    PeerId,
+    Swarm,
+    Transport,
+};
+
+use crate::{
+    codec::QuantaSwapCodec,
+    ledger::Ledgers,
+    protobuffable::Protobuffable,
+    protocol::{QuantaSwapProtocol, LEGACY_MAX_MESSAGE_SIZE},
+    queue::LookupQueue,
+    request::QuantaSwapRequest,
+    response::QuantaSwapRespone,
+    searchid::SearchID,
+    want,
+    BanReason,
+    Behaviour,
+    Config,
+    Event,
+    Ledger,
+    LedgerStorage,
+    Storage,
+    Validator,
+};
 
 #[test]
 fn test_query_id() {
@@ -62,3 +105,1148 @@ fn test_request_query_want() {
     let from_proto_request = QuantaSwapRequest::from_proto(proto_bytes_request).unwrap();
     assert_eq!(request, from_proto_request);
 }
+
+#[test]
+fn test_request_want_have() {
+    let request = QuantaSwapRequest::WantHave {
+        search_id: SearchID::random(),
+        keys: Vec::from([b"beep".to_vec(), b"boop".to_vec()]),
+    };
+    let proto_bytes_request = request.to_proto();
+    let from_proto_request = QuantaSwapRequest::from_proto(proto_bytes_request).unwrap();
+    assert_eq!(request, from_proto_request);
+}
+
+#[test]
+fn test_request_want_blocks() {
+    let request = QuantaSwapRequest::WantBlocks {
+        search_id: SearchID::random(),
+        keys: Vec::from([b"beep".to_vec(), b"boop".to_vec()]),
+    };
+    let proto_bytes_request = request.to_proto();
+    let from_proto_request = QuantaSwapRequest::from_proto(proto_bytes_request).unwrap();
+    assert_eq!(request, from_proto_request);
+}
+
+#[test]
+fn test_response_have_and_blocks() {
+    let have = QuantaSwapRespone::Have {
+        search_id: SearchID::random(),
+        have: want::to_bitmap(&[true, false, true]),
+    };
+    assert_eq!(
+        have,
+        QuantaSwapRespone::from_proto(have.to_proto()).unwrap()
+    );
+    let blocks = QuantaSwapRespone::Blocks {
+        search_id: SearchID::random(),
+        blocks: Vec::from([(b"beep".to_vec(), b"boop".to_vec())]),
+    };
+    assert_eq!(
+        blocks,
+        QuantaSwapRespone::from_proto(blocks.to_proto()).unwrap()
+    );
+}
+
+#[test]
+fn test_bitmap() {
+    let flags = (0..20)
+        .map(|idx| idx % 3 == 0)
+        .collect::<Vec<bool>>();
+    let bitmap = want::to_bitmap(flags.as_slice());
+    assert_eq!(bitmap.len(), 3);
+    for (idx, flag) in flags.iter().enumerate() {
+        assert_eq!(want::is_set(bitmap.as_slice(), idx), *flag);
+    }
+    assert!(!want::is_set(bitmap.as_slice(), 100));
+}
+
+#[test]
+fn test_codec_message_size_per_protocol() {
+    async_std::task::block_on(async {
+        let mut codec = QuantaSwapCodec::new(64 * 1024);
+        let response = QuantaSwapRespone::QueryWant {
+            search_id: SearchID::random(),
+            item: vec![7; LEGACY_MAX_MESSAGE_SIZE * 4],
+        };
+        // first version of protocol can not carry item bigger than 2 KB
+        let mut io = Cursor::new(Vec::new());
+        assert!(codec
+            .write_response(&QuantaSwapProtocol::V1, &mut io, response.clone().into())
+            .await
+            .is_err());
+        // second version can
+        let mut io = Cursor::new(Vec::new());
+        codec
+            .write_response(&QuantaSwapProtocol::V2, &mut io, response.clone().into())
+            .await
+            .unwrap();
+        let mut io = Cursor::new(io.into_inner());
+        let read = codec
+            .read_response(&QuantaSwapProtocol::V2, &mut io)
+            .await
+            .unwrap();
+        assert_eq!(read.response, response);
+        assert_eq!(read.protocol, Some(QuantaSwapProtocol::V2));
+        // but reading it over first version fails
+        io.set_position(0);
+        assert!(codec
+            .read_response(&QuantaSwapProtocol::V1, &mut io)
+            .await
+            .is_err());
+        // want lists are sent only over second version
+        let request = QuantaSwapRequest::WantHave {
+            search_id: SearchID::random(),
+            keys: Vec::from([b"beep".to_vec()]),
+        };
+        let mut io = Cursor::new(Vec::new());
+        assert!(codec
+            .write_request(&QuantaSwapProtocol::V1, &mut io, request.clone().into())
+            .await
+            .is_err());
+        let mut io = Cursor::new(Vec::new());
+        codec
+            .write_request(&QuantaSwapProtocol::V2, &mut io, request.clone().into())
+            .await
+            .unwrap();
+        let mut io = Cursor::new(io.into_inner());
+        let read = codec
+            .read_request(&QuantaSwapProtocol::V2, &mut io)
+            .await
+            .unwrap();
+        assert_eq!(read.request, request);
+        assert_eq!(read.protocol, Some(QuantaSwapProtocol::V2));
+        io.set_position(0);
+        assert!(codec
+            .read_request(&QuantaSwapProtocol::V1, &mut io)
+            .await
+            .is_err());
+    });
+}
+
+/// Storage that used in tests. Items can not be changed after creation
+struct MemoryStorage(HashMap<Vec<u8>, Vec<u8>>);
+
+#[async_trait::async_trait]
+impl Storage for MemoryStorage {
+    async fn exists(self: Arc<Self>, key: Vec<u8>) -> bool { self.0.contains_key(&key) }
+
+    async fn get(self: Arc<Self>, key: Vec<u8>) -> Option<Vec<u8>> { self.0.get(&key).cloned() }
+}
+
+/// Ledger storage that used in tests
+#[derive(Default)]
+struct MemoryLedgerStorage(Mutex<HashMap<PeerId, Ledger>>);
+
+#[async_trait::async_trait]
+impl LedgerStorage for MemoryLedgerStorage {
+    async fn load(self: Arc<Self>, peer: PeerId) -> Option<Ledger> {
+        self.0
+            .lock()
+            .unwrap()
+            .get(&peer)
+            .copied()
+    }
+
+    async fn save(self: Arc<Self>, peer: PeerId, ledger: Ledger) {
+        self.0
+            .lock()
+            .unwrap()
+            .insert(peer, ledger);
+    }
+}
+
+/// Validator that accept item only if it equals the key
+struct KeyEqualsItemValidator;
+
+impl Validator for KeyEqualsItemValidator {
+    fn validate(&self, key: &[u8], item: &[u8]) -> bool { key == item }
+}
+
+/// Storage that resolves lookups of slow key only after delay
+struct SlowStorage {
+    /// Items of storage
+    items: HashMap<Vec<u8>, Vec<u8>>,
+    /// Key which lookups are slow
+    slow_key: Vec<u8>,
+    /// Delay of lookups of slow key
+    delay: Duration,
+}
+
+impl SlowStorage {
+    async fn wait_if_slow(&self, key: &[u8]) {
+        if key == self.slow_key.as_slice() {
+            async_std::task::sleep(self.delay).await;
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl Storage for SlowStorage {
+    async fn exists(self: Arc<Self>, key: Vec<u8>) -> bool {
+        self.wait_if_slow(&key).await;
+        self.items.contains_key(&key)
+    }
+
+    async fn get(self: Arc<Self>, key: Vec<u8>) -> Option<Vec<u8>> {
+        self.wait_if_slow(&key).await;
+        self.items.get(&key).cloned()
+    }
+}
+
+/// Create new swarm over memory transport
+fn memory_swarm<S: Storage + 'static>(behaviour: Behaviour<S>) -> Swarm<Behaviour<S>> {
+    let keypair = Keypair::generate_ed25519();
+    let local_peer_id = PeerId::from(keypair.public());
+    let transport = MemoryTransport::default()
+        .upgrade(upgrade::Version::V1)
+        .authenticate(noise::Config::new(&keypair).unwrap())
+        .multiplex(yamux::Config::default())
+        .boxed();
+    SwarmBuilder::with_async_std_executor(transport, behaviour, local_peer_id).build()
+}
+
+/// Connect two swarms. First swarm listens and second one dials
+async fn connect<A, B>(listener: &mut Swarm<Behaviour<A>>, dialer: &mut Swarm<Behaviour<B>>)
+where
+    A: Storage + 'static,
+    B: Storage + 'static,
+{
+    listener
+        .listen_on("/memory/0".parse().unwrap())
+        .unwrap();
+    loop {
+        if let SwarmEvent::NewListenAddr { address, .. } = listener.select_next_some().await {
+            dialer.dial(address).unwrap();
+            return;
+        }
+    }
+}
+
+/// Poll both swarms until second one emits [`Event`] that matches given predicate
+async fn wait_event<A, B, F>(
+    first: &mut Swarm<Behaviour<A>>,
+    second: &mut Swarm<Behaviour<B>>,
+    mut predicate: F,
+) -> Event
+where
+    A: Storage + 'static,
+    B: Storage + 'static,
+    F: FnMut(&Event) -> bool,
+{
+    let wait = async {
+        loop {
+            futures::select! {
+                _ = first.select_next_some() => {},
+                event = second.select_next_some() => {
+                    if let SwarmEvent::Behaviour(event) = event {
+                        if predicate(&event) {
+                            return event;
+                        }
+                    }
+                },
+            }
+        }
+    };
+    async_std::future::timeout(Duration::from_secs(10), wait)
+        .await
+        .expect("expected event was not emitted")
+}
+
+#[test]
+fn test_validator_rejects_invalid_item() {
+    async_std::task::block_on(async {
+        let storage = MemoryStorage(HashMap::from([(b"boop".to_vec(), b"invalid".to_vec())]));
+        let mut provider = memory_swarm(Behaviour::new(Arc::new(storage)));
+        let mut searcher = memory_swarm(Behaviour::with_validator(
+            Arc::new(MemoryStorage(HashMap::new())),
+            KeyEqualsItemValidator,
+        ));
+        connect(&mut provider, &mut searcher).await;
+
+        let invalid_search_id = searcher
+            .behaviour_mut()
+            .search_item_with(b"boop".to_vec());
+        let event = wait_event(&mut provider, &mut searcher, |event| {
+            matches!(event, Event::QueryValidationFailed { .. })
+        })
+        .await;
+        assert!(matches!(
+            event,
+            Event::QueryValidationFailed { search_id, .. } if search_id == invalid_search_id
+        ));
+    });
+}
+
+#[test]
+fn test_validator_accepts_valid_item() {
+    async_std::task::block_on(async {
+        let storage = MemoryStorage(HashMap::from([(b"beep".to_vec(), b"beep".to_vec())]));
+        let mut provider = memory_swarm(Behaviour::new(Arc::new(storage)));
+        let mut searcher = memory_swarm(Behaviour::with_validator(
+            Arc::new(MemoryStorage(HashMap::new())),
+            KeyEqualsItemValidator,
+        ));
+        connect(&mut provider, &mut searcher).await;
+
+        searcher
+            .behaviour_mut()
+            .search_item_with(b"beep".to_vec());
+        let event = wait_event(&mut provider, &mut searcher, |event| {
+            matches!(event, Event::QueryCompleted { .. })
+        })
+        .await;
+        assert!(matches!(event, Event::QueryCompleted { item, .. } if item == b"beep"));
+    });
+}
+
+#[test]
+fn test_large_item_transfer() {
+    async_std::task::block_on(async {
+        let item = (0..256 * 1024)
+            .map(|idx| idx as u8)
+            .collect::<Vec<u8>>();
+        let storage = MemoryStorage(HashMap::from([(b"large".to_vec(), item.to_vec())]));
+        let mut provider = memory_swarm(Behaviour::new(Arc::new(storage)));
+        let mut searcher = memory_swarm(Behaviour::new(Arc::new(MemoryStorage(HashMap::new()))));
+        connect(&mut provider, &mut searcher).await;
+
+        searcher
+            .behaviour_mut()
+            .search_item_with(b"large".to_vec());
+        let event = wait_event(&mut provider, &mut searcher, |event| {
+            matches!(event, Event::QueryCompleted { .. })
+        })
+        .await;
+        assert!(matches!(event, Event::QueryCompleted { item: received, .. } if received == item));
+    });
+}
+
+#[test]
+fn test_served_items_are_reported() {
+    async_std::task::block_on(async {
+        let storage = MemoryStorage(HashMap::from([(b"beep".to_vec(), b"boop".to_vec())]));
+        let mut provider = memory_swarm(Behaviour::new(Arc::new(storage)));
+        let mut searcher = memory_swarm(Behaviour::new(Arc::new(MemoryStorage(HashMap::new()))));
+        connect(&mut provider, &mut searcher).await;
+
+        searcher
+            .behaviour_mut()
+            .search_item_with(b"beep".to_vec());
+        let searcher_peer_id = *searcher.local_peer_id();
+        let event = wait_event(&mut searcher, &mut provider, |event| {
+            matches!(event, Event::ItemsServed { .. })
+        })
+        .await;
+        assert!(matches!(
+            event,
+            Event::ItemsServed { peer, items: 1, bytes: 4 } if peer == searcher_peer_id
+        ));
+    });
+}
+
+#[test]
+fn test_query_not_found() {
+    async_std::task::block_on(async {
+        let mut provider = memory_swarm(Behaviour::new(Arc::new(MemoryStorage(HashMap::new()))));
+        let mut searcher = memory_swarm(Behaviour::new(Arc::new(MemoryStorage(HashMap::new()))));
+        connect(&mut provider, &mut searcher).await;
+
+        let search_id = searcher
+            .behaviour_mut()
+            .search_item_with(b"beep".to_vec());
+        let event = wait_event(&mut provider, &mut searcher, |event| {
+            matches!(event, Event::QueryNotFound { .. })
+        })
+        .await;
+        assert!(matches!(event, Event::QueryNotFound { search_id: id, .. } if id == search_id));
+        // query is already removed
+        assert!(!searcher
+            .behaviour_mut()
+            .cancel_search(&search_id));
+    });
+}
+
+#[test]
+fn test_query_not_found_when_peer_is_gone() {
+    async_std::task::block_on(async {
+        // provider does not answer until it is disconnected
+        let storage = SlowStorage {
+            items: HashMap::new(),
+            slow_key: b"beep".to_vec(),
+            delay: Duration::from_secs(60),
+        };
+        let mut provider = memory_swarm(Behaviour::new(Arc::new(storage)));
+        let mut searcher = memory_swarm(Behaviour::new(Arc::new(MemoryStorage(HashMap::new()))));
+        connect(&mut provider, &mut searcher).await;
+
+        let search_id = searcher
+            .behaviour_mut()
+            .search_item_with(b"beep".to_vec());
+        // provider is disconnected as soon as connection is established
+        loop {
+            futures::select! {
+                _ = provider.select_next_some() => {},
+                event = searcher.select_next_some() => {
+                    if let SwarmEvent::ConnectionEstablished { peer_id, .. } = event {
+                        searcher.disconnect_peer_id(peer_id).unwrap();
+                        break;
+                    }
+                },
+            }
+        }
+        let event = wait_event(&mut provider, &mut searcher, |event| {
+            matches!(event, Event::QueryNotFound { .. })
+        })
+        .await;
+        assert!(matches!(event, Event::QueryNotFound { search_id: id, .. } if id == search_id));
+    });
+}
+
+#[test]
+fn test_query_not_found_without_peers() {
+    async_std::task::block_on(async {
+        let mut idle = memory_swarm(Behaviour::new(Arc::new(MemoryStorage(HashMap::new()))));
+        let mut searcher = memory_swarm(Behaviour::new(Arc::new(MemoryStorage(HashMap::new()))));
+
+        // nobody can answer, so query is finished on the next check instead of deadline
+        let search_id = searcher
+            .behaviour_mut()
+            .search_item_with_timeout(b"beep".to_vec(), Duration::from_secs(60));
+        let event = wait_event(&mut idle, &mut searcher, |event| {
+            matches!(
+                event,
+                Event::QueryNotFound { .. } | Event::QueryTimedOut { .. }
+            )
+        })
+        .await;
+        assert!(matches!(event, Event::QueryNotFound { search_id: id, .. } if id == search_id));
+    });
+}
+
+#[test]
+fn test_query_timed_out() {
+    async_std::task::block_on(async {
+        let mut idle = memory_swarm(Behaviour::new(Arc::new(MemoryStorage(HashMap::new()))));
+        let mut searcher = memory_swarm(Behaviour::new(Arc::new(MemoryStorage(HashMap::new()))));
+
+        let search_id = searcher
+            .behaviour_mut()
+            .search_item_with_timeout(b"beep".to_vec(), Duration::from_millis(100));
+        let event = wait_event(&mut idle, &mut searcher, |event| {
+            matches!(event, Event::QueryTimedOut { .. })
+        })
+        .await;
+        assert!(matches!(event, Event::QueryTimedOut { search_id: id, .. } if id == search_id));
+    });
+}
+
+#[test]
+fn test_cancel_search() {
+    let mut searcher = memory_swarm(Behaviour::new(Arc::new(MemoryStorage(HashMap::new()))));
+    let search_id = searcher
+        .behaviour_mut()
+        .search_item_with(b"beep".to_vec());
+    assert!(searcher
+        .behaviour_mut()
+        .cancel_search(&search_id));
+    assert!(!searcher
+        .behaviour_mut()
+        .cancel_search(&search_id));
+}
+
+#[test]
+fn test_want_list_completed() {
+    async_std::task::block_on(async {
+        let keys = (0..300u16)
+            .map(|idx| idx.to_be_bytes().to_vec())
+            .collect::<Vec<Vec<u8>>>();
+        let storage = MemoryStorage(
+            keys.iter()
+                .map(|key| (key.to_vec(), key.to_vec()))
+                .collect(),
+        );
+        let mut provider = memory_swarm(Behaviour::new(Arc::new(storage)));
+        let mut searcher = memory_swarm(Behaviour::with_validator(
+            Arc::new(MemoryStorage(HashMap::new())),
+            KeyEqualsItemValidator,
+        ));
+        connect(&mut provider, &mut searcher).await;
+
+        let search_id = searcher
+            .behaviour_mut()
+            .search_items_with(keys.to_vec());
+        let mut received = Vec::new();
+        let event = wait_event(&mut provider, &mut searcher, |event| match event {
+            Event::WantListProgress { items, .. } => {
+                received.extend(
+                    items
+                        .iter()
+                        .map(|(key, _)| key.to_vec()),
+                );
+                false
+            },
+            event => matches!(event, Event::WantListCompleted { .. }),
+        })
+        .await;
+        assert!(matches!(event, Event::WantListCompleted { search_id: id } if id == search_id));
+        received.sort();
+        assert_eq!(received, keys);
+    });
+}
+
+#[test]
+fn test_want_list_not_found() {
+    async_std::task::block_on(async {
+        let storage = MemoryStorage(HashMap::from([(b"beep".to_vec(), b"beep".to_vec())]));
+        let mut provider = memory_swarm(Behaviour::new(Arc::new(storage)));
+        let mut searcher = memory_swarm(Behaviour::new(Arc::new(MemoryStorage(HashMap::new()))));
+        connect(&mut provider, &mut searcher).await;
+
+        let search_id = searcher
+            .behaviour_mut()
+            .search_items_with(Vec::from([b"beep".to_vec(), b"boop".to_vec()]));
+        let mut received = Vec::new();
+        let event = wait_event(&mut provider, &mut searcher, |event| match event {
+            Event::WantListProgress { items, .. } => {
+                received.extend(items.iter().cloned());
+                false
+            },
+            event => matches!(event, Event::WantListNotFound { .. }),
+        })
+        .await;
+        assert!(matches!(
+            event,
+            Event::WantListNotFound { search_id: id, missing } if id == search_id && missing == [b"boop".to_vec()]
+        ));
+        assert_eq!(received, [(b"beep".to_vec(), b"beep".to_vec())]);
+    });
+}
+
+#[test]
+fn test_want_list_waits_for_all_have_responses() {
+    async_std::task::block_on(async {
+        // keys are asked with two want have requests and provider has only keys of second one
+        let keys = (0..300u16)
+            .map(|idx| idx.to_be_bytes().to_vec())
+            .collect::<Vec<Vec<u8>>>();
+        let storage = MemoryStorage(
+            keys[256..]
+                .iter()
+                .map(|key| (key.to_vec(), key.to_vec()))
+                .collect(),
+        );
+        let mut provider = memory_swarm(Behaviour::new(Arc::new(storage)));
+        let mut searcher = memory_swarm(Behaviour::new(Arc::new(MemoryStorage(HashMap::new()))));
+        connect(&mut provider, &mut searcher).await;
+
+        let search_id = searcher
+            .behaviour_mut()
+            .search_items_with(keys.to_vec());
+        let mut received = 0;
+        let event = wait_event(&mut provider, &mut searcher, |event| match event {
+            Event::WantListProgress { items, .. } => {
+                received += items.len();
+                false
+            },
+            event => matches!(event, Event::WantListNotFound { .. }),
+        })
+        .await;
+        assert!(matches!(
+            event,
+            Event::WantListNotFound { search_id: id, missing } if id == search_id && missing == keys[..256]
+        ));
+        assert_eq!(received, 44);
+    });
+}
+
+/// Create new swarm of peer that speaks only first version of protocol and answers queries
+/// with requests of [`Behaviour`] by hand
+fn legacy_swarm() -> Swarm<request_response::Behaviour<QuantaSwapCodec>> {
+    let keypair = Keypair::generate_ed25519();
+    let local_peer_id = PeerId::from(keypair.public());
+    let transport = MemoryTransport::default()
+        .upgrade(upgrade::Version::V1)
+        .authenticate(noise::Config::new(&keypair).unwrap())
+        .multiplex(yamux::Config::default())
+        .boxed();
+    let behaviour = request_response::Behaviour::new(
+        QuantaSwapCodec::new(LEGACY_MAX_MESSAGE_SIZE),
+        [(QuantaSwapProtocol::V1, ProtocolSupport::Full)],
+        Default::default(),
+    );
+    SwarmBuilder::with_async_std_executor(transport, behaviour, local_peer_id).build()
+}
+
+#[test]
+fn test_want_list_with_legacy_peer() {
+    async_std::task::block_on(async {
+        let keys = (0..20u16)
+            .map(|idx| idx.to_be_bytes().to_vec())
+            .collect::<Vec<Vec<u8>>>();
+        let mut legacy = legacy_swarm();
+        let mut searcher = memory_swarm(Behaviour::with_validator(
+            Arc::new(MemoryStorage(HashMap::new())),
+            KeyEqualsItemValidator,
+        ));
+        legacy
+            .listen_on("/memory/0".parse().unwrap())
+            .unwrap();
+        loop {
+            if let SwarmEvent::NewListenAddr { address, .. } = legacy.select_next_some().await {
+                searcher.dial(address).unwrap();
+                break;
+            }
+        }
+
+        let search_id = searcher
+            .behaviour_mut()
+            .search_items_with(keys.to_vec());
+        let mut received = Vec::new();
+        let wait = async {
+            loop {
+                futures::select! {
+                    event = legacy.select_next_some() => {
+                        let SwarmEvent::Behaviour(request_response::Event::Message {
+                            message: request_response::Message::Request { request, channel, .. },
+                            ..
+                        }) = event else {
+                            continue;
+                        };
+                        // legacy peer has items of all keys, it is asked only about real keys
+                        let response = match request.request {
+                            QuantaSwapRequest::Query { search_id, searching } => {
+                                assert!(!searching.is_empty(), "legacy peer got query of empty key");
+                                QuantaSwapRespone::Query { search_id, exists: true }
+                            },
+                            QuantaSwapRequest::QueryWant { search_id, searching } => {
+                                QuantaSwapRespone::QueryWant { search_id, item: searching }
+                            },
+                            request => panic!("legacy peer got want list request {}", request),
+                        };
+                        legacy
+                            .behaviour_mut()
+                            .send_response(channel, response.into())
+                            .unwrap();
+                    },
+                    event = searcher.select_next_some() => match event {
+                        SwarmEvent::Behaviour(Event::WantListProgress { items, .. }) => {
+                            received.extend(items.into_iter().map(|(key, _)| key));
+                        },
+                        SwarmEvent::Behaviour(event @ Event::WantListCompleted { .. }) => {
+                            return event;
+                        },
+                        SwarmEvent::ConnectionClosed { .. } => {
+                            panic!("connection with legacy peer was closed");
+                        },
+                        _ => {},
+                    },
+                }
+            }
+        };
+        let event = async_std::future::timeout(Duration::from_secs(10), wait)
+            .await
+            .expect("want list was not completed");
+        assert!(matches!(event, Event::WantListCompleted { search_id: id } if id == search_id));
+        received.sort();
+        assert_eq!(received, keys);
+    });
+}
+
+#[test]
+fn test_search_with_unreachable_provider() {
+    async_std::task::block_on(async {
+        let mut connected = memory_swarm(Behaviour::new(Arc::new(MemoryStorage(HashMap::new()))));
+        let mut searcher = memory_swarm(Behaviour::new(Arc::new(MemoryStorage(HashMap::new()))));
+        connect(&mut connected, &mut searcher).await;
+
+        let search_id = searcher
+            .behaviour_mut()
+            .search_item_with(b"beep".to_vec());
+        wait_event(&mut connected, &mut searcher, |event| {
+            matches!(event, Event::QueryNotFound { .. })
+        })
+        .await;
+        // address of provider is unknown, so dial fails and search is finished again
+        searcher
+            .behaviour_mut()
+            .search_item_with_providers(search_id, b"beep".to_vec(), Vec::from([PeerId::random()]));
+        let event = wait_event(&mut connected, &mut searcher, |event| {
+            matches!(event, Event::QueryNotFound { .. })
+        })
+        .await;
+        assert!(matches!(event, Event::QueryNotFound { search_id: id, .. } if id == search_id));
+    });
+}
+
+#[test]
+fn test_want_list_with_unreachable_provider() {
+    async_std::task::block_on(async {
+        let storage = MemoryStorage(HashMap::from([(b"beep".to_vec(), b"beep".to_vec())]));
+        let mut connected = memory_swarm(Behaviour::new(Arc::new(storage)));
+        let mut searcher = memory_swarm(Behaviour::new(Arc::new(MemoryStorage(HashMap::new()))));
+        connect(&mut connected, &mut searcher).await;
+
+        let search_id = searcher
+            .behaviour_mut()
+            .search_items_with(Vec::from([b"beep".to_vec(), b"boop".to_vec()]));
+        wait_event(&mut connected, &mut searcher, |event| {
+            matches!(event, Event::WantListNotFound { .. })
+        })
+        .await;
+        // address of provider is unknown, so dial fails and want list is finished again without
+        // asking connected peer that already answered
+        searcher
+            .behaviour_mut()
+            .search_items_with_providers(
+                search_id,
+                Vec::from([b"boop".to_vec()]),
+                Vec::from([PeerId::random()]),
+            );
+        let event = wait_event(&mut connected, &mut searcher, |event| {
+            matches!(event, Event::WantListNotFound { .. })
+        })
+        .await;
+        assert!(matches!(
+            event,
+            Event::WantListNotFound { search_id: id, missing } if id == search_id && missing == [b"boop".to_vec()]
+        ));
+    });
+}
+
+#[test]
+fn test_slow_lookup_does_not_block_other_requests() {
+    async_std::task::block_on(async {
+        let storage = SlowStorage {
+            items: HashMap::from([
+                (b"slow".to_vec(), b"slow".to_vec()),
+                (b"fast".to_vec(), b"fast".to_vec()),
+            ]),
+            slow_key: b"slow".to_vec(),
+            delay: Duration::from_secs(2),
+        };
+        let mut provider = memory_swarm(Behaviour::new(Arc::new(storage)));
+        let mut searcher = memory_swarm(Behaviour::new(Arc::new(MemoryStorage(HashMap::new()))));
+        connect(&mut provider, &mut searcher).await;
+
+        let slow_search_id = searcher
+            .behaviour_mut()
+            .search_item_with(b"slow".to_vec());
+        let fast_search_id = searcher
+            .behaviour_mut()
+            .search_item_with(b"fast".to_vec());
+        // item that is found fast is sent while lookup of slow item is resolving
+        let event = wait_event(&mut provider, &mut searcher, |event| {
+            matches!(event, Event::QueryCompleted { .. })
+        })
+        .await;
+        assert!(matches!(
+            event,
+            Event::QueryCompleted { search_id, .. } if search_id == fast_search_id
+        ));
+        let event = wait_event(&mut provider, &mut searcher, |event| {
+            matches!(event, Event::QueryCompleted { .. })
+        })
+        .await;
+        assert!(matches!(
+            event,
+            Event::QueryCompleted { search_id, item, .. }
+                if search_id == slow_search_id && item == b"slow"
+        ));
+    });
+}
+
+#[test]
+fn test_rate_limited_peer_is_banned() {
+    async_std::task::block_on(async {
+        let config = Config::default()
+            .with_max_requests_per_second(Some(1))
+            .with_max_rate_limit_violations(3);
+        let mut provider = memory_swarm(Behaviour::with_config(
+            Arc::new(MemoryStorage(HashMap::new())),
+            crate::AcceptAllValidator,
+            config,
+        ));
+        let mut searcher = memory_swarm(Behaviour::new(Arc::new(MemoryStorage(HashMap::new()))));
+        let searcher_peer_id = *searcher.local_peer_id();
+        connect(&mut provider, &mut searcher).await;
+
+        for idx in 0..8u8 {
+            searcher
+                .behaviour_mut()
+                .search_item_with(vec![idx]);
+        }
+        let event = wait_event(&mut searcher, &mut provider, |event| {
+            matches!(event, Event::PeerBanned { .. })
+        })
+        .await;
+        assert!(matches!(
+            event,
+            Event::PeerBanned { peer, reason: BanReason::RateLimited, .. }
+                if peer == searcher_peer_id
+        ));
+        assert!(provider
+            .behaviour()
+            .is_banned(&searcher_peer_id));
+        let stats = provider
+            .behaviour()
+            .peer_stats(&searcher_peer_id)
+            .unwrap();
+        assert!(stats.rate_limited >= 3);
+    });
+}
+
+#[test]
+fn test_want_list_from_rate_limited_provider() {
+    async_std::task::block_on(async {
+        // every response of blocks takes half of served bytes per second, so provider limits
+        // the third one
+        let keys = (0..24u16)
+            .map(|idx| idx.to_be_bytes().to_vec())
+            .collect::<Vec<Vec<u8>>>();
+        let storage = MemoryStorage(
+            keys.iter()
+                .map(|key| (key.to_vec(), vec![key[1]; 8 * 1024]))
+                .collect(),
+        );
+        let config = Config::default()
+            .with_max_served_bytes_per_second(Some(64 * 1024))
+            .with_max_rate_limit_violations(3);
+        let mut provider = memory_swarm(Behaviour::with_config(
+            Arc::new(storage),
+            crate::AcceptAllValidator,
+            config,
+        ));
+        let mut searcher = memory_swarm(Behaviour::new(Arc::new(MemoryStorage(HashMap::new()))));
+        let searcher_peer_id = *searcher.local_peer_id();
+        connect(&mut provider, &mut searcher).await;
+
+        let search_id = searcher
+            .behaviour_mut()
+            .search_items_with(keys.to_vec());
+        let mut received = Vec::new();
+        let event = wait_event(&mut provider, &mut searcher, |event| match event {
+            Event::WantListProgress { items, .. } => {
+                received.extend(
+                    items
+                        .iter()
+                        .map(|(key, _)| key.to_vec()),
+                );
+                false
+            },
+            event => matches!(
+                event,
+                Event::WantListCompleted { .. } | Event::WantListNotFound { .. }
+            ),
+        })
+        .await;
+        // searcher waits until provider serves it again instead of being banned
+        assert!(matches!(event, Event::WantListCompleted { search_id: id } if id == search_id));
+        received.sort();
+        assert_eq!(received, keys);
+        assert!(!provider
+            .behaviour()
+            .is_banned(&searcher_peer_id));
+        let stats = provider
+            .behaviour()
+            .peer_stats(&searcher_peer_id)
+            .unwrap();
+        assert!(stats.rate_limited > 0);
+    });
+}
+
+#[test]
+fn test_invalid_responses_ban_peer() {
+    async_std::task::block_on(async {
+        let storage = MemoryStorage(HashMap::from([(b"boop".to_vec(), b"invalid".to_vec())]));
+        let mut provider = memory_swarm(Behaviour::new(Arc::new(storage)));
+        let provider_peer_id = *provider.local_peer_id();
+        let mut searcher = memory_swarm(Behaviour::with_config(
+            Arc::new(MemoryStorage(HashMap::new())),
+            KeyEqualsItemValidator,
+            Config::default().with_max_invalid_responses(1),
+        ));
+        connect(&mut provider, &mut searcher).await;
+
+        searcher
+            .behaviour_mut()
+            .search_item_with(b"boop".to_vec());
+        let event = wait_event(&mut provider, &mut searcher, |event| {
+            matches!(event, Event::PeerBanned { .. })
+        })
+        .await;
+        assert!(matches!(
+            event,
+            Event::PeerBanned { peer, reason: BanReason::InvalidResponses, .. }
+                if peer == provider_peer_id
+        ));
+        assert!(searcher
+            .behaviour()
+            .is_banned(&provider_peer_id));
+        assert_eq!(
+            searcher
+                .behaviour()
+                .peer_stats(&provider_peer_id)
+                .unwrap()
+                .invalid_responses,
+            1
+        );
+    });
+}
+
+#[test]
+fn test_ledger_counts_exchanged_bytes() {
+    async_std::task::block_on(async {
+        let storage = MemoryStorage(HashMap::from([(b"beep".to_vec(), b"beep".to_vec())]));
+        let mut provider = memory_swarm(Behaviour::new(Arc::new(storage)));
+        let provider_peer_id = *provider.local_peer_id();
+        let mut searcher = memory_swarm(Behaviour::new(Arc::new(MemoryStorage(HashMap::new()))));
+        let searcher_peer_id = *searcher.local_peer_id();
+        connect(&mut provider, &mut searcher).await;
+
+        searcher
+            .behaviour_mut()
+            .search_item_with(b"beep".to_vec());
+        wait_event(&mut searcher, &mut provider, |event| {
+            matches!(event, Event::ItemsServed { .. })
+        })
+        .await;
+        wait_event(&mut provider, &mut searcher, |event| {
+            matches!(event, Event::QueryCompleted { .. })
+        })
+        .await;
+        assert_eq!(
+            provider
+                .behaviour()
+                .ledger(&searcher_peer_id),
+            Some(&Ledger {
+                bytes_sent: 4,
+                bytes_received: 0,
+            })
+        );
+        assert_eq!(
+            searcher
+                .behaviour()
+                .ledger(&provider_peer_id),
+            Some(&Ledger {
+                bytes_sent: 0,
+                bytes_received: 4,
+            })
+        );
+    });
+}
+
+#[test]
+fn test_ledger_is_loaded_and_saved() {
+    async_std::task::block_on(async {
+        let storage = MemoryStorage(HashMap::from([(b"beep".to_vec(), b"beep".to_vec())]));
+        let ledger_storage = Arc::new(MemoryLedgerStorage::default());
+        let mut provider = memory_swarm(
+            Behaviour::new(Arc::new(storage)).with_ledger_storage(Arc::clone(&ledger_storage)),
+        );
+        let provider_peer_id = *provider.local_peer_id();
+        let mut searcher = memory_swarm(Behaviour::new(Arc::new(MemoryStorage(HashMap::new()))));
+        let searcher_peer_id = *searcher.local_peer_id();
+        let saved = Ledger {
+            bytes_sent: 10,
+            bytes_received: 100,
+        };
+        ledger_storage
+            .0
+            .lock()
+            .unwrap()
+            .insert(searcher_peer_id, saved);
+        connect(&mut provider, &mut searcher).await;
+
+        searcher
+            .behaviour_mut()
+            .search_item_with(b"beep".to_vec());
+        wait_event(&mut searcher, &mut provider, |event| {
+            matches!(event, Event::ItemsServed { .. })
+        })
+        .await;
+        let expected = Ledger {
+            bytes_sent: 14,
+            bytes_received: 100,
+        };
+        assert_eq!(
+            provider
+                .behaviour()
+                .ledger(&searcher_peer_id),
+            Some(&expected)
+        );
+        // ledger is saved when connection is closed
+        searcher
+            .disconnect_peer_id(provider_peer_id)
+            .unwrap();
+        let wait = async {
+            while ledger_storage
+                .0
+                .lock()
+                .unwrap()
+                .get(&searcher_peer_id) !=
+                Some(&expected)
+            {
+                futures::select! {
+                    _ = provider.select_next_some() => {},
+                    _ = searcher.select_next_some() => {},
+                    // ledger is saved in poll that does not always produce swarm event
+                    _ = async_std::task::sleep(Duration::from_millis(50)).fuse() => {},
+                }
+            }
+        };
+        async_std::future::timeout(Duration::from_secs(10), wait)
+            .await
+            .expect("ledger was not saved");
+    });
+}
+
+#[test]
+fn test_most_reciprocating_peer_is_first() {
+    let uploader = PeerId::random();
+    let downloader = PeerId::random();
+    let newcomer = PeerId::random();
+    let mut ledgers = Ledgers::default();
+    ledgers.on_received(uploader, 100);
+    ledgers.on_sent(downloader, 100);
+    let now = Instant::now();
+    let mut queue = LookupQueue::default();
+    queue.push(downloader, 0, now);
+    queue.push(uploader, 1, now);
+    queue.push(newcomer, 2, now);
+    queue.push(newcomer, 3, now);
+    queue.push(uploader, 4, now);
+    assert_eq!(queue.len(), 5);
+    // peers without debt are served in order of arrival before peer in debt
+    assert_eq!(queue.pop(&ledgers), Some((uploader, 1)));
+    assert_eq!(queue.pop(&ledgers), Some((newcomer, 2)));
+    assert_eq!(queue.pop(&ledgers), Some((newcomer, 3)));
+    assert_eq!(queue.pop(&ledgers), Some((uploader, 4)));
+    assert_eq!(queue.pop(&ledgers), Some((downloader, 0)));
+    assert_eq!(queue.pop(&ledgers), None);
+    assert_eq!(queue.len(), 0);
+}
+
+#[test]
+fn test_lookup_queue_drops_expired_and_removed_requests() {
+    let first = PeerId::random();
+    let second = PeerId::random();
+    let ledgers = Ledgers::default();
+    let start = Instant::now();
+    let later = start + Duration::from_secs(5);
+    let mut queue = LookupQueue::default();
+    queue.push(first, 0, start);
+    queue.push(second, 1, start);
+    queue.push(first, 2, later);
+    queue.push(second, 3, later);
+    assert_eq!(queue.remove_queued_before(later), 2);
+    assert_eq!(queue.len(), 2);
+    queue.remove_peer(&second);
+    assert_eq!(queue.len(), 1);
+    assert_eq!(queue.pop(&ledgers), Some((first, 2)));
+    assert_eq!(queue.pop(&ledgers), None);
+}
+
+#[test]
+fn test_ledger_is_saved_after_previous_save() {
+    let peer = PeerId::random();
+    let mut ledgers = Ledgers::default();
+    ledgers.on_sent(peer, 100);
+    let first = ledgers.take_dirty(&peer).unwrap();
+    assert_eq!(first.bytes_sent, 100);
+    // ledger that is changed while it is saving waits for save to finish
+    ledgers.on_sent(peer, 50);
+    assert!(ledgers.take_dirty(&peer).is_none());
+    assert!(ledgers.take_all_dirty().is_empty());
+    ledgers.on_saved(peer);
+    assert_eq!(
+        ledgers
+            .take_dirty(&peer)
+            .unwrap()
+            .bytes_sent,
+        150
+    );
+    ledgers.on_saved(peer);
+    assert!(ledgers.take_dirty(&peer).is_none());
+}
+
+#[test]
+fn test_idle_ledgers_are_forgotten() {
+    let idle = PeerId::random();
+    let connected = PeerId::random();
+    let mut ledgers = Ledgers::default();
+    ledgers.on_sent(idle, 100);
+    ledgers.on_sent(connected, 100);
+    let now = Instant::now();
+    ledgers.on_disconnected(idle, now);
+    // ledger that is not saved yet is kept
+    ledgers.forget_idle(now + Duration::from_secs(60 * 60), true);
+    assert!(ledgers.debt_ratio(&idle) != 0.0);
+    assert_eq!(ledgers.take_all_dirty().len(), 2);
+    ledgers.forget_idle(now, true);
+    assert!(ledgers.debt_ratio(&idle) != 0.0);
+    // ledger that is saving is kept
+    ledgers.forget_idle(now + Duration::from_secs(60 * 60), true);
+    assert!(ledgers.debt_ratio(&idle) != 0.0);
+    ledgers.on_saved(idle);
+    ledgers.on_saved(connected);
+    ledgers.forget_idle(now + Duration::from_secs(60 * 60), true);
+    assert_eq!(ledgers.debt_ratio(&idle), 0.0);
+    assert!(ledgers.debt_ratio(&connected) != 0.0);
+    // without storage ledgers are never saved, so they are forgotten anyway
+    ledgers.on_sent(idle, 100);
+    ledgers.on_disconnected(idle, now);
+    ledgers.forget_idle(now + Duration::from_secs(60 * 60), false);
+    assert_eq!(ledgers.debt_ratio(&idle), 0.0);
+}
+
+#[test]
+fn test_peer_stays_connected_while_other_connection_is_open() {
+    let mut behaviour = Behaviour::new(Arc::new(MemoryStorage(HashMap::new())));
+    let peer = PeerId::random();
+    let address = "/memory/1"
+        .parse::<Multiaddr>()
+        .unwrap();
+    let endpoint = ConnectedPoint::Dialer {
+        address: address.clone(),
+        role_override: Endpoint::Dialer,
+    };
+    // e.g. QUIC next to TCP
+    let mut handlers = Vec::new();
+    for other_established in 0..2 {
+        let connection_id = ConnectionId::new_unchecked(other_established + 1);
+        let handler = behaviour
+            .handle_established_outbound_connection(connection_id, peer, &address, Endpoint::Dialer)
+            .unwrap();
+        behaviour.on_swarm_event(FromSwarm::ConnectionEstablished(ConnectionEstablished {
+            peer_id: peer,
+            connection_id,
+            endpoint: &endpoint,
+            failed_addresses: &[],
+            other_established,
+        }));
+        handlers.push((connection_id, handler));
+    }
+    behaviour.on_peer_protocol(peer, QuantaSwapProtocol::V2);
+    let search_id = behaviour.search_items_with(Vec::from([b"beep".to_vec()]));
+    assert!(behaviour.has_want_requests(search_id));
+    for (remaining_established, (connection_id, handler)) in handlers.into_iter().enumerate().rev()
+    {
+        behaviour.on_swarm_event(FromSwarm::ConnectionClosed(ConnectionClosed {
+            peer_id: peer,
+            connection_id,
+            endpoint: &endpoint,
+            handler,
+            remaining_established,
+        }));
+        // peer is gone only when its last connection is closed
+        let connected = remaining_established > 0;
+        assert_eq!(behaviour.connections.contains(&peer), connected);
+        assert_eq!(
+            behaviour
+                .peer_protocols
+                .contains_key(&peer),
+            connected
+        );
+    }
+}
@@ -0,0 +1,15 @@
+/// Validator checks items that we are receive from network before [`crate::Event::QueryCompleted`]
+/// is emitted. Most of storages are content-addressed, so validator just checks that hash of item
+/// equals the key that we are searched
+pub trait Validator {
+    /// Returns true if item really belongs to key
+    fn validate(&self, key: &[u8], item: &[u8]) -> bool;
+}
+
+/// Default [`Validator`] that accept any item. Used when storage is not content-addressed
+#[derive(Debug, Clone, Copy, Default)]
+pub struct AcceptAllValidator;
+
+impl Validator for AcceptAllValidator {
+    fn validate(&self, _key: &[u8], _item: &[u8]) -> bool { true }
+}
@@ -0,0 +1,189 @@
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+use fnv::FnvHashSet;
+use libp2p::PeerId;
+
+/// Max count of keys that sends in one [`crate::request::QuantaSwapRequest::WantHave`] or
+/// [`crate::request::QuantaSwapRequest::WantBlocks`] request
+pub(crate) const MAX_WANT_LIST_KEYS: usize = 256;
+/// Max size of items that peer puts into one [`crate::response::QuantaSwapRespone::Blocks`]
+/// response. Keys that does not fit are requested again. Response is also limited with half of
+/// [`crate::Config::max_message_size`]
+pub(crate) const MAX_BLOCKS_RESPONSE_SIZE: usize = 32 * 1024;
+/// Time for which peer is not asked for blocks after it answered without any requested item,
+/// e.g. because we are over its rate limits. It is doubled for every such answer in a row
+const WANT_BLOCKS_BACKOFF: Duration = Duration::from_secs(1);
+/// Max time for which peer is not asked for blocks. It is longer than interval after which rate
+/// limits forget violations, so peer that waits is not banned
+const MAX_WANT_BLOCKS_BACKOFF: Duration = Duration::from_secs(16);
+
+/// Convert flags into bitmap. Bit with index of flag is set if flag is true
+pub(crate) fn to_bitmap(flags: &[bool]) -> Vec<u8> {
+    let mut bitmap = vec![0; flags.len().div_ceil(8)];
+    for (idx, flag) in flags.iter().enumerate() {
+        if *flag {
+            bitmap[idx / 8] |= 1 << (idx % 8);
+        }
+    }
+    bitmap
+}
+
+/// Check if bit with given index is set in bitmap
+pub(crate) fn is_set(bitmap: &[u8], idx: usize) -> bool {
+    bitmap
+        .get(idx / 8)
+        .map(|byte| byte & (1 << (idx % 8)) != 0)
+        .unwrap_or(false)
+}
+
+/// Active search of many items in network. Keys are identified by their index in
+/// [`WantList::keys`]
+#[derive(Debug, Clone)]
+pub(crate) struct WantList {
+    /// Keys in [`crate::Storage`] that peer looking for
+    pub(crate) keys: Vec<Vec<u8>>,
+    /// Indexes of keys that are not received yet
+    remaining: FnvHashSet<usize>,
+    /// Indexes of keys which items are requested from peer and not received yet
+    requested: HashMap<usize, PeerId>,
+    /// Indexes of keys that peer have
+    haves: HashMap<PeerId, FnvHashSet<usize>>,
+    /// Peers that answered which keys they have
+    answered: FnvHashSet<PeerId>,
+    /// Providers of keys that we are dialing. Want list is not exhausted until they answer or
+    /// dial fails
+    pub(crate) pending_peers: FnvHashSet<PeerId>,
+    /// Peers that are not asked for blocks until given moment, see [`WantList::back_off`]
+    paused: HashMap<PeerId, Instant>,
+    /// Count of answers in a row without requested items of peers
+    empty_answers: HashMap<PeerId, u32>,
+    /// After this moment want list is finished with [`crate::Event::WantListTimedOut`]
+    deadline: Instant,
+}
+
+impl WantList {
+    /// Create new [`WantList`] that expires after timeout
+    pub(crate) fn new(keys: Vec<Vec<u8>>, timeout: Duration) -> Self {
+        Self {
+            remaining: (0..keys.len()).collect(),
+            keys,
+            requested: HashMap::default(),
+            haves: HashMap::default(),
+            answered: FnvHashSet::default(),
+            pending_peers: FnvHashSet::default(),
+            paused: HashMap::default(),
+            empty_answers: HashMap::default(),
+            deadline: Instant::now() + timeout,
+        }
+    }
+    /// Check if deadline of want list is reached
+    pub(crate) fn is_expired(&self, now: Instant) -> bool { self.deadline <= now }
+    /// Check if all items are received
+    pub(crate) fn is_completed(&self) -> bool { self.remaining.is_empty() }
+    /// Check if nobody of connected peers can send remaining items. Requests that are waiting
+    /// for response are not known here, so caller should check them too
+    pub(crate) fn is_exhausted(&self, connections: &FnvHashSet<PeerId>) -> bool {
+        !self.is_completed() &&
+            self.requested.is_empty() &&
+            self.pending_peers.is_empty() &&
+            self.paused.is_empty() &&
+            connections
+                .iter()
+                .all(|peer| self.answered.contains(peer))
+    }
+    /// Indexes of keys that are not received yet
+    pub(crate) fn remaining(&self) -> Vec<usize> {
+        let mut remaining = self
+            .remaining
+            .iter()
+            .copied()
+            .collect::<Vec<usize>>();
+        remaining.sort_unstable();
+        remaining
+    }
+    /// Keys that are not received yet
+    pub(crate) fn missing_keys(&self) -> Vec<Vec<u8>> {
+        self.remaining()
+            .into_iter()
+            .map(|idx| self.keys[idx].to_vec())
+            .collect()
+    }
+    /// Save which keys peer have
+    pub(crate) fn on_have(&mut self, peer: PeerId, indexes: impl IntoIterator<Item = usize>) {
+        self.answered.insert(peer);
+        self.haves
+            .entry(peer)
+            .or_default()
+            .extend(indexes);
+    }
+    /// Peers that have at least one remaining key
+    pub(crate) fn providers(&self) -> Vec<PeerId> { self.haves.keys().copied().collect() }
+    /// Returns indexes of keys that peer have and that are not requested yet from anybody.
+    /// Returned keys are marked as requested from peer
+    pub(crate) fn assign(&mut self, peer: PeerId) -> Vec<usize> {
+        if self.paused.contains_key(&peer) {
+            return Vec::new();
+        }
+        let Some(haves) = self.haves.get(&peer) else {
+            return Vec::new();
+        };
+        let mut assigned = haves
+            .iter()
+            .filter(|idx| self.remaining.contains(idx) && !self.requested.contains_key(idx))
+            .copied()
+            .collect::<Vec<usize>>();
+        assigned.sort_unstable();
+        for idx in &assigned {
+            self.requested.insert(*idx, peer);
+        }
+        assigned
+    }
+    /// Mark key as received. Returns false if key was already received before
+    pub(crate) fn on_received(&mut self, idx: usize) -> bool {
+        self.requested.remove(&idx);
+        self.remaining.remove(&idx)
+    }
+    /// Mark keys requested from peer as not requested, so they can be requested from other peer
+    pub(crate) fn release(&mut self, peer: &PeerId, indexes: impl IntoIterator<Item = usize>) {
+        for idx in indexes {
+            if self.requested.get(&idx) == Some(peer) {
+                self.requested.remove(&idx);
+            }
+        }
+    }
+    /// Stop asking peer for blocks for a while, because it answered without any requested item.
+    /// Asking it again right away would make a loop of requests that is banned by rate limits
+    pub(crate) fn back_off(&mut self, peer: PeerId, now: Instant) {
+        let empty_answers = self
+            .empty_answers
+            .entry(peer)
+            .or_default();
+        let backoff = WANT_BLOCKS_BACKOFF
+            .saturating_mul(1 << (*empty_answers).min(u32::BITS - 1))
+            .min(MAX_WANT_BLOCKS_BACKOFF);
+        *empty_answers += 1;
+        self.paused.insert(peer, now + backoff);
+    }
+    /// Peer answered with requested items, so next empty answer is waited with shortest backoff
+    pub(crate) fn on_answered(&mut self, peer: &PeerId) { self.empty_answers.remove(peer); }
+    /// Allow peers which backoff is over to be asked for blocks again. Returns true if there
+    /// are such peers
+    pub(crate) fn resume(&mut self, now: Instant) -> bool {
+        let paused = self.paused.len();
+        self.paused
+            .retain(|_, until| *until > now);
+        self.paused.len() < paused
+    }
+    /// Forget everything about peer. Used when peer disconnected or sent invalid item
+    pub(crate) fn forget_peer(&mut self, peer: &PeerId) {
+        self.requested
+            .retain(|_, requested_from| requested_from != peer);
+        self.haves.remove(peer);
+        self.answered.remove(peer);
+        self.paused.remove(peer);
+        self.empty_answers.remove(peer);
+    }
+}